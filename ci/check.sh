@@ -7,7 +7,13 @@ cargo fmt --all --check
 echo "==> cargo clippy"
 cargo clippy --workspace --all-targets -- -D warnings
 
+echo "==> cargo clippy --all-features"
+cargo clippy --workspace --all-targets --all-features -- -D warnings
+
 echo "==> cargo test"
 cargo test --workspace
 
+echo "==> cargo test --all-features"
+cargo test --workspace --all-features
+
 echo "==> All checks passed"
@@ -30,6 +30,9 @@ pub enum Commands {
 
     /// Start a CORECONF CoAP server backed by a local datastore
     Serve(commands::serve::ServeArgs),
+
+    /// Work with SID files (compile to the binary model format)
+    Sid(commands::sid::SidArgs),
 }
 
 pub fn run(cli: Cli) -> Result<(), CliError> {
@@ -39,5 +42,6 @@ pub fn run(cli: Cli) -> Result<(), CliError> {
         Commands::Shell(args) => commands::shell::run(args),
         Commands::Live(args) => commands::live::run(args),
         Commands::Serve(args) => commands::serve::run(args),
+        Commands::Sid(args) => commands::sid::run(args),
     }
 }
@@ -1,4 +1,5 @@
 use clap::Args;
+use coreconf_model::JsonEncoding;
 use std::path::PathBuf;
 
 use crate::CliError;
@@ -21,6 +22,10 @@ pub struct ConvertArgs {
     /// Reverse: convert CBOR back to JSON instead
     #[arg(long, default_value_t = false)]
     pub reverse: bool,
+
+    /// With --reverse, emit int64/uint64/decimal64 values as JSON strings (RFC 7951)
+    #[arg(long, default_value_t = false)]
+    pub rfc7951: bool,
 }
 
 pub fn run(args: ConvertArgs) -> Result<(), CliError> {
@@ -28,8 +33,13 @@ pub fn run(args: ConvertArgs) -> Result<(), CliError> {
 
     if args.reverse {
         let cbor_data = std::fs::read(&args.input)?;
-        let json =
-            coreconf_model::decode_cbor_to_json(&model, &cbor_data).map_err(CliError::Model)?;
+        let encoding = if args.rfc7951 {
+            JsonEncoding::Rfc7951
+        } else {
+            JsonEncoding::Native
+        };
+        let json = coreconf_model::decode_cbor_to_json_with_encoding(&model, &cbor_data, encoding)
+            .map_err(CliError::Model)?;
 
         let value: serde_json::Value = serde_json::from_str(&json)?;
         let pretty = serde_json::to_string_pretty(&value)?;
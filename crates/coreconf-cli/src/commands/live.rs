@@ -1,7 +1,12 @@
 use clap::Args;
+use coreconf_runtime::commit::DEFAULT_CONFIRM_TIMEOUT;
+use coreconf_runtime::lock::DEFAULT_LOCK_TIMEOUT;
 use coreconf_runtime::transport::coap_lite::CoapLiteClient;
+use coreconf_runtime::{DatastoreName, JournalEntry};
 use rustyline::Editor;
 use rustyline::error::ReadlineError;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use crate::CliError;
 use crate::commands::shell::changes_to_text;
@@ -22,21 +27,38 @@ pub struct LiveArgs {
     /// CORECONF CoAP resource path
     #[arg(long, default_value = "c")]
     pub path: String,
+
+    /// NMDA datastore to read and edit (running, candidate, startup,
+    /// operational); defaults to the server's choice per request
+    #[arg(long, value_parser = parse_datastore)]
+    pub datastore: Option<DatastoreName>,
 }
 
 pub fn run(args: LiveArgs) -> Result<(), CliError> {
-    let model = crate::load_model(&args.sid)?;
-    let completer_model = model.clone();
+    let model = Arc::new(crate::load_model(&args.sid)?);
+    let completer_model = Arc::clone(&model);
 
-    let client = CoapLiteClient::connect(model.clone(), &args.server, &args.path)
+    let mut client = CoapLiteClient::connect(Arc::clone(&model), &args.server, &args.path)
         .map_err(CliError::Model)?;
+    client.set_datastore(args.datastore);
 
-    eprintln!("Connected to coap://{}/{}", args.server, args.path);
+    match args.datastore {
+        Some(datastore) => eprintln!(
+            "Connected to coap://{}/{} ({datastore} datastore)",
+            args.server, args.path
+        ),
+        None => eprintln!("Connected to coap://{}/{}", args.server, args.path),
+    }
 
     let mut session = LiveSession::empty(model, client);
+    // `lock` and `unlock` default to the datastore this session edits.
+    let lock_target = args
+        .datastore
+        .filter(|datastore| datastore.is_configuration())
+        .unwrap_or(DatastoreName::Running);
 
     eprintln!(
-        "Commands: discover [d=0], get <path>, set <path> <json-value>, delete <path>, push, reload, quit"
+        "Commands: discover [d=0], get <path>, set <path> <json-value>, delete <path>, push, commit, lock, revisions, reload, quit"
     );
     eprintln!("No startup GET was sent; run `discover d=0` or `reload` when needed.");
     eprintln!("Tab-complete: commands and model paths");
@@ -78,6 +100,22 @@ pub fn run(args: LiveArgs) -> Result<(), CliError> {
                         eprintln!("  delete <path>              stage a deletion");
                         eprintln!("  diff                       show staged changes");
                         eprintln!("  push                       send staged changes to server");
+                        eprintln!(
+                            "  commit [confirmed [secs]]  commit candidate into running on the server"
+                        );
+                        eprintln!(
+                            "  cancel-commit              roll back a pending confirmed commit"
+                        );
+                        eprintln!("  discard-changes            reset candidate to running");
+                        eprintln!("  revisions                  list the server's change journal");
+                        eprintln!(
+                            "  revision <n>               show the changes made by revision n"
+                        );
+                        eprintln!("  rollback <n>               roll running back to revision n");
+                        eprintln!(
+                            "  lock [datastore] [secs]    lock a datastore against other clients' edits"
+                        );
+                        eprintln!("  unlock [datastore]         release this session's lock");
                         eprintln!("  reload                     fetch fresh snapshot from server");
                         eprintln!("  help | ?                   show this help");
                         eprintln!("  quit | exit | q            disconnect");
@@ -144,6 +182,98 @@ pub fn run(args: LiveArgs) -> Result<(), CliError> {
                         Ok(())
                     })(),
 
+                    "commit" => (|| -> Result<(), CliError> {
+                        let confirm_timeout = match parts.next() {
+                            None => None,
+                            Some("confirmed") => Some(match parts.next() {
+                                Some(seconds) => parse_timeout(seconds, "confirm timeout")?,
+                                None => DEFAULT_CONFIRM_TIMEOUT,
+                            }),
+                            Some(_) => {
+                                return Err(CliError::InvalidInput(
+                                    "usage: commit [confirmed [seconds]]".into(),
+                                ));
+                            }
+                        };
+                        session.commit(confirm_timeout)?;
+                        match confirm_timeout {
+                            Some(timeout) => eprintln!(
+                                "committed; confirm with `commit` within {}s or it rolls back",
+                                timeout.as_secs()
+                            ),
+                            None => eprintln!("committed"),
+                        }
+                        Ok(())
+                    })(),
+
+                    "cancel-commit" => (|| -> Result<(), CliError> {
+                        session.cancel_commit()?;
+                        eprintln!("confirmed commit cancelled");
+                        Ok(())
+                    })(),
+
+                    "discard-changes" => (|| -> Result<(), CliError> {
+                        session.discard_changes()?;
+                        eprintln!("candidate reset to running");
+                        Ok(())
+                    })(),
+
+                    "revisions" => (|| -> Result<(), CliError> {
+                        let revisions = session.revisions()?;
+                        if revisions.is_empty() {
+                            eprintln!("(no journaled revisions)");
+                        }
+                        for entry in revisions {
+                            println!("{}", revision_summary(&entry));
+                        }
+                        Ok(())
+                    })(),
+
+                    "revision" => (|| -> Result<(), CliError> {
+                        let revision = parse_revision(parts.next(), "usage: revision <n>")?;
+                        for line in changes_to_text(
+                            &session.revision_changes(revision)?,
+                            Some(session.model()),
+                        ) {
+                            println!("{line}");
+                        }
+                        Ok(())
+                    })(),
+
+                    "rollback" => (|| -> Result<(), CliError> {
+                        let revision = parse_revision(parts.next(), "usage: rollback <n>")?;
+                        session.rollback_to(revision)?;
+                        eprintln!("rolled back to revision {revision}; run `reload` to refresh");
+                        Ok(())
+                    })(),
+
+                    "lock" => (|| -> Result<(), CliError> {
+                        let mut target = lock_target;
+                        let mut timeout = None;
+                        for argument in parts.by_ref() {
+                            match DatastoreName::parse(argument) {
+                                Some(datastore) => target = datastore,
+                                None => timeout = Some(parse_timeout(argument, "lock timeout")?),
+                            }
+                        }
+                        session.lock(target, timeout)?;
+                        eprintln!(
+                            "{target} locked for {}s; run `lock` again to renew it",
+                            timeout.unwrap_or(DEFAULT_LOCK_TIMEOUT).as_secs()
+                        );
+                        Ok(())
+                    })(),
+
+                    "unlock" => (|| -> Result<(), CliError> {
+                        let target = match parts.next() {
+                            Some(name) => parse_datastore(name).map_err(CliError::InvalidInput)?,
+                            None => lock_target,
+                        };
+                        session.unlock(target)?;
+                        eprintln!("{target} unlocked");
+                        Ok(())
+                    })(),
+
                     "reload" => (|| -> Result<(), CliError> {
                         session.reload()?;
                         eprintln!("reloaded from server");
@@ -174,6 +304,44 @@ pub fn run(args: LiveArgs) -> Result<(), CliError> {
     Ok(())
 }
 
+fn parse_datastore(value: &str) -> Result<DatastoreName, String> {
+    DatastoreName::parse(value).ok_or_else(|| format!("unknown datastore '{value}'"))
+}
+
+fn parse_timeout(value: &str, what: &str) -> Result<Duration, CliError> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|seconds| *seconds > 0)
+        .map(|seconds| Duration::from_secs(u64::from(seconds)))
+        .ok_or_else(|| {
+            CliError::InvalidInput(format!(
+                "{what} must be a positive number of seconds, got '{value}'"
+            ))
+        })
+}
+
+fn parse_revision(value: Option<&str>, usage: &str) -> Result<u64, CliError> {
+    required(value, usage)?
+        .parse()
+        .map_err(|_| CliError::InvalidInput(usage.to_string()))
+}
+
+/// One line per journal entry: revision, age, peer and changed paths.
+fn revision_summary(entry: &JournalEntry) -> String {
+    let age = SystemTime::now()
+        .duration_since(entry.timestamp)
+        .unwrap_or_default()
+        .as_secs();
+    format!(
+        "r{:<5} {:>6}s ago  {:<21}  {}",
+        entry.revision,
+        age,
+        entry.peer.as_deref().unwrap_or("-"),
+        entry.changed_paths.join(", ")
+    )
+}
+
 fn required<'a>(value: Option<&'a str>, message: &str) -> Result<&'a str, CliError> {
     value.ok_or_else(|| CliError::InvalidInput(message.to_string()))
 }
@@ -2,4 +2,5 @@ pub mod convert;
 pub mod live;
 pub mod serve;
 pub mod shell;
+pub mod sid;
 pub mod validate;
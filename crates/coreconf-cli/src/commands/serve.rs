@@ -3,20 +3,23 @@
 //! ```bash
 //! coreconf-cli serve --sid model.sid --data datastore.json
 //! coreconf-cli serve --sid model.sid --port 5683 --path c -v
+//! coreconf-cli serve --sid model.sid --data datastore.json --wal ./wal
+//! coreconf-cli serve --sid model.sid --bind 0.0.0.0 --allow-remote --nacm nacm.json
 //! ```
 
 use clap::Args;
 use std::io::{self, Write};
 use std::net::IpAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 use coap_lite::{CoapOption, MessageClass, Packet, ResponseType};
+use coreconf_model::CompositeModel;
 use coreconf_runtime::transport::coap_lite::CoapLiteServer;
-use coreconf_runtime::{Datastore, RequestHandler};
+use coreconf_runtime::{AccessControl, Backend, Datastore, RequestHandler, WalBackend};
 
 use crate::CliError;
 
@@ -31,6 +34,12 @@ pub struct ServeArgs {
     #[arg(long)]
     pub data: Option<PathBuf>,
 
+    /// Directory for a write-ahead log that makes every committed change
+    /// durable. An empty log is seeded from `--data`; an existing one is
+    /// recovered and `--data` is ignored.
+    #[arg(long)]
+    pub wal: Option<PathBuf>,
+
     /// UDP port to listen on
     #[arg(long, default_value = "5683")]
     pub port: u16,
@@ -43,6 +52,11 @@ pub struct ServeArgs {
     #[arg(long, default_value_t = false)]
     pub allow_remote: bool,
 
+    /// Access-control rules (JSON, laid out as the ietf-netconf-acm `nacm`
+    /// container) checked against each client's address.
+    #[arg(long)]
+    pub nacm: Option<PathBuf>,
+
     /// CORECONF resource path (URI segment before sub-paths)
     #[arg(long, default_value = "c")]
     pub path: String,
@@ -55,25 +69,38 @@ pub struct ServeArgs {
 pub fn run(args: ServeArgs) -> Result<(), CliError> {
     let model = crate::load_model(&args.sid)?;
 
-    let datastore = if let Some(ref data_path) = args.data {
+    let datastore = if let Some(ref wal_dir) = args.wal {
+        open_wal_datastore(model, wal_dir, args.data.as_deref())?
+    } else if let Some(ref data_path) = args.data {
         let json_str = std::fs::read_to_string(data_path).map_err(CliError::Io)?;
-        Datastore::from_json_with_model(model.clone(), &json_str).map_err(CliError::Model)?
+        Datastore::from_json_with_model(model, &json_str).map_err(CliError::Model)?
     } else {
-        Datastore::new_in_memory(model.clone())
+        Datastore::new_in_memory(model)
     };
 
     let bind_addr = bind_addr(&args.bind, args.port, args.allow_remote)?;
-    let handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).map_err(CliError::Model)?;
+    if let Some(ref nacm_path) = args.nacm {
+        handler
+            .set_access_control(load_access_control(nacm_path)?)
+            .map_err(CliError::Model)?;
+    }
     let mut server =
         CoapLiteServer::bind(&bind_addr, &args.path, handler).map_err(CliError::Model)?;
 
     eprintln!("CORECONF server listening on coap://{}", bind_addr);
-    if args.allow_remote && !is_loopback_host(&args.bind) {
+    if args.allow_remote && args.nacm.is_none() && !is_loopback_host(&args.bind) {
         eprintln!(
             "WARNING: --allow-remote exposes this demo server without authentication; use only on trusted networks."
         );
     }
     eprintln!("  Datastore resource: /{}", args.path);
+    if let Some(ref wal_dir) = args.wal {
+        eprintln!("  Write-ahead log:    {}", wal_dir.display());
+    }
+    if let Some(ref nacm_path) = args.nacm {
+        eprintln!("  Access control:     {}", nacm_path.display());
+    }
     if let Some(ref dp) = args.data {
         let out = dp.with_extension("modified.json");
         eprintln!("  Output on close:    {}", out.display());
@@ -106,6 +133,14 @@ pub fn run(args: ServeArgs) -> Result<(), CliError> {
     let mut buf = [0u8; 1500];
 
     while running.load(Ordering::SeqCst) {
+        match server.expire_confirmed_commit() {
+            Ok(true) => eprintln!("[commit] confirm timeout elapsed, running rolled back"),
+            Ok(false) => {}
+            Err(e) => {
+                let _ = writeln!(io::stderr(), "[error] confirmed-commit rollback: {e}");
+            }
+        }
+
         let (len, peer) = match socket.recv_from(&mut buf) {
             Ok(v) => v,
             Err(ref e)
@@ -191,7 +226,11 @@ pub fn run(args: ServeArgs) -> Result<(), CliError> {
 
     // ── Save modified datastore ────────────────────────────────────────────
     if let Some(ref data_path) = args.data {
-        let tree = server.handler().datastore().get_all();
+        let tree = server
+            .handler()
+            .datastore()
+            .get_all()
+            .map_err(CliError::Model)?;
         let json = serde_json::to_string_pretty(&tree).map_err(|e| CliError::Model(e.into()))?;
         let output = data_path.with_extension("modified.json");
         std::fs::write(&output, &json).map_err(CliError::Io)?;
@@ -202,6 +241,35 @@ pub fn run(args: ServeArgs) -> Result<(), CliError> {
     Ok(())
 }
 
+/// Open the write-ahead log in `wal_dir`, seeding a new log from `data`.
+fn open_wal_datastore(
+    model: CompositeModel,
+    wal_dir: &Path,
+    data: Option<&Path>,
+) -> Result<Datastore, CliError> {
+    let model = Arc::new(model);
+    let mut backend = WalBackend::open(Arc::clone(&model), wal_dir).map_err(CliError::Model)?;
+    if backend.sequence() > 0 {
+        eprintln!(
+            "Recovered datastore from {} (sequence {})",
+            wal_dir.display(),
+            backend.sequence()
+        );
+    } else if let Some(data_path) = data {
+        let json_str = std::fs::read_to_string(data_path).map_err(CliError::Io)?;
+        let tree = serde_json::from_str(&json_str).map_err(|e| CliError::Model(e.into()))?;
+        backend.replace_tree(tree).map_err(CliError::Model)?;
+    }
+    Ok(Datastore::with_backend(model, backend))
+}
+
+/// Read the access-control rules at `path`.
+fn load_access_control(path: &Path) -> Result<AccessControl, CliError> {
+    let json_str = std::fs::read_to_string(path).map_err(CliError::Io)?;
+    let config = serde_json::from_str(&json_str)?;
+    AccessControl::from_json(&config).map_err(CliError::Model)
+}
+
 fn bind_addr(bind: &str, port: u16, allow_remote: bool) -> Result<String, CliError> {
     if !allow_remote && !is_loopback_host(bind) {
         return Err(CliError::InvalidInput(format!(
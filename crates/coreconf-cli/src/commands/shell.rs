@@ -6,6 +6,7 @@ use rustyline::error::ReadlineError;
 use serde_json::Value;
 use std::collections::BTreeSet;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use crate::CliError;
 use crate::complete::CoreconfCompleter;
@@ -36,8 +37,8 @@ pub struct ShellArgs {
 }
 
 pub fn run(args: ShellArgs) -> Result<(), CliError> {
-    let model = crate::load_model(&args.sid)?;
-    let completer_model = model.clone();
+    let model = Arc::new(crate::load_model(&args.sid)?);
+    let completer_model = Arc::clone(&model);
     let backup_by_default = !args.no_backup;
 
     let mut session = if let Some(file_path) = &args.file {
@@ -52,7 +53,7 @@ pub fn run(args: ShellArgs) -> Result<(), CliError> {
 
     eprintln!("CORECONF interactive shell");
     eprintln!(
-        "Commands: get <path>, set <path> <json-value>, delete <path>, dump, diff, save, reload, quit"
+        "Commands: get <path>, find <query>, set <path> <json-value>, delete <path>, dump, diff, save, reload, quit"
     );
     eprintln!("Tab-complete: commands and model paths");
     eprintln!();
@@ -142,6 +143,7 @@ fn dispatch_command(
         "help" | "?" => {
             eprintln!("Commands:");
             eprintln!("  get <path>                     read a value");
+            eprintln!("  find <query>                   list nodes matching a query");
             eprintln!("  set <path> <json-value>        stage a change");
             eprintln!("  delete <path>                  stage a deletion");
             eprintln!("  dump                           show full datastore as JSON");
@@ -177,6 +179,23 @@ fn dispatch_command(
             Ok(ShellAction::Continue)
         }
 
+        "find" => {
+            // Predicates may contain spaces, so the query is the rest of the line.
+            let query = line[verb.len()..].trim();
+            let query = required(Some(query).filter(|q| !q.is_empty()), "usage: find <query>")?;
+            let matches = match session {
+                ShellSession::Memory(session) => session.find(query)?,
+                ShellSession::File(session) => session.find(query)?,
+            };
+            if matches.is_empty() {
+                eprintln!("(no matches)");
+            }
+            for (path, value) in matches {
+                println!("{path} = {}", compact_json(&value));
+            }
+            Ok(ShellAction::Continue)
+        }
+
         "set" => {
             let path = required(path, "usage: set <path> <json-value>")?;
             let raw_value = required(rest, "usage: set <path> <json-value>")?;
@@ -205,8 +224,8 @@ fn dispatch_command(
 
         "dump" => {
             let tree = match session {
-                ShellSession::Memory(session) => session.dump(),
-                ShellSession::File(session) => session.dump(),
+                ShellSession::Memory(session) => session.dump()?,
+                ShellSession::File(session) => session.dump()?,
             };
             println!("{}", serde_json::to_string_pretty(&tree)?);
             Ok(ShellAction::Continue)
@@ -257,7 +276,7 @@ fn dispatch_command(
         }
 
         _ => Err(invalid_input(
-            "unknown command; expected get, set, delete, dump, diff, save, reload, or quit",
+            "unknown command; expected get, find, set, delete, dump, diff, save, reload, or quit",
         )),
     }
 }
@@ -0,0 +1,51 @@
+use clap::{Args, Subcommand};
+use coreconf_model::CompactModel;
+use std::path::PathBuf;
+
+use crate::CliError;
+
+/// SID file utilities.
+#[derive(Args)]
+pub struct SidArgs {
+    #[command(subcommand)]
+    pub command: SidCommand,
+}
+
+#[derive(Subcommand)]
+pub enum SidCommand {
+    /// Compile .sid JSON files into a compact binary (CBOR) model
+    Compile(CompileArgs),
+}
+
+#[derive(Args)]
+pub struct CompileArgs {
+    /// Path(s) to .sid JSON files describing the YANG model
+    #[arg(long, required = true, num_args = 1..)]
+    pub sid: Vec<String>,
+
+    /// Path to write the compiled model
+    #[arg(long)]
+    pub output: PathBuf,
+}
+
+pub fn run(args: SidArgs) -> Result<(), CliError> {
+    match args.command {
+        SidCommand::Compile(args) => compile(args),
+    }
+}
+
+fn compile(args: CompileArgs) -> Result<(), CliError> {
+    let model = crate::load_model(&args.sid)?;
+    let compact = CompactModel::from_composite(&model);
+    let bytes = compact.to_bytes()?;
+    std::fs::write(&args.output, &bytes)?;
+
+    eprintln!(
+        "Compiled {} module(s), {} SID entries → {} ({} bytes)",
+        compact.modules().count(),
+        compact.len(),
+        args.output.display(),
+        bytes.len()
+    );
+    Ok(())
+}
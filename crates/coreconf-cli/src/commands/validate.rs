@@ -17,7 +17,7 @@ pub struct ValidateArgs {
 
 pub fn run(args: ValidateArgs) -> Result<(), CliError> {
     let model = crate::load_model(&args.sid)?;
-    let sid_count = model.sids.len();
+    let sid_count = model.len();
 
     eprintln!(
         "Model loaded: {} SID entries across {} file(s)",
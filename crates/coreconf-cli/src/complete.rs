@@ -11,16 +11,19 @@ use rustyline::hint::Hinter;
 use rustyline::validate::Validator;
 use rustyline::{Context, Helper};
 
+use std::sync::Arc;
+
 use coreconf_model::CompositeModel;
 
 /// A rustyline `Helper` that autocompletes CORECONF commands and paths.
 pub struct CoreconfCompleter {
-    pub model: CompositeModel,
+    pub model: Arc<CompositeModel>,
 }
 
 impl CoreconfCompleter {
     const COMMANDS: &[&str] = &[
-        "get", "set", "delete", "dump", "diff", "save", "reload", "push", "quit", "exit", "help",
+        "get", "find", "set", "delete", "dump", "diff", "save", "reload", "push", "quit", "exit",
+        "help",
     ];
 
     /// Return every model identifier whose string representation
@@ -34,10 +37,10 @@ impl CoreconfCompleter {
 
         let mut matches: Vec<String> = self
             .model
-            .sids
-            .keys()
+            .iter()
+            .map(|(_, path)| path)
             .filter(|path| path.to_lowercase().starts_with(&prefix_lower))
-            .cloned()
+            .map(str::to_string)
             .collect();
 
         matches.sort_by_key(|m| m.len());
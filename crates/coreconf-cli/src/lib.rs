@@ -7,24 +7,52 @@ pub mod commands;
 pub mod complete;
 pub mod session;
 
-use coreconf_model::{CompositeModel, CoreconfError, SidFile};
+use coreconf_model::{CompactModel, CompositeModel, CoreconfError, SidFile};
 
 /// Load a [`CompositeModel`] from one or more SID file paths.
 ///
 /// Each path is read and parsed as a `.sid` JSON file, then merged into
-/// a single composite model that spans all loaded modules.
+/// a single composite model that spans all loaded modules. Paths may also
+/// point at models compiled with `coreconf-cli sid compile`. A single
+/// compiled model backs the returned model as is, without parsing JSON or
+/// building lookup maps; several paths involving one are merged and
+/// compacted again.
 pub fn load_model(sid_paths: &[String]) -> Result<CompositeModel, CliError> {
     if sid_paths.is_empty() {
         return Err(CliError::NoSidFiles);
     }
 
     let mut sid_files = Vec::with_capacity(sid_paths.len());
+    let mut any_compiled = false;
     for path in sid_paths {
-        let sid_file = SidFile::from_file(path).map_err(|e| CliError::SidLoad(path.clone(), e))?;
+        let bytes = std::fs::read(path).map_err(|e| CliError::SidLoad(path.clone(), e.into()))?;
+        if CompactModel::is_compiled(&bytes) {
+            let compact =
+                CompactModel::from_bytes(&bytes).map_err(|e| CliError::SidLoad(path.clone(), e))?;
+            if sid_paths.len() == 1 {
+                return Ok(CompositeModel::from_compact(compact));
+            }
+            let expanded = CompositeModel::from_compact(compact)
+                .into_sid_files()
+                .map_err(|e| CliError::SidLoad(path.clone(), e))?;
+            sid_files.extend(expanded);
+            any_compiled = true;
+            continue;
+        }
+        let sid_file = String::from_utf8(bytes)
+            .map_err(|e| CoreconfError::InvalidSidFile(e.to_string()))
+            .and_then(|content| SidFile::from_json_str(&content))
+            .map_err(|e| CliError::SidLoad(path.clone(), e))?;
         sid_files.push(sid_file);
     }
 
-    CompositeModel::from_sid_files(sid_files).map_err(CliError::Model)
+    let model = CompositeModel::from_sid_files(sid_files).map_err(CliError::Model)?;
+    if any_compiled {
+        return Ok(CompositeModel::from_compact(CompactModel::from_composite(
+            &model,
+        )));
+    }
+    Ok(model)
 }
 
 #[derive(Debug, thiserror::Error)]
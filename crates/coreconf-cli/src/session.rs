@@ -1,10 +1,13 @@
 use coreconf_model::{CompositeModel, CoreconfError};
 use coreconf_runtime::transport::coap_lite::CoreconfClient;
 use coreconf_runtime::{
-    Backend, Datastore, EditableFormat, FileBackend, encode_editable_value, read_editable_file,
+    Backend, Datastore, DatastoreName, Diff, EditableFormat, FileBackend, JournalEntry,
+    encode_editable_value, read_editable_file,
 };
 use serde_json::Value;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::CliError;
 
@@ -29,7 +32,7 @@ impl Default for SaveOptions {
 }
 
 pub struct FileSession {
-    model: CompositeModel,
+    model: Arc<CompositeModel>,
     backend: FileBackend,
     base_snapshot: Value,
     backup_created: bool,
@@ -44,12 +47,14 @@ pub struct StagedChange {
 
 impl FileSession {
     pub fn open(
-        model: CompositeModel,
+        model: impl Into<Arc<CompositeModel>>,
         path: impl Into<PathBuf>,
         format: EditableFormat,
     ) -> Result<Self, CliError> {
-        let backend = FileBackend::open(model.clone(), path, format).map_err(CliError::Model)?;
-        let base_snapshot = backend.read_tree();
+        let model = model.into();
+        let backend =
+            FileBackend::open(Arc::clone(&model), path, format).map_err(CliError::Model)?;
+        let base_snapshot = backend.read_tree().map_err(CliError::Model)?;
         Ok(Self {
             model,
             backend,
@@ -74,6 +79,10 @@ impl FileSession {
         self.with_datastore(|datastore| datastore.get_path(path))
     }
 
+    pub fn find(&self, query: &str) -> Result<Vec<(String, Value)>, CliError> {
+        self.with_datastore(|datastore| datastore.query(query))
+    }
+
     pub fn set(&mut self, path: &str, value: Value) -> Result<(), CliError> {
         self.with_datastore_mut(|datastore| datastore.set_path(path, value))
     }
@@ -82,24 +91,22 @@ impl FileSession {
         self.with_datastore_mut(|datastore| datastore.delete_path(path))
     }
 
-    pub fn dump(&self) -> Value {
-        self.backend.read_tree()
+    pub fn dump(&self) -> Result<Value, CliError> {
+        self.backend.read_tree().map_err(CliError::Model)
     }
 
     pub fn pending_patch(&self) -> Result<Vec<(String, Option<Value>)>, CliError> {
-        diff_trees(&self.base_snapshot, &self.backend.read_tree()).map_err(CliError::Model)
+        Ok(self.pending_diff()?.patch())
     }
 
     pub fn staged_changes(&self) -> Result<Vec<StagedChange>, CliError> {
-        Ok(self
-            .pending_patch()?
-            .into_iter()
-            .map(|(path, after)| StagedChange {
-                before: value_at_path(&self.base_snapshot, &path).cloned(),
-                after,
-                path,
-            })
-            .collect())
+        Ok(staged_changes(self.pending_diff()?))
+    }
+
+    fn pending_diff(&self) -> Result<Diff, CliError> {
+        self.with_datastore(|datastore| {
+            snapshot_datastore(&self.model, &self.base_snapshot).diff(datastore)
+        })
     }
 
     pub fn is_dirty(&self) -> Result<bool, CliError> {
@@ -123,22 +130,19 @@ impl FileSession {
             self.backup_created = true;
         }
         self.backend.save().map_err(CliError::Model)?;
-        self.base_snapshot = self.backend.read_tree();
+        self.base_snapshot = self.backend.read_tree().map_err(CliError::Model)?;
         Ok(())
     }
 
     pub fn encoded_working_copy(&self) -> Result<Vec<u8>, CliError> {
-        encode_editable_value(
-            &self.model,
-            &self.backend.read_tree(),
-            self.backend.format(),
-        )
-        .map_err(CliError::Model)
+        let tree = self.backend.read_tree().map_err(CliError::Model)?;
+        encode_editable_value(&self.model, &tree, self.backend.format()).map_err(CliError::Model)
     }
 
     fn validate_save(&self, _force: bool) -> Result<(), CliError> {
+        let tree = self.backend.read_tree().map_err(CliError::Model)?;
         self.model
-            .identifier_value_to_sid_value(self.backend.read_tree())
+            .identifier_value_to_sid_value(tree)
             .map(|_| ())
             .map_err(CliError::Model)
     }
@@ -147,7 +151,7 @@ impl FileSession {
         &self,
         f: impl FnOnce(&Datastore) -> coreconf_model::Result<T>,
     ) -> Result<T, CliError> {
-        let datastore = Datastore::with_backend(self.model.clone(), self.backend.clone());
+        let datastore = Datastore::with_backend(Arc::clone(&self.model), self.backend.clone());
         f(&datastore).map_err(CliError::Model)
     }
 
@@ -155,25 +159,24 @@ impl FileSession {
         &mut self,
         f: impl FnOnce(&mut Datastore) -> coreconf_model::Result<T>,
     ) -> Result<T, CliError> {
-        let mut datastore = Datastore::with_backend(self.model.clone(), self.backend.clone());
+        let mut datastore = Datastore::with_backend(Arc::clone(&self.model), self.backend.clone());
         let result = f(&mut datastore).map_err(CliError::Model)?;
-        self.backend
-            .replace_tree(datastore.get_all())
-            .map_err(CliError::Model)?;
+        let tree = datastore.get_all().map_err(CliError::Model)?;
+        self.backend.replace_tree(tree).map_err(CliError::Model)?;
         Ok(result)
     }
 }
 
 impl Session {
     /// Create a new session with an empty datastore.
-    pub fn new(model: CompositeModel) -> Self {
+    pub fn new(model: impl Into<Arc<CompositeModel>>) -> Self {
         Self {
             datastore: Datastore::new_in_memory(model),
         }
     }
 
     /// Create a session pre-loaded with JSON data.
-    pub fn with_json(model: CompositeModel, json: &str) -> Result<Self, CliError> {
+    pub fn with_json(model: impl Into<Arc<CompositeModel>>, json: &str) -> Result<Self, CliError> {
         let data: Value = serde_json::from_str(json)?;
         Ok(Self {
             datastore: Datastore::with_backend(model, coreconf_runtime::MemoryBackend::new(data)),
@@ -185,6 +188,11 @@ impl Session {
         self.datastore.get_path(path).map_err(CliError::Model)
     }
 
+    /// List the nodes matching a query, with their concrete paths.
+    pub fn find(&self, query: &str) -> Result<Vec<(String, Value)>, CliError> {
+        self.datastore.query(query).map_err(CliError::Model)
+    }
+
     /// Set a value at the given predicate path.
     pub fn set(&mut self, path: &str, value: Value) -> Result<(), CliError> {
         self.datastore
@@ -198,8 +206,8 @@ impl Session {
     }
 
     /// Export the full datastore tree as JSON.
-    pub fn dump(&self) -> Value {
-        self.datastore.get_all()
+    pub fn dump(&self) -> Result<Value, CliError> {
+        self.datastore.get_all().map_err(CliError::Model)
     }
 
     /// Apply a batch of changes: set if value is `Some`, delete if `None`.
@@ -218,21 +226,25 @@ impl Session {
 /// A live session stages local edits against a remote CORECONF snapshot.
 pub struct LiveSession<C> {
     client: C,
-    model: CompositeModel,
+    model: Arc<CompositeModel>,
     base_snapshot: Value,
+    /// Entity-tag of the remote datastore that `base_snapshot` reflects.
+    etag: Option<Vec<u8>>,
     working_copy: Datastore,
 }
 
 impl<C: CoreconfClient> LiveSession<C> {
     /// Fetch the current remote snapshot and start a staged working copy.
-    pub fn new(model: CompositeModel, mut client: C) -> Result<Self, CliError> {
-        let snapshot = client.fetch_snapshot().map_err(CliError::Model)?;
-        Ok(Self::from_snapshot(model, client, snapshot))
+    pub fn new(model: impl Into<Arc<CompositeModel>>, mut client: C) -> Result<Self, CliError> {
+        let (snapshot, etag) = client.fetch_tagged_snapshot().map_err(CliError::Model)?;
+        let mut session = Self::from_snapshot(model, client, snapshot);
+        session.etag = etag;
+        Ok(session)
     }
 
     /// Start a live session without issuing a startup GET. The working copy is
     /// empty until the operator explicitly runs `reload` or stages edits.
-    pub fn empty(model: CompositeModel, client: C) -> Self {
+    pub fn empty(model: impl Into<Arc<CompositeModel>>, client: C) -> Self {
         Self::from_snapshot(model, client, Value::Object(Default::default()))
     }
 
@@ -242,15 +254,21 @@ impl<C: CoreconfClient> LiveSession<C> {
     }
 
     /// Start a live session from an already-fetched snapshot.
-    pub fn from_snapshot(model: CompositeModel, client: C, snapshot: Value) -> Self {
+    pub fn from_snapshot(
+        model: impl Into<Arc<CompositeModel>>,
+        client: C,
+        snapshot: Value,
+    ) -> Self {
+        let model = model.into();
         let working_copy = Datastore::with_backend(
-            model.clone(),
+            Arc::clone(&model),
             coreconf_runtime::MemoryBackend::new(snapshot.clone()),
         );
         Self {
             client,
             model,
             base_snapshot: snapshot,
+            etag: None,
             working_copy,
         }
     }
@@ -264,16 +282,7 @@ impl<C: CoreconfClient> LiveSession<C> {
     }
 
     pub fn staged_changes(&self) -> Result<Vec<StagedChange>, CliError> {
-        let tree = self.working_copy.get_all();
-        let patch = diff_trees(&self.base_snapshot, &tree).map_err(CliError::Model)?;
-        Ok(patch
-            .into_iter()
-            .map(|(path, after)| StagedChange {
-                before: value_at_path(&self.base_snapshot, &path).cloned(),
-                after,
-                path,
-            })
-            .collect())
+        Ok(staged_changes(self.pending_diff()?))
     }
 
     pub fn set(&mut self, path: &str, value: Value) -> Result<(), CliError> {
@@ -302,100 +311,166 @@ impl<C: CoreconfClient> LiveSession<C> {
         };
 
         let mut base = Datastore::with_backend(
-            self.model.clone(),
+            Arc::clone(&self.model),
             coreconf_runtime::MemoryBackend::new(self.base_snapshot.clone()),
         );
         base.set_path(path, remote_value.clone())
             .map_err(CliError::Model)?;
-        self.base_snapshot = base.get_all();
+        self.base_snapshot = base.get_all().map_err(CliError::Model)?;
         self.working_copy
             .set_path(path, remote_value)
             .map_err(CliError::Model)
     }
 
     pub fn pending_patch(&self) -> Result<Vec<(String, Option<Value>)>, CliError> {
-        diff_trees(&self.base_snapshot, &self.working_copy.get_all()).map_err(CliError::Model)
+        Ok(self.pending_diff()?.patch())
     }
 
-    /// Push staged changes to the remote server.
+    fn pending_diff(&self) -> Result<Diff, CliError> {
+        snapshot_datastore(&self.model, &self.base_snapshot)
+            .diff(&self.working_copy)
+            .map_err(CliError::Model)
+    }
+
+    /// Push staged changes to the remote server as one root iPATCH.
+    ///
+    /// The iPATCH carries the entity-tag the session loaded with as
+    /// If-Match, so the server refuses it with 4.12 if the datastore changed
+    /// since. A session without one compares the remote snapshot first.
     pub fn push(&mut self) -> Result<(), CliError> {
-        let remote_snapshot = self.client.fetch_snapshot().map_err(CliError::Model)?;
-        if remote_snapshot != self.base_snapshot {
-            return Err(CliError::Model(CoreconfError::ValidationError(
-                "remote datastore changed since this live session was loaded".into(),
-            )));
-        }
+        let etag = match &self.etag {
+            Some(etag) => Some(etag.clone()),
+            None => {
+                let (remote_snapshot, etag) = self
+                    .client
+                    .fetch_tagged_snapshot()
+                    .map_err(CliError::Model)?;
+                if remote_snapshot != self.base_snapshot {
+                    return Err(CliError::Model(CoreconfError::ValidationError(
+                        "remote datastore changed since this live session was loaded".into(),
+                    )));
+                }
+                etag
+            }
+        };
 
-        let patch = self.pending_patch()?;
-        self.client.apply_patch(&patch).map_err(CliError::Model)?;
-        self.base_snapshot = self.working_copy.get_all();
+        let diff = self.pending_diff()?;
+        if diff.is_empty() {
+            self.etag = etag;
+        } else {
+            let payload = self
+                .working_copy
+                .encode_diff(&diff)
+                .map_err(CliError::Model)?;
+            self.etag = match etag {
+                Some(etag) => self
+                    .client
+                    .apply_instances_if_match(&payload, &etag)
+                    .map_err(CliError::Model)?,
+                None => {
+                    self.client
+                        .apply_instances(&payload)
+                        .map_err(CliError::Model)?;
+                    None
+                }
+            };
+        }
+        self.base_snapshot = self.working_copy.get_all().map_err(CliError::Model)?;
         Ok(())
     }
 
+    /// Commit the server's candidate datastore into running, as a confirmed
+    /// commit when `confirm_timeout` is given.
+    pub fn commit(&mut self, confirm_timeout: Option<Duration>) -> Result<(), CliError> {
+        self.client.commit(confirm_timeout).map_err(CliError::Model)
+    }
+
+    /// Roll back the server's pending confirmed commit.
+    pub fn cancel_commit(&mut self) -> Result<(), CliError> {
+        self.client.cancel_commit().map_err(CliError::Model)
+    }
+
+    /// Reset the server's candidate datastore to running.
+    pub fn discard_changes(&mut self) -> Result<(), CliError> {
+        self.client.discard_changes().map_err(CliError::Model)
+    }
+
+    /// The server's journal entries, newest first.
+    pub fn revisions(&mut self) -> Result<Vec<JournalEntry>, CliError> {
+        self.client.journal_revisions().map_err(CliError::Model)
+    }
+
+    /// The edits journal `revision` made on the server.
+    pub fn revision_changes(&mut self, revision: u64) -> Result<Vec<StagedChange>, CliError> {
+        let entry = self
+            .client
+            .journal_revision(revision)
+            .map_err(CliError::Model)?;
+        // The inverse patch undoes the edits in reverse order, so read it
+        // backwards to pair each edit with the value it replaced.
+        Ok(entry
+            .patch
+            .into_iter()
+            .zip(entry.inverse_patch.into_iter().rev())
+            .map(|((path, after), (_, before))| StagedChange {
+                path,
+                before,
+                after,
+            })
+            .collect())
+    }
+
+    /// Roll the server's running datastore back to journal `revision`.
+    pub fn rollback_to(&mut self, revision: u64) -> Result<(), CliError> {
+        self.client.rollback_to(revision).map_err(CliError::Model)
+    }
+
+    /// Lock `target` on the server so only this session can edit it.
+    pub fn lock(
+        &mut self,
+        target: DatastoreName,
+        timeout: Option<Duration>,
+    ) -> Result<(), CliError> {
+        self.client.lock(target, timeout).map_err(CliError::Model)
+    }
+
+    /// Release this session's lock on `target`.
+    pub fn unlock(&mut self, target: DatastoreName) -> Result<(), CliError> {
+        self.client.unlock(target).map_err(CliError::Model)
+    }
+
     /// Reload the working copy from the remote server.
     pub fn reload(&mut self) -> Result<(), CliError> {
-        let snapshot = self.client.fetch_snapshot().map_err(CliError::Model)?;
+        let (snapshot, etag) = self
+            .client
+            .fetch_tagged_snapshot()
+            .map_err(CliError::Model)?;
         self.base_snapshot = snapshot.clone();
+        self.etag = etag;
         self.working_copy = Datastore::with_backend(
-            self.model.clone(),
+            Arc::clone(&self.model),
             coreconf_runtime::MemoryBackend::new(snapshot),
         );
         Ok(())
     }
 }
 
-pub fn diff_trees(
-    before: &Value,
-    after: &Value,
-) -> Result<Vec<(String, Option<Value>)>, CoreconfError> {
-    let mut patch = Vec::new();
-    diff_value(String::new(), before, after, &mut patch);
-    patch.sort_by(|left, right| left.0.cmp(&right.0));
-    Ok(patch)
+fn snapshot_datastore(model: &Arc<CompositeModel>, snapshot: &Value) -> Datastore {
+    Datastore::with_backend(
+        Arc::clone(model),
+        coreconf_runtime::MemoryBackend::new(snapshot.clone()),
+    )
 }
-fn diff_value(
-    path: String,
-    before: &Value,
-    after: &Value,
-    patch: &mut Vec<(String, Option<Value>)>,
-) {
-    match (before, after) {
-        (Value::Object(before_map), Value::Object(after_map)) => {
-            for (key, before_value) in before_map {
-                let child_path = join_json_path(&path, key);
-                match after_map.get(key) {
-                    Some(after_value) => diff_value(child_path, before_value, after_value, patch),
-                    None => patch.push((child_path, None)),
-                }
-            }
 
-            for (key, after_value) in after_map {
-                if !before_map.contains_key(key) {
-                    patch.push((join_json_path(&path, key), Some(after_value.clone())));
-                }
-            }
-        }
-        _ if before != after => {
-            patch.push((normalize_root_path(&path), Some(after.clone())));
-        }
-        _ => {}
-    }
-}
-
-fn join_json_path(parent: &str, key: &str) -> String {
-    if parent.is_empty() || parent == "/" {
-        format!("/{key}")
-    } else {
-        format!("{parent}/{key}")
-    }
-}
-
-fn normalize_root_path(path: &str) -> String {
-    if path.is_empty() {
-        "/".to_string()
-    } else {
-        path.to_string()
-    }
+fn staged_changes(diff: Diff) -> Vec<StagedChange> {
+    diff.edits
+        .into_iter()
+        .map(|edit| StagedChange {
+            path: edit.path,
+            before: edit.before,
+            after: edit.after,
+        })
+        .collect()
 }
 
 pub fn backup_path(path: &Path) -> PathBuf {
@@ -403,14 +478,3 @@ pub fn backup_path(path: &Path) -> PathBuf {
     backup.push(".bak");
     PathBuf::from(backup)
 }
-
-fn value_at_path<'a>(tree: &'a Value, path: &str) -> Option<&'a Value> {
-    let mut current = tree;
-    for segment in path.trim_matches('/').split('/') {
-        if segment.is_empty() {
-            continue;
-        }
-        current = current.as_object()?.get(segment)?;
-    }
-    Some(current)
-}
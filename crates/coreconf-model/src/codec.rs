@@ -1,5 +1,6 @@
 use crate::composite_model::CompositeModel;
 use crate::error::{CoreconfError, Result};
+use crate::types::JsonEncoding;
 
 fn float_to_decimal64(f: f64) -> Option<(i64, i64)> {
     let s = format!("{:.18}", f);
@@ -118,8 +119,16 @@ pub fn encode_json_to_cbor(model: &CompositeModel, json_data: &str) -> Result<Ve
 }
 
 pub fn decode_cbor_to_json(model: &CompositeModel, bytes: &[u8]) -> Result<String> {
+    decode_cbor_to_json_with_encoding(model, bytes, JsonEncoding::Native)
+}
+
+pub fn decode_cbor_to_json_with_encoding(
+    model: &CompositeModel,
+    bytes: &[u8],
+    encoding: JsonEncoding,
+) -> Result<String> {
     let value = cbor_to_json_value(bytes)?;
-    let json_value = model.sid_value_to_identifier_value(value)?;
+    let json_value = model.sid_value_to_identifier_value_with_encoding(value, encoding)?;
     Ok(serde_json::to_string(&json_value)?)
 }
 
@@ -130,20 +139,45 @@ pub fn cbor_to_json_value(bytes: &[u8]) -> Result<serde_json::Value> {
     ciborium_value_to_serde(ciborium_val)
 }
 
+/// CBOR tag 2: unsigned bignum (RFC 8949 section 3.4.3).
+const BIGNUM_POSITIVE: u64 = 2;
+/// CBOR tag 3: negative bignum, encoding `-1 - n`.
+const BIGNUM_NEGATIVE: u64 = 3;
+
+/// Maps an integer onto a JSON number when it fits `i64` or `u64`, and onto
+/// its decimal string otherwise, so out-of-range values survive decoding
+/// instead of being rejected or rounded.
+fn integer_to_serde(num: i128) -> serde_json::Value {
+    if let Ok(n) = i64::try_from(num) {
+        serde_json::Value::Number(n.into())
+    } else if let Ok(n) = u64::try_from(num) {
+        serde_json::Value::Number(n.into())
+    } else {
+        serde_json::Value::String(num.to_string())
+    }
+}
+
+fn bignum_magnitude(bytes: &[u8]) -> Result<u128> {
+    let significant = bytes
+        .iter()
+        .position(|&byte| byte != 0)
+        .map_or(&[][..], |start| &bytes[start..]);
+    if significant.len() > 16 {
+        return Err(CoreconfError::TypeConversion(format!(
+            "bignum of {} bytes exceeds 128 bits",
+            significant.len()
+        )));
+    }
+    Ok(significant
+        .iter()
+        .fold(0u128, |acc, &byte| (acc << 8) | u128::from(byte)))
+}
+
 pub fn ciborium_value_to_serde(val: ciborium::value::Value) -> Result<serde_json::Value> {
     match val {
         ciborium::value::Value::Null => Ok(serde_json::Value::Null),
         ciborium::value::Value::Bool(b) => Ok(serde_json::Value::Bool(b)),
-        ciborium::value::Value::Integer(i) => {
-            let num: i128 = i.into();
-            if let Ok(n) = i64::try_from(num) {
-                Ok(serde_json::Value::Number(n.into()))
-            } else if let Ok(n) = u64::try_from(num) {
-                Ok(serde_json::Value::Number(n.into()))
-            } else {
-                Err(CoreconfError::TypeConversion("integer overflow".into()))
-            }
-        }
+        ciborium::value::Value::Integer(i) => Ok(integer_to_serde(i.into())),
         ciborium::value::Value::Float(f) => {
             if let Some(n) = serde_json::Number::from_f64(f) {
                 Ok(serde_json::Value::Number(n))
@@ -186,9 +220,74 @@ pub fn ciborium_value_to_serde(val: ciborium::value::Value) -> Result<serde_json
             }
             Ok(serde_json::Value::Object(serde_map))
         }
+        ciborium::value::Value::Tag(tag @ (BIGNUM_POSITIVE | BIGNUM_NEGATIVE), boxed_val) => {
+            let ciborium::value::Value::Bytes(bytes) = *boxed_val else {
+                return Err(CoreconfError::TypeConversion(format!(
+                    "bignum tag {tag} must wrap a byte string"
+                )));
+            };
+            let magnitude = bignum_magnitude(&bytes)?;
+            if tag == BIGNUM_POSITIVE {
+                return Ok(match i128::try_from(magnitude) {
+                    Ok(num) => integer_to_serde(num),
+                    Err(_) => serde_json::Value::String(magnitude.to_string()),
+                });
+            }
+            match i128::try_from(magnitude) {
+                Ok(num) => Ok(integer_to_serde(-1 - num)),
+                Err(_) => magnitude
+                    .checked_add(1)
+                    .map(|negated| serde_json::Value::String(format!("-{negated}")))
+                    .ok_or_else(|| {
+                        CoreconfError::TypeConversion("negative bignum exceeds 128 bits".into())
+                    }),
+            }
+        }
         ciborium::value::Value::Tag(_, boxed_val) => ciborium_value_to_serde(*boxed_val),
         _ => Err(CoreconfError::TypeConversion(
             "unsupported CBOR type".into(),
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn bignum(tag: u64, bytes: &[u8]) -> ciborium::value::Value {
+        ciborium::value::Value::Tag(tag, Box::new(ciborium::value::Value::Bytes(bytes.to_vec())))
+    }
+
+    #[test]
+    fn test_bignum_tags_decode_to_integers() {
+        let small = ciborium_value_to_serde(bignum(2, &[0x01, 0x00])).unwrap();
+        assert_eq!(small, json!(256));
+
+        let max_u64 = ciborium_value_to_serde(bignum(2, &[0xff; 8])).unwrap();
+        assert_eq!(max_u64, json!(u64::MAX));
+
+        let negative = ciborium_value_to_serde(bignum(3, &[0x00, 0xff])).unwrap();
+        assert_eq!(negative, json!(-256));
+    }
+
+    #[test]
+    fn test_out_of_range_integers_decode_as_decimal_strings() {
+        let wide = ciborium_value_to_serde(bignum(2, &[0x01, 0, 0, 0, 0, 0, 0, 0, 0])).unwrap();
+        assert_eq!(wide, json!("18446744073709551616"));
+
+        let below_i64 = ciborium::value::Value::Integer((-(1i128 << 64)).try_into().unwrap());
+        assert_eq!(
+            ciborium_value_to_serde(below_i64).unwrap(),
+            json!("-18446744073709551616")
+        );
+    }
+
+    #[test]
+    fn test_bignum_rejects_non_byte_content() {
+        let tagged =
+            ciborium::value::Value::Tag(2, Box::new(ciborium::value::Value::Text("1".into())));
+        assert!(ciborium_value_to_serde(tagged).is_err());
+    }
+}
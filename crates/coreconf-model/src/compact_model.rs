@@ -0,0 +1,644 @@
+//! Compiled, memory-lean SID model.
+//!
+//! [`CompactModel`] holds the same lookup data as [`CompositeModel`] but stores
+//! every identifier once in a single interned string buffer and keeps SIDs in
+//! sorted arrays searched with binary search, instead of four `HashMap`s of
+//! owned `String`s. It is loaded from a binary CBOR image produced by
+//! `coreconf-cli sid compile`, so startup needs no JSON parsing.
+//!
+//! The image is a CBOR self-described (tag 55799) map:
+//!
+//! ```text
+//! { "version": 1,
+//!   "strings": [tstr, ...],                       ; interned identifiers/names
+//!   "modules": [[name-idx, revision-idx], ...],
+//!   "items":   [[sid, identifier-idx, module-idx, type-idx / null], ...], ; sorted by SID
+//!   "types":   [sid-file type metadata, ...],
+//!   "keys":    [[list-sid, [key-sid, ...]], ...],
+//!   "ordered": [list-sid, ...] }                  ; ordered-by user, may be absent
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::composite_model::CompositeModel;
+use crate::error::{CoreconfError, Result};
+use crate::sid_file::SidFile;
+use crate::types::YangType;
+
+/// Current version of the compiled image layout.
+pub const COMPACT_MODEL_VERSION: u32 = 1;
+
+/// CBOR self-described prefix (tag 55799) that starts every compiled image.
+const SELF_DESCRIBED_CBOR: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+const NO_TYPE: u32 = u32::MAX;
+
+#[derive(Serialize, Deserialize)]
+struct CompiledImage {
+    version: u32,
+    strings: Vec<String>,
+    modules: Vec<(u32, u32)>,
+    items: Vec<(i64, u32, u32, Option<u32>)>,
+    types: Vec<Value>,
+    keys: Vec<(i64, Vec<i64>)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ordered: Vec<i64>,
+}
+
+/// Identifiers stored back to back in one buffer, addressed by index.
+#[derive(Debug, Clone, Default)]
+struct InternedStrings {
+    buffer: String,
+    spans: Vec<(u32, u32)>,
+}
+
+impl InternedStrings {
+    fn push(&mut self, value: &str) -> u32 {
+        let start = self.buffer.len() as u32;
+        self.buffer.push_str(value);
+        self.spans.push((start, value.len() as u32));
+        (self.spans.len() - 1) as u32
+    }
+
+    fn get(&self, index: u32) -> &str {
+        let (start, len) = self.spans[index as usize];
+        &self.buffer[start as usize..(start + len) as usize]
+    }
+
+    fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.buffer.shrink_to_fit();
+        self.spans.shrink_to_fit();
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CompactEntry {
+    sid: i64,
+    identifier: u32,
+    module: u32,
+    yang_type: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CompactModule {
+    name: u32,
+    revision: u32,
+}
+
+/// Read-only SID model with interned strings and sorted lookup tables.
+#[derive(Debug, Clone, Default)]
+pub struct CompactModel {
+    strings: InternedStrings,
+    modules: Vec<CompactModule>,
+    /// Sorted by SID.
+    entries: Vec<CompactEntry>,
+    /// Entry indices sorted by identifier.
+    by_identifier: Vec<u32>,
+    types: Vec<YangType>,
+    /// `(list SID, start, len)` into `key_sids`, sorted by list SID.
+    key_lists: Vec<(i64, u32, u32)>,
+    key_sids: Vec<i64>,
+    /// `ordered-by user` list SIDs, sorted.
+    ordered: Vec<i64>,
+}
+
+impl CompactModel {
+    /// Builds a compact model from an already merged composite model.
+    pub fn from_composite(model: &CompositeModel) -> Self {
+        if let Some(compact) = model.compact() {
+            return compact.clone();
+        }
+        let mut strings = InternedStrings::default();
+        let mut interned = HashMap::new();
+        let mut intern = |strings: &mut InternedStrings, value: &str| -> u32 {
+            *interned
+                .entry(value.to_string())
+                .or_insert_with(|| strings.push(value))
+        };
+
+        let mut types: Vec<YangType> = Vec::new();
+        let mut modules = Vec::with_capacity(model.sid_files.len());
+        let mut entries = Vec::with_capacity(model.ids.len());
+        let mut seen = std::collections::HashSet::new();
+        for (module_index, sid_file) in model.sid_files.iter().enumerate() {
+            modules.push(CompactModule {
+                name: intern(&mut strings, &sid_file.module_name),
+                revision: intern(&mut strings, &sid_file.module_revision),
+            });
+            let mut sids: Vec<_> = sid_file.ids.iter().collect();
+            sids.sort_unstable_by_key(|(sid, _)| **sid);
+            for (&sid, identifier) in sids {
+                if !seen.insert(sid) {
+                    continue;
+                }
+                let yang_type = match model.get_type(identifier) {
+                    Some(yang_type) => match types.iter().position(|known| known == yang_type) {
+                        Some(index) => index as u32,
+                        None => {
+                            types.push(yang_type.clone());
+                            (types.len() - 1) as u32
+                        }
+                    },
+                    None => NO_TYPE,
+                };
+                entries.push(CompactEntry {
+                    sid,
+                    identifier: intern(&mut strings, identifier),
+                    module: module_index as u32,
+                    yang_type,
+                });
+            }
+        }
+
+        let mut key_mapping: Vec<_> = model.key_mapping.iter().collect();
+        key_mapping.sort_unstable_by_key(|(sid, _)| **sid);
+        let mut key_lists = Vec::with_capacity(key_mapping.len());
+        let mut key_sids = Vec::new();
+        for (&list_sid, keys) in key_mapping {
+            key_lists.push((list_sid, key_sids.len() as u32, keys.len() as u32));
+            key_sids.extend_from_slice(keys);
+        }
+        let ordered = model.ordered_by_user.iter().copied().collect();
+
+        Self::assemble(
+            strings, modules, entries, types, key_lists, key_sids, ordered,
+        )
+    }
+
+    /// Merges SID files and builds a compact model from them.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same conflict errors as [`CompositeModel::from_sid_files`].
+    pub fn from_sid_files(sid_files: Vec<SidFile>) -> Result<Self> {
+        Ok(Self::from_composite(&CompositeModel::from_sid_files(
+            sid_files,
+        )?))
+    }
+
+    /// Returns `true` when `bytes` starts like a compiled model image.
+    pub fn is_compiled(bytes: &[u8]) -> bool {
+        bytes.starts_with(&SELF_DESCRIBED_CBOR)
+    }
+
+    /// Loads a compiled model image from disk.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Decodes a compiled model image.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoreconfError::InvalidSidFile`] when the image is not a
+    /// compiled model, uses an unsupported version, or holds dangling indices.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let body = bytes.strip_prefix(&SELF_DESCRIBED_CBOR).ok_or_else(|| {
+            CoreconfError::InvalidSidFile(
+                "not a compiled SID model (missing CBOR tag 55799)".into(),
+            )
+        })?;
+        let image: CompiledImage =
+            ciborium::from_reader(body).map_err(|e| CoreconfError::CborDecode(e.to_string()))?;
+        if image.version != COMPACT_MODEL_VERSION {
+            return Err(CoreconfError::InvalidSidFile(format!(
+                "unsupported compiled model version {}",
+                image.version
+            )));
+        }
+
+        let mut strings = InternedStrings::default();
+        strings
+            .buffer
+            .reserve(image.strings.iter().map(String::len).sum());
+        for value in &image.strings {
+            strings.push(value);
+        }
+        let string_count = strings.len() as u32;
+        let check_string = |index: u32| {
+            if index < string_count {
+                Ok(index)
+            } else {
+                Err(CoreconfError::InvalidSidFile(format!(
+                    "compiled model references missing string {index}"
+                )))
+            }
+        };
+
+        let modules = image
+            .modules
+            .iter()
+            .map(|&(name, revision)| {
+                Ok(CompactModule {
+                    name: check_string(name)?,
+                    revision: check_string(revision)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let types = image
+            .types
+            .iter()
+            .map(YangType::from_sid_type)
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut entries = Vec::with_capacity(image.items.len());
+        for &(sid, identifier, module, yang_type) in &image.items {
+            if module as usize >= modules.len() {
+                return Err(CoreconfError::InvalidSidFile(format!(
+                    "SID {sid} references missing module {module}"
+                )));
+            }
+            let yang_type = match yang_type {
+                Some(index) if (index as usize) < types.len() => index,
+                Some(index) => {
+                    return Err(CoreconfError::InvalidSidFile(format!(
+                        "SID {sid} references missing type {index}"
+                    )));
+                }
+                None => NO_TYPE,
+            };
+            entries.push(CompactEntry {
+                sid,
+                identifier: check_string(identifier)?,
+                module,
+                yang_type,
+            });
+        }
+        if !entries.windows(2).all(|pair| pair[0].sid < pair[1].sid) {
+            return Err(CoreconfError::InvalidSidFile(
+                "compiled model items must be sorted by unique SID".into(),
+            ));
+        }
+
+        let mut key_lists = Vec::with_capacity(image.keys.len());
+        let mut key_sids = Vec::new();
+        for (list_sid, keys) in image.keys {
+            key_lists.push((list_sid, key_sids.len() as u32, keys.len() as u32));
+            key_sids.extend(keys);
+        }
+        key_lists.sort_unstable_by_key(|(sid, _, _)| *sid);
+
+        Ok(Self::assemble(
+            strings,
+            modules,
+            entries,
+            types,
+            key_lists,
+            key_sids,
+            image.ordered,
+        ))
+    }
+
+    /// Encodes the model as a compiled CBOR image.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let image = CompiledImage {
+            version: COMPACT_MODEL_VERSION,
+            strings: (0..self.strings.len() as u32)
+                .map(|index| self.strings.get(index).to_string())
+                .collect(),
+            modules: self
+                .modules
+                .iter()
+                .map(|module| (module.name, module.revision))
+                .collect(),
+            items: self
+                .entries
+                .iter()
+                .map(|entry| {
+                    (
+                        entry.sid,
+                        entry.identifier,
+                        entry.module,
+                        (entry.yang_type != NO_TYPE).then_some(entry.yang_type),
+                    )
+                })
+                .collect(),
+            types: self.types.iter().map(YangType::to_sid_type).collect(),
+            keys: self
+                .key_lists
+                .iter()
+                .map(|&(list_sid, start, len)| {
+                    (
+                        list_sid,
+                        self.key_sids[start as usize..(start + len) as usize].to_vec(),
+                    )
+                })
+                .collect(),
+            ordered: self.ordered.clone(),
+        };
+        let mut bytes = SELF_DESCRIBED_CBOR.to_vec();
+        ciborium::into_writer(&image, &mut bytes)
+            .map_err(|e| CoreconfError::CborEncode(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    /// Expands the compact model into a map-based [`CompositeModel`], for
+    /// code reading its public maps or merging it with other modules.
+    /// [`CompositeModel::from_compact`] wraps it without the copy.
+    pub fn to_composite_model(&self) -> Result<CompositeModel> {
+        let mut sid_files: Vec<SidFile> = self
+            .modules
+            .iter()
+            .map(|module| {
+                let module_name = self.strings.get(module.name).to_string();
+                SidFile {
+                    module_prefix: format!("/{module_name}:"),
+                    module_name,
+                    module_revision: self.strings.get(module.revision).to_string(),
+                    sids: HashMap::new(),
+                    ids: HashMap::new(),
+                    types: HashMap::new(),
+                    key_mapping: HashMap::new(),
+                    ordered_by_user: HashSet::new(),
+                }
+            })
+            .collect();
+
+        for entry in &self.entries {
+            let identifier = self.strings.get(entry.identifier).to_string();
+            let sid_file = &mut sid_files[entry.module as usize];
+            if entry.yang_type != NO_TYPE {
+                sid_file.types.insert(
+                    identifier.clone(),
+                    self.types[entry.yang_type as usize].clone(),
+                );
+            }
+            if let Some(keys) = self.get_keys(entry.sid) {
+                sid_file.key_mapping.insert(entry.sid, keys.to_vec());
+            }
+            if self.is_ordered_by_user(entry.sid) {
+                sid_file.ordered_by_user.insert(entry.sid);
+            }
+            sid_file.ids.insert(entry.sid, identifier.clone());
+            sid_file.sids.insert(identifier, entry.sid);
+        }
+
+        CompositeModel::from_sid_files(sid_files)
+    }
+
+    pub fn get_sid(&self, identifier: &str) -> Option<i64> {
+        self.by_identifier
+            .binary_search_by(|&index| {
+                self.strings
+                    .get(self.entries[index as usize].identifier)
+                    .cmp(identifier)
+            })
+            .ok()
+            .map(|position| self.entries[self.by_identifier[position] as usize].sid)
+    }
+
+    pub fn get_identifier(&self, sid: i64) -> Option<&str> {
+        self.entry(sid)
+            .map(|entry| self.strings.get(entry.identifier))
+    }
+
+    pub fn get_type(&self, identifier: &str) -> Option<&YangType> {
+        let entry = self.entry(self.get_sid(identifier)?)?;
+        self.types.get(entry.yang_type as usize)
+    }
+
+    pub fn get_keys(&self, list_sid: i64) -> Option<&[i64]> {
+        self.key_lists
+            .binary_search_by_key(&list_sid, |(sid, _, _)| *sid)
+            .ok()
+            .map(|position| {
+                let (_, start, len) = self.key_lists[position];
+                &self.key_sids[start as usize..(start + len) as usize]
+            })
+    }
+
+    pub fn is_ordered_by_user(&self, sid: i64) -> bool {
+        self.ordered.binary_search(&sid).is_ok()
+    }
+
+    /// Name of the module that assigned `sid`.
+    pub fn module_name(&self, sid: i64) -> Option<&str> {
+        let entry = self.entry(sid)?;
+        Some(self.strings.get(self.modules[entry.module as usize].name))
+    }
+
+    /// `(module name, revision)` pairs in load order.
+    pub fn modules(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.modules.iter().map(|module| {
+            (
+                self.strings.get(module.name),
+                self.strings.get(module.revision),
+            )
+        })
+    }
+
+    /// `(SID, identifier)` pairs in ascending SID order.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, &str)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.sid, self.strings.get(entry.identifier)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn entry(&self, sid: i64) -> Option<&CompactEntry> {
+        self.entries
+            .binary_search_by_key(&sid, |entry| entry.sid)
+            .ok()
+            .map(|position| &self.entries[position])
+    }
+
+    fn assemble(
+        mut strings: InternedStrings,
+        mut modules: Vec<CompactModule>,
+        mut entries: Vec<CompactEntry>,
+        mut types: Vec<YangType>,
+        mut key_lists: Vec<(i64, u32, u32)>,
+        mut key_sids: Vec<i64>,
+        mut ordered: Vec<i64>,
+    ) -> Self {
+        entries.sort_unstable_by_key(|entry| entry.sid);
+        let mut by_identifier: Vec<u32> = (0..entries.len() as u32).collect();
+        by_identifier.sort_unstable_by(|&a, &b| {
+            strings
+                .get(entries[a as usize].identifier)
+                .cmp(strings.get(entries[b as usize].identifier))
+        });
+
+        strings.shrink_to_fit();
+        modules.shrink_to_fit();
+        entries.shrink_to_fit();
+        types.shrink_to_fit();
+        key_lists.shrink_to_fit();
+        key_sids.shrink_to_fit();
+        ordered.sort_unstable();
+        ordered.dedup();
+        ordered.shrink_to_fit();
+        Self {
+            strings,
+            modules,
+            entries,
+            by_identifier,
+            types,
+            key_lists,
+            key_sids,
+            ordered,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SENSOR_SID: &str = r#"{
+        "module-name": "sensors",
+        "module-revision": "2026-01-01",
+        "item": [
+            {"namespace": "module", "identifier": "sensors", "sid": 60000},
+            {"namespace": "identity", "identifier": "temperature", "sid": 60001},
+            {"identifier": "/sensors:sensor", "sid": 60002, "ordered-by": "user"},
+            {"identifier": "/sensors:sensor/id", "sid": 60003, "type": "uint8"},
+            {"identifier": "/sensors:sensor/kind", "sid": 60004, "type": "identityref"},
+            {"identifier": "/sensors:sensor/mode", "sid": 60005, "type": {"0": "direct", "1": "delta"}},
+            {"identifier": "/sensors:sensor/count", "sid": 60006, "type": "uint64"}
+        ],
+        "key-mapping": {"60002": [60004, 60003]}
+    }"#;
+
+    const CLOCK_SID: &str = r#"{
+        "module-name": "clock",
+        "module-revision": "unknown",
+        "item": [
+            {"identifier": "clock", "sid": 61000},
+            {"identifier": "/clock:now", "sid": 61001, "type": "yang:date-and-time"}
+        ],
+        "key-mapping": {}
+    }"#;
+
+    fn composite() -> CompositeModel {
+        CompositeModel::from_sid_strings(&[SENSOR_SID, CLOCK_SID]).unwrap()
+    }
+
+    #[test]
+    fn test_compact_lookups_match_composite_model() {
+        let composite = composite();
+        let compact = CompactModel::from_composite(&composite);
+
+        assert_eq!(compact.len(), composite.ids.len());
+        for (identifier, sid) in &composite.sids {
+            assert_eq!(compact.get_sid(identifier), Some(*sid));
+            assert_eq!(compact.get_identifier(*sid), Some(identifier.as_str()));
+            assert_eq!(compact.get_type(identifier), composite.get_type(identifier));
+        }
+        assert_eq!(compact.get_keys(60002), Some(&[60004, 60003][..]));
+        assert!(compact.is_ordered_by_user(60002));
+        assert!(!compact.is_ordered_by_user(60003));
+        assert_eq!(compact.get_sid("sensors:temperature"), Some(60001));
+        assert_eq!(compact.module_name(61001), Some("clock"));
+        assert_eq!(compact.get_sid("/sensors:missing"), None);
+        assert_eq!(compact.get_identifier(59999), None);
+    }
+
+    #[test]
+    fn test_compiled_image_round_trips() {
+        let compact = CompactModel::from_composite(&composite());
+        let bytes = compact.to_bytes().unwrap();
+        assert!(CompactModel::is_compiled(&bytes));
+
+        let loaded = CompactModel::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            compact.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            loaded.modules().collect::<Vec<_>>(),
+            vec![("sensors", "2026-01-01"), ("clock", "unknown")]
+        );
+        assert_eq!(
+            loaded.get_type("/clock:now"),
+            Some(&YangType::Unknown("yang:date-and-time".into()))
+        );
+    }
+
+    #[test]
+    fn test_compiled_image_expands_to_equivalent_composite_model() {
+        let original = composite();
+        let bytes = CompactModel::from_composite(&original).to_bytes().unwrap();
+        let expanded = CompactModel::from_bytes(&bytes)
+            .unwrap()
+            .to_composite_model()
+            .unwrap();
+
+        assert_eq!(expanded.sids, original.sids);
+        assert_eq!(expanded.ids, original.ids);
+        assert_eq!(expanded.types, original.types);
+        assert_eq!(expanded.key_mapping, original.key_mapping);
+        assert_eq!(expanded.ordered_by_user, original.ordered_by_user);
+        assert_eq!(expanded.sid_files[1].module_prefix, "/clock:");
+    }
+
+    #[test]
+    fn test_composite_model_backed_by_compact_model_converts_alike() {
+        let original = composite();
+        let bytes = CompactModel::from_composite(&original).to_bytes().unwrap();
+        let backed = CompositeModel::from_compact(CompactModel::from_bytes(&bytes).unwrap());
+
+        assert!(backed.sids.is_empty());
+        assert_eq!(backed.len(), original.len());
+        assert_eq!(backed.get_keys(60002), original.get_keys(60002));
+        assert!(backed.is_ordered_by_user(60002));
+        assert_eq!(
+            backed.module_names().collect::<Vec<_>>(),
+            vec!["sensors", "clock"]
+        );
+
+        let data = serde_json::json!({
+            "sensors:sensor": [{"id": 1, "kind": "sensors:temperature", "mode": "delta", "count": 7}]
+        });
+        let encoded = backed.identifier_value_to_sid_value(data.clone()).unwrap();
+        assert_eq!(
+            encoded,
+            original
+                .identifier_value_to_sid_value(data.clone())
+                .unwrap()
+        );
+        assert_eq!(backed.sid_value_to_identifier_value(encoded).unwrap(), data);
+        assert_eq!(
+            CompactModel::from_composite(&backed).to_bytes().unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_json_and_dangling_indices() {
+        let err = CompactModel::from_bytes(SENSOR_SID.as_bytes()).unwrap_err();
+        assert!(matches!(err, CoreconfError::InvalidSidFile(_)));
+
+        let image = CompiledImage {
+            version: COMPACT_MODEL_VERSION,
+            strings: vec!["m".into()],
+            modules: vec![(0, 0)],
+            items: vec![(1, 7, 0, None)],
+            types: Vec::new(),
+            keys: Vec::new(),
+            ordered: Vec::new(),
+        };
+        let mut bytes = SELF_DESCRIBED_CBOR.to_vec();
+        ciborium::into_writer(&image, &mut bytes).unwrap();
+        let err = CompactModel::from_bytes(&bytes).unwrap_err();
+        assert!(
+            matches!(err, CoreconfError::InvalidSidFile(message) if message.contains("string 7"))
+        );
+    }
+}
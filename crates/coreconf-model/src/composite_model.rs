@@ -1,18 +1,28 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use serde_json::{Map, Value};
 
+use crate::compact_model::CompactModel;
 use crate::error::{CoreconfError, Result};
 use crate::sid_file::SidFile;
-use crate::types::{YangType, cast_from_coreconf, cast_to_coreconf};
-
+use crate::types::{JsonEncoding, YangType, cast_from_coreconf_with_encoding, cast_to_coreconf};
+
+/// SID model merged from one or more modules.
+///
+/// A model loaded from SID files keeps its lookup tables in maps. One built
+/// with [`CompositeModel::from_compact`] answers every lookup from the
+/// compiled [`CompactModel`] instead, so lookups go through the accessor
+/// methods either way.
 #[derive(Debug, Clone)]
 pub struct CompositeModel {
-    pub sid_files: Vec<SidFile>,
-    pub sids: HashMap<String, i64>,
-    pub ids: HashMap<i64, String>,
-    pub types: HashMap<String, YangType>,
-    pub key_mapping: HashMap<i64, Vec<i64>>,
+    pub(crate) sid_files: Vec<SidFile>,
+    pub(crate) sids: HashMap<String, i64>,
+    pub(crate) ids: HashMap<i64, String>,
+    pub(crate) types: HashMap<String, YangType>,
+    pub(crate) key_mapping: HashMap<i64, Vec<i64>>,
+    /// Lists and leaf-lists declared `ordered-by user`.
+    pub(crate) ordered_by_user: HashSet<i64>,
+    compact: Option<CompactModel>,
 }
 
 impl CompositeModel {
@@ -29,6 +39,7 @@ impl CompositeModel {
         let mut ids = HashMap::new();
         let mut types = HashMap::new();
         let mut key_mapping = HashMap::new();
+        let mut ordered_by_user = HashSet::new();
 
         for sid_file in &sid_files {
             for (identifier, sid) in &sid_file.sids {
@@ -78,6 +89,8 @@ impl CompositeModel {
                     key_mapping.insert(*sid, keys.clone());
                 }
             }
+
+            ordered_by_user.extend(&sid_file.ordered_by_user);
         }
 
         Ok(Self {
@@ -86,23 +99,110 @@ impl CompositeModel {
             ids,
             types,
             key_mapping,
+            ordered_by_user,
+            compact: None,
         })
     }
 
+    /// A model answering lookups straight from a compiled model, without
+    /// expanding it into maps.
+    pub fn from_compact(compact: CompactModel) -> Self {
+        Self {
+            sid_files: Vec::new(),
+            sids: HashMap::new(),
+            ids: HashMap::new(),
+            types: HashMap::new(),
+            key_mapping: HashMap::new(),
+            ordered_by_user: HashSet::new(),
+            compact: Some(compact),
+        }
+    }
+
+    /// The SID files of the model, expanding a compiled model into them.
+    pub fn into_sid_files(self) -> Result<Vec<SidFile>> {
+        match self.compact {
+            Some(compact) => Ok(compact.to_composite_model()?.sid_files),
+            None => Ok(self.sid_files),
+        }
+    }
+
+    /// The compiled model backing this one, if it was built from one.
+    pub fn compact(&self) -> Option<&CompactModel> {
+        self.compact.as_ref()
+    }
+
     pub fn get_sid(&self, identifier: &str) -> Option<i64> {
-        self.sids.get(identifier).copied()
+        match &self.compact {
+            Some(compact) => compact.get_sid(identifier),
+            None => self.sids.get(identifier).copied(),
+        }
     }
 
     pub fn get_identifier(&self, sid: i64) -> Option<&str> {
-        self.ids.get(&sid).map(String::as_str)
+        match &self.compact {
+            Some(compact) => compact.get_identifier(sid),
+            None => self.ids.get(&sid).map(String::as_str),
+        }
     }
 
     pub fn get_type(&self, identifier: &str) -> Option<&YangType> {
-        self.types.get(identifier)
+        match &self.compact {
+            Some(compact) => compact.get_type(identifier),
+            None => self.types.get(identifier),
+        }
+    }
+
+    pub fn get_keys(&self, list_sid: i64) -> Option<&[i64]> {
+        match &self.compact {
+            Some(compact) => compact.get_keys(list_sid),
+            None => self.key_mapping.get(&list_sid).map(Vec::as_slice),
+        }
     }
 
-    pub fn get_keys(&self, list_sid: i64) -> Option<&Vec<i64>> {
-        self.key_mapping.get(&list_sid)
+    /// Whether the list or leaf-list `sid` is `ordered-by user`, so its
+    /// entries keep the order clients give them.
+    pub fn is_ordered_by_user(&self, sid: i64) -> bool {
+        match &self.compact {
+            Some(compact) => compact.is_ordered_by_user(sid),
+            None => self.ordered_by_user.contains(&sid),
+        }
+    }
+
+    /// `(SID, identifier)` pairs of every schema node, module and identity,
+    /// in no particular order.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (i64, &str)> + '_> {
+        match &self.compact {
+            Some(compact) => Box::new(compact.iter()),
+            None => Box::new(
+                self.ids
+                    .iter()
+                    .map(|(sid, identifier)| (*sid, identifier.as_str())),
+            ),
+        }
+    }
+
+    /// Number of SIDs the model assigns.
+    pub fn len(&self) -> usize {
+        match &self.compact {
+            Some(compact) => compact.len(),
+            None => self.ids.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Names of the loaded modules, in load order.
+    pub fn module_names(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        match &self.compact {
+            Some(compact) => Box::new(compact.modules().map(|(name, _)| name)),
+            None => Box::new(
+                self.sid_files
+                    .iter()
+                    .map(|sid_file| sid_file.module_name.as_str()),
+            ),
+        }
     }
 
     pub fn identifier_value_to_sid_value(&self, json_data: Value) -> Result<Value> {
@@ -123,7 +223,17 @@ impl CompositeModel {
     /// Decode CORECONF CBOR to identifier-keyed JSON, converting SIDs to
     /// human-readable names for identityref and enumeration leaf values.
     pub fn sid_value_to_identifier_value(&self, coreconf_data: Value) -> Result<Value> {
-        self.process_value_for_identifier(&coreconf_data, 0, None, true)
+        self.sid_value_to_identifier_value_with_encoding(coreconf_data, JsonEncoding::Native)
+    }
+
+    /// Like `sid_value_to_identifier_value`, with an explicit JSON encoding for
+    /// 64-bit integer and decimal64 leaves (see [`JsonEncoding`]).
+    pub fn sid_value_to_identifier_value_with_encoding(
+        &self,
+        coreconf_data: Value,
+        encoding: JsonEncoding,
+    ) -> Result<Value> {
+        self.process_value_for_identifier(&coreconf_data, 0, None, true, encoding)
     }
 
     pub fn sid_value_to_identifier_value_at_path(
@@ -134,7 +244,13 @@ impl CompositeModel {
         let sid = self
             .get_sid(canonical_path)
             .ok_or_else(|| CoreconfError::SidNotFound(canonical_path.to_string()))?;
-        self.process_value_for_identifier(&coreconf_data, sid, Some(canonical_path), true)
+        self.process_value_for_identifier(
+            &coreconf_data,
+            sid,
+            Some(canonical_path),
+            true,
+            JsonEncoding::Native,
+        )
     }
 
     /// Like `sid_value_to_identifier_value`, but preserves integer SIDs for
@@ -143,7 +259,7 @@ impl CompositeModel {
         &self,
         coreconf_data: Value,
     ) -> Result<Value> {
-        self.process_value_for_identifier(&coreconf_data, 0, None, false)
+        self.process_value_for_identifier(&coreconf_data, 0, None, false, JsonEncoding::Native)
     }
 
     fn process_value_for_sid(
@@ -189,9 +305,8 @@ impl CompositeModel {
                 {
                     let sid_lookup = |id: &str| {
                         self.get_sid(id).or_else(|| {
-                            self.sid_files.iter().find_map(|sid_file| {
-                                self.get_sid(&format!("{}:{id}", sid_file.module_name))
-                            })
+                            self.module_names()
+                                .find_map(|module| self.get_sid(&format!("{module}:{id}")))
                         })
                     };
                     return cast_to_coreconf(value, yang_type, Some(&sid_lookup));
@@ -207,12 +322,13 @@ impl CompositeModel {
         delta: i64,
         current_path: Option<&str>,
         resolve_identityref: bool,
+        encoding: JsonEncoding,
     ) -> Result<Value> {
         if let Some(path) = current_path
             && let Some(yang_type @ YangType::Binary) = self.get_type(path)
         {
             let module_name = self.module_name_for_identifier(path).unwrap_or_default();
-            return cast_from_coreconf(value, yang_type, None, module_name);
+            return cast_from_coreconf_with_encoding(value, yang_type, None, module_name, encoding);
         }
 
         match value {
@@ -237,6 +353,7 @@ impl CompositeModel {
                                 sid,
                                 Some(identifier),
                                 resolve_identityref,
+                                encoding,
                             )?;
                             new_map.insert(storage_key, processed);
                         } else {
@@ -246,6 +363,7 @@ impl CompositeModel {
                                 sid,
                                 current_path,
                                 resolve_identityref,
+                                encoding,
                             )?;
                             new_map.insert(key.clone(), processed);
                         }
@@ -256,6 +374,7 @@ impl CompositeModel {
                             delta,
                             current_path,
                             resolve_identityref,
+                            encoding,
                         )?;
                         new_map.insert(key.clone(), processed);
                     }
@@ -270,6 +389,7 @@ impl CompositeModel {
                         delta,
                         current_path,
                         resolve_identityref,
+                        encoding,
                     )?);
                 }
                 Ok(Value::Array(new_arr))
@@ -281,7 +401,13 @@ impl CompositeModel {
                 {
                     let id_lookup = |sid: i64| self.get_identifier(sid).map(str::to_string);
                     let module_name = self.module_name_for_identifier(path).unwrap_or_default();
-                    return cast_from_coreconf(value, yang_type, Some(&id_lookup), module_name);
+                    return cast_from_coreconf_with_encoding(
+                        value,
+                        yang_type,
+                        Some(&id_lookup),
+                        module_name,
+                        encoding,
+                    );
                 }
                 Ok(value.clone())
             }
@@ -289,6 +415,9 @@ impl CompositeModel {
     }
 
     fn module_name_for_identifier(&self, identifier: &str) -> Option<&str> {
+        if let Some(compact) = &self.compact {
+            return compact.module_name(compact.get_sid(identifier)?);
+        }
         self.sid_files
             .iter()
             .find(|sid_file| sid_file.get_sid(identifier).is_some())
@@ -301,6 +430,7 @@ mod tests {
     use serde_json::Value;
 
     use super::CompositeModel;
+    use crate::types::JsonEncoding;
 
     const SAMPLE_SID: &str = r#"{
         "assignment-range": [{"entry-point": 60000, "size": 10}],
@@ -436,4 +566,38 @@ mod tests {
 
         assert_eq!(converted["1"], 60001);
     }
+
+    #[test]
+    fn test_rfc7951_encoding_emits_64_bit_counters_as_strings() {
+        let model = CompositeModel::from_sid_strings(&[r#"{
+            "module-name": "counters",
+            "module-revision": "unknown",
+            "item": [
+                {"identifier": "counters", "sid": 62000},
+                {"identifier": "/counters:stats", "sid": 62001},
+                {"identifier": "/counters:stats/messages-sent", "sid": 62002, "type": "uint64"},
+                {"identifier": "/counters:stats/retries", "sid": 62003, "type": "uint8"}
+            ],
+            "key-mapping": {}
+        }"#])
+        .unwrap();
+        let coreconf = serde_json::json!({"62001": {"1": u64::MAX, "2": 3}});
+
+        let native = model
+            .sid_value_to_identifier_value(coreconf.clone())
+            .unwrap();
+        assert_eq!(native["counters:stats"]["messages-sent"], u64::MAX);
+
+        let rfc7951 = model
+            .sid_value_to_identifier_value_with_encoding(coreconf.clone(), JsonEncoding::Rfc7951)
+            .unwrap();
+        assert_eq!(
+            rfc7951["counters:stats"]["messages-sent"],
+            "18446744073709551615"
+        );
+        assert_eq!(rfc7951["counters:stats"]["retries"], 3);
+
+        let round_trip = model.identifier_value_to_sid_value(rfc7951).unwrap();
+        assert_eq!(round_trip, coreconf);
+    }
 }
@@ -1,3 +1,4 @@
+use serde_json::Value;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -38,6 +39,247 @@ pub enum CoreconfError {
 
     #[error("Unsupported content format")]
     UnsupportedContentFormat,
+
+    /// A failure tied to a data node, with the fields of a YANG error report.
+    #[error("{0}")]
+    Data(Box<DataError>),
+}
+
+impl CoreconfError {
+    /// The YANG error-tag describing this error.
+    ///
+    /// Structured [`CoreconfError::Data`] errors carry their own tag; the
+    /// free-form variants map onto the closest RFC 8040 error-tag.
+    pub fn error_tag(&self) -> ErrorTag {
+        match self {
+            Self::Data(data) => data.tag,
+            Self::SidNotFound(_) | Self::IdentifierNotFound(_) => ErrorTag::UnknownElement,
+            Self::Json(_) | Self::CborDecode(_) => ErrorTag::MalformedMessage,
+            Self::TypeConversion(_) | Self::ValidationError(_) => ErrorTag::InvalidValue,
+            Self::ResourceNotFound(_) => ErrorTag::DataMissing,
+            Self::MethodNotAllowed(_) | Self::UnsupportedContentFormat => {
+                ErrorTag::OperationNotSupported
+            }
+            Self::Io(_) | Self::CborEncode(_) | Self::InvalidSidFile(_) => {
+                ErrorTag::OperationFailed
+            }
+        }
+    }
+
+    /// Structured details, when the error was raised for a specific data node.
+    pub fn data(&self) -> Option<&DataError> {
+        match self {
+            Self::Data(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+impl From<DataError> for CoreconfError {
+    fn from(error: DataError) -> Self {
+        Self::Data(Box::new(error))
+    }
+}
+
+impl From<std::convert::Infallible> for CoreconfError {
+    fn from(never: std::convert::Infallible) -> Self {
+        match never {}
+    }
+}
+
+/// YANG error-tag values (RFC 6241 appendix A, RFC 8040 section 7).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorTag {
+    InUse,
+    InvalidValue,
+    TooBig,
+    MissingAttribute,
+    BadAttribute,
+    UnknownAttribute,
+    MissingElement,
+    BadElement,
+    UnknownElement,
+    UnknownNamespace,
+    AccessDenied,
+    LockDenied,
+    ResourceDenied,
+    RollbackFailed,
+    DataExists,
+    DataMissing,
+    OperationNotSupported,
+    OperationFailed,
+    PartialOperation,
+    MalformedMessage,
+}
+
+impl ErrorTag {
+    const ALL: [Self; 20] = [
+        Self::InUse,
+        Self::InvalidValue,
+        Self::TooBig,
+        Self::MissingAttribute,
+        Self::BadAttribute,
+        Self::UnknownAttribute,
+        Self::MissingElement,
+        Self::BadElement,
+        Self::UnknownElement,
+        Self::UnknownNamespace,
+        Self::AccessDenied,
+        Self::LockDenied,
+        Self::ResourceDenied,
+        Self::RollbackFailed,
+        Self::DataExists,
+        Self::DataMissing,
+        Self::OperationNotSupported,
+        Self::OperationFailed,
+        Self::PartialOperation,
+        Self::MalformedMessage,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InUse => "in-use",
+            Self::InvalidValue => "invalid-value",
+            Self::TooBig => "too-big",
+            Self::MissingAttribute => "missing-attribute",
+            Self::BadAttribute => "bad-attribute",
+            Self::UnknownAttribute => "unknown-attribute",
+            Self::MissingElement => "missing-element",
+            Self::BadElement => "bad-element",
+            Self::UnknownElement => "unknown-element",
+            Self::UnknownNamespace => "unknown-namespace",
+            Self::AccessDenied => "access-denied",
+            Self::LockDenied => "lock-denied",
+            Self::ResourceDenied => "resource-denied",
+            Self::RollbackFailed => "rollback-failed",
+            Self::DataExists => "data-exists",
+            Self::DataMissing => "data-missing",
+            Self::OperationNotSupported => "operation-not-supported",
+            Self::OperationFailed => "operation-failed",
+            Self::PartialOperation => "partial-operation",
+            Self::MalformedMessage => "malformed-message",
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|tag| tag.as_str() == input)
+    }
+}
+
+impl std::fmt::Display for ErrorTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error report for a data node: error-tag, optional error-app-tag, the
+/// offending data path and SID, the failing value and a human-readable
+/// message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataError {
+    pub tag: ErrorTag,
+    pub app_tag: Option<String>,
+    pub path: Option<String>,
+    pub sid: Option<i64>,
+    pub value: Option<Value>,
+    pub message: String,
+}
+
+impl DataError {
+    pub fn new(tag: ErrorTag, message: impl Into<String>) -> Self {
+        Self {
+            tag,
+            app_tag: None,
+            path: None,
+            sid: None,
+            value: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn with_app_tag(mut self, app_tag: impl Into<String>) -> Self {
+        self.app_tag = Some(app_tag.into());
+        self
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_sid(mut self, sid: i64) -> Self {
+        self.sid = Some(sid);
+        self
+    }
+
+    pub fn with_value(mut self, value: Value) -> Self {
+        self.value = Some(value);
+        self
+    }
+}
+
+impl std::fmt::Display for DataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [{}", self.message, self.tag)?;
+        if let Some(app_tag) = &self.app_tag {
+            write!(f, ", app-tag {app_tag}")?;
+        }
+        if let Some(sid) = self.sid {
+            write!(f, ", SID {sid}")?;
+        }
+        if let Some(value) = &self.value {
+            write!(f, ", value {value}")?;
+        }
+        f.write_str("]")
+    }
 }
 
 pub type Result<T> = std::result::Result<T, CoreconfError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_tag_names_round_trip() {
+        for tag in ErrorTag::ALL {
+            assert_eq!(ErrorTag::parse(tag.as_str()), Some(tag));
+        }
+        assert_eq!(ErrorTag::parse("no-such-tag"), None);
+    }
+
+    #[test]
+    fn test_data_error_reports_structured_fields() {
+        let error = CoreconfError::from(
+            DataError::new(ErrorTag::InvalidValue, "cannot parse 'x' as uint8")
+                .with_app_tag("data-type-mismatch")
+                .with_path("/example:rule/id")
+                .with_sid(60003)
+                .with_value(Value::String("x".into())),
+        );
+
+        assert_eq!(error.error_tag(), ErrorTag::InvalidValue);
+        assert_eq!(
+            error.data().and_then(|data| data.path.as_deref()),
+            Some("/example:rule/id")
+        );
+        assert_eq!(
+            error.to_string(),
+            "cannot parse 'x' as uint8 [invalid-value, app-tag data-type-mismatch, SID 60003, value \"x\"]"
+        );
+    }
+
+    #[test]
+    fn test_legacy_variants_map_to_error_tags() {
+        assert_eq!(
+            CoreconfError::IdentifierNotFound(1).error_tag(),
+            ErrorTag::UnknownElement
+        );
+        assert_eq!(
+            CoreconfError::ResourceNotFound("x".into()).error_tag(),
+            ErrorTag::DataMissing
+        );
+        assert!(CoreconfError::TypeConversion("x".into()).data().is_none());
+    }
+}
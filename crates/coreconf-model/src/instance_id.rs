@@ -166,7 +166,7 @@ impl InstancePath {
             }
             index += 1;
 
-            let key_count = model.get_keys(sid).map_or(0, Vec::len);
+            let key_count = model.get_keys(sid).map_or(0, <[i64]>::len);
             for _ in 0..key_count {
                 let key = values.get(index).ok_or_else(|| {
                     CoreconfError::TypeConversion(format!(
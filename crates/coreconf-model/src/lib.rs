@@ -1,25 +1,28 @@
 use std::path::Path;
+use std::sync::Arc;
 
 use serde_json::Value;
 
 pub mod codec;
+pub mod compact_model;
 pub mod composite_model;
 pub mod error;
 pub mod instance_id;
 pub mod sid_file;
 pub mod types;
 
-pub use codec::{decode_cbor_to_json, encode_json_to_cbor};
+pub use codec::{decode_cbor_to_json, decode_cbor_to_json_with_encoding, encode_json_to_cbor};
+pub use compact_model::CompactModel;
 pub use composite_model::CompositeModel;
-pub use error::{CoreconfError, Result};
+pub use error::{CoreconfError, DataError, ErrorTag, Result};
 pub use instance_id::{Instance, InstancePath};
 pub use sid_file::SidFile;
-pub use types::YangType;
+pub use types::{JsonEncoding, YangType};
 
 #[derive(Debug, Clone)]
 pub struct CoreconfModel {
     pub sid_file: SidFile,
-    composite: CompositeModel,
+    composite: Arc<CompositeModel>,
 }
 
 impl CoreconfModel {
@@ -58,8 +61,14 @@ impl CoreconfModel {
         &self.composite
     }
 
+    /// Shared handle to the composite model, for datastores and transports
+    /// that keep the model alive without copying it.
+    pub fn shared_composite_model(&self) -> Arc<CompositeModel> {
+        Arc::clone(&self.composite)
+    }
+
     fn from_sid_file(sid_file: SidFile) -> Result<Self> {
-        let composite = CompositeModel::from_sid_files(vec![sid_file.clone()])?;
+        let composite = Arc::new(CompositeModel::from_sid_files(vec![sid_file.clone()])?);
         Ok(Self {
             sid_file,
             composite,
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 
@@ -16,6 +16,8 @@ pub struct SidFile {
     pub ids: HashMap<i64, String>,
     pub types: HashMap<String, YangType>,
     pub key_mapping: HashMap<i64, Vec<i64>>,
+    /// Lists and leaf-lists declared `ordered-by user`.
+    pub ordered_by_user: HashSet<i64>,
 }
 
 /// Extract a string field from a JSON object. Returns an error if the key is missing
@@ -33,6 +35,7 @@ struct ParsedItem {
     sid_value: Value,
     item_type: Option<Value>,
     namespace: Option<String>,
+    ordered_by: Option<String>,
 }
 
 /// Extract items from the "item" or "items" array.
@@ -62,6 +65,10 @@ fn extract_items(sid_data: &serde_json::Map<String, Value>) -> Result<Vec<Parsed
                     .get("namespace")
                     .and_then(Value::as_str)
                     .map(str::to_string),
+                ordered_by: obj
+                    .get("ordered-by")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
             })
         })
         .collect()
@@ -128,6 +135,7 @@ impl SidFile {
         let mut sids = HashMap::with_capacity(items.len());
         let mut ids = HashMap::with_capacity(items.len());
         let mut types = HashMap::with_capacity(items.len());
+        let mut ordered_by_user = HashSet::new();
 
         for item in items {
             let sid = parse_sid_value(&item.sid_value)?;
@@ -162,6 +170,18 @@ impl SidFile {
                 ids.insert(sid, storage_key.clone());
             }
 
+            match item.ordered_by.as_deref() {
+                None | Some("system") => {}
+                Some("user") => {
+                    ordered_by_user.insert(sid);
+                }
+                Some(other) => {
+                    return Err(CoreconfError::InvalidSidFile(format!(
+                        "invalid ordered-by '{other}' for '{storage_key}'"
+                    )));
+                }
+            }
+
             if let Some(ref type_val) = item.item_type {
                 let parsed_type = YangType::from_sid_type(type_val)?;
                 if let Some(existing_type) = types.get(&storage_key) {
@@ -195,6 +215,7 @@ impl SidFile {
             ids,
             types,
             key_mapping,
+            ordered_by_user,
         })
     }
 
@@ -213,6 +234,10 @@ impl SidFile {
     pub fn get_keys(&self, list_sid: i64) -> Option<&Vec<i64>> {
         self.key_mapping.get(&list_sid)
     }
+
+    pub fn is_ordered_by_user(&self, sid: i64) -> bool {
+        self.ordered_by_user.contains(&sid)
+    }
 }
 
 impl std::str::FromStr for SidFile {
@@ -271,6 +296,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ordered_by_user_lists() {
+        let sid_file = SidFile::from_json_str(
+            r#"{
+                "module-name": "example-1",
+                "item": [
+                    {"identifier": "/example-1:rule", "sid": 60001, "ordered-by": "user"},
+                    {"identifier": "/example-1:host", "sid": 60002, "ordered-by": "system"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(sid_file.is_ordered_by_user(60001));
+        assert!(!sid_file.is_ordered_by_user(60002));
+
+        let err = SidFile::from_json_str(
+            r#"{
+                "module-name": "example-1",
+                "item": [{"identifier": "/example-1:rule", "sid": 60001, "ordered-by": "client"}]
+            }"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::error::CoreconfError::InvalidSidFile(message) if message.contains("ordered-by")
+        ));
+    }
+
     #[test]
     fn test_parse_sid_file_rejects_invalid_key_mapping_keys() {
         let err = SidFile::from_json_str(
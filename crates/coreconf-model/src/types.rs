@@ -7,6 +7,19 @@ use crate::error::{CoreconfError, Result};
 
 type SidLookupFn<'a> = dyn Fn(&str) -> Option<i64> + 'a;
 
+/// JSON representation used when decoding CORECONF values.
+///
+/// `Native` keeps 64-bit integers and decimal64 values as JSON numbers, which
+/// is convenient for Rust consumers. `Rfc7951` follows RFC 7951 section 6.1
+/// and emits `int64`, `uint64` and `decimal64` values as JSON strings, so that
+/// consumers limited to IEEE 754 doubles do not lose precision.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonEncoding {
+    #[default]
+    Native,
+    Rfc7951,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum YangType {
     String,
@@ -71,6 +84,47 @@ impl YangType {
         }
     }
 
+    /// Renders the type back into its SID-file `type` metadata, the inverse
+    /// of [`YangType::from_sid_type`].
+    pub fn to_sid_type(&self) -> Value {
+        match self {
+            YangType::Enumeration(enum_map) => Value::Object(
+                enum_map
+                    .iter()
+                    .map(|(name, value)| (value.to_string(), Value::String(name.clone())))
+                    .collect(),
+            ),
+            YangType::Union(types) => Value::Array(types.iter().map(Self::to_sid_type).collect()),
+            other => Value::String(other.type_name().to_string()),
+        }
+    }
+
+    fn type_name(&self) -> &str {
+        match self {
+            YangType::String => "string",
+            YangType::Int8 => "int8",
+            YangType::Int16 => "int16",
+            YangType::Int32 => "int32",
+            YangType::Int64 => "int64",
+            YangType::Uint8 => "uint8",
+            YangType::Uint16 => "uint16",
+            YangType::Uint32 => "uint32",
+            YangType::Uint64 => "uint64",
+            YangType::Decimal64 => "decimal64",
+            YangType::Binary => "binary",
+            YangType::Boolean => "boolean",
+            YangType::Empty => "empty",
+            YangType::Identityref => "identityref",
+            YangType::Leafref => "leafref",
+            YangType::InstanceIdentifier => "instance-identifier",
+            YangType::Bits => "bits",
+            YangType::Uri => "inet:uri",
+            YangType::Enumeration(_) => "enumeration",
+            YangType::Union(_) => "union",
+            YangType::Unknown(other) => other,
+        }
+    }
+
     fn from_string(s: &str) -> Self {
         match s {
             "string" => YangType::String,
@@ -241,6 +295,24 @@ pub fn cast_from_coreconf(
     yang_type: &YangType,
     id_lookup: Option<&dyn Fn(i64) -> Option<String>>,
     module_name: &str,
+) -> Result<Value> {
+    cast_from_coreconf_with_encoding(
+        value,
+        yang_type,
+        id_lookup,
+        module_name,
+        JsonEncoding::Native,
+    )
+}
+
+/// Like [`cast_from_coreconf`], but lets the caller choose how 64-bit
+/// integers and decimal64 values are represented in JSON.
+pub fn cast_from_coreconf_with_encoding(
+    value: &Value,
+    yang_type: &YangType,
+    id_lookup: Option<&dyn Fn(i64) -> Option<String>>,
+    module_name: &str,
+    encoding: JsonEncoding,
 ) -> Result<Value> {
     match yang_type {
         YangType::String | YangType::Uri => {
@@ -259,6 +331,12 @@ pub fn cast_from_coreconf(
         | YangType::Uint16
         | YangType::Uint32
         | YangType::Uint64 => match yang_type {
+            YangType::Int64 if encoding == JsonEncoding::Rfc7951 => {
+                Ok(Value::String(value_to_i64(value)?.to_string()))
+            }
+            YangType::Uint64 if encoding == JsonEncoding::Rfc7951 => {
+                Ok(Value::String(value_to_u64(value)?.to_string()))
+            }
             YangType::Int8 | YangType::Int16 | YangType::Int32 | YangType::Int64 => {
                 Ok(Value::Number(value_to_i64(value)?.into()))
             }
@@ -268,14 +346,19 @@ pub fn cast_from_coreconf(
             _ => unreachable!(),
         },
         YangType::Decimal64 => {
-            let f = if let Some(arr) = value.as_array()
-                && arr.len() == 2
-                && let Some(exp) = arr[0].as_i64()
-                && let Some(mant) = arr[1].as_i64()
-            {
-                (mant as f64) * 10.0f64.powi(exp as i32)
-            } else {
-                value_to_f64(value)?
+            let decimal = value.as_array().and_then(|arr| match arr.as_slice() {
+                [exp, mant] => Some((exp.as_i64()?, mant.as_i64()?)),
+                _ => None,
+            });
+            if encoding == JsonEncoding::Rfc7951 {
+                return Ok(Value::String(match decimal {
+                    Some((exp, mant)) => format_decimal64(exp, mant),
+                    None => value_to_f64(value)?.to_string(),
+                }));
+            }
+            let f = match decimal {
+                Some((exp, mant)) => (mant as f64) * 10.0f64.powi(exp as i32),
+                None => value_to_f64(value)?,
             };
             serde_json::Number::from_f64(f)
                 .map(Value::Number)
@@ -346,7 +429,9 @@ pub fn cast_from_coreconf(
         YangType::Leafref | YangType::InstanceIdentifier | YangType::Bits => Ok(value.clone()),
         YangType::Union(types) => {
             for t in types {
-                if let Ok(v) = cast_from_coreconf(value, t, id_lookup, module_name) {
+                if let Ok(v) =
+                    cast_from_coreconf_with_encoding(value, t, id_lookup, module_name, encoding)
+                {
                     return Ok(v);
                 }
             }
@@ -400,6 +485,21 @@ fn value_to_f64(value: &Value) -> Result<f64> {
     }
 }
 
+/// Renders a decimal64 `[exponent, mantissa]` pair exactly, without going
+/// through `f64`.
+fn format_decimal64(exponent: i64, mantissa: i64) -> String {
+    let digits = mantissa.unsigned_abs().to_string();
+    let sign = if mantissa < 0 { "-" } else { "" };
+    if exponent >= 0 {
+        let zeros = "0".repeat(exponent as usize);
+        return format!("{sign}{digits}{zeros}");
+    }
+    let scale = exponent.unsigned_abs() as usize;
+    let padded = format!("{digits:0>width$}", width = scale + 1);
+    let (integer, fraction) = padded.split_at(padded.len() - scale);
+    format!("{sign}{integer}.{fraction}")
+}
+
 fn format_identityref(identifier: &str, module_name: &str) -> String {
     let normalized = identifier.trim_start_matches('/');
     if normalized.contains(':') || module_name.is_empty() {
@@ -421,6 +521,20 @@ mod tests {
         assert_eq!(YangType::from_string("inet:uri"), YangType::Uri);
     }
 
+    #[test]
+    fn test_sid_type_metadata_round_trips() {
+        for yang_type in [
+            YangType::Uint64,
+            YangType::Uri,
+            YangType::Unknown("yang:date-and-time".into()),
+            YangType::Enumeration(HashMap::from([("direct".into(), 0), ("delta".into(), 1)])),
+            YangType::Union(vec![YangType::String, YangType::Int32]),
+        ] {
+            let metadata = yang_type.to_sid_type();
+            assert_eq!(YangType::from_sid_type(&metadata).unwrap(), yang_type);
+        }
+    }
+
     #[test]
     fn test_cast_string() {
         let value = Value::String("hello".to_string());
@@ -496,6 +610,63 @@ mod tests {
         assert_eq!(converted, Value::String("example:up".to_string()));
     }
 
+    #[test]
+    fn test_cast_uint64_above_i64_max_round_trips() {
+        let value = Value::Number(u64::MAX.into());
+        let coreconf = cast_to_coreconf(&value, &YangType::Uint64, None).unwrap();
+        assert_eq!(coreconf, Value::Number(u64::MAX.into()));
+
+        let from_string = cast_to_coreconf(
+            &Value::String(u64::MAX.to_string()),
+            &YangType::Uint64,
+            None,
+        )
+        .unwrap();
+        assert_eq!(from_string, coreconf);
+
+        let json = cast_from_coreconf(&coreconf, &YangType::Uint64, None, "example").unwrap();
+        assert_eq!(json, value);
+    }
+
+    #[test]
+    fn test_rfc7951_encodes_64_bit_values_as_strings() {
+        let encode = |value: Value, yang_type: YangType| {
+            cast_from_coreconf_with_encoding(
+                &value,
+                &yang_type,
+                None,
+                "example",
+                JsonEncoding::Rfc7951,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            encode(Value::Number(u64::MAX.into()), YangType::Uint64),
+            Value::String("18446744073709551615".into())
+        );
+        assert_eq!(
+            encode(Value::Number(i64::MIN.into()), YangType::Int64),
+            Value::String("-9223372036854775808".into())
+        );
+        assert_eq!(
+            encode(serde_json::json!([-2, -5]), YangType::Decimal64),
+            Value::String("-0.05".into())
+        );
+        assert_eq!(
+            encode(Value::Number(42.into()), YangType::Uint32),
+            Value::Number(42.into())
+        );
+    }
+
+    #[test]
+    fn test_format_decimal64_is_exact() {
+        assert_eq!(format_decimal64(-6, 48_858_200), "48.858200");
+        assert_eq!(format_decimal64(-3, 5), "0.005");
+        assert_eq!(format_decimal64(2, -7), "-700");
+        assert_eq!(format_decimal64(0, 0), "0");
+    }
+
     #[test]
     fn test_cast_enumeration_rejects_unknown_numeric_values() {
         let value = Value::Number(99.into());
@@ -33,17 +33,14 @@ fn composite_model_exposes_canonical_schema_fields() {
     ])
     .unwrap();
 
-    assert_eq!(model.sid_files.len(), 2);
-    assert_eq!(model.sids.get("/example-a:root"), Some(&60001));
-    assert_eq!(
-        model.ids.get(&61002).map(String::as_str),
-        Some("/example-b:list/id")
-    );
+    assert_eq!(model.module_names().count(), 2);
+    assert_eq!(model.get_sid("/example-a:root"), Some(60001));
+    assert_eq!(model.get_identifier(61002), Some("/example-b:list/id"));
     assert_eq!(
-        model.types.get("/example-b:list/id"),
+        model.get_type("/example-b:list/id"),
         Some(&YangType::Uint32)
     );
-    assert_eq!(model.key_mapping.get(&61001), Some(&vec![61002]));
+    assert_eq!(model.get_keys(61001), Some(&[61002][..]));
 }
 
 #[test]
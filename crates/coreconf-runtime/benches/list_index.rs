@@ -0,0 +1,71 @@
+//! Measures the cost of creating keyed list entries one path at a time.
+//!
+//! Each `set_path` under `transducer[type=...][id=...]` looks its entry up by
+//! key. With list entries hashed by key, the cost per entry stays flat as the
+//! list grows instead of rising with the number of entries already stored.
+//!
+//! ```bash
+//! cargo bench -p coreconf-runtime --bench list_index
+//! ```
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use coreconf_model::{CompositeModel, SidFile};
+use coreconf_runtime::Datastore;
+use serde_json::json;
+
+fn m2m_model() -> CompositeModel {
+    let sid_path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/coreconf-m2m@2026-03-29.sid");
+    let sid_file = SidFile::from_file(&sid_path).expect("m2m SID file");
+    CompositeModel::from_sid_files(vec![sid_file]).expect("m2m model")
+}
+
+fn transducer_path(id: u32, leaf: &str) -> String {
+    format!(
+        "/coreconf-m2m:transducers/transducer[type='coreconf-m2m:solar-radiation'][id='{id}']/{leaf}"
+    )
+}
+
+fn per_entry(elapsed: Duration, entries: u32) -> Duration {
+    elapsed / entries
+}
+
+fn main() {
+    println!(
+        "{:>12}  {:>14}  {:>14}",
+        "transducers", "create/entry", "update/entry"
+    );
+    for transducers in [1_000, 2_500, 5_000, 10_000] {
+        let mut datastore = Datastore::new_in_memory(m2m_model());
+
+        let start = Instant::now();
+        for id in 0..transducers {
+            datastore
+                .set_path(&transducer_path(id, "precision"), json!(2))
+                .unwrap();
+        }
+        let create_cost = per_entry(start.elapsed(), transducers);
+
+        let start = Instant::now();
+        for id in (0..transducers).rev() {
+            datastore
+                .set_path(&transducer_path(id, "precision"), json!(3))
+                .unwrap();
+        }
+        let update_cost = per_entry(start.elapsed(), transducers);
+
+        assert_eq!(
+            datastore
+                .predicates("/coreconf-m2m:transducers/transducer")
+                .unwrap()
+                .len(),
+            transducers as usize
+        );
+        println!(
+            "{:>12}  {:>14?}  {:>14?}",
+            transducers, create_cost, update_cost
+        );
+    }
+}
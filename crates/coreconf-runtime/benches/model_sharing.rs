@@ -0,0 +1,86 @@
+//! Measures the per-request cost of root iPATCH against a large model.
+//!
+//! Every root iPATCH builds a candidate datastore. With the model held in an
+//! `Arc`, creating the candidate no longer copies the model's maps, so the
+//! request cost stays flat as the model grows while a deep `CompositeModel`
+//! clone scales with the number of SIDs.
+//!
+//! ```bash
+//! cargo bench -p coreconf-runtime --bench model_sharing
+//! ```
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use coreconf_model::CompositeModel;
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request, ResponseCode};
+use coreconf_runtime::{Datastore, RequestHandler};
+
+const REQUESTS: u32 = 200;
+
+/// A synthetic module with one container holding `leaves` boolean leaves.
+fn large_model(leaves: i64) -> CompositeModel {
+    let mut items = vec![
+        r#"{"identifier":"bench","sid":100000}"#.to_string(),
+        r#"{"identifier":"/bench:settings","sid":100001}"#.to_string(),
+    ];
+    for index in 0..leaves {
+        items.push(format!(
+            r#"{{"identifier":"/bench:settings/flag-{index}","sid":{},"type":"boolean"}}"#,
+            100002 + index
+        ));
+    }
+    let sid_file = format!(
+        r#"{{"module-name":"bench","module-revision":"2026-01-01","item":[{}],"key-mapping":{{}}}}"#,
+        items.join(",")
+    );
+    CompositeModel::from_sid_strings(&[&sid_file]).expect("bench model")
+}
+
+fn root_ipatch(value: bool) -> Request {
+    let mut payload = Vec::new();
+    ciborium::into_writer(&serde_json::json!({"100002": value}), &mut payload).unwrap();
+    Request::new(Method::IPatch).with_payload(payload, ContentFormat::YangInstancesCborSeq)
+}
+
+fn per_iteration(elapsed: Duration, iterations: u32) -> Duration {
+    elapsed / iterations
+}
+
+fn main() {
+    println!(
+        "{:>8}  {:>16}  {:>18}",
+        "SIDs", "root iPATCH", "deep model clone"
+    );
+    for leaves in [1_000, 10_000, 50_000] {
+        let model = Arc::new(large_model(leaves));
+        let mut handler =
+            RequestHandler::new(Datastore::new_in_memory(Arc::clone(&model))).unwrap();
+        let handles = Arc::strong_count(&model);
+
+        let start = Instant::now();
+        for index in 0..REQUESTS {
+            let response = handler.handle(&root_ipatch(index % 2 == 0));
+            assert_eq!(response.code, ResponseCode::Changed);
+        }
+        let request_cost = per_iteration(start.elapsed(), REQUESTS);
+        assert_eq!(
+            Arc::strong_count(&model),
+            handles,
+            "candidates must share the model, not retain copies"
+        );
+
+        let start = Instant::now();
+        for _ in 0..REQUESTS {
+            std::hint::black_box(CompositeModel::clone(&model));
+        }
+        let clone_cost = per_iteration(start.elapsed(), REQUESTS);
+
+        println!(
+            "{:>8}  {:>16?}  {:>18?}",
+            model.len(),
+            request_cost,
+            clone_cost
+        );
+    }
+}
@@ -0,0 +1,896 @@
+//! Access control modeled on NACM (RFC 8341).
+//!
+//! An [`AccessControl`] maps client identities to groups and holds rule
+//! lists that apply to some of those groups. A rule permits or denies
+//! operations on a target:
+//!
+//! - a [`Query`] pattern such as `/m:sensors/sensor[unit='mm']`, covering
+//!   every node it selects and their descendants,
+//! - an operation (RPC or action) by path, or
+//! - anything.
+//!
+//! For each access, the first rule in order, across the rule lists of the
+//! client's groups, that covers the node and includes the operation
+//! decides. Without one, the read, write or exec default applies.
+//!
+//! Identities are the peer strings transports put on requests. Requests
+//! without a peer come from the server itself and are never checked.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::net::SocketAddr;
+
+use coreconf_model::{CompositeModel, DataError, ErrorTag, Result};
+use serde_json::{Map, Value};
+
+use crate::datastore::{
+    TreeNode, ensure_object, format_predicate_string, join_path, list_keys,
+    split_canonical_segments, storage_key,
+};
+use crate::diff::{Diff, DiffOperation};
+use crate::path::PredicatePath;
+use crate::query::Query;
+use crate::shared_tree::SharedTree;
+
+/// Group that [`RuleList::with_group`] may name to apply to every client.
+pub const ALL_GROUPS: &str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessOperation {
+    Read,
+    Create,
+    Update,
+    Delete,
+    Exec,
+}
+
+impl AccessOperation {
+    pub const ALL: [Self; 5] = [
+        Self::Read,
+        Self::Create,
+        Self::Update,
+        Self::Delete,
+        Self::Exec,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Exec => "exec",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|operation| operation.as_str() == name)
+    }
+}
+
+impl fmt::Display for AccessOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleAction {
+    Permit,
+    Deny,
+}
+
+impl RuleAction {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "permit" => Some(Self::Permit),
+            "deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// What a rule applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleTarget {
+    /// Every data node and operation.
+    Any,
+    /// The data nodes a query selects, with their descendants.
+    Path(Query),
+    /// An operation by its schema path (`/m:reboot`), by its name alone
+    /// (`reboot`) in any module, or `*` for all of them.
+    Operation(String),
+}
+
+impl RuleTarget {
+    pub fn path(pattern: &str) -> Result<Self> {
+        Query::parse(pattern).map(Self::Path)
+    }
+
+    pub fn operation(name: impl Into<String>) -> Self {
+        Self::Operation(name.into())
+    }
+
+    fn names_operation(&self, path: &str) -> bool {
+        let Self::Operation(name) = self else {
+            return false;
+        };
+        if name == "*" || name == path {
+            return true;
+        }
+        !name.starts_with('/')
+            && path
+                .rsplit('/')
+                .next()
+                .is_some_and(|last| last.rsplit(':').next() == Some(name.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessRule {
+    pub name: String,
+    pub target: RuleTarget,
+    pub operations: Vec<AccessOperation>,
+    pub action: RuleAction,
+}
+
+impl AccessRule {
+    /// A rule taking `action` on every operation of `target`.
+    pub fn new(name: impl Into<String>, target: RuleTarget, action: RuleAction) -> Self {
+        Self {
+            name: name.into(),
+            target,
+            operations: AccessOperation::ALL.to_vec(),
+            action,
+        }
+    }
+
+    pub fn permit(name: impl Into<String>, target: RuleTarget) -> Self {
+        Self::new(name, target, RuleAction::Permit)
+    }
+
+    pub fn deny(name: impl Into<String>, target: RuleTarget) -> Self {
+        Self::new(name, target, RuleAction::Deny)
+    }
+
+    /// Restrict the rule to `operations`.
+    pub fn with_operations(
+        mut self,
+        operations: impl IntoIterator<Item = AccessOperation>,
+    ) -> Self {
+        self.operations = operations.into_iter().collect();
+        self
+    }
+
+    fn includes(&self, operation: AccessOperation) -> bool {
+        self.operations.contains(&operation)
+    }
+}
+
+/// Rules that apply to the members of some groups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleList {
+    pub name: String,
+    pub groups: Vec<String>,
+    pub rules: Vec<AccessRule>,
+}
+
+impl RuleList {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            groups: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Apply the list to the members of `group`, or to every client for
+    /// [`ALL_GROUPS`].
+    pub fn with_group(mut self, group: impl Into<String>) -> Self {
+        self.groups.push(group.into());
+        self
+    }
+
+    pub fn with_rule(mut self, rule: AccessRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+}
+
+/// Groups, rule lists and defaults deciding what each client may do.
+///
+/// By default reads and operations are permitted and writes denied, as
+/// in NACM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessControl {
+    pub enabled: bool,
+    pub read_default: RuleAction,
+    pub write_default: RuleAction,
+    pub exec_default: RuleAction,
+    /// Members of each group: peer identities, or bare IP addresses
+    /// matching a peer from any port.
+    pub groups: BTreeMap<String, Vec<String>>,
+    pub rule_lists: Vec<RuleList>,
+}
+
+impl Default for AccessControl {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            read_default: RuleAction::Permit,
+            write_default: RuleAction::Deny,
+            exec_default: RuleAction::Permit,
+            groups: BTreeMap::new(),
+            rule_lists: Vec::new(),
+        }
+    }
+}
+
+impl AccessControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn with_read_default(mut self, action: RuleAction) -> Self {
+        self.read_default = action;
+        self
+    }
+
+    pub fn with_write_default(mut self, action: RuleAction) -> Self {
+        self.write_default = action;
+        self
+    }
+
+    pub fn with_exec_default(mut self, action: RuleAction) -> Self {
+        self.exec_default = action;
+        self
+    }
+
+    /// Add `members` to `group`.
+    pub fn with_group<I, S>(mut self, group: impl Into<String>, members: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.groups
+            .entry(group.into())
+            .or_default()
+            .extend(members.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_rule_list(mut self, rule_list: RuleList) -> Self {
+        self.rule_lists.push(rule_list);
+        self
+    }
+
+    /// Read a configuration laid out as the `ietf-netconf-acm` `nacm`
+    /// container in JSON, with or without its module-qualified wrapper.
+    ///
+    /// Rules name their target with `path` or `rpc-name` (qualified by
+    /// `module-name`), or neither to match anything. `access-operations`
+    /// is `*` or a space-separated list and defaults to `*`.
+    pub fn from_json(config: &Value) -> Result<Self> {
+        let config = config.get("ietf-netconf-acm:nacm").unwrap_or(config);
+        let config = object(config, "nacm")?;
+        let mut access = Self::new();
+        if let Some(enabled) = config.get("enable-nacm") {
+            access.enabled = enabled
+                .as_bool()
+                .ok_or_else(|| invalid("enable-nacm must be a boolean"))?;
+        }
+        for (member, default) in [
+            ("read-default", &mut access.read_default),
+            ("write-default", &mut access.write_default),
+            ("exec-default", &mut access.exec_default),
+        ] {
+            if let Some(name) = text(config, member)? {
+                *default = RuleAction::parse(name)
+                    .ok_or_else(|| invalid(format!("{member} must be permit or deny")))?;
+            }
+        }
+
+        let groups = match config.get("groups") {
+            Some(groups) => entries(object(groups, "groups")?, "group")?,
+            None => &[],
+        };
+        for group in groups {
+            let group = object(group, "group")?;
+            let name = required_text(group, "name", "group")?;
+            let members = strings(group, "user-name")?;
+            access = access.with_group(name, members);
+        }
+
+        for list in entries(config, "rule-list")? {
+            let list = object(list, "rule-list")?;
+            let mut rule_list = RuleList::new(required_text(list, "name", "rule-list")?);
+            rule_list.groups = strings(list, "group")?;
+            for rule in entries(list, "rule")? {
+                rule_list = rule_list.with_rule(parse_rule(object(rule, "rule")?)?);
+            }
+            access = access.with_rule_list(rule_list);
+        }
+        Ok(access)
+    }
+
+    /// Check that every rule path names schema nodes of `model`.
+    pub fn validate(&self, model: &CompositeModel) -> Result<()> {
+        for rule in self.rule_lists.iter().flat_map(|list| &list.rules) {
+            if let RuleTarget::Path(query) = &rule.target {
+                query.run(model, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The groups `peer` belongs to.
+    pub fn groups_of(&self, peer: &str) -> Vec<&str> {
+        let host = peer
+            .parse::<SocketAddr>()
+            .ok()
+            .map(|address| address.ip().to_string());
+        self.groups
+            .iter()
+            .filter(|(_, members)| {
+                members
+                    .iter()
+                    .any(|member| member == peer || host.as_deref() == Some(member.as_str()))
+            })
+            .map(|(group, _)| group.as_str())
+            .collect()
+    }
+
+    /// The rules that apply to `peer`, in the order they are tried.
+    fn rules_for(&self, peer: &str) -> Vec<&AccessRule> {
+        let groups = self.groups_of(peer);
+        self.rule_lists
+            .iter()
+            .filter(|list| {
+                list.groups
+                    .iter()
+                    .any(|group| group == ALL_GROUPS || groups.contains(&group.as_str()))
+            })
+            .flat_map(|list| &list.rules)
+            .collect()
+    }
+
+    /// Whether deciding `operation` for `peer` depends on data: some rule
+    /// that applies to it has a path target.
+    pub(crate) fn matches_data(&self, peer: &str, operation: AccessOperation) -> bool {
+        self.rules_for(peer)
+            .iter()
+            .any(|rule| rule.includes(operation) && matches!(rule.target, RuleTarget::Path(_)))
+    }
+
+    /// Decide the accesses of `peer` to the data in `trees`, which rule
+    /// paths are matched against.
+    pub(crate) fn decider<'a>(
+        &'a self,
+        model: &'a CompositeModel,
+        peer: &str,
+        trees: &[&SharedTree],
+    ) -> Result<Decider<'a>> {
+        let rules = self.rules_for(peer);
+        let mut matches = Vec::with_capacity(rules.len());
+        for rule in &rules {
+            let mut selected = HashSet::new();
+            if let RuleTarget::Path(query) = &rule.target
+                && let Some(first) = query.steps.first()
+            {
+                for tree in trees {
+                    let root = tree.get(&storage_key(&first.name, 0));
+                    selected.extend(
+                        query
+                            .matching(model, root)?
+                            .into_iter()
+                            .map(|(path, _)| path),
+                    );
+                }
+            }
+            matches.push(selected);
+        }
+        Ok(Decider {
+            model,
+            access: self,
+            rules,
+            matches,
+        })
+    }
+
+    fn default_for(&self, operation: AccessOperation) -> RuleAction {
+        match operation {
+            AccessOperation::Read => self.read_default,
+            AccessOperation::Exec => self.exec_default,
+            _ => self.write_default,
+        }
+    }
+}
+
+/// The rules of one client, with the concrete nodes their paths select.
+pub(crate) struct Decider<'a> {
+    model: &'a CompositeModel,
+    access: &'a AccessControl,
+    rules: Vec<&'a AccessRule>,
+    matches: Vec<HashSet<String>>,
+}
+
+impl Decider<'_> {
+    /// `tree` without the nodes the client may not read. Entries that
+    /// stay keep their keys.
+    pub(crate) fn prune(&self, tree: &Value) -> Result<Value> {
+        let empty = Map::new();
+        let members = tree.as_object().unwrap_or(&empty);
+        Ok(Value::Object(self.prune_members("", "", members, None)?))
+    }
+
+    /// Add to `pruned` the part of `tree` a read of the schema node `read`
+    /// returns, without the nodes the client may not read. The read is
+    /// followed down to the first list, whose entries are pruned whole.
+    pub(crate) fn prune_read(
+        &self,
+        tree: &SharedTree,
+        read: &str,
+        pruned: &mut Value,
+    ) -> Result<()> {
+        let mut node = tree;
+        let mut schema = String::new();
+        let mut path = String::new();
+        let mut keys = Vec::new();
+        for (depth, segment) in split_canonical_segments(read).into_iter().enumerate() {
+            let key = storage_key(segment, depth);
+            let Some(child) = node.get(&key) else {
+                return Ok(());
+            };
+            schema = join_path(&schema, &key);
+            path = format!("{path}/{key}");
+            keys.push(key);
+            node = child;
+            if node.entries().is_some() {
+                break;
+            }
+        }
+        let Some((last, parents)) = keys.split_last() else {
+            return Ok(());
+        };
+        let inherited = self.covering(AccessOperation::Read, &path);
+        let Some(value) = self.prune_node(&schema, &path, &node.to_value(), inherited)? else {
+            return Ok(());
+        };
+        let mut parent = pruned;
+        for key in parents {
+            parent = ensure_object(parent)?
+                .entry(key.clone())
+                .or_insert_with(|| Value::Object(Map::new()));
+        }
+        ensure_object(parent)?.insert(last.clone(), value);
+        Ok(())
+    }
+
+    /// `value`, the node at the predicate path `path`, without what the
+    /// client may not read, or `None` if it may not read the node itself.
+    pub(crate) fn prune_at(&self, path: &str, value: &Value) -> Result<Option<Value>> {
+        if path.trim_end_matches('/').is_empty() {
+            return self.prune(value).map(Some);
+        }
+        let schema = PredicatePath::parse(path)?.canonical_path;
+        let inherited = self.covering(AccessOperation::Read, path);
+        self.prune_node(&schema, path, value, inherited)
+    }
+
+    fn prune_members(
+        &self,
+        schema: &str,
+        path: &str,
+        members: &Map<String, Value>,
+        inherited: Option<usize>,
+    ) -> Result<Map<String, Value>> {
+        let mut kept = Map::new();
+        for (name, value) in members {
+            let child_schema = join_path(schema, name);
+            let child_path = format!("{path}/{name}");
+            if let Some(value) = self.prune_node(&child_schema, &child_path, value, inherited)? {
+                kept.insert(name.clone(), value);
+            }
+        }
+        Ok(kept)
+    }
+
+    fn prune_node(
+        &self,
+        schema: &str,
+        path: &str,
+        value: &Value,
+        inherited: Option<usize>,
+    ) -> Result<Option<Value>> {
+        let rule = self.decide(AccessOperation::Read, inherited, path);
+        if !self.permits(AccessOperation::Read, rule) {
+            return Ok(None);
+        }
+        let pruned = match value {
+            Value::Array(entries) => {
+                let mut kept = Vec::with_capacity(entries.len());
+                for (entry_path, entry) in self.entries(schema, path, entries)? {
+                    let entry_rule = self.decide(AccessOperation::Read, rule, &entry_path);
+                    if !self.permits(AccessOperation::Read, entry_rule) {
+                        continue;
+                    }
+                    let Value::Object(members) = entry else {
+                        kept.push(entry.clone());
+                        continue;
+                    };
+                    let mut pruned =
+                        self.prune_members(schema, &entry_path, members, entry_rule)?;
+                    for (key, _) in list_keys(self.model, schema)? {
+                        if let Some(value) = members.get(&key) {
+                            pruned.insert(key, value.clone());
+                        }
+                    }
+                    kept.push(Value::Object(pruned));
+                }
+                Value::Array(kept)
+            }
+            Value::Object(members) => {
+                Value::Object(self.prune_members(schema, path, members, rule)?)
+            }
+            leaf => leaf.clone(),
+        };
+        Ok(Some(pruned))
+    }
+
+    /// Check that the client may make every edit of `diff`: create what it
+    /// adds, update what it modifies and delete what it removes, down to
+    /// each descendant of added and removed nodes.
+    pub(crate) fn check_edits(&self, diff: &Diff) -> Result<()> {
+        for edit in &diff.edits {
+            let operation = match edit.operation {
+                DiffOperation::Add => AccessOperation::Create,
+                DiffOperation::Modify => AccessOperation::Update,
+                DiffOperation::Delete => AccessOperation::Delete,
+            };
+            let schema = PredicatePath::parse(&edit.path)?.canonical_path;
+            let inherited = self.covering(operation, &edit.path);
+            let value = edit.after.as_ref().or(edit.before.as_ref());
+            self.check_node(operation, &schema, &edit.path, value, inherited)?;
+        }
+        Ok(())
+    }
+
+    fn check_node(
+        &self,
+        operation: AccessOperation,
+        schema: &str,
+        path: &str,
+        value: Option<&Value>,
+        inherited: Option<usize>,
+    ) -> Result<()> {
+        let rule = self.decide(operation, inherited, path);
+        if !self.permits(operation, rule) {
+            return Err(denied(operation, path));
+        }
+        match value {
+            Some(Value::Array(entries)) => {
+                for (entry_path, entry) in self.entries(schema, path, entries)? {
+                    let entry_rule = self.decide(operation, rule, &entry_path);
+                    if !self.permits(operation, entry_rule) {
+                        return Err(denied(operation, &entry_path));
+                    }
+                    if let Value::Object(members) = entry {
+                        self.check_members(operation, schema, &entry_path, members, entry_rule)?;
+                    }
+                }
+            }
+            Some(Value::Object(members)) => {
+                self.check_members(operation, schema, path, members, rule)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn check_members(
+        &self,
+        operation: AccessOperation,
+        schema: &str,
+        path: &str,
+        members: &Map<String, Value>,
+        inherited: Option<usize>,
+    ) -> Result<()> {
+        for (name, value) in members {
+            self.check_node(
+                operation,
+                &join_path(schema, name),
+                &format!("{path}/{name}"),
+                Some(value),
+                inherited,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Check that the client may invoke the operation at `path`.
+    pub(crate) fn check_exec(&self, path: &str) -> Result<()> {
+        let canonical = PredicatePath::parse(path)
+            .map(|parsed| parsed.canonical_path)
+            .unwrap_or_else(|_| path.to_string());
+        let rule = self.rules.iter().enumerate().position(|(index, rule)| {
+            rule.includes(AccessOperation::Exec)
+                && match &rule.target {
+                    RuleTarget::Any => true,
+                    RuleTarget::Path(_) => self.selects(index, path, true),
+                    RuleTarget::Operation(_) => rule.target.names_operation(&canonical),
+                }
+        });
+        if self.permits(AccessOperation::Exec, rule) {
+            Ok(())
+        } else {
+            Err(denied(AccessOperation::Exec, path))
+        }
+    }
+
+    /// The first rule deciding `operation` on the node at `path`, given
+    /// the rule `inherited` from its ancestors.
+    fn decide(
+        &self,
+        operation: AccessOperation,
+        inherited: Option<usize>,
+        path: &str,
+    ) -> Option<usize> {
+        let limit = inherited.unwrap_or(self.rules.len());
+        (0..limit)
+            .find(|&index| self.applies(index, operation, path, false))
+            .or(inherited)
+    }
+
+    /// The first rule deciding `operation` on `path` or any ancestor.
+    fn covering(&self, operation: AccessOperation, path: &str) -> Option<usize> {
+        (0..self.rules.len()).find(|&index| self.applies(index, operation, path, true))
+    }
+
+    fn applies(&self, index: usize, operation: AccessOperation, path: &str, within: bool) -> bool {
+        let rule = self.rules[index];
+        rule.includes(operation)
+            && match rule.target {
+                RuleTarget::Any => true,
+                RuleTarget::Path(_) => self.selects(index, path, within),
+                RuleTarget::Operation(_) => false,
+            }
+    }
+
+    /// Whether rule `index` selects `path`, or with `within` one of its
+    /// ancestors.
+    fn selects(&self, index: usize, path: &str, within: bool) -> bool {
+        let matches = &self.matches[index];
+        matches.contains(path)
+            || (within
+                && matches.iter().any(|selected| {
+                    path.strip_prefix(selected.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+                }))
+    }
+
+    fn permits(&self, operation: AccessOperation, rule: Option<usize>) -> bool {
+        let action = rule.map_or_else(
+            || self.access.default_for(operation),
+            |index| self.rules[index].action,
+        );
+        action == RuleAction::Permit
+    }
+
+    /// The entries of the list instance at `path` with their paths;
+    /// entries of keyless lists share the list's path.
+    fn entries<'v>(
+        &self,
+        schema: &str,
+        path: &str,
+        entries: &'v [Value],
+    ) -> Result<Vec<(String, &'v Value)>> {
+        let keys = if self.model.get_sid(schema).is_some() {
+            list_keys(self.model, schema)?
+        } else {
+            Vec::new()
+        };
+        if keys.is_empty() {
+            return Ok(entries
+                .iter()
+                .map(|entry| (path.to_string(), entry))
+                .collect());
+        }
+        let key_sids = self
+            .model
+            .get_sid(schema)
+            .and_then(|sid| self.model.get_keys(sid))
+            .unwrap_or_default();
+        let mut keyed = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let Some(key_values) = keys
+                .iter()
+                .map(|(leaf, _)| entry.get(leaf).cloned())
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let predicates = format_predicate_string(self.model, key_sids, &key_values)?;
+            keyed.push((format!("{path}{predicates}"), entry));
+        }
+        Ok(keyed)
+    }
+}
+
+fn parse_rule(rule: &Map<String, Value>) -> Result<AccessRule> {
+    let name = required_text(rule, "name", "rule")?;
+    let target = match (text(rule, "path")?, text(rule, "rpc-name")?) {
+        (Some(_), Some(_)) => {
+            return Err(invalid(format!(
+                "rule '{name}' has both a path and an rpc-name"
+            )));
+        }
+        (Some(path), None) => RuleTarget::path(path)?,
+        (None, Some(rpc)) => match text(rule, "module-name")? {
+            Some(module) if module != "*" && rpc != "*" => {
+                RuleTarget::operation(format!("/{module}:{rpc}"))
+            }
+            _ => RuleTarget::operation(rpc),
+        },
+        (None, None) => match text(rule, "module-name")? {
+            None | Some("*") => RuleTarget::Any,
+            Some(_) => {
+                return Err(invalid(format!(
+                    "rule '{name}' needs a path or an rpc-name to name a module"
+                )));
+            }
+        },
+    };
+    let action = RuleAction::parse(required_text(rule, "action", "rule")?)
+        .ok_or_else(|| invalid(format!("rule '{name}' action must be permit or deny")))?;
+    let mut parsed = AccessRule::new(name, target, action);
+    if let Some(operations) = text(rule, "access-operations")?
+        && operations != "*"
+    {
+        let operations = operations
+            .split_whitespace()
+            .map(|operation| {
+                AccessOperation::parse(operation).ok_or_else(|| {
+                    invalid(format!(
+                        "rule '{name}' has unknown access operation '{operation}'"
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        parsed = parsed.with_operations(operations);
+    }
+    Ok(parsed)
+}
+
+fn object<'v>(value: &'v Value, name: &str) -> Result<&'v Map<String, Value>> {
+    value
+        .as_object()
+        .ok_or_else(|| invalid(format!("{name} must be an object")))
+}
+
+fn text<'v>(object: &'v Map<String, Value>, member: &str) -> Result<Option<&'v str>> {
+    match object.get(member) {
+        None => Ok(None),
+        Some(value) => value
+            .as_str()
+            .map(Some)
+            .ok_or_else(|| invalid(format!("{member} must be a string"))),
+    }
+}
+
+fn required_text<'v>(
+    object: &'v Map<String, Value>,
+    member: &str,
+    within: &str,
+) -> Result<&'v str> {
+    text(object, member)?.ok_or_else(|| invalid(format!("every {within} needs a {member}")))
+}
+
+/// A leaf-list member, given as an array or a single string.
+fn strings(object: &Map<String, Value>, member: &str) -> Result<Vec<String>> {
+    let values = match object.get(member) {
+        None => return Ok(Vec::new()),
+        Some(Value::Array(values)) => values.as_slice(),
+        Some(value) => std::slice::from_ref(value),
+    };
+    values
+        .iter()
+        .map(|value| {
+            value
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| invalid(format!("{member} must hold strings")))
+        })
+        .collect()
+}
+
+fn entries<'v>(object: &'v Map<String, Value>, member: &str) -> Result<&'v [Value]> {
+    match object.get(member) {
+        None => Ok(&[]),
+        Some(Value::Array(values)) => Ok(values),
+        Some(_) => Err(invalid(format!("{member} must be an array"))),
+    }
+}
+
+fn invalid(message: impl Into<String>) -> coreconf_model::CoreconfError {
+    DataError::new(ErrorTag::InvalidValue, message).into()
+}
+
+fn denied(operation: AccessOperation, path: &str) -> coreconf_model::CoreconfError {
+    DataError::new(ErrorTag::AccessDenied, format!("{operation} access denied"))
+        .with_path(path)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn nacm_json_becomes_groups_and_ordered_rules() {
+        let access = AccessControl::from_json(&json!({
+            "ietf-netconf-acm:nacm": {
+                "write-default": "permit",
+                "groups": {"group": [
+                    {"name": "operators", "user-name": ["10.0.0.7", "sensor-1"]}
+                ]},
+                "rule-list": [{
+                    "name": "ops",
+                    "group": ["operators"],
+                    "rule": [
+                        {"name": "no-reboot", "module-name": "m", "rpc-name": "reboot",
+                         "access-operations": "exec", "action": "deny"},
+                        {"name": "read-only", "path": "/m:settings",
+                         "access-operations": "create update delete", "action": "deny"},
+                        {"name": "rest", "action": "permit"}
+                    ]
+                }]
+            }
+        }))
+        .unwrap();
+
+        assert!(access.enabled);
+        assert_eq!(access.write_default, RuleAction::Permit);
+        assert_eq!(access.groups_of("10.0.0.7:5683"), ["operators"]);
+        assert_eq!(access.groups_of("sensor-1"), ["operators"]);
+        assert!(access.groups_of("10.0.0.8:5683").is_empty());
+
+        let rules = &access.rule_lists[0].rules;
+        assert_eq!(rules[0].target, RuleTarget::operation("/m:reboot"));
+        assert_eq!(rules[0].operations, [AccessOperation::Exec]);
+        assert_eq!(rules[1].target, RuleTarget::path("/m:settings").unwrap());
+        assert_eq!(rules[2].target, RuleTarget::Any);
+        assert_eq!(rules[2].operations, AccessOperation::ALL);
+    }
+
+    #[test]
+    fn malformed_rules_are_rejected() {
+        for rule in [
+            json!({"name": "r", "action": "allow"}),
+            json!({"name": "r", "action": "deny", "access-operations": "write"}),
+            json!({"name": "r", "action": "deny", "path": "/m:a", "rpc-name": "x"}),
+            json!({"name": "r", "action": "deny", "module-name": "m"}),
+        ] {
+            let config = json!({"rule-list": [{"name": "l", "group": "*", "rule": [rule]}]});
+            assert!(AccessControl::from_json(&config).is_err(), "{config}");
+        }
+    }
+
+    #[test]
+    fn operation_names_match_with_or_without_their_module() {
+        let qualified = RuleTarget::operation("/m:reboot");
+        let bare = RuleTarget::operation("reboot");
+        assert!(qualified.names_operation("/m:reboot"));
+        assert!(!qualified.names_operation("/n:reboot"));
+        assert!(bare.names_operation("/n:reboot"));
+        assert!(bare.names_operation("/m:interfaces/interface/reboot"));
+        assert!(RuleTarget::operation("*").names_operation("/m:anything"));
+    }
+}
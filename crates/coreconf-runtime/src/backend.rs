@@ -1,9 +1,198 @@
+use std::collections::VecDeque;
+
+use coreconf_model::{CompositeModel, Result};
+use serde_json::Value;
+
+use crate::datastore::{tree_delete, tree_get, tree_set};
+use crate::shared_tree::SharedTree;
+
+/// Number of changes a [`ChangeLog`] keeps unless configured otherwise.
+pub const DEFAULT_CHANGE_LOG_CAPACITY: usize = 256;
+
 pub trait Backend: Send + Sync {
-    fn read_tree(&self) -> serde_json::Value;
+    /// The complete tree. Fails only for backends whose storage can fail
+    /// to read.
+    fn read_tree(&self) -> Result<Value>;
 
     /// Replace the complete tree.
     ///
     /// A backend returning `Err` must leave the previously published tree
     /// unchanged.  Root iPATCH candidate atomicity relies on this contract.
-    fn replace_tree(&mut self, next: serde_json::Value) -> coreconf_model::Result<()>;
+    fn replace_tree(&mut self, next: Value) -> Result<()>;
+
+    /// The tree as a [`SharedTree`]. The default converts [`Backend::read_tree`];
+    /// backends that keep a `SharedTree` hand out a clone sharing it.
+    fn snapshot(&self) -> Result<SharedTree> {
+        self.read_tree().map(SharedTree::from)
+    }
+
+    /// Replace the complete tree with a snapshot, under the contract of
+    /// [`Backend::replace_tree`].
+    fn replace_snapshot(&mut self, next: SharedTree) -> Result<()> {
+        self.replace_tree(next.to_value())
+    }
+
+    /// Whether the tree holds no data. The default takes a
+    /// [`Backend::snapshot`]; backends that would have to load the tree for
+    /// that override it.
+    fn is_empty(&self) -> Result<bool> {
+        Ok(self.snapshot()?.is_empty())
+    }
+
+    /// Read the node at a predicate path such as
+    /// `/example:devices/device[id='rdc-1']/enabled`.
+    ///
+    /// The default reads the whole tree; backends with granular storage
+    /// override this to load only the records along `path`.
+    fn read_path(&self, model: &CompositeModel, path: &str) -> Result<Option<Value>> {
+        tree_get(&self.read_tree()?, model, path, None)
+    }
+
+    /// The top-level node stored under `name`, such as `example:sensors`.
+    ///
+    /// The default reads it through [`Backend::read_path`]; backends that
+    /// keep a `SharedTree` hand out the node they share.
+    fn read_top_level(&self, model: &CompositeModel, name: &str) -> Result<Option<SharedTree>> {
+        Ok(self
+            .read_path(model, &format!("/{name}"))?
+            .map(SharedTree::from))
+    }
+
+    /// Write `value` at a predicate path, creating missing ancestors and list
+    /// entries. Same failure contract as [`Backend::replace_tree`].
+    fn write_path(&mut self, model: &CompositeModel, path: &str, value: Value) -> Result<()> {
+        let mut tree = self.read_tree()?;
+        tree_set(&mut tree, model, path, value, None)?;
+        self.replace_tree(tree)
+    }
+
+    /// Delete the node at a predicate path, returning whether it existed.
+    fn delete_path(&mut self, model: &CompositeModel, path: &str) -> Result<bool> {
+        let mut tree = self.read_tree()?;
+        let deleted = tree_delete(&mut tree, model, path, None)?;
+        self.replace_tree(tree)?;
+        Ok(deleted)
+    }
+
+    /// Apply `edits` in order as one write, deleting where the value is
+    /// `None`.
+    ///
+    /// Backends that can make a batch durable as a unit, such as one log
+    /// record or one transaction, override this so a crash never leaves
+    /// part of it. The default writes path by path; if that fails part way,
+    /// earlier edits stay applied and the caller puts back the tree it had.
+    fn apply_edits(
+        &mut self,
+        model: &CompositeModel,
+        edits: &[(String, Option<Value>)],
+    ) -> Result<()> {
+        for (path, value) in edits {
+            match value {
+                Some(value) => self.write_path(model, path, value.clone())?,
+                None => {
+                    self.delete_path(model, path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sequence number of the last recorded change, or `None` if the backend
+    /// keeps no change log.
+    fn last_change(&self) -> Option<u64> {
+        None
+    }
+
+    /// Changes recorded after sequence number `since`, oldest first.
+    ///
+    /// `None` if the backend keeps no change log or has already dropped
+    /// some of those changes; callers then fall back to comparing trees.
+    fn changes_since(&self, since: u64) -> Option<Box<dyn Iterator<Item = &Change> + '_>> {
+        let _ = since;
+        None
+    }
+}
+
+/// One write recorded in a [`ChangeLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub sequence: u64,
+    /// Predicate path that was written or deleted; `/` for a whole-tree
+    /// replacement.
+    pub path: String,
+}
+
+/// Bounded log of the paths a backend has written, numbered from 1.
+#[derive(Debug, Clone)]
+pub struct ChangeLog {
+    changes: VecDeque<Change>,
+    capacity: usize,
+    last: u64,
+}
+
+impl Default for ChangeLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHANGE_LOG_CAPACITY)
+    }
+}
+
+impl ChangeLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            changes: VecDeque::new(),
+            capacity,
+            last: 0,
+        }
+    }
+
+    /// Record a write to `path` and return its sequence number.
+    pub fn record(&mut self, path: impl Into<String>) -> u64 {
+        self.last += 1;
+        self.changes.push_back(Change {
+            sequence: self.last,
+            path: path.into(),
+        });
+        while self.changes.len() > self.capacity {
+            self.changes.pop_front();
+        }
+        self.last
+    }
+
+    pub fn last(&self) -> u64 {
+        self.last
+    }
+
+    /// Changes after `since`, or `None` if some have been dropped.
+    pub fn since(&self, since: u64) -> Option<impl Iterator<Item = &Change>> {
+        let oldest = self
+            .changes
+            .front()
+            .map_or(self.last + 1, |change| change.sequence);
+        if since < self.last && since + 1 < oldest {
+            return None;
+        }
+        Some(
+            self.changes
+                .iter()
+                .filter(move |change| change.sequence > since),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn change_log_reports_dropped_changes() {
+        let mut log = ChangeLog::new(2);
+        for path in ["/a", "/b", "/c"] {
+            log.record(path);
+        }
+
+        assert!(log.since(0).is_none());
+        let paths: Vec<_> = log.since(1).unwrap().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["/b", "/c"]);
+        assert_eq!(log.since(3).unwrap().count(), 0);
+    }
 }
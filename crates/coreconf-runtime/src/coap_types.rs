@@ -1,3 +1,9 @@
+use coreconf_model::{CoreconfError, DataError, ErrorTag};
+
+use crate::datastore::Position;
+use crate::error_payload::encode_error_payload;
+use crate::nmda::DatastoreName;
+
 /// CORECONF interface type — distinguishes management (`/c`) from streaming (`/s`).
 ///
 /// Per draft-ietf-core-comi:
@@ -37,6 +43,10 @@ pub enum ContentFormat {
     /// Legacy/internal yang-instances+cbor-seq value retained for compatibility
     /// with existing runtime helpers.
     YangInstancesCborSeq = 143,
+    /// application/yang-patch+cbor: a name-keyed YANG Patch (RFC 8072) for
+    /// iPATCH. No number is registered for it yet, so it takes one from the
+    /// experimental range (RFC 7252 section 12.3).
+    YangPatchCbor = 65000,
 }
 
 impl ContentFormat {
@@ -45,6 +55,7 @@ impl ContentFormat {
             141 => Some(Self::YangIdentifiersCbor),
             142 => Some(Self::YangDataCbor),
             143 => Some(Self::YangInstancesCborSeq),
+            65000 => Some(Self::YangPatchCbor),
             _ => None,
         }
     }
@@ -83,10 +94,12 @@ pub enum ResponseCode {
     BadRequest,
     Unauthorized,
     BadOption,
+    Forbidden,
     NotFound,
     MethodNotAllowed,
     RequestEntityIncomplete,
     Conflict,
+    PreconditionFailed,
     RequestEntityTooLarge,
     UnsupportedContentFormat,
     InternalServerError,
@@ -108,10 +121,12 @@ impl ResponseCode {
             Self::BadRequest => (4, 0),
             Self::Unauthorized => (4, 1),
             Self::BadOption => (4, 2),
+            Self::Forbidden => (4, 3),
             Self::NotFound => (4, 4),
             Self::MethodNotAllowed => (4, 5),
             Self::RequestEntityIncomplete => (4, 8),
             Self::Conflict => (4, 9),
+            Self::PreconditionFailed => (4, 12),
             Self::RequestEntityTooLarge => (4, 13),
             Self::UnsupportedContentFormat => (4, 15),
             Self::InternalServerError => (5, 0),
@@ -121,6 +136,38 @@ impl ResponseCode {
     pub fn is_success(self) -> bool {
         matches!(self, Self::Created | Self::Changed | Self::Content)
     }
+
+    /// Response code for a YANG error-tag, following RFC 8040 section 7
+    /// with the HTTP status mapped onto its CoAP counterpart.
+    pub fn from_error_tag(tag: ErrorTag) -> Self {
+        match tag {
+            ErrorTag::InUse
+            | ErrorTag::LockDenied
+            | ErrorTag::ResourceDenied
+            | ErrorTag::DataExists => Self::Conflict,
+            ErrorTag::TooBig => Self::RequestEntityTooLarge,
+            ErrorTag::AccessDenied => Self::Forbidden,
+            ErrorTag::DataMissing => Self::NotFound,
+            ErrorTag::OperationNotSupported => Self::MethodNotAllowed,
+            ErrorTag::RollbackFailed | ErrorTag::OperationFailed | ErrorTag::PartialOperation => {
+                Self::InternalServerError
+            }
+            _ => Self::BadRequest,
+        }
+    }
+
+    /// Response code for a failed request.
+    ///
+    /// Structured [`CoreconfError::Data`] errors map through their error-tag.
+    /// Free-form variants don't say enough about where they came from, so the
+    /// caller supplies the code it reports for that operation.
+    pub fn from_error(error: &CoreconfError, fallback: Self) -> Self {
+        match error {
+            CoreconfError::Data(data) => Self::from_error_tag(data.tag),
+            CoreconfError::UnsupportedContentFormat => Self::UnsupportedContentFormat,
+            _ => fallback,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -163,6 +210,18 @@ impl DefaultsParam {
 pub struct QueryParams {
     pub content: ContentParam,
     pub defaults: DefaultsParam,
+    /// Target datastore (`ds=`); reads default to operational and edits
+    /// to running.
+    pub datastore: Option<DatastoreName>,
+    /// A `ds=` value naming no datastore the server knows; such requests
+    /// are rejected rather than served from the default datastore.
+    pub unknown_datastore: Option<String>,
+    /// Where a path iPATCH places its list entry (`insert=`: first, last,
+    /// before or after), in an `ordered-by user` list.
+    pub insert: Option<String>,
+    /// The entry `insert=before` and `insert=after` are relative to
+    /// (`point=`), as a predicate path.
+    pub point: Option<String>,
 }
 
 impl QueryParams {
@@ -181,12 +240,44 @@ impl QueryParams {
                             params.defaults = defaults;
                         }
                     }
+                    "ds" => match DatastoreName::parse(value) {
+                        Some(datastore) => {
+                            params.datastore = Some(datastore);
+                            params.unknown_datastore = None;
+                        }
+                        None => params.unknown_datastore = Some(value.to_string()),
+                    },
+                    "insert" => params.insert = Some(value.to_string()),
+                    "point" => params.point = Some(value.to_string()),
                     _ => {}
                 }
             }
         }
         params
     }
+
+    /// The entry position requested by `insert=` and `point=`, if any.
+    pub fn position(&self) -> Result<Option<Position>, CoreconfError> {
+        let position = match (self.insert.as_deref(), self.point.clone()) {
+            (None, None) => return Ok(None),
+            (Some("first"), None) => Position::First,
+            (Some("last"), None) => Position::Last,
+            (Some("before"), Some(point)) => Position::Before(point),
+            (Some("after"), Some(point)) => Position::After(point),
+            (insert, _) => {
+                return Err(DataError::new(
+                    ErrorTag::InvalidValue,
+                    format!(
+                        "invalid insert '{}'; point goes with insert=before or insert=after",
+                        insert.unwrap_or_default()
+                    ),
+                )
+                .with_app_tag("invalid-insert")
+                .into());
+            }
+        };
+        Ok(Some(position))
+    }
 }
 
 pub mod resource_types {
@@ -212,6 +303,13 @@ pub struct Request {
     pub observe: Option<u32>,
     /// CoAP token for matching requests to responses / observer state.
     pub token: Vec<u8>,
+    /// Transport address of the client, when the transport knows it.
+    pub peer: Option<String>,
+    /// CoAP If-Match entity-tags; an empty tag matches any existing
+    /// representation.
+    pub if_match: Vec<Vec<u8>>,
+    /// CoAP If-None-Match: only act if the target resource does not exist.
+    pub if_none_match: bool,
 }
 
 impl Request {
@@ -226,6 +324,9 @@ impl Request {
             interface: None,
             observe: None,
             token: Vec::new(),
+            peer: None,
+            if_match: Vec::new(),
+            if_none_match: false,
         }
     }
 
@@ -234,6 +335,11 @@ impl Request {
         self
     }
 
+    pub fn with_peer(mut self, peer: impl Into<String>) -> Self {
+        self.peer = Some(peer.into());
+        self
+    }
+
     pub fn with_path(mut self, path: impl Into<String>) -> Self {
         self.path = path.into();
         self
@@ -260,6 +366,26 @@ impl Request {
         self.observe = Some(observe);
         self
     }
+
+    pub fn with_if_match(mut self, etag: impl Into<Vec<u8>>) -> Self {
+        self.if_match.push(etag.into());
+        self
+    }
+
+    pub fn with_if_none_match(mut self) -> Self {
+        self.if_none_match = true;
+        self
+    }
+
+    /// Whether the request only reads: GET, and FETCH other than Observe
+    /// registrations on the streaming interface.
+    pub fn is_read(&self) -> bool {
+        match self.method {
+            Method::Get => self.interface != Some(Interface::Streaming),
+            Method::Fetch => self.interface != Some(Interface::Streaming) || self.observe.is_none(),
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -269,6 +395,10 @@ pub struct Response {
     pub content_format: Option<ContentFormat>,
     /// CoAP Observe sequence number (present on notifications).
     pub observe: Option<u32>,
+    /// CoAP Max-Age in seconds, for content computed by subtree providers.
+    pub max_age: Option<u32>,
+    /// CoAP ETag of the resource the response describes.
+    pub etag: Option<Vec<u8>>,
 }
 
 impl Response {
@@ -278,6 +408,8 @@ impl Response {
             payload,
             content_format: Some(format),
             observe: None,
+            max_age: None,
+            etag: None,
         }
     }
 
@@ -287,6 +419,8 @@ impl Response {
             payload,
             content_format: Some(format),
             observe: Some(sequence),
+            max_age: None,
+            etag: None,
         }
     }
 
@@ -296,6 +430,8 @@ impl Response {
             payload: Vec::new(),
             content_format: None,
             observe: None,
+            max_age: None,
+            etag: None,
         }
     }
 
@@ -305,20 +441,62 @@ impl Response {
             payload: message.as_bytes().to_vec(),
             content_format: None,
             observe: None,
+            max_age: None,
+            etag: None,
+        }
+    }
+
+    /// Error response carrying a CORECONF error payload (the
+    /// `ietf-coreconf:error` container) for `error`.
+    pub fn data_error(code: ResponseCode, error: &DataError) -> Self {
+        match encode_error_payload(error) {
+            Ok(payload) => Self {
+                code,
+                payload,
+                content_format: Some(ContentFormat::YangDataCbor),
+                observe: None,
+                max_age: None,
+                etag: None,
+            },
+            Err(_) => Self::error(code, &error.to_string()),
         }
     }
 
+    /// Error response for `error`, using the code its error-tag maps to and
+    /// `fallback` for errors that carry no structured details.
+    pub fn from_error(error: &CoreconfError, fallback: ResponseCode) -> Self {
+        let code = ResponseCode::from_error(error, fallback);
+        match error.data() {
+            Some(data) => Self::data_error(code, data),
+            None => Self::data_error(code, &DataError::new(error.error_tag(), error.to_string())),
+        }
+    }
+
+    pub fn with_max_age(mut self, seconds: u32) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    pub fn with_etag(mut self, etag: impl Into<Vec<u8>>) -> Self {
+        self.etag = Some(etag.into());
+        self
+    }
+
     pub fn not_found(path: &str) -> Self {
-        Self::error(
+        Self::data_error(
             ResponseCode::NotFound,
-            &format!("Resource not found: {path}"),
+            &DataError::new(ErrorTag::DataMissing, format!("Resource not found: {path}"))
+                .with_path(path),
         )
     }
 
     pub fn method_not_allowed(method: Method) -> Self {
-        Self::error(
+        Self::data_error(
             ResponseCode::MethodNotAllowed,
-            &format!("Method {method} not allowed"),
+            &DataError::new(
+                ErrorTag::OperationNotSupported,
+                format!("Method {method} not allowed"),
+            ),
         )
     }
 }
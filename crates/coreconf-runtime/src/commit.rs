@@ -0,0 +1,129 @@
+use std::time::{Duration, Instant};
+
+use coreconf_model::{DataError, ErrorTag, Result};
+use serde_json::Value;
+
+use crate::shared_tree::SharedTree;
+
+/// `ietf-netconf` operations served on the CORECONF resource via POST.
+pub const COMMIT_PATH: &str = "/ietf-netconf:commit";
+pub const CANCEL_COMMIT_PATH: &str = "/ietf-netconf:cancel-commit";
+pub const DISCARD_CHANGES_PATH: &str = "/ietf-netconf:discard-changes";
+
+/// Confirm timeout used when a confirmed commit gives none (RFC 6241
+/// section 8.4.5.1).
+pub const DEFAULT_CONFIRM_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// An outstanding confirmed commit.
+///
+/// Unless a confirming commit arrives before `deadline`, running is put back
+/// to `rollback_tree`, the tree it held before the first unconfirmed commit.
+#[derive(Debug, Clone)]
+pub struct PendingConfirm {
+    pub rollback_tree: SharedTree,
+    pub deadline: Instant,
+}
+
+impl PendingConfirm {
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now >= self.deadline
+    }
+}
+
+/// Parse the input of a commit request into its confirm timeout.
+///
+/// The input is a name-keyed map following the `ietf-netconf` commit RPC:
+/// `confirmed` (present or `true`) asks for a confirmed commit and
+/// `confirm-timeout` gives its timeout in seconds. Names may carry the
+/// `ietf-netconf:` prefix. `None` means a plain, confirming commit.
+pub fn parse_commit_input(input: Option<&Value>) -> Result<Option<Duration>> {
+    let Some(input) = input else {
+        return Ok(None);
+    };
+    let Some(fields) = input.as_object() else {
+        return Err(invalid_input("commit input must be a map"));
+    };
+
+    let mut confirmed = false;
+    let mut timeout = None;
+    for (name, value) in fields {
+        match name.strip_prefix("ietf-netconf:").unwrap_or(name) {
+            "confirmed" => confirmed = !matches!(value, Value::Bool(false)),
+            "confirm-timeout" => {
+                let seconds = value
+                    .as_u64()
+                    .filter(|seconds| *seconds > 0 && *seconds <= u64::from(u32::MAX))
+                    .ok_or_else(|| {
+                        DataError::new(
+                            ErrorTag::InvalidValue,
+                            "confirm-timeout must be a positive uint32",
+                        )
+                        .with_path("/ietf-netconf:commit/confirm-timeout")
+                        .with_value(value.clone())
+                    })?;
+                timeout = Some(Duration::from_secs(seconds));
+            }
+            other => {
+                return Err(DataError::new(
+                    ErrorTag::UnknownElement,
+                    format!("unknown commit input '{other}'"),
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(confirmed.then(|| timeout.unwrap_or(DEFAULT_CONFIRM_TIMEOUT)))
+}
+
+/// Build the input of a commit request; the inverse of [`parse_commit_input`].
+pub fn commit_input(confirm_timeout: Option<Duration>) -> Option<Value> {
+    confirm_timeout.map(|timeout| {
+        serde_json::json!({
+            "confirmed": true,
+            "confirm-timeout": timeout.as_secs().max(1),
+        })
+    })
+}
+
+fn invalid_input(message: &str) -> coreconf_model::CoreconfError {
+    DataError::new(ErrorTag::InvalidValue, message)
+        .with_path(COMMIT_PATH)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn commit_input_round_trips_confirm_timeout() {
+        let input = commit_input(Some(Duration::from_secs(30)));
+
+        assert_eq!(
+            parse_commit_input(input.as_ref()).unwrap(),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse_commit_input(None).unwrap(), None);
+    }
+
+    #[test]
+    fn confirmed_commit_without_timeout_uses_default() {
+        let input = json!({"ietf-netconf:confirmed": null});
+
+        assert_eq!(
+            parse_commit_input(Some(&input)).unwrap(),
+            Some(DEFAULT_CONFIRM_TIMEOUT)
+        );
+    }
+
+    #[test]
+    fn commit_input_rejects_zero_timeout() {
+        let input = json!({"confirmed": true, "confirm-timeout": 0});
+
+        let error = parse_commit_input(Some(&input)).unwrap_err();
+
+        assert_eq!(error.error_tag(), ErrorTag::InvalidValue);
+    }
+}
@@ -1,25 +1,46 @@
-use coreconf_model::instance_id::{decode_instances_with_model, PathComponent};
+use coreconf_model::instance_id::{PathComponent, decode_instances_with_model};
 use coreconf_model::{
-    CompositeModel, CoreconfError, CoreconfModel, Instance, InstancePath, Result, YangType,
+    CompositeModel, CoreconfError, CoreconfModel, DataError, ErrorTag, Instance, InstancePath,
+    Result, YangType,
 };
 use serde_json::{Map, Value};
+use std::sync::Arc;
 
-use crate::backend::Backend;
+use crate::backend::{Backend, Change};
+use crate::diff::{Diff, diff_trees};
+use crate::list_index::{EntrySlot, ListIndex};
 use crate::memory_backend::MemoryBackend;
-use crate::path::PredicatePath;
+use crate::path::{PredicatePath, entry_list_path};
+use crate::query::Query;
+use crate::shared_tree::SharedTree;
+use crate::subscription::{ChangeEvent, SubscriptionId, Subscriptions, Watched};
+use crate::yang_patch::{YangPatch, apply_edit};
+
+/// Where [`Datastore::insert_entry`] and [`Datastore::move_entry`] put a
+/// list entry. `Before` and `After` name another entry of the same list by
+/// its predicate path.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Position {
+    First,
+    #[default]
+    Last,
+    Before(String),
+    After(String),
+}
 
 pub struct Datastore {
-    model: CompositeModel,
+    model: Arc<CompositeModel>,
     backend: Box<dyn Backend>,
+    subscriptions: Subscriptions,
 }
 
 impl Datastore {
     pub fn new(model: CoreconfModel) -> Self {
-        Self::new_in_memory(model.composite_model().clone())
+        Self::new_in_memory(model.shared_composite_model())
     }
 
     pub fn with_data(model: CoreconfModel, data: Value) -> Self {
-        Self::with_backend(model.composite_model().clone(), MemoryBackend::new(data))
+        Self::with_backend(model.shared_composite_model(), MemoryBackend::new(data))
     }
 
     pub fn from_json(model: CoreconfModel, json: &str) -> Result<Self> {
@@ -27,7 +48,7 @@ impl Datastore {
         Ok(Self::with_data(model, data))
     }
 
-    pub fn from_json_with_model(model: CompositeModel, json: &str) -> Result<Self> {
+    pub fn from_json_with_model(model: impl Into<Arc<CompositeModel>>, json: &str) -> Result<Self> {
         let data: Value = serde_json::from_str(json)?;
         Ok(Self::with_backend(model, MemoryBackend::new(data)))
     }
@@ -58,8 +79,7 @@ impl Datastore {
     ///
     /// Instance IDs with list-key values navigate into keyed list entries.
     pub fn from_cbor_instance_seq(model: CoreconfModel, cbor: &[u8]) -> Result<Self> {
-        let composite = model.composite_model().clone();
-        let mut ds = Datastore::new_in_memory(composite);
+        let mut ds = Datastore::new_in_memory(model.shared_composite_model());
         ds.apply_instance_seq(cbor)?;
         Ok(ds)
     }
@@ -70,56 +90,58 @@ impl Datastore {
     /// resolved predicate path.  Instance IDs with key values navigate into
     /// keyed list entries.
     pub fn apply_instance_seq(&mut self, cbor: &[u8]) -> Result<()> {
-        for instance in decode_instances_with_model(&self.model, cbor)? {
-            let Some(sid) = instance.path.absolute_sid() else {
-                continue;
-            };
+        self.notifying(|datastore| {
+            for instance in decode_instances_with_model(&datastore.model, cbor)? {
+                let Some(sid) = instance.path.absolute_sid() else {
+                    continue;
+                };
 
-            let key_values: Vec<Value> = instance
-                .path
-                .components
-                .iter()
-                .filter_map(|c| match c {
-                    coreconf_model::instance_id::PathComponent::KeyValue(v) => Some(v.clone()),
-                    _ => None,
-                })
-                .collect();
+                let key_values: Vec<Value> = instance
+                    .path
+                    .components
+                    .iter()
+                    .filter_map(|c| match c {
+                        coreconf_model::instance_id::PathComponent::KeyValue(v) => Some(v.clone()),
+                        _ => None,
+                    })
+                    .collect();
 
-            if key_values.is_empty() {
-                let identifier = self
-                    .model
-                    .get_identifier(sid)
-                    .ok_or(CoreconfError::IdentifierNotFound(sid))?
-                    .to_string();
-                if let Some(value) = instance.value {
-                    // Instance values from FETCH responses are in CORECONF
-                    // (SID-keyed) format.  Convert to identifier keys, then
-                    // unwrap the top-level container key so set_path doesn't
-                    // double-nest it.
-                    let id_value = self
+                if key_values.is_empty() {
+                    let identifier = datastore
                         .model
-                        .sid_value_to_identifier_value_preserve_sids(value)?;
-                    let leaf = identifier.rsplit('/').next().unwrap_or(&identifier);
-                    let unwrapped = id_value.get(leaf).cloned().unwrap_or(id_value);
-                    self.set_path(&identifier, unwrapped)?;
-                } else {
-                    self.delete_path(&identifier)?;
-                }
-            } else {
-                let xpath = self.create_xpath(sid, &key_values)?;
-                if let Some(value) = instance.value {
-                    // For leaf values inside keyed lists, the value is
-                    // typically a scalar or small map — no unwrapping needed.
-                    let id_value = self
-                        .model
-                        .sid_value_to_identifier_value_preserve_sids(value)?;
-                    self.set_path(&xpath, id_value)?;
+                        .get_identifier(sid)
+                        .ok_or(CoreconfError::IdentifierNotFound(sid))?
+                        .to_string();
+                    if let Some(value) = instance.value {
+                        // Instance values from FETCH responses are in CORECONF
+                        // (SID-keyed) format.  Convert to identifier keys, then
+                        // unwrap the top-level container key so set_path doesn't
+                        // double-nest it.
+                        let id_value = datastore
+                            .model
+                            .sid_value_to_identifier_value_preserve_sids(value)?;
+                        let leaf = identifier.rsplit('/').next().unwrap_or(&identifier);
+                        let unwrapped = id_value.get(leaf).cloned().unwrap_or(id_value);
+                        datastore.set_path(&identifier, unwrapped)?;
+                    } else {
+                        datastore.delete_path(&identifier)?;
+                    }
                 } else {
-                    self.delete_path(&xpath)?;
+                    let xpath = datastore.create_xpath(sid, &key_values)?;
+                    if let Some(value) = instance.value {
+                        // For leaf values inside keyed lists, the value is
+                        // typically a scalar or small map — no unwrapping needed.
+                        let id_value = datastore
+                            .model
+                            .sid_value_to_identifier_value_preserve_sids(value)?;
+                        datastore.set_path(&xpath, id_value)?;
+                    } else {
+                        datastore.delete_path(&xpath)?;
+                    }
                 }
             }
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
     /// Replace the entire datastore tree with a decoded CBOR payload.
@@ -131,20 +153,31 @@ impl Datastore {
         let value = self
             .model
             .sid_value_to_identifier_value_preserve_sids(coreconf_value)?;
-        self.backend.replace_tree(value)
+        self.replace_tree(value)
     }
 
-    pub fn new_in_memory(model: CompositeModel) -> Self {
-        Self {
-            model,
-            backend: Box::new(MemoryBackend::default()),
-        }
+    /// Create an empty in-memory datastore.
+    ///
+    /// The model is shared, not copied: pass an `Arc<CompositeModel>` to
+    /// reuse one model across datastores, candidates and transports.
+    pub fn new_in_memory(model: impl Into<Arc<CompositeModel>>) -> Self {
+        Self::with_backend(model, MemoryBackend::default())
+    }
+
+    /// An in-memory datastore starting from `tree`, sharing its subtrees
+    /// until they are written.
+    pub fn from_snapshot(model: impl Into<Arc<CompositeModel>>, tree: SharedTree) -> Self {
+        Self::with_backend(model, MemoryBackend::from_snapshot(tree))
     }
 
-    pub fn with_backend(model: CompositeModel, backend: impl Backend + 'static) -> Self {
+    pub fn with_backend(
+        model: impl Into<Arc<CompositeModel>>,
+        backend: impl Backend + 'static,
+    ) -> Self {
         Self {
-            model,
+            model: model.into(),
             backend: Box::new(backend),
+            subscriptions: Subscriptions::default(),
         }
     }
 
@@ -152,17 +185,63 @@ impl Datastore {
         &self.model
     }
 
-    pub fn get_all(&self) -> Value {
+    /// The shared model handle; cloning it is a reference-count bump.
+    pub fn shared_model(&self) -> &Arc<CompositeModel> {
+        &self.model
+    }
+
+    /// The complete tree. Fails only when the backend can't read its
+    /// storage.
+    pub fn get_all(&self) -> Result<Value> {
         self.backend.read_tree()
     }
 
     /// Publish a complete datastore tree through the configured backend.
     pub fn replace_tree(&mut self, tree: Value) -> Result<()> {
-        self.backend.replace_tree(tree)
+        self.notifying(|datastore| datastore.backend.replace_tree(tree))
+    }
+
+    /// The tree as a [`SharedTree`]. For in-memory datastores this copies
+    /// nothing; the snapshot is unaffected by later writes.
+    pub fn snapshot(&self) -> Result<SharedTree> {
+        self.backend.snapshot()
+    }
+
+    /// Whether the datastore holds no data, without reading it out.
+    pub fn is_empty(&self) -> Result<bool> {
+        self.backend.is_empty()
+    }
+
+    /// Publish a snapshot as the complete tree.
+    pub fn replace_snapshot(&mut self, tree: SharedTree) -> Result<()> {
+        self.notifying(|datastore| datastore.backend.replace_snapshot(tree))
+    }
+
+    /// Merge `overlay` into the stored tree.
+    ///
+    /// Containers merge member by member and keyed list entries are matched
+    /// on their key leaves; any other value in `overlay` replaces the stored
+    /// one.
+    pub fn merge_tree(&mut self, overlay: &Value) -> Result<()> {
+        let mut tree = self.backend.snapshot()?;
+        merge_value(&mut tree, overlay, &self.model, "");
+        self.replace_snapshot(tree)
+    }
+
+    /// Sequence number of the backend's last recorded change, or `None` if
+    /// it keeps no change log. See [`Backend::changes_since`].
+    pub fn last_change(&self) -> Option<u64> {
+        self.backend.last_change()
+    }
+
+    /// Paths written after change `since`, oldest first, when the backend
+    /// still has them all.
+    pub fn changes_since(&self, since: u64) -> Option<impl Iterator<Item = &Change>> {
+        self.backend.changes_since(since)
     }
 
     pub fn get_all_cbor(&self) -> Result<Vec<u8>> {
-        encode_identifier_value_to_cbor(&self.model, &self.backend.read_tree())
+        encode_identifier_value_to_cbor(&self.model, &self.backend.read_tree()?)
     }
 
     pub fn get_by_sid(&self, sid: i64) -> Result<Option<Value>> {
@@ -178,25 +257,26 @@ impl Datastore {
     }
 
     pub fn get_path(&self, path: &str) -> Result<Option<Value>> {
-        let parsed = PredicatePath::parse(path)?;
-        let tree = self.backend.read_tree();
-        let segments = split_canonical_segments(&parsed.canonical_path);
-        let mut predicate_index = 0usize;
-        let value = get_at_path(
-            &tree,
-            &self.model,
-            &segments,
-            0,
-            String::new(),
-            &parsed.predicates,
-            &mut predicate_index,
-        )?;
-        if predicate_index != parsed.predicates.len() {
-            return Err(CoreconfError::ValidationError(format!(
-                "unused predicates in path '{path}'"
-            )));
+        self.backend.read_path(&self.model, path)
+    }
+
+    /// Like [`Datastore::get_path`], except that a keyed list node without
+    /// trailing predicates reads as the array of its entries rather than
+    /// as its parent.
+    pub fn get_node(&self, path: &str) -> Result<Option<Value>> {
+        if !path.ends_with(']') && self.is_keyed_list(path)? {
+            return Ok(self.list_entries(path)?.map(Value::Array));
         }
-        Ok(value)
+        self.get_path(path)
+    }
+
+    fn is_keyed_list(&self, path: &str) -> Result<bool> {
+        let parsed = PredicatePath::parse(path)?;
+        Ok(self
+            .model
+            .get_sid(&parsed.canonical_path)
+            .and_then(|sid| self.model.get_keys(sid))
+            .is_some())
     }
 
     pub fn set_by_sid(&mut self, sid: i64, value: Value) -> Result<()> {
@@ -213,29 +293,182 @@ impl Datastore {
     }
 
     pub fn set_path(&mut self, path: &str, value: Value) -> Result<()> {
-        let parsed = PredicatePath::parse(path)?;
-        let mut tree = self.backend.read_tree();
+        self.notifying(|datastore| datastore.backend.write_path(&datastore.model, path, value))
+    }
 
-        if parsed.canonical_path == "/" {
-            tree = value;
-        } else {
-            let segments = split_canonical_segments(&parsed.canonical_path);
-            let mut predicate_index = 0usize;
-            let mut ctx = TreeCtx {
-                model: &self.model,
-                segments: &segments,
-                predicates: &parsed.predicates,
-                predicate_index: &mut predicate_index,
+    /// Merge `value` into the node at `path` as [`Datastore::merge_tree`]
+    /// does for the whole tree, creating the node if it is absent.
+    pub fn merge_path(&mut self, path: &str, value: Value) -> Result<()> {
+        self.notifying(|datastore| {
+            let Some(mut merged) = datastore.get_path(path)? else {
+                return datastore.set_path(path, value);
             };
-            set_at_path(&mut tree, &mut ctx, 0, String::new(), value)?;
-            if predicate_index != parsed.predicates.len() {
-                return Err(CoreconfError::ValidationError(format!(
-                    "unused predicates in path '{path}'"
-                )));
+            let schema_path = PredicatePath::parse(path)?.canonical_path;
+            merge_value(&mut merged, &value, &datastore.model, &schema_path);
+            datastore.set_path(path, merged)
+        })
+    }
+
+    /// Create the entry at `path` in an `ordered-by user` list, placed at
+    /// `position`. Fails with `data-exists` if the entry is already there.
+    pub fn insert_entry(&mut self, path: &str, value: Value, position: &Position) -> Result<()> {
+        self.notifying(|datastore| {
+            datastore.user_ordered_list(path)?;
+            if datastore.get_path(path)?.is_some() {
+                return Err(
+                    DataError::new(ErrorTag::DataExists, "list entry already exists")
+                        .with_path(path)
+                        .into(),
+                );
             }
+            let before = datastore.snapshot()?;
+            datastore.set_path(path, value)?;
+            datastore.position_entry(path, position).or_else(|error| {
+                datastore.replace_snapshot(before)?;
+                Err(error)
+            })
+        })
+    }
+
+    /// Move the existing entry at `path` in an `ordered-by user` list to
+    /// `position`.
+    pub fn move_entry(&mut self, path: &str, position: &Position) -> Result<()> {
+        self.notifying(|datastore| {
+            datastore.user_ordered_list(path)?;
+            if datastore.get_path(path)?.is_none() {
+                return Err(missing_entry(path));
+            }
+            datastore.position_entry(path, position)
+        })
+    }
+
+    /// Call `callback` with a [`ChangeEvent`] for each node matching
+    /// `pattern` that a later mutation changes. `pattern` is a query (see
+    /// [`crate::query`]) and is checked against the model here.
+    pub fn subscribe(
+        &mut self,
+        pattern: &str,
+        callback: impl FnMut(&ChangeEvent) + Send + Sync + 'static,
+    ) -> Result<SubscriptionId> {
+        self.query(pattern)?;
+        let query = Query::parse(pattern)?;
+        Ok(self.subscriptions.add(query, Box::new(callback)))
+    }
+
+    /// Drop a subscription. Returns whether it existed.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        self.subscriptions.remove(id)
+    }
+
+    /// Run `mutate` and report what it changed to the subscriptions.
+    ///
+    /// Subscriptions are taken out while `mutate` runs, so mutations made
+    /// up of smaller ones report once, with their net effect, even if they
+    /// fail part way.
+    fn notifying<T>(&mut self, mutate: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.subscriptions.is_empty() {
+            return mutate(self);
+        }
+        let roots = self.subscriptions.roots();
+        let before = self.watched(&roots)?;
+        let mut subscriptions = std::mem::take(&mut self.subscriptions);
+        let result = mutate(self);
+        // A backend that can't be read back reports nothing this time.
+        if let Ok(after) = self.watched(&roots) {
+            subscriptions.notify(&self.model, &before, &after);
         }
+        self.subscriptions = subscriptions;
+        result
+    }
 
-        self.backend.replace_tree(tree)
+    /// The top-level nodes `roots` that are present, as subscriptions
+    /// compare them.
+    fn watched(&self, roots: &[String]) -> Result<Watched> {
+        let mut watched = Watched::new();
+        for name in roots {
+            if let Some(node) = self.backend.read_top_level(&self.model, name)? {
+                watched.insert(name.clone(), node);
+            }
+        }
+        Ok(watched)
+    }
+
+    /// The nodes matching `query`, a predicate path whose list predicates
+    /// may be wildcards or comparisons (see [`crate::query`]), each under
+    /// its concrete predicate path.
+    pub fn query(&self, query: &str) -> Result<Vec<(String, Value)>> {
+        let query = Query::parse(query)?;
+        let Some(first) = query.steps.first() else {
+            return Ok(Vec::new());
+        };
+        let root = self.get_node(&format!("/{}", first.name))?;
+        query.run(&self.model, root.as_ref())
+    }
+
+    /// The list instance holding the entry at `path`, if that list is
+    /// `ordered-by user`.
+    fn user_ordered_list<'p>(&self, path: &'p str) -> Result<&'p str> {
+        let list = entry_list_path(path).ok_or_else(|| {
+            CoreconfError::from(
+                DataError::new(ErrorTag::InvalidValue, "path does not name a list entry")
+                    .with_path(path),
+            )
+        })?;
+        let schema_path = PredicatePath::parse(list)?.canonical_path;
+        let sid = self
+            .model
+            .get_sid(&schema_path)
+            .ok_or_else(|| CoreconfError::SidNotFound(schema_path.clone()))?;
+        if !self.model.is_ordered_by_user(sid) {
+            return Err(DataError::new(
+                ErrorTag::InvalidValue,
+                format!("list '{schema_path}' is not ordered-by user"),
+            )
+            .with_app_tag("not-ordered-by-user")
+            .with_path(path)
+            .with_sid(sid)
+            .into());
+        }
+        Ok(list)
+    }
+
+    fn position_entry(&mut self, path: &str, position: &Position) -> Result<()> {
+        let list = self.user_ordered_list(path)?;
+        let Some(mut entries) = self.list_entries(list)? else {
+            return Err(missing_entry(path));
+        };
+        // Keys are unique, so an entry is the one equal to its own value.
+        let locate = |entries: &[Value], path: &str| -> Result<usize> {
+            let entry = self.get_path(path)?.ok_or_else(|| missing_entry(path))?;
+            entries
+                .iter()
+                .position(|candidate| *candidate == entry)
+                .ok_or_else(|| missing_entry(path))
+        };
+        let moving = entries.remove(locate(&entries, path)?);
+        let index = match position {
+            Position::First => 0,
+            Position::Last => entries.len(),
+            Position::Before(point) | Position::After(point) => {
+                if entry_list_path(point) != Some(list) || point == path {
+                    return Err(DataError::new(
+                        ErrorTag::InvalidValue,
+                        "insertion point must be another entry of the same list",
+                    )
+                    .with_app_tag("invalid-point")
+                    .with_path(point.as_str())
+                    .into());
+                }
+                let index = locate(&entries, point)?;
+                if matches!(position, Position::After(_)) {
+                    index + 1
+                } else {
+                    index
+                }
+            }
+        };
+        entries.insert(index, moving);
+        self.set_path(list, Value::Array(entries))
     }
 
     pub fn delete_by_sid(&mut self, sid: i64) -> Result<bool> {
@@ -252,32 +485,7 @@ impl Datastore {
     }
 
     pub fn delete_path(&mut self, path: &str) -> Result<bool> {
-        let parsed = PredicatePath::parse(path)?;
-        if parsed.canonical_path == "/" {
-            self.backend
-                .replace_tree(Value::Object(Map::new()))
-                .map(|_| true)
-        } else {
-            let mut tree = self.backend.read_tree();
-            let segments = split_canonical_segments(&parsed.canonical_path);
-            let mut predicate_index = 0usize;
-            let deleted = delete_at_path(
-                &mut tree,
-                &self.model,
-                &segments,
-                0,
-                String::new(),
-                &parsed.predicates,
-                &mut predicate_index,
-            )?;
-            if predicate_index != parsed.predicates.len() {
-                return Err(CoreconfError::ValidationError(format!(
-                    "unused predicates in path '{path}'"
-                )));
-            }
-            self.backend.replace_tree(tree)?;
-            Ok(deleted)
-        }
+        self.notifying(|datastore| datastore.backend.delete_path(&datastore.model, path))
     }
 
     pub fn delete(&mut self, path: &InstancePath) -> Result<bool> {
@@ -294,7 +502,7 @@ impl Datastore {
         if let Some(sid) = path.absolute_sid() {
             self.get_by_sid(sid)
         } else if path.is_empty() {
-            Ok(Some(self.backend.read_tree()))
+            self.backend.read_tree().map(Some)
         } else {
             Ok(None)
         }
@@ -304,22 +512,65 @@ impl Datastore {
         if let Some(sid) = path.absolute_sid() {
             self.set_by_sid(sid, value)
         } else if path.is_empty() {
-            self.backend.replace_tree(value)
+            self.replace_tree(value)
         } else {
             Err(CoreconfError::ResourceNotFound("invalid path".into()))
         }
     }
 
     pub fn apply_changes(&mut self, changes: &[(String, Option<Value>)]) -> Result<()> {
-        for (path, value) in changes {
-            match value {
-                Some(value) => self.set_path(path, value.clone())?,
-                None => {
-                    self.delete_path(path)?;
+        self.notifying(|datastore| {
+            for (path, value) in changes {
+                match value {
+                    Some(value) => datastore.set_path(path, value.clone())?,
+                    None => {
+                        datastore.delete_path(path)?;
+                    }
                 }
             }
-        }
-        Ok(())
+            Ok(())
+        })
+    }
+
+    /// Apply `edits` to a datastore whose tree is `before`, as one change
+    /// for subscriptions. An edit directly followed by another of the same
+    /// path is skipped. If one fails, the tree is put back to `before`.
+    pub(crate) fn publish_edits(
+        &mut self,
+        edits: &[(String, Option<Value>)],
+        before: &SharedTree,
+    ) -> Result<()> {
+        let edits: Vec<_> = edits
+            .iter()
+            .enumerate()
+            .filter(|(index, (path, _))| edits.get(index + 1).is_none_or(|(next, _)| next != path))
+            .map(|(_, edit)| edit.clone())
+            .collect();
+        self.notifying(|datastore| {
+            if let Err(error) = datastore.backend.apply_edits(&datastore.model, &edits) {
+                // Backends that write path by path may have applied some.
+                if edits.len() > 1 {
+                    datastore.backend.replace_snapshot(before.clone())?;
+                }
+                return Err(error);
+            }
+            Ok(())
+        })
+    }
+
+    /// Apply the edits of `patch` in order. If one fails, the datastore is
+    /// put back as it was and the error names the failing edit.
+    pub fn apply_yang_patch(&mut self, patch: &YangPatch) -> Result<()> {
+        self.notifying(|datastore| {
+            let before = datastore.snapshot()?;
+            for edit in &patch.edits {
+                if let Err(error) = apply_edit(datastore, edit) {
+                    datastore.replace_snapshot(before)?;
+                    return Err(error);
+                }
+            }
+            Ok(())
+        })
     }
 
     pub fn fetch_instances(&self, payload: &[u8]) -> Result<Vec<Instance>> {
@@ -375,9 +626,7 @@ impl Datastore {
             let xpath = self.create_xpath(sid, keys)?;
             let value = match self.get_path(&xpath) {
                 Ok(value) => value,
-                Err(CoreconfError::ValidationError(message))
-                    if message.starts_with("unused predicates in path") =>
-                {
+                Err(error) if error.error_tag() == ErrorTag::DataMissing => {
                     return Ok(Vec::new());
                 }
                 Err(error) => return Err(error),
@@ -450,9 +699,13 @@ impl Datastore {
             .and_then(Value::as_array)
             .cloned()
             .unwrap_or_default();
-        let key_sids = self.model.get_keys(list_sid).cloned().ok_or_else(|| {
-            CoreconfError::ValidationError(format!("path is not a keyed list: '{list_path}'"))
-        })?;
+        let key_sids = self
+            .model
+            .get_keys(list_sid)
+            .map(<[i64]>::to_vec)
+            .ok_or_else(|| {
+                CoreconfError::ValidationError(format!("path is not a keyed list: '{list_path}'"))
+            })?;
         let mut result = Vec::with_capacity(entries.len());
         for entry in entries {
             let Some(entry) = entry.as_object() else {
@@ -496,7 +749,7 @@ impl Datastore {
             path.push_delta(segment_sid - previous_sid);
             previous_sid = segment_sid;
             if self.model.get_keys(segment_sid).is_some() {
-                let key_count = self.model.get_keys(segment_sid).map_or(0, Vec::len);
+                let key_count = self.model.get_keys(segment_sid).map_or(0, <[i64]>::len);
                 if key_index + key_count > keys.len() {
                     return Err(CoreconfError::ValidationError(format!(
                         "missing key values for list '{current}'"
@@ -516,6 +769,40 @@ impl Datastore {
         Ok(path)
     }
 
+    /// The edits turning this datastore into `other`, matching keyed list
+    /// entries by key. See [`crate::diff`].
+    pub fn diff(&self, other: &Datastore) -> Result<Diff> {
+        diff_trees(&self.model, &self.get_all()?, &other.get_all()?)
+    }
+
+    /// Encode `diff` as a yang-instances+cbor-seq payload, ready to send as
+    /// a root iPATCH. Deletions become null-valued instances.
+    pub fn encode_diff(&self, diff: &Diff) -> Result<Vec<u8>> {
+        self.encode_edits(
+            diff.edits
+                .iter()
+                .map(|edit| (edit.path.as_str(), edit.after.as_ref())),
+        )
+    }
+
+    /// Encode values written at predicate paths as yang-instances+cbor-seq,
+    /// with `None` for a deletion.
+    pub(crate) fn encode_edits<'a>(
+        &self,
+        edits: impl IntoIterator<Item = (&'a str, Option<&'a Value>)>,
+    ) -> Result<Vec<u8>> {
+        let mut instances = Vec::new();
+        for (path, value) in edits {
+            let (sid, keys) = self.resolve_xpath(path)?;
+            let path = self.instance_path(sid, &keys)?;
+            instances.push(match value {
+                Some(value) => Instance::new(path, value.clone()),
+                None => Instance::delete(path),
+            });
+        }
+        self.encode_instances(&instances)
+    }
+
     pub fn encode_instances(&self, instances: &[Instance]) -> Result<Vec<u8>> {
         let mut bytes = Vec::new();
         for inst in instances {
@@ -563,9 +850,13 @@ impl Datastore {
     /// `["[type='coreconf-m2m:solar-radiation'][id='0']", ...]`.
     pub fn predicates(&self, path: &str) -> Result<Vec<String>> {
         let (list_sid, existing_keys) = self.resolve_xpath(path)?;
-        let key_sids = self.model.get_keys(list_sid).cloned().ok_or_else(|| {
-            CoreconfError::ValidationError(format!("path is not a keyed list: '{path}'"))
-        })?;
+        let key_sids = self
+            .model
+            .get_keys(list_sid)
+            .map(<[i64]>::to_vec)
+            .ok_or_else(|| {
+                CoreconfError::ValidationError(format!("path is not a keyed list: '{path}'"))
+            })?;
 
         // If the XPath already includes predicates, return that single filter.
         if !existing_keys.is_empty() {
@@ -574,23 +865,7 @@ impl Datastore {
         }
 
         // Enumerate all entries in the list.
-        let tree = self.backend.read_tree();
-        let segments = split_canonical_segments(path);
-
-        let list_value =
-            match get_at_path(&tree, &self.model, &segments, 0, String::new(), &[], &mut 0)? {
-                Some(val) => val,
-                None => return Ok(Vec::new()),
-            };
-
-        let list_name = segments.last().copied().unwrap_or("");
-        let storage_key = storage_key(list_name, segments.len() - 1);
-        let entries = list_value
-            .as_object()
-            .and_then(|map| map.get(&storage_key))
-            .and_then(Value::as_array)
-            .map(|arr| arr.to_vec())
-            .unwrap_or_default();
+        let entries = self.list_entries(path)?.unwrap_or_default();
 
         let mut result = Vec::with_capacity(entries.len());
         for entry in &entries {
@@ -624,6 +899,20 @@ impl Datastore {
         Ok(result)
     }
 
+    /// The entries of the list instance at `path` (a list node without
+    /// trailing predicates), in stored order.
+    pub(crate) fn list_entries(&self, path: &str) -> Result<Option<Vec<Value>>> {
+        // Reading a list node without predicates yields its parent.
+        let Some(parent) = self.backend.read_path(&self.model, path)? else {
+            return Ok(None);
+        };
+        let parsed = PredicatePath::parse(path)?;
+        let segments = split_canonical_segments(&parsed.canonical_path);
+        let list_name = segments.last().copied().unwrap_or("");
+        let storage_key = storage_key(list_name, segments.len() - 1);
+        Ok(parent.get(&storage_key).and_then(Value::as_array).cloned())
+    }
+
     /// Resolve an XPath string to (target SID, key values).
     ///
     /// This is the inverse of `create_xpath`.
@@ -738,6 +1027,146 @@ impl Datastore {
     }
 }
 
+/// Read the node at predicate path `path` in an identifier-keyed tree,
+/// looking list entries up through `index` where it covers them.
+pub(crate) fn tree_get<N: TreeNode>(
+    tree: &N,
+    model: &CompositeModel,
+    path: &str,
+    index: Option<&ListIndex>,
+) -> Result<Option<Value>> {
+    let parsed = PredicatePath::parse(path)?;
+    let segments = split_canonical_segments(&parsed.canonical_path);
+    let mut predicate_index = 0usize;
+    let mut ctx = ReadCtx {
+        model,
+        segments: &segments,
+        predicates: &parsed.predicates,
+        predicate_index: &mut predicate_index,
+        index,
+    };
+    let value = get_at_path(tree, &mut ctx, 0, String::new(), Vec::new())?;
+    if predicate_index != parsed.predicates.len() {
+        return Err(unused_predicates(path));
+    }
+    Ok(value)
+}
+
+/// Write `value` at predicate path `path`, creating missing ancestors and
+/// list entries. `tree` is left unchanged on error; `index`, if given, must
+/// describe `tree` and is kept in step with it.
+pub(crate) fn tree_set<N: TreeNode>(
+    tree: &mut N,
+    model: &CompositeModel,
+    path: &str,
+    value: Value,
+    mut index: Option<&mut ListIndex>,
+) -> Result<()> {
+    let parsed = PredicatePath::parse(path)?;
+    if parsed.canonical_path == "/" {
+        *tree = N::from_value(value);
+        if let Some(index) = index.as_deref_mut() {
+            index.clear();
+        }
+        return Ok(());
+    }
+
+    let segments = split_canonical_segments(&parsed.canonical_path);
+    // Path errors can surface after ancestors have been created, so find
+    // them on an empty tree first. Errors from the data itself only occur
+    // at nodes that already existed, before anything below is touched.
+    set_checked(
+        &mut Value::Object(Map::new()),
+        model,
+        path,
+        &segments,
+        &parsed.predicates,
+        Value::Null,
+        None,
+    )?;
+    set_checked(
+        tree,
+        model,
+        path,
+        &segments,
+        &parsed.predicates,
+        value,
+        index,
+    )
+}
+
+fn set_checked<N: TreeNode>(
+    tree: &mut N,
+    model: &CompositeModel,
+    path: &str,
+    segments: &[&str],
+    predicates: &[(String, String)],
+    value: Value,
+    index: Option<&mut ListIndex>,
+) -> Result<()> {
+    let mut predicate_index = 0usize;
+    let mut ctx = TreeCtx {
+        model,
+        segments,
+        predicates,
+        predicate_index: &mut predicate_index,
+        index,
+    };
+    set_at_path(tree, &mut ctx, 0, String::new(), Vec::new(), value)?;
+    if predicate_index != predicates.len() {
+        // Missing entries are created on the way down, so leftover
+        // predicates can only sit on nodes that aren't keyed lists.
+        return Err(DataError::new(
+            ErrorTag::InvalidValue,
+            format!("unused predicates in path '{path}'"),
+        )
+        .with_app_tag("invalid-path")
+        .with_path(path)
+        .into());
+    }
+    Ok(())
+}
+
+/// Delete the node at predicate path `path`, returning whether it existed.
+/// Deleting `/` empties the tree. `tree` is left unchanged on error.
+pub(crate) fn tree_delete<N: TreeNode>(
+    tree: &mut N,
+    model: &CompositeModel,
+    path: &str,
+    mut index: Option<&mut ListIndex>,
+) -> Result<bool> {
+    let parsed = PredicatePath::parse(path)?;
+    if parsed.canonical_path == "/" {
+        *tree = N::from_value(Value::Object(Map::new()));
+        if let Some(index) = index.as_deref_mut() {
+            index.clear();
+        }
+        return Ok(true);
+    }
+
+    let segments = split_canonical_segments(&parsed.canonical_path);
+    // Leftover predicates are only known once the traversal is done, so
+    // find the node first and remove it in a second pass.
+    for remove in [false, true] {
+        let mut predicate_index = 0usize;
+        let mut ctx = TreeCtx {
+            model,
+            segments: &segments,
+            predicates: &parsed.predicates,
+            predicate_index: &mut predicate_index,
+            index: index.as_deref_mut(),
+        };
+        let found = delete_at_path(tree, &mut ctx, 0, String::new(), Vec::new(), remove)?;
+        if predicate_index != parsed.predicates.len() {
+            return Err(unused_predicates(path));
+        }
+        if !found || remove {
+            return Ok(found);
+        }
+    }
+    unreachable!("the second pass always returns")
+}
+
 fn encode_identifier_value_to_cbor(model: &CompositeModel, value: &Value) -> Result<Vec<u8>> {
     let sid_value = model.identifier_value_to_sid_value(value.clone())?;
     let ciborium_val = coreconf_model::codec::json_to_cbor_value(model, &sid_value, 0);
@@ -747,55 +1176,133 @@ fn encode_identifier_value_to_cbor(model: &CompositeModel, value: &Value) -> Res
     Ok(bytes)
 }
 
-fn split_canonical_segments(path: &str) -> Vec<&str> {
+pub(crate) fn split_canonical_segments(path: &str) -> Vec<&str> {
     path.split('/')
         .filter(|segment| !segment.is_empty())
         .collect()
 }
 
-fn get_at_path(
-    current: &Value,
-    model: &CompositeModel,
-    segments: &[&str],
+/// A tree the path walkers can operate on: a plain JSON value, or a
+/// [`SharedTree`](crate::SharedTree) whose writes copy only the containers they pass through.
+///
+/// Object accessors return `None` on other nodes; the inserting ones turn
+/// null into an empty object first and fail on anything else.
+pub(crate) trait TreeNode: Sized {
+    fn from_value(value: Value) -> Self;
+    fn to_value(&self) -> Value;
+    /// Whether this is an object or array, which may hold keyed lists.
+    fn is_container(&self) -> bool;
+    fn member(&self, key: &str) -> Option<&Self>;
+    fn member_mut(&mut self, key: &str) -> Option<&mut Self>;
+    /// Member `key` when it is a leaf, for comparing list keys.
+    fn leaf(&self, key: &str) -> Option<&Value>;
+    fn entries(&self) -> Option<&[Self]>;
+    fn entries_mut(&mut self) -> Option<&mut Vec<Self>>;
+    /// Entries of a list node, turning null into an empty list.
+    fn ensure_entries(&mut self) -> Result<&mut Vec<Self>>;
+    fn insert_member(&mut self, key: String, value: Self) -> Result<Option<Self>>;
+    fn member_or_insert(
+        &mut self,
+        key: String,
+        default: impl FnOnce() -> Self,
+    ) -> Result<&mut Self>;
+    fn remove_member(&mut self, key: &str) -> Option<Self>;
+}
+
+impl TreeNode for Value {
+    fn from_value(value: Value) -> Self {
+        value
+    }
+
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+
+    fn is_container(&self) -> bool {
+        self.is_object() || self.is_array()
+    }
+
+    fn member(&self, key: &str) -> Option<&Self> {
+        self.as_object()?.get(key)
+    }
+
+    fn member_mut(&mut self, key: &str) -> Option<&mut Self> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    fn leaf(&self, key: &str) -> Option<&Value> {
+        self.member(key)
+    }
+
+    fn entries(&self) -> Option<&[Self]> {
+        self.as_array().map(Vec::as_slice)
+    }
+
+    fn entries_mut(&mut self) -> Option<&mut Vec<Self>> {
+        self.as_array_mut()
+    }
+
+    fn ensure_entries(&mut self) -> Result<&mut Vec<Self>> {
+        ensure_array(self)
+    }
+
+    fn insert_member(&mut self, key: String, value: Self) -> Result<Option<Self>> {
+        Ok(ensure_object(self)?.insert(key, value))
+    }
+
+    fn member_or_insert(
+        &mut self,
+        key: String,
+        default: impl FnOnce() -> Self,
+    ) -> Result<&mut Self> {
+        Ok(ensure_object(self)?.entry(key).or_insert_with(default))
+    }
+
+    fn remove_member(&mut self, key: &str) -> Option<Self> {
+        self.as_object_mut()?.remove(key)
+    }
+}
+
+/// Read-only counterpart of [`TreeCtx`].
+struct ReadCtx<'a, 'b> {
+    model: &'a CompositeModel,
+    segments: &'a [&'a str],
+    predicates: &'a [(String, String)],
+    predicate_index: &'b mut usize,
+    index: Option<&'a ListIndex>,
+}
+
+/// `chain` identifies the innermost list entry holding `current` in the
+/// list index; it stays empty when there is no index.
+fn get_at_path<N: TreeNode>(
+    current: &N,
+    ctx: &mut ReadCtx<'_, '_>,
     depth: usize,
     current_path: String,
-    predicates: &[(String, String)],
-    predicate_index: &mut usize,
+    chain: Vec<u8>,
 ) -> Result<Option<Value>> {
-    if depth == segments.len() {
-        return Ok(Some(current.clone()));
+    if depth == ctx.segments.len() {
+        return Ok(Some(current.to_value()));
     }
 
-    let segment = segments[depth];
+    let segment = ctx.segments[depth];
     let next_path = join_path(&current_path, segment);
-    let list_keys = list_keys(model, &next_path)?;
+    let list_keys = list_keys(ctx.model, &next_path)?;
 
     if list_keys.is_empty() {
-        let map = match current.as_object() {
-            Some(map) => map,
-            None => return Ok(None),
-        };
         let storage_key = storage_key(segment, depth);
-        let child = map
-            .get(&storage_key)
-            .or_else(|| map.get(segment_leaf(segment)))
-            .or_else(|| map.get(segment));
+        let child = current
+            .member(&storage_key)
+            .or_else(|| current.member(segment_leaf(segment)))
+            .or_else(|| current.member(segment));
         match child {
-            Some(child) => get_at_path(
-                child,
-                model,
-                segments,
-                depth + 1,
-                next_path,
-                predicates,
-                predicate_index,
-            ),
+            Some(child) => get_at_path(child, ctx, depth + 1, next_path, chain),
             None => Ok(None),
         }
     } else {
-        let end = *predicate_index + list_keys.len();
-        let has_predicates = end <= predicates.len();
-        let is_last = depth == segments.len() - 1;
+        let end = *ctx.predicate_index + list_keys.len();
+        let has_predicates = end <= ctx.predicates.len();
+        let is_last = depth == ctx.segments.len() - 1;
 
         if !has_predicates && !is_last {
             return Err(CoreconfError::ValidationError(format!(
@@ -806,33 +1313,33 @@ fn get_at_path(
         if !has_predicates && is_last {
             // Last segment is a list node with no predicates — return current
             // value as-is (used by predicates() to read the entire list).
-            return Ok(Some(current.clone()));
+            return Ok(Some(current.to_value()));
         }
 
-        let key_values = consume_key_values(model, &list_keys, predicates, predicate_index)?;
-        let map = match current.as_object() {
-            Some(map) => map,
-            None => return Ok(None),
-        };
-        let storage_key = storage_key(segment, depth);
-        let list = match map.get(&storage_key).and_then(Value::as_array) {
+        let key_values =
+            consume_key_values(ctx.model, &list_keys, ctx.predicates, ctx.predicate_index)?;
+        let list = match current
+            .member(&storage_key(segment, depth))
+            .and_then(N::entries)
+        {
             Some(list) => list,
             None => return Ok(None),
         };
-        let entry = list
-            .iter()
-            .find(|value| list_entry_matches(value, &key_values, model, &next_path))
-            .cloned();
-        match entry {
-            Some(entry) => get_at_path(
-                &entry,
-                model,
-                segments,
-                depth + 1,
-                next_path,
-                predicates,
-                predicate_index,
+        let (position, chain) = match ctx.index {
+            Some(index) => {
+                let slot = EntrySlot::new(&chain, ctx.model, &next_path, &key_values);
+                let position = index.lookup(&slot, list, ctx.model, &next_path, &key_values);
+                (position, slot.chain())
+            }
+            None => (
+                list.iter().position(|entry| {
+                    list_entry_matches(entry, &key_values, ctx.model, &next_path)
+                }),
+                chain,
             ),
+        };
+        match position {
+            Some(position) => get_at_path(&list[position], ctx, depth + 1, next_path, chain),
             None => Ok(None),
         }
     }
@@ -844,13 +1351,41 @@ struct TreeCtx<'a, 'b> {
     segments: &'a [&'a str],
     predicates: &'a [(String, String)],
     predicate_index: &'b mut usize,
+    index: Option<&'b mut ListIndex>,
 }
 
-fn set_at_path(
-    current: &mut Value,
+impl TreeCtx<'_, '_> {
+    /// Position of the entry selected by `key_values`, with its slot in the
+    /// list index when there is one.
+    fn find_entry<N: TreeNode>(
+        &mut self,
+        entries: &[N],
+        chain: &[u8],
+        list_path: &str,
+        key_values: &[(String, Value)],
+    ) -> (Option<usize>, Option<EntrySlot>) {
+        match self.index.as_deref_mut() {
+            Some(index) => {
+                let slot = EntrySlot::new(chain, self.model, list_path, key_values);
+                let position = index.find(&slot, entries, self.model, list_path, key_values);
+                (position, Some(slot))
+            }
+            None => (
+                entries
+                    .iter()
+                    .position(|entry| list_entry_matches(entry, key_values, self.model, list_path)),
+                None,
+            ),
+        }
+    }
+}
+
+fn set_at_path<N: TreeNode>(
+    current: &mut N,
     ctx: &mut TreeCtx<'_, '_>,
     depth: usize,
     current_path: String,
+    chain: Vec<u8>,
     value: Value,
 ) -> Result<()> {
     let segment = ctx.segments[depth];
@@ -858,15 +1393,19 @@ fn set_at_path(
     let list_keys = list_keys(ctx.model, &next_path)?;
 
     if list_keys.is_empty() {
-        let map = ensure_object(current)?;
         let key = storage_key(segment, depth);
         if depth == ctx.segments.len() - 1 {
-            map.insert(key, value);
+            let previous = current.insert_member(key, N::from_value(value))?;
+            if let Some(index) = ctx.index.as_deref_mut()
+                && previous.is_some_and(|previous| previous.is_container())
+            {
+                index.forget_below(&chain, &next_path);
+            }
             return Ok(());
         }
 
-        let child = map.entry(key).or_insert_with(|| Value::Object(Map::new()));
-        set_at_path(child, ctx, depth + 1, next_path, value)
+        let child = current.member_or_insert(key, || N::from_value(Value::Object(Map::new())))?;
+        set_at_path(child, ctx, depth + 1, next_path, chain, value)
     } else {
         let end = *ctx.predicate_index + list_keys.len();
         let has_predicates = end <= ctx.predicates.len();
@@ -881,110 +1420,148 @@ fn set_at_path(
         if !has_predicates && is_last {
             // Last segment is a list node with no predicates — replace the
             // entire list value (used when setting a full list array).
-            let map = ensure_object(current)?;
-            map.insert(storage_key(segment, depth), value);
+            current.insert_member(storage_key(segment, depth), N::from_value(value))?;
+            if let Some(index) = ctx.index.as_deref_mut() {
+                index.forget_below(&chain, &next_path);
+            }
             return Ok(());
         }
 
         let key_values =
             consume_key_values(ctx.model, &list_keys, ctx.predicates, ctx.predicate_index)?;
-        let map = ensure_object(current)?;
-        let list = map
-            .entry(storage_key(segment, depth))
-            .or_insert_with(|| Value::Array(Vec::new()));
-        let entries = ensure_array(list)?;
-        let entry = find_or_create_list_entry(entries, &key_values, ctx.model, &next_path);
+        let entries = current
+            .member_or_insert(storage_key(segment, depth), || {
+                N::from_value(Value::Array(Vec::new()))
+            })?
+            .ensure_entries()?;
+        let (position, slot) = ctx.find_entry(entries, &chain, &next_path, &key_values);
+        let position = match position {
+            Some(position) => position,
+            None => {
+                entries.push(N::from_value(Value::Object(
+                    key_values.iter().cloned().collect(),
+                )));
+                if let (Some(index), Some(slot)) = (ctx.index.as_deref_mut(), &slot) {
+                    index.inserted(slot, entries.len() - 1);
+                }
+                entries.len() - 1
+            }
+        };
 
-        if depth == ctx.segments.len() - 1 {
+        if is_last {
             let mut next_value = value;
             if let Value::Object(map) = &mut next_value {
                 for (key, key_value) in &key_values {
                     map.entry(key.clone()).or_insert_with(|| key_value.clone());
                 }
             }
-            *entry = next_value;
-            return Ok(());
+            entries[position] = N::from_value(next_value);
+            if let (Some(index), Some(slot)) = (ctx.index.as_deref_mut(), &slot) {
+                index.forget_under(&slot.chain());
+            }
+        } else {
+            let entry_chain = slot.as_ref().map(EntrySlot::chain).unwrap_or_default();
+            set_at_path(
+                &mut entries[position],
+                ctx,
+                depth + 1,
+                next_path.clone(),
+                entry_chain,
+                value,
+            )?;
         }
 
-        set_at_path(entry, ctx, depth + 1, next_path, value)
+        // The write may have replaced the entry's own key leaves.
+        if let (Some(index), Some(slot)) = (ctx.index.as_deref_mut(), &slot)
+            && !list_entry_matches(&entries[position], &key_values, ctx.model, &next_path)
+        {
+            index.forget_list(slot);
+        }
+        Ok(())
     }
 }
 
-fn delete_at_path(
-    current: &mut Value,
-    model: &CompositeModel,
-    segments: &[&str],
+/// Find the node at the path in `ctx`, removing it if `remove` is set.
+fn delete_at_path<N: TreeNode>(
+    current: &mut N,
+    ctx: &mut TreeCtx<'_, '_>,
     depth: usize,
     current_path: String,
-    predicates: &[(String, String)],
-    predicate_index: &mut usize,
+    chain: Vec<u8>,
+    remove: bool,
 ) -> Result<bool> {
-    let segment = segments[depth];
+    let segment = ctx.segments[depth];
     let next_path = join_path(&current_path, segment);
-    let list_keys = list_keys(model, &next_path)?;
+    let list_keys = list_keys(ctx.model, &next_path)?;
 
     if list_keys.is_empty() {
-        let map = match current.as_object_mut() {
-            Some(map) => map,
-            None => return Ok(false),
-        };
         let key = storage_key(segment, depth);
-        if depth == segments.len() - 1 {
-            return Ok(map.remove(&key).is_some());
+        if depth == ctx.segments.len() - 1 {
+            if !remove {
+                return Ok(current.member(&key).is_some());
+            }
+            let Some(removed) = current.remove_member(&key) else {
+                return Ok(false);
+            };
+            if let Some(index) = ctx.index.as_deref_mut()
+                && removed.is_container()
+            {
+                index.forget_below(&chain, &next_path);
+            }
+            return Ok(true);
         }
 
-        match map.get_mut(&key) {
-            Some(child) => delete_at_path(
-                child,
-                model,
-                segments,
-                depth + 1,
-                next_path,
-                predicates,
-                predicate_index,
-            ),
+        match current.member_mut(&key) {
+            Some(child) => delete_at_path(child, ctx, depth + 1, next_path, chain, remove),
             None => Ok(false),
         }
     } else {
-        let key_values = consume_key_values(model, &list_keys, predicates, predicate_index)?;
-        let map = match current.as_object_mut() {
-            Some(map) => map,
-            None => return Ok(false),
-        };
-        let list = match map
-            .get_mut(&storage_key(segment, depth))
-            .and_then(Value::as_array_mut)
+        let key_values =
+            consume_key_values(ctx.model, &list_keys, ctx.predicates, ctx.predicate_index)?;
+        let list = match current
+            .member_mut(&storage_key(segment, depth))
+            .and_then(N::entries_mut)
         {
             Some(list) => list,
             None => return Ok(false),
         };
 
-        let position = list
-            .iter()
-            .position(|entry| list_entry_matches(entry, &key_values, model, &next_path));
-
+        let (position, slot) = ctx.find_entry(list, &chain, &next_path, &key_values);
         let Some(position) = position else {
             return Ok(false);
         };
 
-        if depth == segments.len() - 1 {
-            list.remove(position);
+        if depth == ctx.segments.len() - 1 {
+            if remove {
+                list.remove(position);
+                if let (Some(index), Some(slot)) = (ctx.index.as_deref_mut(), &slot) {
+                    index.removed(slot, position);
+                }
+            }
             return Ok(true);
         }
 
-        delete_at_path(
+        let entry_chain = slot.as_ref().map(EntrySlot::chain).unwrap_or_default();
+        let found = delete_at_path(
             &mut list[position],
-            model,
-            segments,
+            ctx,
             depth + 1,
-            next_path,
-            predicates,
-            predicate_index,
-        )
+            next_path.clone(),
+            entry_chain,
+            remove,
+        )?;
+        // Deleting a key leaf leaves the entry indexed under its old key.
+        if remove
+            && let (Some(index), Some(slot)) = (ctx.index.as_deref_mut(), &slot)
+            && !list_entry_matches(&list[position], &key_values, ctx.model, &next_path)
+        {
+            index.forget_list(slot);
+        }
+        Ok(found)
     }
 }
 
-fn list_keys(model: &CompositeModel, list_path: &str) -> Result<Vec<(String, Value)>> {
+pub(crate) fn list_keys(model: &CompositeModel, list_path: &str) -> Result<Vec<(String, Value)>> {
     let Some(list_sid) = model.get_sid(list_path) else {
         return Err(CoreconfError::SidNotFound(list_path.to_string()));
     };
@@ -1006,7 +1583,7 @@ fn list_keys(model: &CompositeModel, list_path: &str) -> Result<Vec<(String, Val
     Ok(keys)
 }
 
-fn consume_key_values(
+pub(crate) fn consume_key_values(
     model: &CompositeModel,
     expected_keys: &[(String, Value)],
     predicates: &[(String, String)],
@@ -1079,7 +1656,43 @@ fn consume_key_values(
     Ok(values)
 }
 
-fn coerce_predicate_value(model: &CompositeModel, identifier: &str, raw: &str) -> Result<Value> {
+fn missing_entry(path: &str) -> CoreconfError {
+    DataError::new(ErrorTag::DataMissing, "list entry does not exist")
+        .with_path(path)
+        .into()
+}
+
+/// Error for a path whose predicates select a list entry that doesn't exist,
+/// leaving some of them unconsumed by the traversal.
+fn unused_predicates(path: &str) -> CoreconfError {
+    DataError::new(
+        ErrorTag::DataMissing,
+        format!("unused predicates in path '{path}'"),
+    )
+    .with_path(path)
+    .into()
+}
+
+/// Parse a predicate value as the type of the key leaf `identifier`,
+/// reporting failures against that leaf and its SID.
+pub(crate) fn coerce_predicate_value(
+    model: &CompositeModel,
+    identifier: &str,
+    raw: &str,
+) -> Result<Value> {
+    parse_predicate_value(model, identifier, raw).map_err(|error| {
+        let mut data = DataError::new(ErrorTag::InvalidValue, error.to_string())
+            .with_app_tag("data-type-mismatch")
+            .with_path(identifier)
+            .with_value(Value::String(raw.to_string()));
+        if let Some(sid) = model.get_sid(identifier) {
+            data = data.with_sid(sid);
+        }
+        data.into()
+    })
+}
+
+fn parse_predicate_value(model: &CompositeModel, identifier: &str, raw: &str) -> Result<Value> {
     match model.get_type(identifier) {
         Some(YangType::Boolean) => match raw {
             "true" => Ok(Value::Bool(true)),
@@ -1148,14 +1761,14 @@ fn coerce_predicate_value(model: &CompositeModel, identifier: &str, raw: &str) -
 /// Resolve an unqualified identity name (e.g. "solar-radiation") when unique across modules.
 fn resolve_unqualified_identity(model: &CompositeModel, short_name: &str) -> Option<i64> {
     let mut matches = Vec::new();
-    for (identifier, sid) in &model.sids {
+    for (sid, identifier) in model.iter() {
         // Only consider top-level qualified names (module_name:identity).
         if identifier.contains('/') || !identifier.contains(':') {
             continue;
         }
         let candidate_short = identifier.split(':').next_back().unwrap_or(identifier);
         if candidate_short == short_name {
-            matches.push(*sid);
+            matches.push(sid);
         }
     }
     (matches.len() == 1).then_some(matches[0])
@@ -1173,17 +1786,14 @@ fn predicate_name_matches(expected_leaf: &str, identifier: &str, actual_name: &s
 /// stored as string names (e.g. `"coreconf-m2m:solar-radiation"`) while
 /// predicate values are coerced to SID numbers.  This function resolves both
 /// representations to the same type before comparing.
-fn list_entry_matches(
-    entry: &Value,
+pub(crate) fn list_entry_matches<N: TreeNode>(
+    entry: &N,
     key_values: &[(String, Value)],
     model: &CompositeModel,
     list_identifier: &str,
 ) -> bool {
-    let Some(map) = entry.as_object() else {
-        return false;
-    };
     key_values.iter().all(|(name, value)| {
-        let stored = map.get(name);
+        let stored = entry.leaf(name);
         let is_match = stored.is_some_and(|stored| stored == value);
         if is_match {
             return true;
@@ -1232,7 +1842,7 @@ fn identityref_equal(
 /// Convert a `serde_json::Value` to an identityref SID, accepting both
 /// numeric SIDs and string identity names (matching pycoreconf's
 /// `_resolve_identity_to_sid`).
-fn value_to_identityref_sid(value: &Value, model: &CompositeModel) -> Option<i64> {
+pub(crate) fn value_to_identityref_sid(value: &Value, model: &CompositeModel) -> Option<i64> {
     match value {
         Value::Number(n) => n.as_i64(),
         Value::String(s) => {
@@ -1257,46 +1867,77 @@ fn value_to_identityref_sid(value: &Value, model: &CompositeModel) -> Option<i64
     }
 }
 
-fn find_or_create_list_entry<'a>(
-    entries: &'a mut Vec<Value>,
-    key_values: &[(String, Value)],
+fn merge_value<N: TreeNode>(
+    target: &mut N,
+    overlay: &Value,
     model: &CompositeModel,
-    list_identifier: &str,
-) -> &'a mut Value {
-    if let Some(position) = entries
-        .iter()
-        .position(|entry| list_entry_matches(entry, key_values, model, list_identifier))
-    {
-        return &mut entries[position];
-    }
-
-    let mut map = Map::new();
-    for (name, value) in key_values {
-        map.insert(name.clone(), value.clone());
+    schema_path: &str,
+) {
+    match overlay {
+        Value::Object(overlay) if target.is_container() && target.entries().is_none() => {
+            for (key, value) in overlay {
+                match target.member_mut(key) {
+                    Some(existing) => {
+                        merge_value(existing, value, model, &join_path(schema_path, key))
+                    }
+                    None => {
+                        // Cannot fail: `target` is an object.
+                        let _ = target.insert_member(key.clone(), N::from_value(value.clone()));
+                    }
+                }
+            }
+        }
+        Value::Array(overlay) if target.entries().is_some() => {
+            let keys = list_keys(model, schema_path).unwrap_or_default();
+            let Some(entries) = target.entries_mut().filter(|_| !keys.is_empty()) else {
+                *target = N::from_value(Value::Array(overlay.clone()));
+                return;
+            };
+            for entry in overlay {
+                let key_values: Vec<(String, Value)> = keys
+                    .iter()
+                    .filter_map(|(name, _)| Some((name.clone(), entry.get(name)?.clone())))
+                    .collect();
+                let existing = (key_values.len() == keys.len())
+                    .then(|| {
+                        entries.iter_mut().find(|candidate| {
+                            list_entry_matches(&**candidate, &key_values, model, schema_path)
+                        })
+                    })
+                    .flatten();
+                match existing {
+                    Some(existing) => merge_value(existing, entry, model, schema_path),
+                    None => entries.push(N::from_value(entry.clone())),
+                }
+            }
+        }
+        overlay => *target = N::from_value(overlay.clone()),
     }
-    entries.push(Value::Object(map));
-    entries.last_mut().expect("list entry was just inserted")
 }
 
-fn ensure_object(value: &mut Value) -> Result<&mut Map<String, Value>> {
+pub(crate) fn ensure_object(value: &mut Value) -> Result<&mut Map<String, Value>> {
     if value.is_null() {
         *value = Value::Object(Map::new());
     }
-    value.as_object_mut().ok_or_else(|| {
-        CoreconfError::ValidationError("expected JSON object while traversing datastore".into())
-    })
+    value.as_object_mut().ok_or_else(expected_object)
 }
 
 fn ensure_array(value: &mut Value) -> Result<&mut Vec<Value>> {
     if value.is_null() {
         *value = Value::Array(Vec::new());
     }
-    value
-        .as_array_mut()
-        .ok_or_else(|| CoreconfError::ValidationError("expected JSON array for keyed list".into()))
+    value.as_array_mut().ok_or_else(expected_array)
+}
+
+pub(crate) fn expected_object() -> CoreconfError {
+    CoreconfError::ValidationError("expected JSON object while traversing datastore".into())
+}
+
+pub(crate) fn expected_array() -> CoreconfError {
+    CoreconfError::ValidationError("expected JSON array for keyed list".into())
 }
 
-fn join_path(current_path: &str, segment: &str) -> String {
+pub(crate) fn join_path(current_path: &str, segment: &str) -> String {
     if current_path.is_empty() {
         format!("/{segment}")
     } else {
@@ -1304,7 +1945,7 @@ fn join_path(current_path: &str, segment: &str) -> String {
     }
 }
 
-fn storage_key(segment: &str, depth: usize) -> String {
+pub(crate) fn storage_key(segment: &str, depth: usize) -> String {
     if depth == 0 {
         segment.to_string()
     } else {
@@ -1312,7 +1953,7 @@ fn storage_key(segment: &str, depth: usize) -> String {
     }
 }
 
-fn segment_leaf(segment: &str) -> &str {
+pub(crate) fn segment_leaf(segment: &str) -> &str {
     segment
         .rsplit('/')
         .next()
@@ -1323,7 +1964,7 @@ fn segment_leaf(segment: &str) -> &str {
 }
 
 /// Format predicate string from key SIDs and values (e.g., "[type='solar-radiation'][id='0']").
-fn format_predicate_string(
+pub(crate) fn format_predicate_string(
     model: &CompositeModel,
     key_sids: &[i64],
     key_values: &[Value],
@@ -0,0 +1,276 @@
+//! Model-aware differences between two datastore trees.
+//!
+//! [`Datastore::diff`] walks both trees against the schema. Keyed list
+//! entries are matched by their keys rather than their array position, so
+//! an insertion in the middle of a list is one added entry, not a changed
+//! array. Every edit names its node by a concrete predicate path, and
+//! applying the edits in order to the older tree yields the newer one,
+//! including the order of `ordered-by user` lists.
+//!
+//! [`Datastore::diff`]: crate::Datastore::diff
+
+use std::collections::{HashMap, HashSet};
+
+use coreconf_model::{CompositeModel, Result};
+use serde_json::{Map, Value};
+
+use crate::datastore::{format_predicate_string, join_path, list_keys};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOperation {
+    Add,
+    Modify,
+    Delete,
+}
+
+/// One node that differs, with its value on each side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEdit {
+    pub operation: DiffOperation,
+    pub path: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl DiffEdit {
+    fn add(path: String, after: &Value) -> Self {
+        Self {
+            operation: DiffOperation::Add,
+            path,
+            before: None,
+            after: Some(after.clone()),
+        }
+    }
+
+    fn modify(path: String, before: &Value, after: &Value) -> Self {
+        Self {
+            operation: DiffOperation::Modify,
+            path,
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }
+    }
+
+    fn delete(path: String, before: &Value) -> Self {
+        Self {
+            operation: DiffOperation::Delete,
+            path,
+            before: Some(before.clone()),
+            after: None,
+        }
+    }
+}
+
+/// The edits turning one datastore tree into another, in the order they
+/// must be applied.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diff {
+    pub edits: Vec<DiffEdit>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// The edits as `(path, value)` writes, `None` deleting the node.
+    pub fn patch(&self) -> Vec<(String, Option<Value>)> {
+        self.edits
+            .iter()
+            .map(|edit| (edit.path.clone(), edit.after.clone()))
+            .collect()
+    }
+}
+
+pub(crate) fn diff_trees(model: &CompositeModel, before: &Value, after: &Value) -> Result<Diff> {
+    let mut edits = Vec::new();
+    let empty = Map::new();
+    diff_children(
+        model,
+        "",
+        "",
+        before.as_object().unwrap_or(&empty),
+        after.as_object().unwrap_or(&empty),
+        &mut edits,
+    )?;
+    Ok(Diff { edits })
+}
+
+/// The edits turning the node at the predicate path `path`, an instance of
+/// `schema`, from `before` into `after`, `None` where it is absent.
+pub(crate) fn diff_at(
+    model: &CompositeModel,
+    schema: &str,
+    path: &str,
+    before: Option<&Value>,
+    after: Option<&Value>,
+) -> Result<Vec<DiffEdit>> {
+    let mut edits = Vec::new();
+    match (before, after) {
+        // A list entry is matched by its path already, so only its members
+        // can differ.
+        (Some(Value::Object(before)), Some(Value::Object(after))) if path.ends_with(']') => {
+            diff_children(model, schema, path, before, after, &mut edits)?;
+        }
+        (Some(before), Some(after)) => {
+            diff_node(model, schema, path.to_string(), before, after, &mut edits)?;
+        }
+        (None, Some(after)) => edits.push(DiffEdit::add(path.to_string(), after)),
+        (Some(before), None) => edits.push(DiffEdit::delete(path.to_string(), before)),
+        (None, None) => {}
+    }
+    Ok(edits)
+}
+
+/// Diff the members of a container or list entry. `schema` and `path` are
+/// empty at the root.
+fn diff_children(
+    model: &CompositeModel,
+    schema: &str,
+    path: &str,
+    before: &Map<String, Value>,
+    after: &Map<String, Value>,
+    edits: &mut Vec<DiffEdit>,
+) -> Result<()> {
+    let removed = before.keys().filter(|key| !after.contains_key(*key));
+    let mut names = after.keys().chain(removed).collect::<Vec<_>>();
+    names.sort();
+
+    for name in names {
+        let child_schema = join_path(schema, name);
+        let child_path = format!("{path}/{name}");
+        match (before.get(name), after.get(name)) {
+            (Some(before), Some(after)) => {
+                diff_node(model, &child_schema, child_path, before, after, edits)?;
+            }
+            (None, Some(after)) => edits.push(DiffEdit::add(child_path, after)),
+            (Some(before), None) => edits.push(DiffEdit::delete(child_path, before)),
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+fn diff_node(
+    model: &CompositeModel,
+    schema: &str,
+    path: String,
+    before: &Value,
+    after: &Value,
+    edits: &mut Vec<DiffEdit>,
+) -> Result<()> {
+    if before == after {
+        return Ok(());
+    }
+    // Nodes the model doesn't know, leaves, leaf-lists and keyless lists
+    // change as a whole.
+    let keyed = model.get_sid(schema).is_some() && !list_keys(model, schema)?.is_empty();
+    match (before, after) {
+        (Value::Array(before), Value::Array(after)) if keyed => {
+            diff_entries(model, schema, &path, before, after, edits)
+        }
+        (Value::Object(before), Value::Object(after)) if !keyed => {
+            diff_children(model, schema, &path, before, after, edits)
+        }
+        _ => {
+            edits.push(DiffEdit::modify(path, before, after));
+            Ok(())
+        }
+    }
+}
+
+/// Diff two instances of the keyed list `schema`, matching entries by key.
+fn diff_entries(
+    model: &CompositeModel,
+    schema: &str,
+    path: &str,
+    before: &[Value],
+    after: &[Value],
+    edits: &mut Vec<DiffEdit>,
+) -> Result<()> {
+    let before = keyed_entries(model, schema, path, before)?;
+    let after = keyed_entries(model, schema, path, after)?;
+    let previous = before
+        .iter()
+        .map(|(path, value)| (path.as_str(), *value))
+        .collect::<HashMap<_, _>>();
+    let after_paths = after
+        .iter()
+        .map(|(path, _)| path.as_str())
+        .collect::<HashSet<_>>();
+
+    // Writing an entry that doesn't exist appends it. When that can't
+    // reproduce the new order, entries from the first one out of place
+    // onwards are deleted and written again in order.
+    let in_place = if model
+        .get_sid(schema)
+        .is_some_and(|sid| model.is_ordered_by_user(sid))
+    {
+        before
+            .iter()
+            .filter(|(path, _)| after_paths.contains(path.as_str()))
+            .zip(&after)
+            .take_while(|((surviving, _), (path, _))| surviving == path)
+            .count()
+    } else {
+        after.len()
+    };
+    let rewritten = after[in_place..]
+        .iter()
+        .map(|(path, _)| path.as_str())
+        .collect::<HashSet<_>>();
+
+    for (entry_path, value) in &before {
+        let entry_path = entry_path.as_str();
+        if !after_paths.contains(entry_path) || rewritten.contains(entry_path) {
+            edits.push(DiffEdit::delete(entry_path.to_string(), value));
+        }
+    }
+    for (index, (entry_path, value)) in after.iter().enumerate() {
+        let kept = (index < in_place)
+            .then(|| previous.get(entry_path.as_str()))
+            .flatten();
+        match (kept, value) {
+            (None, _) => edits.push(DiffEdit::add(entry_path.clone(), value)),
+            (Some(Value::Object(kept)), Value::Object(value)) => {
+                diff_children(model, schema, entry_path, kept, value, edits)?;
+            }
+            (Some(kept), _) if *kept != *value => {
+                edits.push(DiffEdit::modify(entry_path.clone(), kept, value));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Pair each entry with its predicate path, skipping entries without keys.
+fn keyed_entries<'v>(
+    model: &CompositeModel,
+    schema: &str,
+    path: &str,
+    entries: &'v [Value],
+) -> Result<Vec<(String, &'v Value)>> {
+    let keys = list_keys(model, schema)?;
+    let key_sids = model
+        .get_sid(schema)
+        .and_then(|sid| model.get_keys(sid))
+        .unwrap_or_default();
+    let mut keyed = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let Some(key_values) = keys
+            .iter()
+            .map(|(leaf, _)| entry.get(leaf).cloned())
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        let predicates = format_predicate_string(model, key_sids, &key_values)?;
+        keyed.push((format!("{path}{predicates}"), entry));
+    }
+    Ok(keyed)
+}
@@ -0,0 +1,181 @@
+use ciborium::Value as CborValue;
+use coreconf_model::{CoreconfError, DataError, ErrorTag, Result};
+
+/// SID of the `ietf-coreconf:error` container (draft-ietf-core-comi).
+pub const ERROR_CONTAINER_SID: i64 = 1024;
+
+// Child leaves of the error container, as deltas from its SID.
+const ERROR_APP_TAG_DELTA: i64 = 1;
+const ERROR_DATA_NODE_DELTA: i64 = 2;
+const ERROR_MESSAGE_DELTA: i64 = 3;
+const ERROR_TAG_DELTA: i64 = 4;
+
+const IDENTITY_MODULE: &str = "ietf-coreconf";
+
+/// Encode an error report as a CORECONF error payload.
+///
+/// The error-tag identityref is sent in its name form
+/// (`ietf-coreconf:invalid-value`, RFC 9254 section 6.10) so peers don't
+/// need identity SIDs to read it. The data node is sent as an
+/// instance-identifier name when the path is known, otherwise as its SID.
+pub fn encode_error_payload(error: &DataError) -> Result<Vec<u8>> {
+    let mut fields = vec![
+        (
+            CborValue::from(ERROR_TAG_DELTA),
+            CborValue::Text(format!("{IDENTITY_MODULE}:{}", error.tag)),
+        ),
+        (
+            CborValue::from(ERROR_MESSAGE_DELTA),
+            CborValue::Text(error.message.clone()),
+        ),
+    ];
+    if let Some(app_tag) = &error.app_tag {
+        fields.push((
+            CborValue::from(ERROR_APP_TAG_DELTA),
+            CborValue::Text(app_tag.clone()),
+        ));
+    }
+    if let Some(path) = &error.path {
+        fields.push((
+            CborValue::from(ERROR_DATA_NODE_DELTA),
+            CborValue::Text(path.clone()),
+        ));
+    } else if let Some(sid) = error.sid {
+        fields.push((CborValue::from(ERROR_DATA_NODE_DELTA), CborValue::from(sid)));
+    }
+
+    let payload = CborValue::Map(vec![(
+        CborValue::from(ERROR_CONTAINER_SID),
+        CborValue::Map(fields),
+    )]);
+    let mut bytes = Vec::new();
+    ciborium::into_writer(&payload, &mut bytes)
+        .map_err(|error| CoreconfError::CborEncode(error.to_string()))?;
+    Ok(bytes)
+}
+
+/// Decode a CORECONF error payload back into an error report.
+///
+/// Error-tags sent as identity SIDs, or naming identities this crate
+/// doesn't know, are reported as `operation-failed` with the peer's message
+/// preserved.
+pub fn decode_error_payload(bytes: &[u8]) -> Result<DataError> {
+    let value: CborValue = ciborium::from_reader(bytes)
+        .map_err(|error| CoreconfError::CborDecode(error.to_string()))?;
+    let container = value
+        .as_map()
+        .and_then(|entries| {
+            entries
+                .iter()
+                .find(|(key, _)| cbor_integer(key) == Some(ERROR_CONTAINER_SID))
+        })
+        .and_then(|(_, container)| container.as_map())
+        .ok_or_else(|| {
+            CoreconfError::CborDecode("payload has no ietf-coreconf error container".into())
+        })?;
+
+    let mut error = DataError::new(ErrorTag::OperationFailed, "");
+    for (key, value) in container {
+        match cbor_integer(key) {
+            Some(ERROR_TAG_DELTA) => {
+                if let Some(tag) = value.as_text().and_then(parse_identity) {
+                    error.tag = tag;
+                }
+            }
+            Some(ERROR_MESSAGE_DELTA) => {
+                if let Some(message) = value.as_text() {
+                    error.message = message.to_string();
+                }
+            }
+            Some(ERROR_APP_TAG_DELTA) => match value {
+                CborValue::Text(app_tag) => error.app_tag = Some(app_tag.clone()),
+                other => error.app_tag = cbor_integer(other).map(|sid| sid.to_string()),
+            },
+            Some(ERROR_DATA_NODE_DELTA) => match value {
+                CborValue::Text(path) => error.path = Some(path.clone()),
+                CborValue::Array(items) => error.sid = items.first().and_then(cbor_integer),
+                other => error.sid = cbor_integer(other),
+            },
+            _ => {}
+        }
+    }
+    Ok(error)
+}
+
+fn parse_identity(name: &str) -> Option<ErrorTag> {
+    let name = name
+        .strip_prefix(IDENTITY_MODULE)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .unwrap_or(name);
+    ErrorTag::parse(name)
+}
+
+fn cbor_integer(value: &CborValue) -> Option<i64> {
+    value
+        .as_integer()
+        .and_then(|integer| i64::try_from(integer).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_payload_round_trips_structured_fields() {
+        let error = DataError::new(ErrorTag::InvalidValue, "cannot parse 'x' as uint8")
+            .with_app_tag("data-type-mismatch")
+            .with_path("/example:devices/device[id='a']/port");
+
+        let decoded = decode_error_payload(&encode_error_payload(&error).unwrap()).unwrap();
+
+        assert_eq!(decoded, error);
+    }
+
+    #[test]
+    fn error_payload_uses_container_sid_and_leaf_deltas() {
+        let error = DataError::new(ErrorTag::DataMissing, "no such entry").with_sid(60003);
+        let bytes = encode_error_payload(&error).unwrap();
+        let value: CborValue = ciborium::from_reader(bytes.as_slice()).unwrap();
+
+        assert_eq!(
+            value,
+            CborValue::Map(vec![(
+                CborValue::from(1024),
+                CborValue::Map(vec![
+                    (
+                        CborValue::from(4),
+                        CborValue::Text("ietf-coreconf:data-missing".into())
+                    ),
+                    (CborValue::from(3), CborValue::Text("no such entry".into())),
+                    (CborValue::from(2), CborValue::from(60003)),
+                ]),
+            )])
+        );
+    }
+
+    #[test]
+    fn error_payload_keeps_message_for_identity_sids() {
+        // Example from draft-ietf-core-comi: tag and app-tag as identity SIDs.
+        let payload = CborValue::Map(vec![(
+            CborValue::from(1024),
+            CborValue::Map(vec![
+                (CborValue::from(4), CborValue::from(1011)),
+                (CborValue::from(1), CborValue::from(1018)),
+                (CborValue::from(2), CborValue::from(1740)),
+                (
+                    CborValue::from(3),
+                    CborValue::Text("Maximum exceeded".into()),
+                ),
+            ]),
+        )]);
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&payload, &mut bytes).unwrap();
+
+        let decoded = decode_error_payload(&bytes).unwrap();
+
+        assert_eq!(decoded.tag, ErrorTag::OperationFailed);
+        assert_eq!(decoded.app_tag.as_deref(), Some("1018"));
+        assert_eq!(decoded.sid, Some(1740));
+        assert_eq!(decoded.message, "Maximum exceeded");
+    }
+}
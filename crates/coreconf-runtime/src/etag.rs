@@ -0,0 +1,171 @@
+//! Entity-tags for conditional requests.
+//!
+//! A resource's entity-tag is a 64-bit FNV-1a hash of its value in the
+//! datastore serving it, so it changes exactly when the data under the
+//! resource does. Object members are hashed in name order, which makes the
+//! tag independent of how the tree was built.
+
+use serde_json::Value;
+
+use crate::datastore::TreeNode;
+use crate::shared_tree::SharedTree;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The entity-tag of a resource whose current value is `value`.
+pub fn entity_tag(value: &Value) -> Vec<u8> {
+    let mut hasher = Fnv(FNV_OFFSET);
+    hasher.value(value);
+    hasher.0.to_be_bytes().to_vec()
+}
+
+/// [`entity_tag`] of a whole tree, read in place.
+pub(crate) fn tree_entity_tag(tree: &SharedTree) -> Vec<u8> {
+    let mut hasher = Fnv(FNV_OFFSET);
+    hasher.tree(tree);
+    hasher.0.to_be_bytes().to_vec()
+}
+
+/// Whether the If-Match and If-None-Match conditions of a request hold for
+/// a resource whose entity-tag is `current`, `None` if it does not exist.
+///
+/// An empty tag in `if_match` matches any existing resource.
+pub fn preconditions_hold(
+    if_match: &[Vec<u8>],
+    if_none_match: bool,
+    current: Option<&[u8]>,
+) -> bool {
+    match current {
+        Some(current) => {
+            !if_none_match
+                && (if_match.is_empty()
+                    || if_match
+                        .iter()
+                        .any(|tag| tag.is_empty() || tag.as_slice() == current))
+        }
+        None => if_match.is_empty(),
+    }
+}
+
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Hash `bytes` behind their length, so adjacent strings can't run
+    /// into each other.
+    fn write_sized(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_be_bytes());
+        self.write(bytes);
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.write(b"n"),
+            Value::Bool(flag) => self.write(if *flag { b"t" } else { b"f" }),
+            Value::Number(number) => {
+                self.write(b"#");
+                self.write_sized(number.to_string().as_bytes());
+            }
+            Value::String(text) => {
+                self.write(b"s");
+                self.write_sized(text.as_bytes());
+            }
+            Value::Array(items) => {
+                self.write(b"[");
+                self.write(&(items.len() as u64).to_be_bytes());
+                for item in items {
+                    self.value(item);
+                }
+            }
+            Value::Object(members) => {
+                let mut names: Vec<&String> = members.keys().collect();
+                names.sort();
+                self.write(b"{");
+                self.write(&(names.len() as u64).to_be_bytes());
+                for name in names {
+                    self.write_sized(name.as_bytes());
+                    self.value(&members[name]);
+                }
+            }
+        }
+    }
+
+    /// [`Fnv::value`] of `tree` as a plain value, whose members are
+    /// already in name order.
+    fn tree(&mut self, tree: &SharedTree) {
+        if let Some(leaf) = tree.as_leaf() {
+            self.value(leaf);
+        } else if let Some(entries) = tree.entries() {
+            self.write(b"[");
+            self.write(&(entries.len() as u64).to_be_bytes());
+            for entry in entries {
+                self.tree(entry);
+            }
+        } else {
+            self.write(b"{");
+            self.write(&(tree.members().count() as u64).to_be_bytes());
+            for (name, member) in tree.members() {
+                self.write_sized(name.as_bytes());
+                self.tree(member);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn tags_follow_content_not_member_order() {
+        let tag = entity_tag(&json!({"a": 1, "b": ["x", "y"]}));
+
+        assert_eq!(tag.len(), 8);
+        assert_eq!(tag, entity_tag(&json!({"b": ["x", "y"], "a": 1})));
+        assert_ne!(tag, entity_tag(&json!({"a": 1, "b": ["xy"]})));
+        assert_ne!(tag, entity_tag(&json!({"a": "1", "b": ["x", "y"]})));
+    }
+
+    #[test]
+    fn shared_trees_hash_like_their_values() {
+        let value = json!({"b": [{"k": 1}, "x"], "a": {"c": null, "B": true}});
+
+        assert_eq!(
+            tree_entity_tag(&SharedTree::from(value.clone())),
+            entity_tag(&value)
+        );
+    }
+
+    #[test]
+    fn preconditions_compare_tags_and_existence() {
+        let tag = entity_tag(&json!(1));
+        let other = entity_tag(&json!(2));
+
+        assert!(preconditions_hold(&[], false, None));
+        assert!(preconditions_hold(
+            std::slice::from_ref(&tag),
+            false,
+            Some(&tag)
+        ));
+        assert!(preconditions_hold(
+            &[other.clone(), tag.clone()],
+            false,
+            Some(&tag)
+        ));
+        assert!(!preconditions_hold(&[other], false, Some(&tag)));
+        assert!(!preconditions_hold(std::slice::from_ref(&tag), false, None));
+        assert!(preconditions_hold(&[Vec::new()], false, Some(&tag)));
+        assert!(!preconditions_hold(&[Vec::new()], false, None));
+        assert!(preconditions_hold(&[], true, None));
+        assert!(!preconditions_hold(&[], true, Some(&tag)));
+    }
+}
@@ -1,10 +1,13 @@
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use coreconf_model::{CompositeModel, CoreconfError, Result};
 use serde_json::Value;
 
 use crate::backend::Backend;
+use crate::datastore::{tree_delete, tree_get, tree_set};
+use crate::list_index::ListIndex;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EditableFormat {
@@ -43,16 +46,18 @@ impl std::fmt::Display for EditableFormat {
 pub struct FileBackend {
     path: PathBuf,
     format: EditableFormat,
-    model: CompositeModel,
+    model: Arc<CompositeModel>,
     tree: Value,
+    index: ListIndex,
 }
 
 impl FileBackend {
     pub fn open(
-        model: CompositeModel,
+        model: impl Into<Arc<CompositeModel>>,
         path: impl Into<PathBuf>,
         format: EditableFormat,
     ) -> Result<Self> {
+        let model = model.into();
         let path = path.into();
         let tree = read_editable_file(&model, &path, format)?;
         Ok(Self {
@@ -60,6 +65,7 @@ impl FileBackend {
             format,
             model,
             tree,
+            index: ListIndex::default(),
         })
     }
 
@@ -82,14 +88,27 @@ impl FileBackend {
 }
 
 impl Backend for FileBackend {
-    fn read_tree(&self) -> Value {
-        self.tree.clone()
+    fn read_tree(&self) -> Result<Value> {
+        Ok(self.tree.clone())
     }
 
     fn replace_tree(&mut self, next: Value) -> Result<()> {
         self.tree = next;
+        self.index.clear();
         Ok(())
     }
+
+    fn read_path(&self, model: &CompositeModel, path: &str) -> Result<Option<Value>> {
+        tree_get(&self.tree, model, path, Some(&self.index))
+    }
+
+    fn write_path(&mut self, model: &CompositeModel, path: &str, value: Value) -> Result<()> {
+        tree_set(&mut self.tree, model, path, value, Some(&mut self.index))
+    }
+
+    fn delete_path(&mut self, model: &CompositeModel, path: &str) -> Result<bool> {
+        tree_delete(&mut self.tree, model, path, Some(&mut self.index))
+    }
 }
 
 pub fn read_editable_file(
@@ -0,0 +1,391 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use coreconf_model::{CoreconfError, DataError, ErrorTag, Result};
+use serde_json::{Map, Value, json};
+
+/// Journal operations served on the CORECONF resource via POST.
+pub const REVISIONS_PATH: &str = "/coreconf-journal:revisions";
+pub const REVISION_PATH: &str = "/coreconf-journal:revision";
+pub const ROLLBACK_PATH: &str = "/coreconf-journal:rollback";
+
+/// Number of revisions a [`Journal`] keeps unless configured otherwise.
+pub const DEFAULT_JOURNAL_CAPACITY: usize = 64;
+
+/// One committed change to running.
+///
+/// Patches use the same `(path, value)` form as
+/// [`CoreconfClient::apply_patch`](crate::transport::coap_lite::CoreconfClient::apply_patch):
+/// `None` deletes the node. Applying `inverse_patch` in order to the tree of
+/// this revision yields the tree of the previous one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub revision: u64,
+    pub timestamp: SystemTime,
+    /// Transport address of the client that made the change, if known.
+    pub peer: Option<String>,
+    pub changed_paths: Vec<String>,
+    pub patch: Vec<(String, Option<Value>)>,
+    pub inverse_patch: Vec<(String, Option<Value>)>,
+}
+
+impl JournalEntry {
+    /// Encode the entry as a name-keyed tree, with or without its patches.
+    pub fn to_value(&self, with_patches: bool) -> Value {
+        let timestamp = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut value = json!({
+            "revision": self.revision,
+            "timestamp": timestamp,
+            "changed-paths": self.changed_paths,
+        });
+        if let Some(peer) = &self.peer {
+            value["peer"] = json!(peer);
+        }
+        if with_patches {
+            value["patch"] = patch_to_value(&self.patch);
+            value["inverse-patch"] = patch_to_value(&self.inverse_patch);
+        }
+        value
+    }
+
+    /// Decode an entry written by [`JournalEntry::to_value`]. Missing patches
+    /// decode as empty.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let revision = value
+            .get("revision")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid_entry("journal entry has no revision"))?;
+        let timestamp = value
+            .get("timestamp")
+            .and_then(Value::as_u64)
+            .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap_or(UNIX_EPOCH);
+        let changed_paths = value
+            .get("changed-paths")
+            .and_then(Value::as_array)
+            .map(|paths| {
+                paths
+                    .iter()
+                    .filter_map(|path| path.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            revision,
+            timestamp,
+            peer: value
+                .get("peer")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            changed_paths,
+            patch: patch_from_value(value.get("patch"))?,
+            inverse_patch: patch_from_value(value.get("inverse-patch"))?,
+        })
+    }
+}
+
+/// Bounded history of transactions committed to running.
+///
+/// Revisions are numbered from 1; revision 0 is the tree the handler
+/// started with. Once `capacity` entries are held the oldest is dropped,
+/// and revisions before it can no longer be rolled back to.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    entries: VecDeque<JournalEntry>,
+    capacity: usize,
+    next_revision: u64,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self::new(DEFAULT_JOURNAL_CAPACITY)
+    }
+}
+
+impl Journal {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            next_revision: 1,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the capacity, dropping the oldest entries that no longer fit.
+    /// A capacity of 0 keeps numbering revisions but stores none.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim();
+    }
+
+    /// Record a committed change and return its revision number.
+    pub fn record(
+        &mut self,
+        peer: Option<String>,
+        changed_paths: Vec<String>,
+        patch: Vec<(String, Option<Value>)>,
+        inverse_patch: Vec<(String, Option<Value>)>,
+    ) -> u64 {
+        let revision = self.next_revision;
+        self.next_revision += 1;
+        self.entries.push_back(JournalEntry {
+            revision,
+            timestamp: SystemTime::now(),
+            peer,
+            changed_paths,
+            patch,
+            inverse_patch,
+        });
+        self.trim();
+        revision
+    }
+
+    /// Stored entries, oldest first.
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &JournalEntry> {
+        self.entries.iter()
+    }
+
+    pub fn entry(&self, revision: u64) -> Option<&JournalEntry> {
+        let oldest = self.entries.front()?.revision;
+        let index = usize::try_from(revision.checked_sub(oldest)?).ok()?;
+        self.entries.get(index)
+    }
+
+    /// The revision running is at, or 0 if nothing has been committed.
+    pub fn latest_revision(&self) -> u64 {
+        self.next_revision - 1
+    }
+
+    /// Edits that take running from the latest revision back to `revision`.
+    ///
+    /// Fails with `invalid-value` for revisions that don't exist yet or
+    /// whose later entries have already been dropped from the journal.
+    pub fn rollback_patch(&self, revision: u64) -> Result<Vec<(String, Option<Value>)>> {
+        let latest = self.latest_revision();
+        if revision > latest {
+            return Err(unknown_revision(revision, "does not exist"));
+        }
+        if revision < latest && self.entry(revision + 1).is_none() {
+            return Err(unknown_revision(
+                revision,
+                "is older than the journal retains",
+            ));
+        }
+        Ok(self
+            .entries
+            .iter()
+            .rev()
+            .take_while(|entry| entry.revision > revision)
+            .flat_map(|entry| entry.inverse_patch.iter().cloned())
+            .collect())
+    }
+
+    fn trim(&mut self) {
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+}
+
+/// Parse the `revision` input of the journal revision and rollback
+/// operations.
+pub fn parse_revision_input(input: Option<&Value>) -> Result<u64> {
+    input
+        .and_then(|input| input.get("revision"))
+        .and_then(Value::as_u64)
+        .ok_or_else(|| {
+            DataError::new(ErrorTag::MissingElement, "a revision number is required")
+                .with_path(format!("{ROLLBACK_PATH}/revision"))
+                .into()
+        })
+}
+
+/// Build the input of the journal revision and rollback operations.
+pub fn revision_input(revision: u64) -> Value {
+    json!({ "revision": revision })
+}
+
+/// The edit undoing a write to `path`, given a lookup into the tree as it
+/// was before the write.
+///
+/// Writing a node creates any missing ancestors, so when `path` didn't exist
+/// the inverse deletes the outermost ancestor the write created.
+pub(crate) fn inverse_edit(
+    path: &str,
+    lookup: impl Fn(&str) -> Option<Value>,
+) -> (String, Option<Value>) {
+    if let Some(before) = lookup(path) {
+        return (path.to_string(), Some(before));
+    }
+    let created = ancestor_paths(path)
+        .into_iter()
+        .find(|ancestor| lookup(ancestor).is_none())
+        .unwrap_or(path);
+    (created.to_string(), None)
+}
+
+/// Proper ancestors of a predicate path, outermost first. Slashes inside
+/// `[...]` predicates don't separate nodes.
+fn ancestor_paths(path: &str) -> Vec<&str> {
+    let mut ancestors = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    for (index, ch) in path.char_indices() {
+        match (quote, ch) {
+            (Some(open), _) if ch == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') if depth > 0 => quote = Some(ch),
+            (None, '[') => depth += 1,
+            (None, ']') => depth = depth.saturating_sub(1),
+            (None, '/') if depth == 0 && index > 0 => ancestors.push(&path[..index]),
+            _ => {}
+        }
+    }
+    ancestors
+}
+
+fn patch_to_value(patch: &[(String, Option<Value>)]) -> Value {
+    Value::Array(
+        patch
+            .iter()
+            .map(|(path, value)| {
+                let mut edit = Map::new();
+                edit.insert("path".into(), json!(path));
+                if let Some(value) = value {
+                    edit.insert("value".into(), value.clone());
+                }
+                Value::Object(edit)
+            })
+            .collect(),
+    )
+}
+
+fn patch_from_value(value: Option<&Value>) -> Result<Vec<(String, Option<Value>)>> {
+    let Some(edits) = value.and_then(Value::as_array) else {
+        return Ok(Vec::new());
+    };
+    edits
+        .iter()
+        .map(|edit| {
+            let path = edit
+                .get("path")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid_entry("journal patch edit has no path"))?;
+            Ok((path.to_string(), edit.get("value").cloned()))
+        })
+        .collect()
+}
+
+fn unknown_revision(revision: u64, reason: &str) -> CoreconfError {
+    DataError::new(
+        ErrorTag::InvalidValue,
+        format!("revision {revision} {reason}"),
+    )
+    .with_path(format!("{ROLLBACK_PATH}/revision"))
+    .with_value(json!(revision))
+    .into()
+}
+
+fn invalid_entry(message: &str) -> CoreconfError {
+    CoreconfError::ValidationError(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_leaf(journal: &mut Journal, before: Option<bool>, after: bool) -> u64 {
+        journal.record(
+            None,
+            vec!["/example:settings/enabled".into()],
+            vec![("/example:settings/enabled".into(), Some(json!(after)))],
+            vec![("/example:settings/enabled".into(), before.map(|v| json!(v)))],
+        )
+    }
+
+    #[test]
+    fn rollback_patch_applies_inverses_newest_first() {
+        let mut journal = Journal::new(8);
+        record_leaf(&mut journal, None, true);
+        record_leaf(&mut journal, Some(true), false);
+
+        let patch = journal.rollback_patch(0).unwrap();
+
+        assert_eq!(
+            patch,
+            vec![
+                ("/example:settings/enabled".into(), Some(json!(true))),
+                ("/example:settings/enabled".into(), None),
+            ]
+        );
+        assert!(journal.rollback_patch(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn inverse_edit_deletes_the_outermost_created_ancestor() {
+        let existing = ["/example:devices"];
+        let lookup = |path: &str| existing.contains(&path).then(|| json!({}));
+
+        assert_eq!(
+            inverse_edit("/example:devices/device[id='a/b']/enabled", lookup),
+            ("/example:devices/device[id='a/b']".to_string(), None)
+        );
+        assert_eq!(
+            inverse_edit("/example:devices", lookup),
+            ("/example:devices".to_string(), Some(json!({})))
+        );
+    }
+
+    #[test]
+    fn journal_drops_oldest_entries_past_capacity() {
+        let mut journal = Journal::new(2);
+        for _ in 0..3 {
+            record_leaf(&mut journal, Some(false), true);
+        }
+
+        assert_eq!(journal.latest_revision(), 3);
+        assert!(journal.entry(1).is_none());
+        assert_eq!(journal.entry(3).unwrap().revision, 3);
+        assert!(journal.rollback_patch(1).is_ok());
+        let error = journal.rollback_patch(0).unwrap_err();
+        assert_eq!(error.error_tag(), ErrorTag::InvalidValue);
+    }
+
+    #[test]
+    fn journal_entry_round_trips_through_value() {
+        let mut journal = Journal::new(1);
+        let revision = journal.record(
+            Some("127.0.0.1:5683".into()),
+            vec!["/example:settings".into()],
+            vec![("/example:settings".into(), None)],
+            vec![("/example:settings".into(), Some(json!({"enabled": true})))],
+        );
+        let entry = journal.entry(revision).unwrap();
+
+        let decoded = JournalEntry::from_value(&entry.to_value(true)).unwrap();
+
+        let expected_timestamp = UNIX_EPOCH
+            + Duration::from_secs(
+                entry
+                    .timestamp
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+            );
+        assert_eq!(
+            decoded,
+            JournalEntry {
+                timestamp: expected_timestamp,
+                ..entry.clone()
+            }
+        );
+    }
+}
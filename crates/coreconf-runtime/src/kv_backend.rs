@@ -0,0 +1,848 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use coreconf_model::{CompositeModel, CoreconfError, Result, YangType};
+use redb::{Database, ReadableTable, ReadableTableMetadata, TableDefinition};
+use serde_json::{Map, Value};
+
+use crate::backend::Backend;
+use crate::datastore::{
+    consume_key_values, ensure_object, join_path, list_keys, segment_leaf,
+    split_canonical_segments, storage_key, tree_delete, tree_get, tree_set,
+    value_to_identityref_sid,
+};
+use crate::path::PredicatePath;
+
+/// Record key (SID followed by key values, each CBOR-encoded) to the CBOR
+/// array `[order, value]`.
+const NODES: TableDefinition<&[u8], &[u8]> = TableDefinition::new("nodes");
+const META: TableDefinition<&str, u64> = TableDefinition::new("meta");
+const NEXT_ORDER: &str = "next-order";
+
+/// Stored records by key, with their list order and value.
+type Records = BTreeMap<Vec<u8>, (u64, Value)>;
+
+/// Backend storing the tree in an embedded [redb](https://docs.rs/redb)
+/// database, one record per top-level node and per keyed list entry.
+///
+/// A top-level container is keyed by its SID; a list entry by the list SID
+/// followed by the key values of every list on its path, so the entries of
+/// one list instance share a key prefix. Each record holds its subtree minus
+/// the keyed lists below it, which are records of their own. Reads and
+/// writes through [`Backend::read_path`] and friends load only the records
+/// along the path, plus the lists under the addressed node.
+///
+/// List entries keep their insertion order through a per-record sequence
+/// number. Empty keyed lists are not stored and read back as absent.
+pub struct KvBackend {
+    path: PathBuf,
+    model: Arc<CompositeModel>,
+    database: Database,
+    layout: Layout,
+}
+
+impl KvBackend {
+    /// Open the database at `path`, creating it if it doesn't exist.
+    pub fn open(model: impl Into<Arc<CompositeModel>>, path: impl Into<PathBuf>) -> Result<Self> {
+        let model = model.into();
+        let path = path.into();
+        let database = Database::create(&path).map_err(storage_error)?;
+
+        let transaction = database.begin_write().map_err(storage_error)?;
+        transaction.open_table(NODES).map_err(storage_error)?;
+        transaction.open_table(META).map_err(storage_error)?;
+        transaction.commit().map_err(storage_error)?;
+
+        let layout = Layout::new(&model)?;
+        Ok(Self {
+            path,
+            model,
+            database,
+            layout,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn try_replace_tree(&self, next: Value) -> Result<()> {
+        let Value::Object(next) = next else {
+            return Err(CoreconfError::ValidationError(
+                "datastore root must be a JSON object".into(),
+            ));
+        };
+
+        let transaction = self.database.begin_write().map_err(storage_error)?;
+        {
+            let mut nodes = transaction.open_table(NODES).map_err(storage_error)?;
+            let mut meta = transaction.open_table(META).map_err(storage_error)?;
+            let mut batch = Batch::new(scan(&nodes, &[])?, read_next_order(&meta)?);
+
+            // Top-level lists become entry records; what remains are roots.
+            let mut root = Value::Object(next);
+            self.split_lists(&mut batch, "", &[], &mut root)?;
+            let Value::Object(remaining) = root else {
+                unreachable!("root was built as an object");
+            };
+            for (name, value) in remaining {
+                let identifier = format!("/{name}");
+                let sid = self
+                    .model
+                    .get_sid(&identifier)
+                    .ok_or_else(|| CoreconfError::SidNotFound(identifier.clone()))?;
+                if self.layout.list(sid).is_some() {
+                    // An empty top-level list.
+                    continue;
+                }
+                self.split(&mut batch, &identifier, &[], record_key(sid, &[]), 0, value)?;
+            }
+
+            batch.write(&mut nodes, &mut meta)?;
+        }
+        transaction.commit().map_err(storage_error)
+    }
+
+    /// The records a predicate path passes through, or `None` when the path
+    /// addresses the whole tree or a node the layout doesn't know.
+    fn locate(&self, path: &str) -> Result<Option<Branch<'_>>> {
+        let parsed = PredicatePath::parse(path)?;
+        let segments = split_canonical_segments(&parsed.canonical_path);
+
+        let mut steps = Vec::new();
+        let mut chain = Vec::new();
+        let mut predicate_index = 0usize;
+        let mut current = String::new();
+        let mut record_depth = 0usize;
+        for (depth, segment) in segments.iter().enumerate() {
+            current = join_path(&current, segment);
+            let Some(sid) = self.model.get_sid(&current) else {
+                break;
+            };
+            let Some(list) = self.layout.list(sid) else {
+                if depth == 0 {
+                    steps.push(Step {
+                        schema: self.layout.root(sid).map_or("", |root| &root.identifier),
+                        key: record_key(sid, &[]),
+                        chain: Vec::new(),
+                        location: vec![storage_key(segment, 0)],
+                        is_entry: false,
+                    });
+                    record_depth = 1;
+                }
+                continue;
+            };
+            let keys = list_keys(&self.model, &current)?;
+            if predicate_index + keys.len() > parsed.predicates.len() {
+                // The path ends at the whole list, or is rejected below.
+                break;
+            }
+            let values =
+                consume_key_values(&self.model, &keys, &parsed.predicates, &mut predicate_index)?;
+            for ((_, value), (_, identifier)) in values.into_iter().zip(&list.keys) {
+                chain.push(self.normalize_key(identifier, value));
+            }
+            steps.push(Step {
+                schema: &list.identifier,
+                key: record_key(sid, &chain),
+                chain: chain.clone(),
+                location: list.location.clone(),
+                is_entry: true,
+            });
+            record_depth = depth + 1;
+        }
+
+        if steps.is_empty() || steps[0].schema.is_empty() && !steps[0].is_entry {
+            return Ok(None);
+        }
+        let target = segments[record_depth..]
+            .iter()
+            .enumerate()
+            .map(|(offset, segment)| storage_key(segment, record_depth + offset))
+            .collect();
+        Ok(Some(Branch { steps, target }))
+    }
+
+    /// A tree holding only the records along `branch`, with the lists under
+    /// the target node filled in. Records loaded for the last step are
+    /// collected in `old`.
+    fn load_branch(
+        &self,
+        table: &impl ReadableTable<&'static [u8], &'static [u8]>,
+        branch: &Branch<'_>,
+        old: &mut Records,
+    ) -> Result<Value> {
+        let mut tree = Value::Object(Map::new());
+        let mut parent = &mut tree;
+        for (index, step) in branch.steps.iter().enumerate() {
+            let Some((order, record)) = read_record(table, &step.key)? else {
+                break;
+            };
+            let value = if index + 1 == branch.steps.len() {
+                old.insert(step.key.clone(), (order, record.clone()));
+                self.assemble(
+                    table,
+                    step.schema,
+                    &step.chain,
+                    record,
+                    Some(&branch.target),
+                    old,
+                )?
+            } else {
+                record
+            };
+            parent = place(parent, &step.location, step.is_entry, value)?;
+        }
+        Ok(tree)
+    }
+
+    /// Write back the records along `branch` from a tree built by
+    /// [`KvBackend::load_branch`] and then edited.
+    fn store_branch(&self, batch: &mut Batch, branch: &Branch<'_>, tree: Value) -> Result<()> {
+        let mut node = Some(&tree);
+        for (index, step) in branch.steps.iter().enumerate() {
+            node = node.and_then(|parent| find(parent, &step.location, step.is_entry));
+            let Some(value) = node else {
+                // The edit deleted this record; anything under it goes too.
+                return Ok(());
+            };
+            if index + 1 == branch.steps.len() {
+                let order = batch.existing_or_next_order(&step.key);
+                return self.split(
+                    batch,
+                    step.schema,
+                    &step.chain,
+                    step.key.clone(),
+                    order,
+                    value.clone(),
+                );
+            }
+
+            let mut record = value.clone();
+            self.strip_lists(step.schema, &mut record);
+            let order = batch.existing_or_next_order(&step.key);
+            batch.new.insert(step.key.clone(), (order, record));
+        }
+        Ok(())
+    }
+
+    /// Run `edit` on the records along `path` in one write transaction,
+    /// writing back only the records it changed.
+    fn edit_path<T>(
+        &mut self,
+        path: &str,
+        edit: impl FnOnce(&mut Value) -> Result<T>,
+    ) -> Result<T> {
+        let Some(branch) = self.locate(path)? else {
+            let mut tree = self.read_tree()?;
+            let result = edit(&mut tree)?;
+            self.try_replace_tree(tree)?;
+            return Ok(result);
+        };
+
+        let transaction = self.database.begin_write().map_err(storage_error)?;
+        let result = {
+            let mut nodes = transaction.open_table(NODES).map_err(storage_error)?;
+            let mut meta = transaction.open_table(META).map_err(storage_error)?;
+            self.edit_branch(&mut nodes, &mut meta, &branch, edit)?
+        };
+        transaction.commit().map_err(storage_error)?;
+        Ok(result)
+    }
+
+    /// Run `edit` on the records along `branch` inside an open write
+    /// transaction, writing back only the records it changed.
+    fn edit_branch<T>(
+        &self,
+        nodes: &mut redb::Table<'_, &'static [u8], &'static [u8]>,
+        meta: &mut redb::Table<'_, &'static str, u64>,
+        branch: &Branch<'_>,
+        edit: impl FnOnce(&mut Value) -> Result<T>,
+    ) -> Result<T> {
+        let mut old = Records::new();
+        let mut tree = self.load_branch(nodes, branch, &mut old)?;
+        let result = edit(&mut tree)?;
+
+        // Ancestor records are compared too, so they're only rewritten
+        // when the edit changed them.
+        for step in &branch.steps[..branch.steps.len() - 1] {
+            if let Some(existing) = read_record(nodes, &step.key)? {
+                old.insert(step.key.clone(), existing);
+            }
+        }
+        let mut batch = Batch::new(old, read_next_order(meta)?);
+        self.store_branch(&mut batch, branch, tree)?;
+        batch.write(nodes, meta)?;
+        Ok(result)
+    }
+
+    /// Fill the keyed lists under a record from their entry records. With
+    /// `target`, only lists at or below that relative location are loaded.
+    fn assemble(
+        &self,
+        table: &impl ReadableTable<&'static [u8], &'static [u8]>,
+        schema: &str,
+        chain: &[Value],
+        record: Value,
+        target: Option<&[String]>,
+        old: &mut Records,
+    ) -> Result<Value> {
+        let mut value = record;
+        for list in self.layout.children(schema) {
+            if target.is_some_and(|target| !list.location.starts_with(target)) {
+                continue;
+            }
+            let mut entries = Vec::new();
+            for (key, (order, entry)) in scan(table, &record_key(list.sid, chain))? {
+                old.insert(key, (order, entry.clone()));
+                let entry_chain = self.entry_chain(list, chain, &entry)?;
+                let entry =
+                    self.assemble(table, &list.identifier, &entry_chain, entry, None, old)?;
+                entries.push((order, entry));
+            }
+            if entries.is_empty() {
+                continue;
+            }
+            entries.sort_by_key(|(order, _)| *order);
+            *slot(&mut value, &list.location)? =
+                Value::Array(entries.into_iter().map(|(_, entry)| entry).collect());
+        }
+        Ok(value)
+    }
+
+    /// Add the record for `value` and the entry records of the keyed lists
+    /// below it to `batch`.
+    fn split(
+        &self,
+        batch: &mut Batch,
+        schema: &str,
+        chain: &[Value],
+        key: Vec<u8>,
+        order: u64,
+        mut value: Value,
+    ) -> Result<()> {
+        self.split_lists(batch, schema, chain, &mut value)?;
+        batch.new.insert(key, (order, value));
+        Ok(())
+    }
+
+    fn split_lists(
+        &self,
+        batch: &mut Batch,
+        schema: &str,
+        chain: &[Value],
+        value: &mut Value,
+    ) -> Result<()> {
+        for list in self.layout.children(schema) {
+            let Some(entries) = take(value, &list.location) else {
+                continue;
+            };
+            let Value::Array(entries) = entries else {
+                return Err(CoreconfError::ValidationError(
+                    "expected JSON array for keyed list".into(),
+                ));
+            };
+            let mut previous = None;
+            for entry in entries {
+                let entry_chain = self.entry_chain(list, chain, &entry)?;
+                let key = record_key(list.sid, &entry_chain);
+                let order = batch.order_after(&key, &mut previous);
+                self.split(batch, &list.identifier, &entry_chain, key, order, entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn strip_lists(&self, schema: &str, value: &mut Value) {
+        for list in self.layout.children(schema) {
+            take(value, &list.location);
+        }
+    }
+
+    /// Key values of `entry` appended to those of its ancestors.
+    fn entry_chain(
+        &self,
+        list: &ListLayout,
+        parent: &[Value],
+        entry: &Value,
+    ) -> Result<Vec<Value>> {
+        let mut chain = parent.to_vec();
+        for (name, identifier) in &list.keys {
+            let value = entry.get(name).ok_or_else(|| {
+                CoreconfError::ValidationError(format!(
+                    "list entry under '{}' is missing key '{name}'",
+                    list.identifier
+                ))
+            })?;
+            chain.push(self.normalize_key(identifier, value.clone()));
+        }
+        Ok(chain)
+    }
+
+    /// Identityref keys may be stored by name or SID; key records by SID.
+    fn normalize_key(&self, identifier: &str, value: Value) -> Value {
+        if matches!(self.model.get_type(identifier), Some(YangType::Identityref))
+            && let Some(sid) = value_to_identityref_sid(&value, &self.model)
+        {
+            return Value::from(sid);
+        }
+        value
+    }
+}
+
+impl Backend for KvBackend {
+    fn read_tree(&self) -> Result<Value> {
+        let transaction = self.database.begin_read().map_err(storage_error)?;
+        let table = transaction.open_table(NODES).map_err(storage_error)?;
+        let mut scratch = Records::new();
+
+        let mut tree = Map::new();
+        for root in &self.layout.roots {
+            if let Some((_, record)) = read_record(&table, &record_key(root.sid, &[]))? {
+                let value =
+                    self.assemble(&table, &root.identifier, &[], record, None, &mut scratch)?;
+                tree.insert(root.name.clone(), value);
+            }
+        }
+        self.assemble(&table, "", &[], Value::Object(tree), None, &mut scratch)
+    }
+
+    fn is_empty(&self) -> Result<bool> {
+        let transaction = self.database.begin_read().map_err(storage_error)?;
+        let table = transaction.open_table(NODES).map_err(storage_error)?;
+        table.is_empty().map_err(storage_error)
+    }
+
+    fn replace_tree(&mut self, next: Value) -> Result<()> {
+        self.try_replace_tree(next)
+    }
+
+    fn read_path(&self, model: &CompositeModel, path: &str) -> Result<Option<Value>> {
+        let Some(branch) = self.locate(path)? else {
+            return tree_get(&self.read_tree()?, model, path, None);
+        };
+        let transaction = self.database.begin_read().map_err(storage_error)?;
+        let table = transaction.open_table(NODES).map_err(storage_error)?;
+        let tree = self.load_branch(&table, &branch, &mut Records::new())?;
+        tree_get(&tree, model, path, None)
+    }
+
+    fn write_path(&mut self, model: &CompositeModel, path: &str, value: Value) -> Result<()> {
+        self.edit_path(path, |tree| tree_set(tree, model, path, value, None))
+    }
+
+    fn delete_path(&mut self, model: &CompositeModel, path: &str) -> Result<bool> {
+        self.edit_path(path, |tree| tree_delete(tree, model, path, None))
+    }
+
+    /// Apply every edit in one write transaction, so a failure or crash
+    /// part way commits none of them.
+    fn apply_edits(
+        &mut self,
+        model: &CompositeModel,
+        edits: &[(String, Option<Value>)],
+    ) -> Result<()> {
+        let apply = |tree: &mut Value, path: &str, value: &Option<Value>| match value {
+            Some(value) => tree_set(tree, model, path, value.clone(), None),
+            None => tree_delete(tree, model, path, None).map(drop),
+        };
+
+        let mut branches = Vec::with_capacity(edits.len());
+        for (path, _) in edits {
+            let Some(branch) = self.locate(path)? else {
+                // An edit of the whole tree: apply them all to it instead.
+                let mut tree = self.read_tree()?;
+                for (path, value) in edits {
+                    apply(&mut tree, path, value)?;
+                }
+                return self.try_replace_tree(tree);
+            };
+            branches.push(branch);
+        }
+
+        let transaction = self.database.begin_write().map_err(storage_error)?;
+        {
+            let mut nodes = transaction.open_table(NODES).map_err(storage_error)?;
+            let mut meta = transaction.open_table(META).map_err(storage_error)?;
+            for (branch, (path, value)) in branches.iter().zip(edits) {
+                self.edit_branch(&mut nodes, &mut meta, branch, |tree| {
+                    apply(tree, path, value)
+                })?;
+            }
+        }
+        transaction.commit().map_err(storage_error)
+    }
+}
+
+impl std::fmt::Debug for KvBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KvBackend")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Records replaced and written by one transaction.
+struct Batch {
+    old: Records,
+    new: Records,
+    next_order: u64,
+}
+
+impl Batch {
+    fn new(old: Records, next_order: u64) -> Self {
+        Self {
+            old,
+            new: Records::new(),
+            next_order,
+        }
+    }
+
+    fn existing_or_next_order(&mut self, key: &[u8]) -> u64 {
+        match self.old.get(key) {
+            Some((order, _)) => *order,
+            None => self.allocate_order(),
+        }
+    }
+
+    /// Order for the list entry `key` following one with order `previous`.
+    /// Existing entries keep theirs while the list order still holds.
+    fn order_after(&mut self, key: &[u8], previous: &mut Option<u64>) -> u64 {
+        let order = match self.old.get(key) {
+            Some((order, _)) if previous.is_none_or(|previous| *order > previous) => *order,
+            _ => self.allocate_order(),
+        };
+        *previous = Some(order);
+        order
+    }
+
+    fn allocate_order(&mut self) -> u64 {
+        let order = self.next_order;
+        self.next_order += 1;
+        order
+    }
+
+    /// Put new and changed records and remove old ones that weren't
+    /// written again.
+    fn write(
+        self,
+        nodes: &mut redb::Table<'_, &'static [u8], &'static [u8]>,
+        meta: &mut redb::Table<'_, &'static str, u64>,
+    ) -> Result<()> {
+        for key in self.old.keys() {
+            if !self.new.contains_key(key) {
+                nodes.remove(key.as_slice()).map_err(storage_error)?;
+            }
+        }
+        for (key, record) in &self.new {
+            if self.old.get(key) != Some(record) {
+                nodes
+                    .insert(key.as_slice(), encode_record(record)?.as_slice())
+                    .map_err(storage_error)?;
+            }
+        }
+        meta.insert(NEXT_ORDER, self.next_order)
+            .map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+/// Records a predicate path passes through, outermost first, and the
+/// storage keys of the remaining path below the last one.
+struct Branch<'a> {
+    steps: Vec<Step<'a>>,
+    target: Vec<String>,
+}
+
+struct Step<'a> {
+    /// Schema identifier of the record; its keyed lists hang off this.
+    schema: &'a str,
+    key: Vec<u8>,
+    chain: Vec<Value>,
+    /// Storage keys from the enclosing record down to this one.
+    location: Vec<String>,
+    is_entry: bool,
+}
+
+/// Which schema nodes get records of their own, derived from the model.
+#[derive(Debug, Default)]
+struct Layout {
+    roots: Vec<RootLayout>,
+    lists: Vec<ListLayout>,
+    /// Indexes into `lists` by the schema identifier of the record holding
+    /// them; `""` for top-level lists.
+    children: HashMap<String, Vec<usize>>,
+    by_sid: HashMap<i64, usize>,
+}
+
+/// A top-level node other than a keyed list.
+#[derive(Debug)]
+struct RootLayout {
+    sid: i64,
+    identifier: String,
+    name: String,
+}
+
+#[derive(Debug)]
+struct ListLayout {
+    sid: i64,
+    identifier: String,
+    /// Storage keys from the enclosing record down to the list array.
+    location: Vec<String>,
+    /// Key leaf names and identifiers, in key order.
+    keys: Vec<(String, String)>,
+}
+
+impl Layout {
+    fn new(model: &CompositeModel) -> Result<Self> {
+        let mut nodes: Vec<(&str, i64)> = model
+            .iter()
+            .map(|(sid, identifier)| (identifier, sid))
+            .collect();
+        nodes.sort_by_key(|(_, sid)| *sid);
+
+        let mut layout = Self::default();
+        for (identifier, sid) in nodes {
+            let Some(body) = identifier.strip_prefix('/') else {
+                continue;
+            };
+            let segments: Vec<&str> = body.split('/').collect();
+            let Some(key_sids) = model.get_keys(sid) else {
+                if segments.len() == 1 {
+                    layout.roots.push(RootLayout {
+                        sid,
+                        identifier: identifier.to_string(),
+                        name: body.to_string(),
+                    });
+                }
+                continue;
+            };
+
+            // The record holding a list is its nearest keyed-list ancestor,
+            // or the top-level node it sits in.
+            let record_depth = (1..segments.len())
+                .rev()
+                .find(|depth| {
+                    *depth == 1
+                        || model
+                            .get_sid(&format!("/{}", segments[..*depth].join("/")))
+                            .is_some_and(|sid| model.get_keys(sid).is_some())
+                })
+                .unwrap_or(0);
+            let holder = if record_depth == 0 {
+                String::new()
+            } else {
+                format!("/{}", segments[..record_depth].join("/"))
+            };
+
+            let mut keys = Vec::with_capacity(key_sids.len());
+            for key_sid in key_sids {
+                let key_identifier = model
+                    .get_identifier(*key_sid)
+                    .ok_or(CoreconfError::IdentifierNotFound(*key_sid))?;
+                keys.push((
+                    segment_leaf(key_identifier).to_string(),
+                    key_identifier.to_string(),
+                ));
+            }
+
+            let index = layout.lists.len();
+            layout.lists.push(ListLayout {
+                sid,
+                identifier: identifier.to_string(),
+                location: segments[record_depth..]
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, segment)| storage_key(segment, record_depth + offset))
+                    .collect(),
+                keys,
+            });
+            layout.children.entry(holder).or_default().push(index);
+            layout.by_sid.insert(sid, index);
+        }
+        Ok(layout)
+    }
+
+    fn children(&self, schema: &str) -> impl Iterator<Item = &ListLayout> {
+        self.children
+            .get(schema)
+            .into_iter()
+            .flatten()
+            .map(|index| &self.lists[*index])
+    }
+
+    fn list(&self, sid: i64) -> Option<&ListLayout> {
+        self.by_sid.get(&sid).map(|index| &self.lists[*index])
+    }
+
+    fn root(&self, sid: i64) -> Option<&RootLayout> {
+        self.roots.iter().find(|root| root.sid == sid)
+    }
+}
+
+fn record_key(sid: i64, chain: &[Value]) -> Vec<u8> {
+    let mut key = Vec::new();
+    // Writing to a Vec can't fail, and JSON values always encode.
+    ciborium::into_writer(&sid, &mut key).expect("SID encodes as CBOR");
+    for value in chain {
+        ciborium::into_writer(value, &mut key).expect("key value encodes as CBOR");
+    }
+    key
+}
+
+fn encode_record(record: &(u64, Value)) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(record, &mut bytes)
+        .map_err(|error| CoreconfError::CborEncode(error.to_string()))?;
+    Ok(bytes)
+}
+
+fn decode_record(bytes: &[u8]) -> Result<(u64, Value)> {
+    ciborium::from_reader(bytes).map_err(|error| CoreconfError::CborDecode(error.to_string()))
+}
+
+fn read_record(
+    table: &impl ReadableTable<&'static [u8], &'static [u8]>,
+    key: &[u8],
+) -> Result<Option<(u64, Value)>> {
+    table
+        .get(key)
+        .map_err(storage_error)?
+        .map(|record| decode_record(record.value()))
+        .transpose()
+}
+
+/// Every record whose key starts with `prefix`.
+fn scan(
+    table: &impl ReadableTable<&'static [u8], &'static [u8]>,
+    prefix: &[u8],
+) -> Result<Records> {
+    let mut records = Records::new();
+    for item in table.range(prefix..).map_err(storage_error)? {
+        let (key, record) = item.map_err(storage_error)?;
+        if !key.value().starts_with(prefix) {
+            break;
+        }
+        records.insert(key.value().to_vec(), decode_record(record.value())?);
+    }
+    Ok(records)
+}
+
+fn read_next_order(meta: &impl ReadableTable<&'static str, u64>) -> Result<u64> {
+    Ok(meta
+        .get(NEXT_ORDER)
+        .map_err(storage_error)?
+        .map_or(0, |order| order.value()))
+}
+
+/// The value at `location` below `value`, creating containers on the way.
+fn slot<'a>(value: &'a mut Value, location: &[String]) -> Result<&'a mut Value> {
+    let mut node = value;
+    for key in location {
+        node = ensure_object(node)?
+            .entry(key.clone())
+            .or_insert(Value::Null);
+    }
+    Ok(node)
+}
+
+/// Put a record at `location` below its parent, as the only entry of a
+/// list array for list entries, and return where it landed.
+fn place<'a>(
+    parent: &'a mut Value,
+    location: &[String],
+    is_entry: bool,
+    value: Value,
+) -> Result<&'a mut Value> {
+    let node = slot(parent, location)?;
+    if is_entry {
+        *node = Value::Array(vec![value]);
+        Ok(&mut node.as_array_mut().expect("array was just set")[0])
+    } else {
+        *node = value;
+        Ok(node)
+    }
+}
+
+/// The record placed by [`place`], if the edit left it in place.
+fn find<'a>(parent: &'a Value, location: &[String], is_entry: bool) -> Option<&'a Value> {
+    let mut node = parent;
+    for key in location {
+        node = node.get(key)?;
+    }
+    if is_entry {
+        node.as_array()?.first()
+    } else {
+        Some(node)
+    }
+}
+
+/// Remove a non-empty keyed list array at `location`. Empty arrays stay in
+/// the record so an emptied list reads back as it was left.
+fn take(value: &mut Value, location: &[String]) -> Option<Value> {
+    let (last, parents) = location.split_last()?;
+    let mut node = value;
+    for key in parents {
+        node = node.get_mut(key)?;
+    }
+    let map = node.as_object_mut()?;
+    if map.get(last)?.as_array().is_some_and(Vec::is_empty) {
+        return None;
+    }
+    map.remove(last)
+}
+
+fn storage_error(error: impl Into<redb::Error>) -> CoreconfError {
+    CoreconfError::Io(std::io::Error::other(error.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn model() -> CompositeModel {
+        CompositeModel::from_sid_strings(&[r#"{
+            "module-name":"example",
+            "module-revision":"2026-01-01",
+            "item":[
+                {"identifier":"example","sid":60000},
+                {"identifier":"/example:devices","sid":60001},
+                {"identifier":"/example:devices/device","sid":60002},
+                {"identifier":"/example:devices/device/id","sid":60003,"type":"string"},
+                {"identifier":"/example:devices/device/enabled","sid":60004,"type":"boolean"}
+            ],
+            "key-mapping":{"60002":[60003]}
+        }"#])
+        .unwrap()
+    }
+
+    #[test]
+    fn list_entries_are_stored_as_separate_records() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut backend = KvBackend::open(model(), directory.path().join("data.redb")).unwrap();
+        backend
+            .replace_tree(json!({"example:devices": {"device": [
+                {"id": "a", "enabled": true},
+                {"id": "b"},
+                {"id": "c"}
+            ]}}))
+            .unwrap();
+
+        let transaction = backend.database.begin_read().unwrap();
+        let table = transaction.open_table(NODES).unwrap();
+        let records = scan(&table, &[]).unwrap();
+
+        assert_eq!(records.len(), 4);
+        assert_eq!(
+            records[&record_key(60001, &[])].1,
+            json!({}),
+            "the container record holds no list entries"
+        );
+        assert_eq!(
+            records[&record_key(60002, &[json!("a")])].1,
+            json!({"id": "a", "enabled": true})
+        );
+    }
+}
@@ -1,22 +1,56 @@
+pub mod access;
 pub mod backend;
 pub mod coap_types;
+pub mod commit;
 pub mod datastore;
+pub mod diff;
+pub mod error_payload;
+pub mod etag;
 pub mod file_backend;
+pub mod journal;
+#[cfg(feature = "kv")]
+pub mod kv_backend;
+mod list_index;
+pub mod lock;
 pub mod memory_backend;
+pub mod nmda;
 pub mod operations;
 pub mod path;
+pub mod provider;
+pub mod query;
 pub mod request_handler;
+pub mod shared_handler;
+pub mod shared_tree;
+pub mod subscription;
 pub mod transaction;
 pub mod transport;
+pub mod wal_backend;
+pub mod yang_patch;
 
-pub use backend::Backend;
+pub use access::{AccessControl, AccessOperation, AccessRule, RuleAction, RuleList, RuleTarget};
+pub use backend::{Backend, Change, ChangeLog};
 pub use coap_types::{
     ContentFormat, Interface, Method, QueryParams, Request, Response, ResponseCode,
 };
-pub use datastore::Datastore;
+pub use commit::PendingConfirm;
+pub use datastore::{Datastore, Position};
+pub use diff::{Diff, DiffEdit, DiffOperation};
+pub use error_payload::{decode_error_payload, encode_error_payload};
 pub use file_backend::{EditableFormat, FileBackend, encode_editable_value, read_editable_file};
+pub use journal::{Journal, JournalEntry};
+#[cfg(feature = "kv")]
+pub use kv_backend::KvBackend;
+pub use lock::DatastoreLock;
 pub use memory_backend::MemoryBackend;
+pub use nmda::{DatastoreName, DatastoreView, Datastores};
 pub use operations::{OperationBinding, OperationRegistry};
 pub use path::PredicatePath;
+pub use provider::SubtreeProvider;
+pub use query::Query;
 pub use request_handler::RequestHandler;
+pub use shared_handler::SharedHandler;
+pub use shared_tree::SharedTree;
+pub use subscription::{ChangeEvent, SubscriptionId};
 pub use transaction::{TransactionContext, TransactionParticipant};
+pub use wal_backend::WalBackend;
+pub use yang_patch::{Edit, EditOperation, YangPatch};
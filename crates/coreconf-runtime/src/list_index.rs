@@ -0,0 +1,259 @@
+//! Index from keyed list entries to their positions in the tree.
+//!
+//! A list instance is identified by the chain of ancestor entries leading to
+//! it plus its schema path, so `device[id='a']/sample` and
+//! `device[id='b']/sample` are indexed separately. Chains and keys are
+//! concatenated JSON values, which delimit themselves: the chain of an entry
+//! is a prefix of the ids of every list instance below it, so a subtree can
+//! be invalidated with one range scan.
+//!
+//! Instances are built lazily from the list array the first time a write
+//! looks an entry up, and kept in step by the tree walkers afterwards. Hits
+//! are checked against the entry before use, so an index that has fallen
+//! behind costs a linear scan rather than a wrong answer.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
+use coreconf_model::{CompositeModel, YangType};
+use serde_json::Value;
+
+use crate::datastore::{TreeNode, list_entry_matches, value_to_identityref_sid};
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ListIndex {
+    lists: BTreeMap<Vec<u8>, Positions>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Positions {
+    by_key: HashMap<Vec<u8>, usize>,
+    /// Entries sharing a key with an earlier one aren't indexed, so removing
+    /// the earlier one would hide them.
+    duplicates: bool,
+}
+
+/// Where one list entry lives in a [`ListIndex`].
+pub(crate) struct EntrySlot {
+    list: Vec<u8>,
+    key: Vec<u8>,
+}
+
+impl EntrySlot {
+    /// The entry selected by `key_values` in the list at `list_path`, below
+    /// the entry chain `chain` (empty at the top level).
+    pub(crate) fn new(
+        chain: &[u8],
+        model: &CompositeModel,
+        list_path: &str,
+        key_values: &[(String, Value)],
+    ) -> Self {
+        let mut list = chain.to_vec();
+        serde_json::to_writer(&mut list, list_path).expect("strings serialize");
+        let key = encode_key(
+            model,
+            list_path,
+            key_values
+                .iter()
+                .map(|(name, value)| (name.as_str(), value)),
+        );
+        Self { list, key }
+    }
+
+    /// Chain of this entry, prefixing the ids of the lists nested in it.
+    pub(crate) fn chain(&self) -> Vec<u8> {
+        let mut chain = self.list.clone();
+        chain.extend_from_slice(&self.key);
+        chain
+    }
+}
+
+impl ListIndex {
+    /// Position of the entry at `slot` in `entries`, indexing the list first
+    /// if needed.
+    pub(crate) fn find<N: TreeNode>(
+        &mut self,
+        slot: &EntrySlot,
+        entries: &[N],
+        model: &CompositeModel,
+        list_path: &str,
+        key_values: &[(String, Value)],
+    ) -> Option<usize> {
+        let positions = self
+            .lists
+            .entry(slot.list.clone())
+            .or_insert_with(|| Positions::build(entries, model, list_path, key_values));
+        match positions.by_key.get(&slot.key) {
+            Some(&position)
+                if entries.get(position).is_some_and(|entry| {
+                    list_entry_matches(entry, key_values, model, list_path)
+                }) =>
+            {
+                Some(position)
+            }
+            Some(_) => {
+                self.lists.remove(&slot.list);
+                scan(entries, model, list_path, key_values)
+            }
+            None => None,
+        }
+    }
+
+    /// Like [`ListIndex::find`] for readers, which scan lists that haven't
+    /// been indexed yet.
+    pub(crate) fn lookup<N: TreeNode>(
+        &self,
+        slot: &EntrySlot,
+        entries: &[N],
+        model: &CompositeModel,
+        list_path: &str,
+        key_values: &[(String, Value)],
+    ) -> Option<usize> {
+        let Some(positions) = self.lists.get(&slot.list) else {
+            return scan(entries, model, list_path, key_values);
+        };
+        let position = *positions.by_key.get(&slot.key)?;
+        if entries
+            .get(position)
+            .is_some_and(|entry| list_entry_matches(entry, key_values, model, list_path))
+        {
+            Some(position)
+        } else {
+            scan(entries, model, list_path, key_values)
+        }
+    }
+
+    /// Record an entry appended at `position`.
+    pub(crate) fn inserted(&mut self, slot: &EntrySlot, position: usize) {
+        if let Some(positions) = self.lists.get_mut(&slot.list) {
+            positions.by_key.entry(slot.key.clone()).or_insert(position);
+        }
+    }
+
+    /// Record the removal of the entry at `position`, and drop the lists
+    /// nested in it.
+    pub(crate) fn removed(&mut self, slot: &EntrySlot, position: usize) {
+        self.forget_under(&slot.chain());
+        let Some(positions) = self.lists.get_mut(&slot.list) else {
+            return;
+        };
+        if positions.duplicates {
+            self.lists.remove(&slot.list);
+            return;
+        }
+        positions.by_key.retain(|_, entry| *entry != position);
+        for entry in positions.by_key.values_mut() {
+            if *entry > position {
+                *entry -= 1;
+            }
+        }
+    }
+
+    /// Drop the list holding `slot`, e.g. after an entry's key changed.
+    pub(crate) fn forget_list(&mut self, slot: &EntrySlot) {
+        self.lists.remove(&slot.list);
+    }
+
+    /// Drop every list at or below the entry chain `chain`.
+    pub(crate) fn forget_under(&mut self, chain: &[u8]) {
+        if chain.is_empty() {
+            self.lists.clear();
+            return;
+        }
+        let stale: Vec<Vec<u8>> = self
+            .lists
+            .range(chain.to_vec()..)
+            .map(|(id, _)| id)
+            .take_while(|id| id.starts_with(chain))
+            .cloned()
+            .collect();
+        for id in stale {
+            self.lists.remove(&id);
+        }
+    }
+
+    /// Drop the lists at or below schema node `schema_path` within the entry
+    /// chain `chain`, e.g. after a container holding them was replaced.
+    pub(crate) fn forget_below(&mut self, chain: &[u8], schema_path: &str) {
+        // Schema paths need no escaping, so the serialized list paths below
+        // `schema_path` all start with this.
+        let mut prefix = chain.to_vec();
+        prefix.push(b'"');
+        prefix.extend_from_slice(schema_path.as_bytes());
+        let stale: Vec<Vec<u8>> = self
+            .lists
+            .range(prefix.clone()..)
+            .map(|(id, _)| id)
+            .take_while(|id| id.starts_with(&prefix))
+            .filter(|id| matches!(id.get(prefix.len()), Some(b'"' | b'/')))
+            .cloned()
+            .collect();
+        for id in stale {
+            self.lists.remove(&id);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.lists.clear();
+    }
+}
+
+impl Positions {
+    fn build<N: TreeNode>(
+        entries: &[N],
+        model: &CompositeModel,
+        list_path: &str,
+        key_values: &[(String, Value)],
+    ) -> Self {
+        let mut positions = Self::default();
+        for (position, entry) in entries.iter().enumerate() {
+            let Some(stored) = key_values
+                .iter()
+                .map(|(name, _)| Some((name.as_str(), entry.leaf(name)?)))
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let key = encode_key(model, list_path, stored.into_iter());
+            match positions.by_key.entry(key) {
+                Entry::Occupied(_) => positions.duplicates = true,
+                Entry::Vacant(slot) => {
+                    slot.insert(position);
+                }
+            }
+        }
+        positions
+    }
+}
+
+fn scan<N: TreeNode>(
+    entries: &[N],
+    model: &CompositeModel,
+    list_path: &str,
+    key_values: &[(String, Value)],
+) -> Option<usize> {
+    entries
+        .iter()
+        .position(|entry| list_entry_matches(entry, key_values, model, list_path))
+}
+
+/// Key values as one JSON array, with identityrefs resolved to SIDs so that
+/// names and numbers compare equal as they do in `list_entry_matches`.
+fn encode_key<'v>(
+    model: &CompositeModel,
+    list_path: &str,
+    values: impl Iterator<Item = (&'v str, &'v Value)>,
+) -> Vec<u8> {
+    let normalized: Vec<Value> = values
+        .map(|(name, value)| {
+            let identifier = format!("{list_path}/{name}");
+            match model.get_type(&identifier) {
+                Some(YangType::Identityref) => value_to_identityref_sid(value, model)
+                    .map(Value::from)
+                    .unwrap_or_else(|| value.clone()),
+                _ => value.clone(),
+            }
+        })
+        .collect();
+    serde_json::to_vec(&normalized).expect("JSON values serialize")
+}
@@ -0,0 +1,148 @@
+//! `ietf-netconf` lock and unlock operations on configuration datastores.
+//!
+//! While a client holds the lock on a datastore, edits of it from anyone
+//! else fail with in-use. CoAP has no sessions whose end would release a
+//! lock, so every lock expires after a timeout; the owner renews it by
+//! locking again.
+
+use std::time::{Duration, Instant};
+
+use coreconf_model::{DataError, ErrorTag, Result};
+use serde_json::{Value, json};
+
+use crate::nmda::DatastoreName;
+
+pub const LOCK_PATH: &str = "/ietf-netconf:lock";
+pub const UNLOCK_PATH: &str = "/ietf-netconf:unlock";
+
+/// Lock timeout used when a lock request gives none.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A lock held on a configuration datastore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatastoreLock {
+    /// Peer identity of the client holding the lock.
+    pub owner: String,
+    pub deadline: Instant,
+}
+
+impl DatastoreLock {
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now >= self.deadline
+    }
+}
+
+/// Parse the input of a lock or unlock request at `path` into its target
+/// datastore and, for a lock, its timeout.
+///
+/// The input is a name-keyed map: `target` names the datastore, either as
+/// a string or as the `ietf-netconf` choice (`{"running": null}`), and
+/// `timeout` optionally gives the lock timeout in seconds. Names may carry
+/// the `ietf-netconf:` prefix.
+pub fn parse_lock_input(
+    path: &str,
+    input: Option<&Value>,
+) -> Result<(DatastoreName, Option<Duration>)> {
+    let Some(fields) = input.and_then(Value::as_object) else {
+        return Err(missing_target(path));
+    };
+
+    let mut target = None;
+    let mut timeout = None;
+    for (name, value) in fields {
+        match name.strip_prefix("ietf-netconf:").unwrap_or(name) {
+            "target" => {
+                let name = match value {
+                    Value::String(name) => Some(name.as_str()),
+                    Value::Object(choice) if choice.len() == 1 => {
+                        choice.keys().next().map(String::as_str)
+                    }
+                    _ => None,
+                };
+                let datastore = name
+                    .map(|name| name.strip_prefix("ietf-netconf:").unwrap_or(name))
+                    .and_then(DatastoreName::parse)
+                    .ok_or_else(|| {
+                        DataError::new(ErrorTag::InvalidValue, "unknown lock target")
+                            .with_path(format!("{path}/target"))
+                            .with_value(value.clone())
+                    })?;
+                target = Some(datastore);
+            }
+            "timeout" if path == LOCK_PATH => {
+                let seconds = value
+                    .as_u64()
+                    .filter(|seconds| *seconds > 0 && *seconds <= u64::from(u32::MAX))
+                    .ok_or_else(|| {
+                        DataError::new(ErrorTag::InvalidValue, "timeout must be a positive uint32")
+                            .with_path(format!("{path}/timeout"))
+                            .with_value(value.clone())
+                    })?;
+                timeout = Some(Duration::from_secs(seconds));
+            }
+            other => {
+                return Err(DataError::new(
+                    ErrorTag::UnknownElement,
+                    format!("unknown input '{other}'"),
+                )
+                .with_path(path)
+                .into());
+            }
+        }
+    }
+
+    let target = target.ok_or_else(|| missing_target(path))?;
+    Ok((target, timeout))
+}
+
+/// Build the input of a lock or unlock request; the inverse of
+/// [`parse_lock_input`].
+pub fn lock_input(target: DatastoreName, timeout: Option<Duration>) -> Value {
+    match timeout {
+        Some(timeout) => json!({"target": target.as_str(), "timeout": timeout.as_secs().max(1)}),
+        None => json!({"target": target.as_str()}),
+    }
+}
+
+fn missing_target(path: &str) -> coreconf_model::CoreconfError {
+    DataError::new(ErrorTag::MissingElement, "a target datastore is required")
+        .with_path(format!("{path}/target"))
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_input_round_trips_target_and_timeout() {
+        let input = lock_input(DatastoreName::Candidate, Some(Duration::from_secs(30)));
+
+        assert_eq!(
+            parse_lock_input(LOCK_PATH, Some(&input)).unwrap(),
+            (DatastoreName::Candidate, Some(Duration::from_secs(30)))
+        );
+        let input = json!({"ietf-netconf:target": {"running": null}});
+        assert_eq!(
+            parse_lock_input(UNLOCK_PATH, Some(&input)).unwrap(),
+            (DatastoreName::Running, None)
+        );
+    }
+
+    #[test]
+    fn lock_input_needs_a_known_target() {
+        for input in [
+            json!({}),
+            json!({"target": "nowhere"}),
+            json!({"target": "running", "timeout": 0}),
+        ] {
+            assert!(
+                parse_lock_input(LOCK_PATH, Some(&input)).is_err(),
+                "{input}"
+            );
+        }
+        let unlock = json!({"target": "running", "timeout": 5});
+        let error = parse_lock_input(UNLOCK_PATH, Some(&unlock)).unwrap_err();
+        assert_eq!(error.error_tag(), ErrorTag::UnknownElement);
+    }
+}
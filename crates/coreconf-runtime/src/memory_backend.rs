@@ -1,31 +1,92 @@
-use serde_json::{Map, Value};
+use coreconf_model::{CompositeModel, Result};
+use serde_json::Value;
 
-use crate::backend::Backend;
+use crate::backend::{Backend, Change, ChangeLog};
+use crate::datastore::{tree_delete, tree_get, tree_set};
+use crate::list_index::ListIndex;
+use crate::shared_tree::SharedTree;
 
+/// Backend holding the tree in memory.
+///
+/// The tree is a [`SharedTree`], so snapshots are reference-count bumps and
+/// a backend built from one shares it until written. Path reads and writes
+/// work on the tree in place, finding keyed list entries through a hash
+/// index, and every write is recorded in a bounded [`ChangeLog`].
 #[derive(Debug, Clone)]
 pub struct MemoryBackend {
-    tree: Value,
+    tree: SharedTree,
+    index: ListIndex,
+    changes: ChangeLog,
 }
 
 impl MemoryBackend {
     pub fn new(tree: Value) -> Self {
-        Self { tree }
+        Self::from_snapshot(SharedTree::from(tree))
+    }
+
+    /// A backend starting from `tree`, sharing it with the caller.
+    pub fn from_snapshot(tree: SharedTree) -> Self {
+        Self {
+            tree,
+            index: ListIndex::default(),
+            changes: ChangeLog::default(),
+        }
     }
 }
 
 impl Default for MemoryBackend {
     fn default() -> Self {
-        Self::new(Value::Object(Map::new()))
+        Self::from_snapshot(SharedTree::default())
     }
 }
 
 impl Backend for MemoryBackend {
-    fn read_tree(&self) -> Value {
-        self.tree.clone()
+    fn read_tree(&self) -> Result<Value> {
+        Ok(self.tree.to_value())
+    }
+
+    fn replace_tree(&mut self, next: Value) -> Result<()> {
+        self.replace_snapshot(SharedTree::from(next))
+    }
+
+    fn snapshot(&self) -> Result<SharedTree> {
+        Ok(self.tree.clone())
     }
 
-    fn replace_tree(&mut self, next: Value) -> coreconf_model::Result<()> {
+    fn replace_snapshot(&mut self, next: SharedTree) -> Result<()> {
         self.tree = next;
+        self.index.clear();
+        self.changes.record("/");
         Ok(())
     }
+
+    fn read_path(&self, model: &CompositeModel, path: &str) -> Result<Option<Value>> {
+        tree_get(&self.tree, model, path, Some(&self.index))
+    }
+
+    fn read_top_level(&self, _model: &CompositeModel, name: &str) -> Result<Option<SharedTree>> {
+        Ok(self.tree.get(name).cloned())
+    }
+
+    fn write_path(&mut self, model: &CompositeModel, path: &str, value: Value) -> Result<()> {
+        tree_set(&mut self.tree, model, path, value, Some(&mut self.index))?;
+        self.changes.record(path);
+        Ok(())
+    }
+
+    fn delete_path(&mut self, model: &CompositeModel, path: &str) -> Result<bool> {
+        let deleted = tree_delete(&mut self.tree, model, path, Some(&mut self.index))?;
+        if deleted {
+            self.changes.record(path);
+        }
+        Ok(deleted)
+    }
+
+    fn last_change(&self) -> Option<u64> {
+        Some(self.changes.last())
+    }
+
+    fn changes_since(&self, since: u64) -> Option<Box<dyn Iterator<Item = &Change> + '_>> {
+        Some(Box::new(self.changes.since(since)?))
+    }
 }
@@ -0,0 +1,353 @@
+use coreconf_model::{CoreconfError, DataError, ErrorTag, Result};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::coap_types::ContentParam;
+use crate::datastore::Datastore;
+use crate::provider::{ProvidedSubtree, SubtreeProvider};
+use crate::shared_tree::SharedTree;
+
+/// The NMDA datastores of RFC 8342.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DatastoreName {
+    Running,
+    Candidate,
+    Startup,
+    Operational,
+}
+
+impl DatastoreName {
+    pub const ALL: [Self; 4] = [
+        Self::Running,
+        Self::Candidate,
+        Self::Startup,
+        Self::Operational,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Candidate => "candidate",
+            Self::Startup => "startup",
+            Self::Operational => "operational",
+        }
+    }
+
+    /// The `ietf-datastores` identity naming this datastore.
+    pub fn identity(self) -> String {
+        format!("ietf-datastores:{}", self.as_str())
+    }
+
+    /// Accepts both the bare name (`running`) and the identity
+    /// (`ietf-datastores:running`).
+    pub fn parse(input: &str) -> Option<Self> {
+        let name = input.strip_prefix("ietf-datastores:").unwrap_or(input);
+        Self::ALL.into_iter().find(|ds| ds.as_str() == name)
+    }
+
+    /// Whether the datastore holds configuration that clients may edit.
+    pub fn is_configuration(self) -> bool {
+        self != Self::Operational
+    }
+}
+
+impl std::fmt::Display for DatastoreName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The set of NMDA datastores served by one device.
+///
+/// Each configuration datastore has its own backend. The operational
+/// datastore is not stored as a whole: it is running configuration merged
+/// with the state store and the [`SubtreeProvider`]s of the subtrees a read
+/// touches.
+///
+/// SID files carry no `config` statement, so "configuration" means whatever
+/// lives in running and "state" whatever the state store and providers add.
+pub struct Datastores {
+    running: Datastore,
+    candidate: Datastore,
+    startup: Datastore,
+    state: Datastore,
+    subtrees: Vec<ProvidedSubtree>,
+}
+
+impl Datastores {
+    /// Use `running` as the running datastore, with in-memory candidate and
+    /// startup datastores initialised from it and an empty state store.
+    ///
+    /// Fails if running can't be read.
+    pub fn new(running: Datastore) -> Result<Self> {
+        let tree = running.snapshot()?;
+        let model = Arc::clone(running.shared_model());
+        Ok(Self {
+            candidate: Datastore::from_snapshot(Arc::clone(&model), tree.clone()),
+            startup: Datastore::from_snapshot(Arc::clone(&model), tree),
+            state: Datastore::new_in_memory(model),
+            running,
+            subtrees: Vec::new(),
+        })
+    }
+
+    pub fn with_candidate(mut self, candidate: Datastore) -> Self {
+        self.candidate = candidate;
+        self
+    }
+
+    pub fn with_startup(mut self, startup: Datastore) -> Self {
+        self.startup = startup;
+        self
+    }
+
+    /// Replace the store that holds application-written operational state.
+    pub fn with_state(mut self, state: Datastore) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Serve the operational node at `path` from `provider`, called only by
+    /// reads of that node, its descendants or its ancestors.
+    ///
+    /// A computed value is reused for `max_age`; `Duration::ZERO` computes
+    /// it on every read. Provided values are merged last, over running and
+    /// the state store.
+    pub fn register_subtree_provider(
+        &mut self,
+        path: &str,
+        max_age: Duration,
+        provider: Box<dyn SubtreeProvider>,
+    ) -> Result<()> {
+        let subtree = ProvidedSubtree::new(self.running.model(), path, max_age, provider)?;
+        self.subtrees.push(subtree);
+        Ok(())
+    }
+
+    pub fn running(&self) -> &Datastore {
+        &self.running
+    }
+
+    pub fn running_mut(&mut self) -> &mut Datastore {
+        &mut self.running
+    }
+
+    pub fn candidate(&self) -> &Datastore {
+        &self.candidate
+    }
+
+    pub fn candidate_mut(&mut self) -> &mut Datastore {
+        &mut self.candidate
+    }
+
+    pub fn startup(&self) -> &Datastore {
+        &self.startup
+    }
+
+    pub fn startup_mut(&mut self) -> &mut Datastore {
+        &mut self.startup
+    }
+
+    /// Operational state written by the application, without configuration.
+    pub fn state(&self) -> &Datastore {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut Datastore {
+        &mut self.state
+    }
+
+    /// A configuration datastore, or `None` for operational.
+    pub fn configuration(&self, name: DatastoreName) -> Option<&Datastore> {
+        match name {
+            DatastoreName::Running => Some(&self.running),
+            DatastoreName::Candidate => Some(&self.candidate),
+            DatastoreName::Startup => Some(&self.startup),
+            DatastoreName::Operational => None,
+        }
+    }
+
+    /// A configuration datastore for editing.
+    ///
+    /// The operational datastore is read-only and yields an
+    /// operation-not-supported error.
+    pub fn configuration_mut(&mut self, name: DatastoreName) -> Result<&mut Datastore> {
+        match name {
+            DatastoreName::Running => Ok(&mut self.running),
+            DatastoreName::Candidate => Ok(&mut self.candidate),
+            DatastoreName::Startup => Ok(&mut self.startup),
+            DatastoreName::Operational => Err(read_only(name)),
+        }
+    }
+
+    /// The tree of datastore `name`, filtered by the `c=` content parameter.
+    ///
+    /// Configuration datastores hold no state, so they read as empty for
+    /// `c=n`. Operational reads merge running, the state store and every
+    /// subtree provider, in that order.
+    pub fn tree(&self, name: DatastoreName, content: ContentParam) -> Result<Value> {
+        self.view(name, content)?.get_all()
+    }
+
+    /// A readable view of datastore `name` filtered by `content`.
+    ///
+    /// Views that match a stored datastore borrow it; anything else is
+    /// materialised into a detached in-memory snapshot.
+    pub fn view(&self, name: DatastoreName, content: ContentParam) -> Result<DatastoreView<'_>> {
+        self.view_reading(name, content, &[])
+    }
+
+    /// [`Datastores::view`] for a read of the schema nodes `reads`, which
+    /// only calls the subtree providers those nodes touch. An empty slice
+    /// reads the whole tree.
+    pub fn view_reading(
+        &self,
+        name: DatastoreName,
+        content: ContentParam,
+        reads: &[String],
+    ) -> Result<DatastoreView<'_>> {
+        let stored = match (self.configuration(name), content) {
+            (Some(datastore), ContentParam::All | ContentParam::Config) => Some(datastore),
+            (None, ContentParam::Config) => Some(&self.running),
+            (None, ContentParam::All)
+                if self.touched(reads).next().is_none() && self.state.is_empty()? =>
+            {
+                Some(&self.running)
+            }
+            _ => None,
+        };
+        match stored {
+            Some(datastore) => Ok(DatastoreView::Stored(datastore)),
+            None if name == DatastoreName::Operational => {
+                Ok(DatastoreView::Snapshot(self.operational(content, reads)?))
+            }
+            None => Ok(DatastoreView::Snapshot(Datastore::new_in_memory(
+                Arc::clone(self.running.shared_model()),
+            ))),
+        }
+    }
+
+    /// The operational datastore for a read of the schema nodes `reads`,
+    /// sharing running's tree. Only the top-level nodes `reads` fall under
+    /// take state from the state store, and only the subtree providers
+    /// `reads` touch are written over them, so a read copies no more than
+    /// that.
+    fn operational(&self, content: ContentParam, reads: &[String]) -> Result<Datastore> {
+        let base = match content {
+            ContentParam::Nonconfig => SharedTree::default(),
+            ContentParam::All | ContentParam::Config => self.running.snapshot()?,
+        };
+        let mut merged = Datastore::from_snapshot(Arc::clone(self.running.shared_model()), base);
+        if content == ContentParam::Config {
+            return Ok(merged);
+        }
+
+        let state: Map<String, Value> = self
+            .state
+            .snapshot()?
+            .members()
+            .filter(|(name, _)| reads_under(reads, name))
+            .map(|(name, node)| (name.to_string(), node.to_value()))
+            .collect();
+        if !state.is_empty() {
+            merged.merge_tree(&Value::Object(state))?;
+        }
+        for subtree in self.touched(reads) {
+            let value = subtree.value()?;
+            if !value.is_null() {
+                merged.set_path(subtree.path(), value)?;
+            }
+        }
+        Ok(merged)
+    }
+
+    /// How long a read of `reads` from operational stays fresh: the least
+    /// remaining max-age of the subtree providers it touches, or `None`
+    /// when it touches none.
+    pub fn provided_max_age(&self, reads: &[String]) -> Option<Duration> {
+        self.touched(reads)
+            .map(ProvidedSubtree::remaining_age)
+            .min()
+    }
+
+    /// Recompute the expired subtrees `reads` touch, returning the paths of
+    /// those whose value changed since they were last read.
+    pub fn refresh_provided(&self, reads: &[String]) -> Result<Vec<String>> {
+        let mut changed = Vec::new();
+        for subtree in self.touched(reads) {
+            if subtree.refresh()? {
+                changed.push(subtree.path().to_string());
+            }
+        }
+        Ok(changed)
+    }
+
+    fn touched<'s>(&'s self, reads: &'s [String]) -> impl Iterator<Item = &'s ProvidedSubtree> {
+        self.subtrees.iter().filter(move |subtree| {
+            reads.is_empty() || reads.iter().any(|read| subtree.touches(read))
+        })
+    }
+
+    /// Replace the contents of configuration datastore `to` with those of
+    /// `from` (NETCONF `<copy-config>`).
+    ///
+    /// Copying from operational copies its configuration only.
+    pub fn copy(&mut self, from: DatastoreName, to: DatastoreName) -> Result<()> {
+        if from == to {
+            return Ok(());
+        }
+        match self.configuration(from) {
+            Some(source) => {
+                let tree = source.snapshot()?;
+                self.configuration_mut(to)?.replace_snapshot(tree)
+            }
+            None => {
+                let tree = self.tree(from, ContentParam::Config)?;
+                self.configuration_mut(to)?.replace_tree(tree)
+            }
+        }
+    }
+}
+
+/// Read access to one datastore, as returned by [`Datastores::view`].
+pub enum DatastoreView<'a> {
+    Stored(&'a Datastore),
+    Snapshot(Datastore),
+}
+
+impl std::ops::Deref for DatastoreView<'_> {
+    type Target = Datastore;
+
+    fn deref(&self) -> &Datastore {
+        match self {
+            Self::Stored(datastore) => datastore,
+            Self::Snapshot(datastore) => datastore,
+        }
+    }
+}
+
+impl TryFrom<Datastore> for Datastores {
+    type Error = CoreconfError;
+
+    fn try_from(running: Datastore) -> Result<Self> {
+        Self::new(running)
+    }
+}
+
+/// Whether a read of the schema nodes `reads` falls under the top-level
+/// node `name`; any does for an empty `reads`.
+fn reads_under(reads: &[String], name: &str) -> bool {
+    reads.is_empty()
+        || reads
+            .iter()
+            .any(|read| read.trim_start_matches('/').split('/').next() == Some(name))
+}
+
+fn read_only(name: DatastoreName) -> CoreconfError {
+    DataError::new(
+        ErrorTag::OperationNotSupported,
+        format!("the {name} datastore is read-only"),
+    )
+    .into()
+}
@@ -1,4 +1,4 @@
-use coreconf_model::{CoreconfError, Result};
+use coreconf_model::{CoreconfError, DataError, ErrorTag, Result};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PredicatePath {
@@ -18,8 +18,10 @@ impl PredicatePath {
         let mut canonical_segments = Vec::new();
         let mut predicates = Vec::new();
 
-        for segment in split_segments(input)? {
-            let (base, segment_predicates) = parse_segment(&segment)?;
+        let segments = split_segments(input).map_err(|error| invalid_path(input, error))?;
+        for segment in segments {
+            let (base, segment_predicates) =
+                parse_segment(&segment).map_err(|error| invalid_path(input, error))?;
             if !base.is_empty() {
                 canonical_segments.push(base);
             }
@@ -39,7 +41,46 @@ impl PredicatePath {
     }
 }
 
-fn split_segments(input: &str) -> Result<Vec<String>> {
+/// The list instance path of a list entry path: `target` without its final
+/// predicates, or `None` if its last node has none.
+pub(crate) fn entry_list_path(target: &str) -> Option<&str> {
+    if !target.ends_with(']') {
+        return None;
+    }
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = None;
+    for (index, ch) in target.char_indices() {
+        match (quote, ch) {
+            (Some(open), _) if ch == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') if depth > 0 => quote = Some(ch),
+            (None, '[') => {
+                if depth == 0 && start.is_none() {
+                    start = Some(index);
+                }
+                depth += 1;
+            }
+            (None, ']') => depth = depth.saturating_sub(1),
+            (None, '/') if depth == 0 => start = None,
+            _ => {}
+        }
+    }
+    start.map(|start| &target[..start])
+}
+
+fn invalid_path(input: &str, error: CoreconfError) -> CoreconfError {
+    let message = match error {
+        CoreconfError::ValidationError(message) => message,
+        other => other.to_string(),
+    };
+    DataError::new(ErrorTag::InvalidValue, message)
+        .with_app_tag("invalid-path")
+        .with_path(input)
+        .into()
+}
+
+pub(crate) fn split_segments(input: &str) -> Result<Vec<String>> {
     let mut segments = Vec::new();
     let mut current = String::new();
     let mut bracket_depth = 0usize;
@@ -106,6 +147,17 @@ fn split_segments(input: &str) -> Result<Vec<String>> {
 }
 
 fn parse_segment(segment: &str) -> Result<(String, Vec<(String, String)>)> {
+    let (base, predicates) = split_predicates(segment)?;
+    let predicates = predicates
+        .iter()
+        .map(|predicate| parse_predicate(predicate))
+        .collect::<Result<_>>()?;
+    Ok((base, predicates))
+}
+
+/// Split a path segment into its node name and the text inside each of its
+/// `[...]` predicates.
+pub(crate) fn split_predicates(segment: &str) -> Result<(String, Vec<String>)> {
     let mut base = String::new();
     let mut predicates = Vec::new();
     let chars: Vec<char> = segment.chars().collect();
@@ -151,9 +203,7 @@ fn parse_segment(segment: &str) -> Result<(String, Vec<(String, String)>)> {
             )));
         }
 
-        let predicate = chars[predicate_start..index].iter().collect::<String>();
-        let (name, value) = parse_predicate(&predicate)?;
-        predicates.push((name, value));
+        predicates.push(chars[predicate_start..index].iter().collect::<String>());
         index += 1;
     }
 
@@ -185,7 +235,7 @@ fn parse_predicate(predicate: &str) -> Result<(String, String)> {
     ))
 }
 
-fn unescape_predicate_value(value: &str) -> Result<String> {
+pub(crate) fn unescape_predicate_value(value: &str) -> Result<String> {
     let mut unescaped = String::with_capacity(value.len());
     let mut escaped = false;
 
@@ -208,3 +258,22 @@ fn unescape_predicate_value(value: &str) -> Result<String> {
 
     Ok(unescaped)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_list_path_strips_the_final_predicates_only() {
+        assert_eq!(
+            entry_list_path("/m:a/list[k='x'][j='y']"),
+            Some("/m:a/list")
+        );
+        assert_eq!(
+            entry_list_path("/m:a/list[k='x/]']/inner[n='1']"),
+            Some("/m:a/list[k='x/]']/inner")
+        );
+        assert_eq!(entry_list_path("/m:a/list[k='x']/leaf"), None);
+        assert_eq!(entry_list_path("/m:a"), None);
+    }
+}
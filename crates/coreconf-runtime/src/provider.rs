@@ -0,0 +1,123 @@
+//! Operational state computed on read.
+//!
+//! A [`SubtreeProvider`] owns one subtree of the operational datastore, such
+//! as `/coreconf-m2m:state/uptime`, and is only called when a GET or FETCH
+//! reads something inside or above it. Its value is kept for the max-age it
+//! was registered with: reads within that window reuse it, and responses
+//! carry the remaining time as the CoAP Max-Age option.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use coreconf_model::{CompositeModel, CoreconfError, Result};
+use serde_json::Value;
+
+use crate::path::PredicatePath;
+
+/// Computes the current value of a subtree of the operational datastore.
+///
+/// Closures returning `Result<Value>` are providers.
+pub trait SubtreeProvider: Send + Sync {
+    /// The value of the registered node, in the layout
+    /// [`crate::Datastore::get_path`] returns for it. `Value::Null` leaves
+    /// the node out.
+    fn provide(&self) -> Result<Value>;
+}
+
+impl<F> SubtreeProvider for F
+where
+    F: Fn() -> Result<Value> + Send + Sync,
+{
+    fn provide(&self) -> Result<Value> {
+        self()
+    }
+}
+
+struct Cached {
+    value: Value,
+    computed: Instant,
+}
+
+/// A provider registered at a predicate path, with its cached value.
+pub(crate) struct ProvidedSubtree {
+    path: String,
+    schema_path: String,
+    max_age: Duration,
+    provider: Box<dyn SubtreeProvider>,
+    cache: Mutex<Option<Cached>>,
+}
+
+impl ProvidedSubtree {
+    pub(crate) fn new(
+        model: &CompositeModel,
+        path: &str,
+        max_age: Duration,
+        provider: Box<dyn SubtreeProvider>,
+    ) -> Result<Self> {
+        let schema_path = PredicatePath::parse(path)?.canonical_path;
+        if model.get_sid(&schema_path).is_none() {
+            return Err(CoreconfError::SidNotFound(schema_path));
+        }
+        Ok(Self {
+            path: path.to_string(),
+            schema_path,
+            max_age,
+            provider,
+            cache: Mutex::new(None),
+        })
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Whether reading the schema node `schema_path` reads this subtree,
+    /// because one contains the other.
+    pub(crate) fn touches(&self, schema_path: &str) -> bool {
+        let within = |inner: &str, outer: &str| {
+            outer == "/"
+                || inner == outer
+                || inner
+                    .strip_prefix(outer)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        };
+        within(&self.schema_path, schema_path) || within(schema_path, &self.schema_path)
+    }
+
+    /// The value to serve, recomputed when the cached one has expired.
+    pub(crate) fn value(&self) -> Result<Value> {
+        let mut cache = self.lock();
+        if let Some(cached) = cache.as_ref()
+            && cached.computed.elapsed() < self.max_age
+        {
+            return Ok(cached.value.clone());
+        }
+        let value = self.provider.provide()?;
+        *cache = Some(Cached {
+            value: value.clone(),
+            computed: Instant::now(),
+        });
+        Ok(value)
+    }
+
+    /// How much longer the cached value stays fresh.
+    pub(crate) fn remaining_age(&self) -> Duration {
+        self.lock().as_ref().map_or(Duration::ZERO, |cached| {
+            self.max_age.saturating_sub(cached.computed.elapsed())
+        })
+    }
+
+    /// Recompute an expired value, returning whether it differs from the
+    /// one served before. A subtree nobody has read yet has not changed.
+    pub(crate) fn refresh(&self) -> Result<bool> {
+        let previous = match self.lock().as_ref() {
+            Some(cached) if cached.computed.elapsed() >= self.max_age => cached.value.clone(),
+            _ => return Ok(false),
+        };
+        Ok(self.value()? != previous)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Cached>> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
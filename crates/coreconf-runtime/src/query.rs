@@ -0,0 +1,579 @@
+//! Datastore queries: predicate paths that select any number of nodes.
+//!
+//! A query is a predicate path whose list predicates filter entries
+//! instead of naming one:
+//!
+//! - `sensor[*]` or a bare `sensor` matches every entry,
+//! - `sensor[unit='mm']` and `sensor[unit!='mm']` compare any leaf of the
+//!   entry, key or not,
+//! - `sensor[level>3]` (also `<`, `<=`, `>=`) compares a numeric leaf.
+//!
+//! Predicates on one segment must all hold. [`Datastore::query`] returns
+//! each match under its concrete predicate path.
+//!
+//! [`Datastore::query`]: crate::Datastore::query
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use coreconf_model::{CompositeModel, CoreconfError, DataError, ErrorTag, Result, YangType};
+use serde_json::Value;
+
+use crate::datastore::{
+    TreeNode, coerce_predicate_value, format_predicate_string, join_path, list_entry_matches,
+    list_keys, storage_key,
+};
+use crate::path::{split_predicates, split_segments, unescape_predicate_value};
+use crate::shared_tree::SharedTree;
+
+/// A match whose value differs between two trees: its concrete path, the
+/// old value and the new one.
+pub(crate) type Change<'a> = (String, Option<&'a SharedTree>, Option<&'a SharedTree>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Operator {
+    /// Longest first, so `<=` isn't read as `<`.
+    const ALL: [(&'static str, Self); 6] = [
+        ("!=", Self::Ne),
+        ("<=", Self::Le),
+        (">=", Self::Ge),
+        ("=", Self::Eq),
+        ("<", Self::Lt),
+        (">", Self::Gt),
+    ];
+
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => ordering.is_eq(),
+            Self::Ne => ordering.is_ne(),
+            Self::Lt => ordering.is_lt(),
+            Self::Le => ordering.is_le(),
+            Self::Gt => ordering.is_gt(),
+            Self::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+/// A condition on the entries of a keyed list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// `[*]`: every entry.
+    Any,
+    /// `[leaf op value]`: entries whose `leaf` compares as asked.
+    Compare {
+        leaf: String,
+        operator: Operator,
+        value: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub name: String,
+    pub filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub steps: Vec<Step>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self> {
+        let steps = split_segments(input)
+            .and_then(|segments| segments.iter().map(|segment| parse_step(segment)).collect())
+            .map_err(|error| invalid_query(input, error))?;
+        Ok(Self { steps })
+    }
+
+    /// Match the query against `root`, the value of its first step.
+    ///
+    /// Every step must name a schema node, and only keyed lists may have
+    /// predicates, whether or not data exists under them.
+    pub(crate) fn run(
+        &self,
+        model: &CompositeModel,
+        root: Option<&Value>,
+    ) -> Result<Vec<(String, Value)>> {
+        Ok(self
+            .matching(model, root)?
+            .into_iter()
+            .map(|(path, node)| (path, node.clone()))
+            .collect())
+    }
+
+    /// [`Query::run`] over any tree, borrowing the matched nodes.
+    pub(crate) fn matching<'a, N: TreeNode>(
+        &self,
+        model: &CompositeModel,
+        root: Option<&'a N>,
+    ) -> Result<Vec<(String, &'a N)>> {
+        self.check(model)?;
+        let mut matches = Vec::new();
+        if let Some(root) = root
+            && !self.steps.is_empty()
+        {
+            self.visit(model, 0, root, "", String::new(), &mut matches)?;
+        }
+        Ok(matches)
+    }
+
+    /// The matches that differ between `before` and `after`, the values of
+    /// the first step in two trees, under the same checks as
+    /// [`Query::run`]. Subtrees both trees share are skipped unvisited.
+    pub(crate) fn changes<'a>(
+        &self,
+        model: &CompositeModel,
+        before: Option<&'a SharedTree>,
+        after: Option<&'a SharedTree>,
+    ) -> Result<Vec<Change<'a>>> {
+        self.check(model)?;
+        let mut changes = Vec::new();
+        if !self.steps.is_empty() {
+            self.visit_changes(model, 0, (before, after), "", String::new(), &mut changes)?;
+        }
+        Ok(changes)
+    }
+
+    fn check(&self, model: &CompositeModel) -> Result<()> {
+        let mut schema = String::new();
+        for step in &self.steps {
+            schema = join_path(&schema, &step.name);
+            if model.get_sid(&schema).is_none() {
+                return Err(CoreconfError::SidNotFound(schema));
+            }
+            if !step.filters.is_empty() && list_keys(model, &schema)?.is_empty() {
+                return Err(DataError::new(
+                    ErrorTag::InvalidValue,
+                    format!("predicates apply to keyed lists, not '{schema}'"),
+                )
+                .with_app_tag("invalid-query")
+                .with_path(schema)
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect the matches under `node`, the value of step `depth`.
+    fn visit<'a, N: TreeNode>(
+        &self,
+        model: &CompositeModel,
+        depth: usize,
+        node: &'a N,
+        parent_schema: &str,
+        parent_path: String,
+        matches: &mut Vec<(String, &'a N)>,
+    ) -> Result<()> {
+        let step = &self.steps[depth];
+        let schema = join_path(parent_schema, &step.name);
+        let keys = list_keys(model, &schema)?;
+        let path = format!("{parent_path}/{}", step.name);
+
+        if keys.is_empty() {
+            return self.descend(model, depth, node, &schema, path, matches);
+        }
+
+        for (entry_path, entry) in step.entries(model, &schema, &keys, &path, Some(node))? {
+            self.descend(model, depth, entry, &schema, entry_path, matches)?;
+        }
+        Ok(())
+    }
+
+    fn descend<'a, N: TreeNode>(
+        &self,
+        model: &CompositeModel,
+        depth: usize,
+        node: &'a N,
+        schema: &str,
+        path: String,
+        matches: &mut Vec<(String, &'a N)>,
+    ) -> Result<()> {
+        let Some(next) = self.steps.get(depth + 1) else {
+            matches.push((path, node));
+            return Ok(());
+        };
+        match node.member(&storage_key(&next.name, depth + 1)) {
+            Some(child) => self.visit(model, depth + 1, child, schema, path, matches),
+            None => Ok(()),
+        }
+    }
+
+    /// [`Query::visit`] over two trees at once. List entries pair up by
+    /// path; entries only `before` has come first.
+    fn visit_changes<'a>(
+        &self,
+        model: &CompositeModel,
+        depth: usize,
+        (before, after): (Option<&'a SharedTree>, Option<&'a SharedTree>),
+        parent_schema: &str,
+        parent_path: String,
+        changes: &mut Vec<Change<'a>>,
+    ) -> Result<()> {
+        match (before, after) {
+            (None, None) => return Ok(()),
+            (Some(before), Some(after)) if SharedTree::ptr_eq(before, after) => return Ok(()),
+            _ => {}
+        }
+        let step = &self.steps[depth];
+        let schema = join_path(parent_schema, &step.name);
+        let keys = list_keys(model, &schema)?;
+        let path = format!("{parent_path}/{}", step.name);
+
+        if keys.is_empty() {
+            return self.descend_changes(model, depth, (before, after), &schema, path, changes);
+        }
+
+        let old = step.entries(model, &schema, &keys, &path, before)?;
+        let new = step.entries(model, &schema, &keys, &path, after)?;
+        let mut unpaired: HashMap<&str, &SharedTree> = new
+            .iter()
+            .map(|(entry_path, entry)| (entry_path.as_str(), *entry))
+            .collect();
+        for (entry_path, entry) in &old {
+            let counterpart = unpaired.remove(entry_path.as_str());
+            self.descend_changes(
+                model,
+                depth,
+                (Some(entry), counterpart),
+                &schema,
+                entry_path.clone(),
+                changes,
+            )?;
+        }
+        for (entry_path, entry) in &new {
+            if unpaired.contains_key(entry_path.as_str()) {
+                self.descend_changes(
+                    model,
+                    depth,
+                    (None, Some(entry)),
+                    &schema,
+                    entry_path.clone(),
+                    changes,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn descend_changes<'a>(
+        &self,
+        model: &CompositeModel,
+        depth: usize,
+        (before, after): (Option<&'a SharedTree>, Option<&'a SharedTree>),
+        schema: &str,
+        path: String,
+        changes: &mut Vec<Change<'a>>,
+    ) -> Result<()> {
+        let Some(next) = self.steps.get(depth + 1) else {
+            if before != after {
+                changes.push((path, before, after));
+            }
+            return Ok(());
+        };
+        let key = storage_key(&next.name, depth + 1);
+        self.visit_changes(
+            model,
+            depth + 1,
+            (
+                before.and_then(|node| node.get(&key)),
+                after.and_then(|node| node.get(&key)),
+            ),
+            schema,
+            path,
+            changes,
+        )
+    }
+}
+
+impl Step {
+    /// The entries of the keyed list `node` that pass this step's filters,
+    /// under their concrete paths. Entries missing a key are left out.
+    fn entries<'a, N: TreeNode>(
+        &self,
+        model: &CompositeModel,
+        list: &str,
+        keys: &[(String, Value)],
+        path: &str,
+        node: Option<&'a N>,
+    ) -> Result<Vec<(String, &'a N)>> {
+        let key_sids = model
+            .get_sid(list)
+            .and_then(|sid| model.get_keys(sid))
+            .unwrap_or_default();
+        let mut entries = Vec::new();
+        for entry in node.and_then(N::entries).into_iter().flatten() {
+            if !self.matches(model, list, entry)? {
+                continue;
+            }
+            let Some(key_values) = keys
+                .iter()
+                .map(|(leaf, _)| entry.leaf(leaf).cloned())
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let entry_path = format!(
+                "{path}{}",
+                format_predicate_string(model, key_sids, &key_values)?
+            );
+            entries.push((entry_path, entry));
+        }
+        Ok(entries)
+    }
+
+    fn matches<N: TreeNode>(&self, model: &CompositeModel, list: &str, entry: &N) -> Result<bool> {
+        for filter in &self.filters {
+            let Filter::Compare {
+                leaf,
+                operator,
+                value,
+            } = filter
+            else {
+                continue;
+            };
+            let Some(stored) = entry.leaf(leaf) else {
+                return Ok(false);
+            };
+            let identifier = format!("{list}/{leaf}");
+            let holds = match operator {
+                Operator::Eq | Operator::Ne => {
+                    let typed = coerce_predicate_value(model, &identifier, value)?;
+                    let equal = list_entry_matches(entry, &[(leaf.clone(), typed)], model, list)
+                        || stored.as_str() == Some(value.as_str());
+                    equal == (*operator == Operator::Eq)
+                }
+                _ => compare_numbers(model, &identifier, stored, value)?
+                    .is_some_and(|ordering| operator.holds(ordering)),
+            };
+            if !holds {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn parse_step(segment: &str) -> Result<Step> {
+    let (name, predicates) = split_predicates(segment)?;
+    let filters = predicates
+        .iter()
+        .map(|predicate| parse_filter(predicate))
+        .collect::<Result<_>>()?;
+    Ok(Step { name, filters })
+}
+
+fn parse_filter(predicate: &str) -> Result<Filter> {
+    if predicate.trim() == "*" {
+        return Ok(Filter::Any);
+    }
+    let (index, symbol, operator) = predicate
+        .char_indices()
+        .find_map(|(index, _)| {
+            Operator::ALL
+                .iter()
+                .find(|(symbol, _)| predicate[index..].starts_with(symbol))
+                .map(|&(symbol, operator)| (index, symbol, operator))
+        })
+        .ok_or_else(|| {
+            CoreconfError::ValidationError(format!(
+                "predicate '{predicate}' needs '*' or a comparison"
+            ))
+        })?;
+    let leaf = predicate[..index].trim();
+    let raw = predicate[index + symbol.len()..].trim();
+    let quote = raw.chars().next().filter(|ch| *ch == '\'' || *ch == '"');
+    let value = match quote {
+        Some(quote) if raw.len() >= 2 && raw.ends_with(quote) => {
+            unescape_predicate_value(&raw[1..raw.len() - 1])?
+        }
+        Some(_) => {
+            return Err(CoreconfError::ValidationError(format!(
+                "predicate '{predicate}' must use matching quotes"
+            )));
+        }
+        None => raw.to_string(),
+    };
+    if leaf.is_empty() || (value.is_empty() && quote.is_none()) {
+        return Err(CoreconfError::ValidationError(format!(
+            "predicate '{predicate}' needs a leaf and a value"
+        )));
+    }
+    Ok(Filter::Compare {
+        leaf: leaf.to_string(),
+        operator,
+        value,
+    })
+}
+
+/// Order the numeric leaf value `stored` against `raw`. `None` if the
+/// stored value isn't a number.
+fn compare_numbers(
+    model: &CompositeModel,
+    identifier: &str,
+    stored: &Value,
+    raw: &str,
+) -> Result<Option<Ordering>> {
+    let numeric = matches!(
+        model.get_type(identifier),
+        Some(
+            YangType::Int8
+                | YangType::Int16
+                | YangType::Int32
+                | YangType::Int64
+                | YangType::Uint8
+                | YangType::Uint16
+                | YangType::Uint32
+                | YangType::Uint64
+                | YangType::Decimal64
+        )
+    );
+    if !numeric {
+        return Err(DataError::new(
+            ErrorTag::InvalidValue,
+            format!("'{identifier}' is not numeric, so it can only be compared with = and !="),
+        )
+        .with_app_tag("invalid-query")
+        .with_path(identifier)
+        .into());
+    }
+    let invalid = || {
+        CoreconfError::from(
+            DataError::new(ErrorTag::InvalidValue, format!("'{raw}' is not a number"))
+                .with_app_tag("invalid-query")
+                .with_path(identifier)
+                .with_value(Value::String(raw.to_string())),
+        )
+    };
+
+    // Integers compare exactly; anything else as floating point.
+    let stored_integer = match stored {
+        Value::Number(number) => number
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| number.as_u64().map(i128::from)),
+        _ => None,
+    };
+    if let (Some(stored), Ok(wanted)) = (stored_integer, raw.parse::<i128>()) {
+        return Ok(Some(stored.cmp(&wanted)));
+    }
+    let wanted = raw.parse::<f64>().map_err(|_| invalid())?;
+    let stored = match stored {
+        Value::Number(number) => number.as_f64(),
+        // decimal64 may be kept as its RFC 7951 string form.
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    };
+    Ok(stored.and_then(|stored| stored.partial_cmp(&wanted)))
+}
+
+fn invalid_query(input: &str, error: CoreconfError) -> CoreconfError {
+    let message = match error {
+        CoreconfError::ValidationError(message) => message,
+        other => other.to_string(),
+    };
+    DataError::new(ErrorTag::InvalidValue, message)
+        .with_app_tag("invalid-query")
+        .with_path(input)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn queries_parse_wildcards_and_comparisons() {
+        let query = Query::parse("/m:a/list[*]/inner[unit='m]m'][level>=3]/leaf").unwrap();
+
+        assert_eq!(query.steps.len(), 4);
+        assert_eq!(query.steps[1].filters, vec![Filter::Any]);
+        assert_eq!(
+            query.steps[2].filters,
+            vec![
+                Filter::Compare {
+                    leaf: "unit".into(),
+                    operator: Operator::Eq,
+                    value: "m]m".into(),
+                },
+                Filter::Compare {
+                    leaf: "level".into(),
+                    operator: Operator::Ge,
+                    value: "3".into(),
+                },
+            ]
+        );
+        assert!(Query::parse("/m:a/list[unit]").is_err());
+        assert!(Query::parse("/m:a/list[unit='mm]").is_err());
+    }
+
+    #[test]
+    fn changes_pair_list_entries_by_key() {
+        let model = CompositeModel::from_sid_strings(&[r#"{
+            "module-name":"m",
+            "module-revision":"2026-01-01",
+            "item":[
+                {"identifier":"m","sid":60000},
+                {"identifier":"/m:a","sid":60001},
+                {"identifier":"/m:a/list","sid":60002},
+                {"identifier":"/m:a/list/id","sid":60003,"type":"string"},
+                {"identifier":"/m:a/list/level","sid":60004,"type":"int32"}
+            ],
+            "key-mapping":{"60002":[60003]}
+        }"#])
+        .unwrap();
+        let query = Query::parse("/m:a/list[*]/level").unwrap();
+        let before = SharedTree::from(json!({"list": [
+            {"id": "x", "level": 1},
+            {"id": "y", "level": 2},
+            {"id": "z", "level": 3}
+        ]}));
+        let after = SharedTree::from(json!({"list": [
+            {"id": "w", "level": 0},
+            {"id": "z", "level": 4},
+            {"id": "y", "level": 2}
+        ]}));
+
+        let changes: Vec<_> = query
+            .changes(&model, Some(&before), Some(&after))
+            .unwrap()
+            .into_iter()
+            .map(|(path, old, new)| {
+                (
+                    path,
+                    old.map(SharedTree::to_value),
+                    new.map(SharedTree::to_value),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("/m:a/list[id='x']/level".into(), Some(json!(1)), None),
+                (
+                    "/m:a/list[id='z']/level".into(),
+                    Some(json!(3)),
+                    Some(json!(4))
+                ),
+                ("/m:a/list[id='w']/level".into(), None, Some(json!(0))),
+            ]
+        );
+        assert!(
+            query
+                .changes(&model, Some(&before), Some(&before.clone()))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
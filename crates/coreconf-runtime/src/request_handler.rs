@@ -1,15 +1,34 @@
-use coreconf_model::instance_id::decode_instances_with_model;
-use coreconf_model::{CoreconfError, Result};
-use serde_json::Value;
+use coreconf_model::instance_id::{Instance, decode_instances_with_model};
+use coreconf_model::{CompositeModel, CoreconfError, DataError, ErrorTag, Result};
+use serde_json::{Map, Value};
 
 use std::collections::{HashMap, HashSet};
-
-use crate::coap_types::{ContentFormat, Interface, Method, Request, Response, ResponseCode};
-use crate::datastore::Datastore;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::access::{AccessControl, AccessOperation, Decider};
+use crate::coap_types::{
+    ContentFormat, ContentParam, Interface, Method, Request, Response, ResponseCode,
+};
+use crate::commit::{
+    CANCEL_COMMIT_PATH, COMMIT_PATH, DISCARD_CHANGES_PATH, PendingConfirm, parse_commit_input,
+};
+use crate::datastore::{Datastore, Position};
+use crate::diff::{Diff, diff_at, diff_trees};
+use crate::etag::{entity_tag, preconditions_hold, tree_entity_tag};
+use crate::journal::{
+    Journal, JournalEntry, REVISION_PATH, REVISIONS_PATH, ROLLBACK_PATH, inverse_edit,
+    parse_revision_input,
+};
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, DatastoreLock, LOCK_PATH, UNLOCK_PATH, parse_lock_input};
 use crate::memory_backend::MemoryBackend;
+use crate::nmda::{DatastoreName, DatastoreView, Datastores};
 use crate::operations::{OperationBinding, OperationRegistry};
-use crate::path::PredicatePath;
+use crate::path::{PredicatePath, entry_list_path};
+use crate::shared_tree::SharedTree;
+use crate::subscription::{ChangeEvent, SubscriptionId};
 use crate::transaction::{TransactionContext, TransactionParticipant};
+use crate::yang_patch::{YangPatch, apply_edit, changed_path};
 
 /// A registered CoAP observer identified by its token.
 #[derive(Debug, Clone)]
@@ -19,8 +38,53 @@ pub struct Observer {
     pub resources: HashSet<String>,
 }
 
+/// Edits applied to a candidate datastore, with what the journal needs to
+/// record and undo them.
+#[derive(Default)]
+struct EditLog {
+    changed_paths: Vec<String>,
+    changed: HashSet<String>,
+    patch: Vec<(String, Option<Value>)>,
+    inverse_patch: Vec<(String, Option<Value>)>,
+}
+
+impl EditLog {
+    fn is_empty(&self) -> bool {
+        self.patch.is_empty()
+    }
+
+    /// Apply `edit` to `path` in `candidate`, recording it on success.
+    fn apply(
+        &mut self,
+        candidate: &mut Datastore,
+        path: &str,
+        edit: impl FnOnce(&mut Datastore) -> Result<()>,
+    ) -> Result<()> {
+        let inverse = inverse_edit(path, |path| candidate.get_node(path).ok().flatten());
+        edit(candidate)?;
+        let value = candidate.get_node(path).ok().flatten();
+        self.patch.push((path.to_string(), value));
+        self.inverse_patch.push(inverse);
+        if self.changed.insert(path.to_string()) {
+            self.changed_paths.push(path.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// How [`RequestHandler::run_transaction`] publishes its candidate.
+enum Publication<'p> {
+    /// Replace the target with the candidate. With `validate` unset,
+    /// preparation and validation are skipped.
+    Replace { validate: bool },
+    /// Apply the edits that made the candidate to the target, path by
+    /// path, validating first. Top-level nodes preparation changed are
+    /// appended to the edits whole.
+    Edits(&'p mut Vec<(String, Option<Value>)>),
+}
+
 pub struct RequestHandler {
-    datastore: Datastore,
+    datastores: Datastores,
     operations: OperationRegistry,
     transaction_participants: Vec<Box<dyn TransactionParticipant>>,
     /// Observe sequence counter (incremented on each notification).
@@ -29,36 +93,46 @@ pub struct RequestHandler {
     observers: HashMap<Vec<u8>, Observer>,
     /// Resources that have changed since last notification.
     dirty_resources: HashSet<String>,
+    /// Confirmed commit waiting for its confirming commit.
+    pending_confirm: Option<PendingConfirm>,
+    /// History of transactions committed to running.
+    journal: Journal,
+    /// Rules checked for requests that carry a peer identity.
+    access_control: Option<AccessControl>,
+    /// Locks on configuration datastores, which may have expired.
+    locks: HashMap<DatastoreName, DatastoreLock>,
 }
 
 impl RequestHandler {
-    pub fn new(datastore: Datastore) -> Self {
-        Self {
-            datastore,
-            operations: OperationRegistry::default(),
-            transaction_participants: Vec::new(),
-            observe_sequence: 0,
-            observers: HashMap::new(),
-            dirty_resources: HashSet::new(),
-        }
+    /// Serve `datastores`, either a [`Datastores`] or the running
+    /// [`Datastore`] to build one from; fails if running can't be read.
+    pub fn new(datastores: impl TryInto<Datastores, Error: Into<CoreconfError>>) -> Result<Self> {
+        Self::with_operations(datastores, OperationRegistry::default())
     }
 
-    pub fn with_operations(datastore: Datastore, operations: OperationRegistry) -> Self {
-        Self {
-            datastore,
+    pub fn with_operations(
+        datastores: impl TryInto<Datastores, Error: Into<CoreconfError>>,
+        operations: OperationRegistry,
+    ) -> Result<Self> {
+        Ok(Self {
+            datastores: datastores.try_into().map_err(Into::into)?,
             operations,
             transaction_participants: Vec::new(),
             observe_sequence: 0,
             observers: HashMap::new(),
             dirty_resources: HashSet::new(),
-        }
+            pending_confirm: None,
+            journal: Journal::default(),
+            access_control: None,
+            locks: HashMap::new(),
+        })
     }
 
     pub fn register_operation(&mut self, binding: Box<dyn OperationBinding>) {
         self.operations.register(binding);
     }
 
-    /// Register a participant for root iPATCH transactions.
+    /// Register a participant in every transaction on running.
     ///
     /// Participants are invoked in registration order.
     pub fn register_transaction_participant(
@@ -68,12 +142,695 @@ impl RequestHandler {
         self.transaction_participants.push(participant);
     }
 
+    /// Subscribe to changes of the running datastore matching `pattern`,
+    /// whatever request or call makes them; see [`Datastore::subscribe`].
+    pub fn subscribe(
+        &mut self,
+        pattern: &str,
+        callback: impl FnMut(&ChangeEvent) + Send + Sync + 'static,
+    ) -> Result<SubscriptionId> {
+        self.datastores.running_mut().subscribe(pattern, callback)
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        self.datastores.running_mut().unsubscribe(id)
+    }
+
+    /// Check requests from peers against `access` from now on: reads
+    /// leave out what the peer may not read, and edits and operations it
+    /// may not make fail with access-denied (4.03).
+    ///
+    /// Fails if a rule path names no schema node.
+    pub fn set_access_control(&mut self, access: AccessControl) -> Result<()> {
+        access.validate(self.model())?;
+        self.access_control = Some(access);
+        Ok(())
+    }
+
+    pub fn access_control(&self) -> Option<&AccessControl> {
+        self.access_control.as_ref()
+    }
+
+    /// The rules for `peer` over `trees`, or `None` when it is not checked.
+    fn decider(&self, peer: Option<&str>, trees: &[&SharedTree]) -> Result<Option<Decider<'_>>> {
+        match (&self.access_control, peer) {
+            (Some(access), Some(peer)) if access.enabled => {
+                access.decider(self.model(), peer, trees).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn checks(&self, peer: Option<&str>) -> bool {
+        peer.is_some()
+            && self
+                .access_control
+                .as_ref()
+                .is_some_and(|access| access.enabled)
+    }
+
+    /// The running datastore.
     pub fn datastore(&self) -> &Datastore {
-        &self.datastore
+        self.datastores.running()
     }
 
     pub fn datastore_mut(&mut self) -> &mut Datastore {
-        &mut self.datastore
+        self.datastores.running_mut()
+    }
+
+    pub fn datastores(&self) -> &Datastores {
+        &self.datastores
+    }
+
+    pub fn datastores_mut(&mut self) -> &mut Datastores {
+        &mut self.datastores
+    }
+
+    fn model(&self) -> &CompositeModel {
+        self.datastores.running().model()
+    }
+
+    /// The view a GET or FETCH reads from: the `ds=` datastore, operational
+    /// by default, filtered by `c=` and by what the peer may read.
+    fn read_view(&self, request: &Request) -> Result<DatastoreView<'_>> {
+        let name = request
+            .query
+            .datastore
+            .unwrap_or(DatastoreName::Operational);
+        let reads = self.read_paths(request);
+        let view = self
+            .datastores
+            .view_reading(name, request.query.content, &reads)?;
+        self.readable(view, request.peer.as_deref(), &reads)
+    }
+
+    /// The part of `view` a read of the schema nodes `reads`, or of the
+    /// whole tree for an empty slice, returns, without the nodes `peer` may
+    /// not read.
+    fn readable<'a>(
+        &'a self,
+        view: DatastoreView<'a>,
+        peer: Option<&str>,
+        reads: &[String],
+    ) -> Result<DatastoreView<'a>> {
+        if !self.checks(peer) {
+            return Ok(view);
+        }
+        let tree = view.snapshot()?;
+        let Some(decider) = self.decider(peer, &[&tree])? else {
+            return Ok(view);
+        };
+        let pruned = if reads.is_empty() {
+            decider.prune(&Value::from(&tree))?
+        } else {
+            let mut pruned = Value::Object(Map::new());
+            for read in reads {
+                decider.prune_read(&tree, read, &mut pruned)?;
+            }
+            pruned
+        };
+        Ok(DatastoreView::Snapshot(Datastore::with_backend(
+            Arc::clone(self.datastores.running().shared_model()),
+            MemoryBackend::new(pruned),
+        )))
+    }
+
+    /// `entry` as `peer` may read it, with or without its patches: changed
+    /// paths and edits of nodes it may not read are left out, and the
+    /// values of the others pruned as a read would prune them.
+    fn readable_entry(
+        &self,
+        entry: &JournalEntry,
+        with_patches: bool,
+        peer: Option<&str>,
+    ) -> Result<Value> {
+        if !self.checks(peer) {
+            return Ok(entry.to_value(with_patches));
+        }
+        // What the entry wrote or removed may be gone from running since,
+        // so rule paths are matched against its patches too.
+        let running = self.datastores.running();
+        let patch_tree = |patch: &[(String, Option<Value>)]| {
+            let mut tree = Datastore::new_in_memory(Arc::clone(running.shared_model()));
+            for (path, value) in patch {
+                if let Some(value) = value {
+                    tree.set_path(path, value.clone())?;
+                }
+            }
+            tree.snapshot()
+        };
+        let trees = [
+            running.snapshot()?,
+            patch_tree(&entry.patch)?,
+            patch_tree(&entry.inverse_patch)?,
+        ];
+        let Some(decider) = self.decider(peer, &[&trees[0], &trees[1], &trees[2]])? else {
+            return Ok(entry.to_value(with_patches));
+        };
+        // Rule paths name entries as the data formats them.
+        let readable = |path: &str, value: Option<&Value>| {
+            let path = running
+                .resolve_xpath(path)
+                .and_then(|(sid, keys)| running.create_xpath(sid, &keys))
+                .unwrap_or_else(|_| path.to_string());
+            decider.prune_at(&path, value.unwrap_or(&Value::Null))
+        };
+        let readable_patch = |patch: &[(String, Option<Value>)]| -> Result<Vec<_>> {
+            let mut kept = Vec::new();
+            for (path, value) in patch {
+                if let Some(pruned) = readable(path, value.as_ref())? {
+                    kept.push((path.clone(), value.as_ref().map(|_| pruned)));
+                }
+            }
+            Ok(kept)
+        };
+
+        let mut changed_paths = Vec::new();
+        for path in &entry.changed_paths {
+            if readable(path, None)?.is_some() {
+                changed_paths.push(path.clone());
+            }
+        }
+        let (patch, inverse_patch) = if with_patches {
+            (
+                readable_patch(&entry.patch)?,
+                readable_patch(&entry.inverse_patch)?,
+            )
+        } else {
+            Default::default()
+        };
+        let entry = JournalEntry {
+            changed_paths,
+            patch,
+            inverse_patch,
+            peer: entry.peer.clone(),
+            ..*entry
+        };
+        Ok(entry.to_value(with_patches))
+    }
+
+    /// The whole operational datastore as `peer` may read it, encoded as
+    /// an Observe notification carries it.
+    pub fn notification_payload(&self, peer: Option<&str>) -> Result<Vec<u8>> {
+        let view = self
+            .datastores
+            .view(DatastoreName::Operational, ContentParam::All)?;
+        self.readable(view, peer, &[])?.get_all_cbor()
+    }
+
+    /// The schema nodes a GET or FETCH reads; empty for the whole tree.
+    fn read_paths(&self, request: &Request) -> Vec<String> {
+        if request.path.is_empty() {
+            if request.method != Method::Fetch || request.payload.is_empty() {
+                return Vec::new();
+            }
+            return self
+                .parse_fetch_request(&request.payload)
+                .map(|identifiers| {
+                    identifiers
+                        .into_iter()
+                        .filter_map(|(sid, _)| self.model().get_identifier(sid).map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+        }
+        PredicatePath::parse(&request.path)
+            .map(|path| vec![path.canonical_path])
+            .unwrap_or_default()
+    }
+
+    /// Answer a GET or FETCH, with an ETag, and with a Max-Age when subtree
+    /// providers computed part of the content.
+    fn serve_read(&self, request: &Request) -> Response {
+        let view = match self.read_view(request) {
+            Ok(view) => view,
+            Err(error) => return Response::from_error(&error, ResponseCode::InternalServerError),
+        };
+        let mut response = match request.method {
+            Method::Get => self.handle_get(request, &view),
+            _ => self.handle_fetch(request, &view),
+        };
+        if response.code.is_success()
+            && let Ok(Some(tag)) = resource_tag(&view, &request.path)
+        {
+            response = response.with_etag(tag);
+        }
+        let operational = request
+            .query
+            .datastore
+            .is_none_or(|name| name == DatastoreName::Operational)
+            && request.query.content != ContentParam::Config;
+        if !operational || !response.code.is_success() {
+            return response;
+        }
+        match self.datastores.provided_max_age(&self.read_paths(request)) {
+            Some(max_age) => {
+                response.with_max_age(u32::try_from(max_age.as_secs()).unwrap_or(u32::MAX))
+            }
+            None => response,
+        }
+    }
+
+    /// The entity-tag of the resource at `path` in the datastore `request`
+    /// edits, running unless it names another, as its peer may read it.
+    /// Reads are tagged from the view they are served from instead.
+    fn entity_tag(&self, request: &Request, path: &str) -> Result<Option<Vec<u8>>> {
+        let name = request.query.datastore.unwrap_or(DatastoreName::Running);
+        let view = self.datastores.view(name, ContentParam::Config)?;
+        let reads = match path {
+            "" => Vec::new(),
+            path => vec![PredicatePath::parse(path)?.canonical_path],
+        };
+        let view = self.readable(view, request.peer.as_deref(), &reads)?;
+        resource_tag(&view, path)
+    }
+
+    /// `response` with the entity-tag of the resource at `path`, if it is a
+    /// success and the resource has one.
+    fn tagged(&self, request: &Request, path: &str, response: Response) -> Response {
+        if !response.code.is_success() {
+            return response;
+        }
+        match self.entity_tag(request, path) {
+            Ok(Some(tag)) => response.with_etag(tag),
+            _ => response,
+        }
+    }
+
+    /// A 4.12 response if the If-Match or If-None-Match conditions of an
+    /// edit or operation do not hold. Operations are conditional on the
+    /// whole datastore, edits on their target resource.
+    fn check_preconditions(&self, request: &Request) -> Option<Response> {
+        if request.if_match.is_empty() && !request.if_none_match {
+            return None;
+        }
+        let path = match request.method {
+            Method::Post => "",
+            _ => request.path.as_str(),
+        };
+        let current = match self.entity_tag(request, path) {
+            Ok(current) => current,
+            Err(error) => return Some(Response::from_error(&error, ResponseCode::BadRequest)),
+        };
+        if preconditions_hold(&request.if_match, request.if_none_match, current.as_deref()) {
+            return None;
+        }
+
+        let resource = match path {
+            "" => format!(
+                "the {} datastore",
+                request.query.datastore.unwrap_or(DatastoreName::Running)
+            ),
+            _ => "the resource".to_string(),
+        };
+        let mut error = match current {
+            Some(_) if request.if_none_match => {
+                DataError::new(ErrorTag::DataExists, format!("{resource} already exists"))
+            }
+            Some(_) => DataError::new(
+                ErrorTag::OperationFailed,
+                format!("{resource} changed since its entity-tag was read"),
+            ),
+            None => DataError::new(ErrorTag::DataMissing, format!("{resource} does not exist")),
+        };
+        if !path.is_empty() {
+            error = error.with_path(path);
+        }
+        Some(Response::data_error(
+            ResponseCode::PreconditionFailed,
+            &error,
+        ))
+    }
+
+    /// The datastore an edit targets: `ds=`, running by default.
+    fn edit_target(request: &Request) -> DatastoreName {
+        request.query.datastore.unwrap_or(DatastoreName::Running)
+    }
+
+    /// Commit the candidate datastore into running.
+    ///
+    /// Transaction participants take part in the commit as in any edit of
+    /// running. With `confirm_timeout` the commit is confirmed-only:
+    /// unless a plain commit follows before the timeout, running rolls back
+    /// to the tree it held before the first unconfirmed commit.
+    pub fn commit(&mut self, confirm_timeout: Option<Duration>) -> Result<()> {
+        let request = Request::new(Method::Post).with_path(COMMIT_PATH);
+        self.commit_from(&request, confirm_timeout)
+    }
+
+    fn commit_from(&mut self, request: &Request, confirm_timeout: Option<Duration>) -> Result<()> {
+        // Committing publishes the candidate, so its lock holds commits back
+        // as well as running's.
+        self.check_lock(DatastoreName::Candidate, request)?;
+        let previous_tree = self.datastores.running().snapshot()?;
+        let candidate_tree = self.datastores.candidate().snapshot()?;
+        self.publish_running(request, &previous_tree, candidate_tree, true)?;
+
+        self.pending_confirm = confirm_timeout.map(|timeout| PendingConfirm {
+            rollback_tree: self
+                .pending_confirm
+                .take()
+                .map_or(previous_tree, |pending| pending.rollback_tree),
+            deadline: Instant::now() + timeout,
+        });
+        Ok(())
+    }
+
+    /// Roll back an outstanding confirmed commit right away.
+    pub fn cancel_commit(&mut self) -> Result<()> {
+        let request = Request::new(Method::Post).with_path(CANCEL_COMMIT_PATH);
+        self.cancel_commit_from(&request)
+    }
+
+    fn cancel_commit_from(&mut self, request: &Request) -> Result<()> {
+        let pending = self.pending_confirm.take().ok_or_else(|| {
+            DataError::new(ErrorTag::InvalidValue, "no confirmed commit is pending")
+                .with_path(CANCEL_COMMIT_PATH)
+        })?;
+        self.roll_back(request, pending)
+    }
+
+    /// Reset the candidate datastore to the contents of running.
+    pub fn discard_changes(&mut self) -> Result<()> {
+        self.datastores
+            .copy(DatastoreName::Running, DatastoreName::Candidate)
+    }
+
+    fn discard_changes_from(&mut self, request: &Request) -> Result<()> {
+        self.check_lock(DatastoreName::Candidate, request)?;
+        self.discard_changes()
+    }
+
+    /// Lock `target` for `owner` until `timeout` elapses,
+    /// [`DEFAULT_LOCK_TIMEOUT`] if `None`. Locking again renews the lock.
+    ///
+    /// While the lock is held, edits of `target` from requests whose peer
+    /// is not `owner` fail with in-use. Requests without a peer come from
+    /// the server itself and are not held back.
+    pub fn lock(
+        &mut self,
+        target: DatastoreName,
+        owner: &str,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        if !target.is_configuration() {
+            return Err(DataError::new(
+                ErrorTag::OperationNotSupported,
+                format!("the {target} datastore cannot be locked"),
+            )
+            .with_path(LOCK_PATH)
+            .into());
+        }
+        if let Some(lock) = self.lock_holder(target)
+            && lock.owner != owner
+        {
+            return Err(locked(target, lock, ErrorTag::LockDenied)
+                .with_path(LOCK_PATH)
+                .into());
+        }
+        self.locks.insert(
+            target,
+            DatastoreLock {
+                owner: owner.to_string(),
+                deadline: Instant::now() + timeout.unwrap_or(DEFAULT_LOCK_TIMEOUT),
+            },
+        );
+        Ok(())
+    }
+
+    /// Release the lock `owner` holds on `target`.
+    pub fn unlock(&mut self, target: DatastoreName, owner: &str) -> Result<()> {
+        match self.lock_holder(target) {
+            Some(lock) if lock.owner == owner => {
+                self.locks.remove(&target);
+                Ok(())
+            }
+            Some(lock) => Err(locked(target, lock, ErrorTag::LockDenied)
+                .with_path(UNLOCK_PATH)
+                .into()),
+            None => Err(DataError::new(
+                ErrorTag::InvalidValue,
+                format!("the {target} datastore is not locked"),
+            )
+            .with_path(UNLOCK_PATH)
+            .into()),
+        }
+    }
+
+    /// The lock held on `target`, unless there is none or it has expired.
+    pub fn lock_holder(&self, target: DatastoreName) -> Option<&DatastoreLock> {
+        self.locks
+            .get(&target)
+            .filter(|lock| !lock.is_expired(Instant::now()))
+    }
+
+    /// Fail with in-use if a peer other than the one of `request` holds
+    /// the lock on `target`.
+    fn check_lock(&self, target: DatastoreName, request: &Request) -> Result<()> {
+        let Some(peer) = request.peer.as_deref() else {
+            return Ok(());
+        };
+        match self.lock_holder(target) {
+            Some(lock) if lock.owner != peer => Err(locked(target, lock, ErrorTag::InUse).into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Serve a lock or unlock POST for the peer of `request`.
+    fn lock_from(&mut self, request: &Request, input: Option<&Value>) -> Result<()> {
+        let (target, timeout) = parse_lock_input(&request.path, input)?;
+        let owner = request.peer.as_deref().ok_or_else(|| {
+            DataError::new(
+                ErrorTag::OperationNotSupported,
+                "locking needs a client identity from the transport",
+            )
+            .with_path(request.path.as_str())
+        })?;
+        if request.path == LOCK_PATH {
+            self.lock(target, owner, timeout)
+        } else {
+            self.unlock(target, owner)
+        }
+    }
+
+    /// The confirmed commit awaiting confirmation, if any.
+    pub fn pending_confirm(&self) -> Option<&PendingConfirm> {
+        self.pending_confirm.as_ref()
+    }
+
+    /// Roll running back if a confirmed commit's timeout has elapsed.
+    ///
+    /// Returns whether a rollback happened. Transports call this
+    /// periodically; `handle` also checks before serving each request.
+    pub fn expire_confirmed_commit(&mut self) -> Result<bool> {
+        match self.pending_confirm.take() {
+            Some(pending) if pending.is_expired(Instant::now()) => {
+                let request = Request::new(Method::Post).with_path(CANCEL_COMMIT_PATH);
+                self.roll_back(&request, pending)?;
+                Ok(true)
+            }
+            pending => {
+                self.pending_confirm = pending;
+                Ok(false)
+            }
+        }
+    }
+
+    fn roll_back(&mut self, request: &Request, pending: PendingConfirm) -> Result<()> {
+        let previous_tree = self.datastores.running().snapshot()?;
+        let rollback_tree = pending.rollback_tree.clone();
+        // A rollback cannot be vetoed; keep the pending commit only if the
+        // backend failed to publish, so the next check retries it.
+        self.publish_running(request, &previous_tree, rollback_tree, false)
+            .inspect_err(|_| self.pending_confirm = Some(pending))
+    }
+
+    /// The journal of transactions committed to running.
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    pub fn journal_mut(&mut self) -> &mut Journal {
+        &mut self.journal
+    }
+
+    /// Put running back to the tree it held at journal `revision`.
+    ///
+    /// The rollback is itself a transaction: participants may veto it, and
+    /// it is journaled as a new revision so it can be undone in turn.
+    pub fn rollback_to(&mut self, revision: u64) -> Result<()> {
+        let request = Request::new(Method::Post).with_path(ROLLBACK_PATH);
+        self.rollback_to_from(&request, revision)
+    }
+
+    fn rollback_to_from(&mut self, request: &Request, revision: u64) -> Result<()> {
+        let patch = self.journal.rollback_patch(revision)?;
+        let previous_tree = self.datastores.running().snapshot()?;
+        let mut target = Datastore::from_snapshot(
+            Arc::clone(self.datastores.running().shared_model()),
+            previous_tree.clone(),
+        );
+        for (path, value) in patch {
+            match value {
+                Some(value) => target.set_path(&path, value)?,
+                None => match target.delete_path(&path) {
+                    Ok(_) => {}
+                    Err(error) if error.error_tag() == ErrorTag::DataMissing => {}
+                    Err(error) => return Err(error),
+                },
+            }
+        }
+        self.publish_running(request, &previous_tree, target.snapshot()?, true)
+    }
+
+    /// Journal a transaction on `target` if it is running.
+    fn record_revision(
+        &mut self,
+        target: DatastoreName,
+        request: &Request,
+        changed_paths: Vec<String>,
+        patch: Vec<(String, Option<Value>)>,
+        inverse_patch: Vec<(String, Option<Value>)>,
+    ) {
+        if target == DatastoreName::Running {
+            self.journal
+                .record(request.peer.clone(), changed_paths, patch, inverse_patch);
+        }
+    }
+
+    /// Replace running with `next` as a transaction, notifying participants
+    /// and observers.
+    fn publish_running(
+        &mut self,
+        request: &Request,
+        previous_tree: &SharedTree,
+        next: SharedTree,
+        validate: bool,
+    ) -> Result<()> {
+        self.check_lock(DatastoreName::Running, request)?;
+        let mut changed_paths = changed_top_level_paths(previous_tree, &next);
+        let mut candidate =
+            Datastore::from_snapshot(Arc::clone(self.datastores.running().shared_model()), next);
+        self.run_transaction(
+            DatastoreName::Running,
+            request,
+            previous_tree,
+            &mut candidate,
+            &mut changed_paths,
+            Publication::Replace { validate },
+        )
+        .map_err(TransactionFailure::into_error)?;
+
+        let next = candidate.snapshot()?;
+        let node_value = |tree: &SharedTree, path: &str| tree.get(&path[1..]).map(Value::from);
+        let patch = changed_paths
+            .iter()
+            .map(|path| (path.clone(), node_value(&next, path)))
+            .collect();
+        let inverse_patch = changed_paths
+            .iter()
+            .map(|path| (path.clone(), node_value(previous_tree, path)))
+            .collect();
+        self.record_revision(
+            DatastoreName::Running,
+            request,
+            changed_paths,
+            patch,
+            inverse_patch,
+        );
+        Ok(())
+    }
+
+    /// Take `candidate` through the participant phases and publish it to
+    /// `target` as `publication` says; see [`TransactionParticipant`]. Only
+    /// running has participants.
+    ///
+    /// Top-level nodes that preparation changed are appended to
+    /// `changed_paths`.
+    fn run_transaction(
+        &mut self,
+        target: DatastoreName,
+        request: &Request,
+        previous_tree: &SharedTree,
+        candidate: &mut Datastore,
+        changed_paths: &mut Vec<String>,
+        mut publication: Publication<'_>,
+    ) -> std::result::Result<(), TransactionFailure> {
+        let participants: &[Box<dyn TransactionParticipant>] = if target == DatastoreName::Running {
+            &self.transaction_participants
+        } else {
+            &[]
+        };
+        let validate = !matches!(publication, Publication::Replace { validate: false });
+
+        if validate && !participants.is_empty() {
+            let edited = candidate
+                .snapshot()
+                .map_err(TransactionFailure::Unpublished)?;
+            for (index, participant) in participants.iter().enumerate() {
+                let snapshot = candidate
+                    .snapshot()
+                    .map_err(TransactionFailure::Unpublished)?;
+                let context =
+                    TransactionContext::new(previous_tree, &snapshot, changed_paths, request);
+                if let Err(error) = participant.prepare(&context, candidate) {
+                    abort_participants(&participants[..index], None, &context);
+                    return Err(TransactionFailure::Rejected(error));
+                }
+            }
+            let prepared = candidate
+                .snapshot()
+                .map_err(TransactionFailure::Unpublished)?;
+            for path in changed_top_level_paths(&edited, &prepared) {
+                if let Publication::Edits(edits) = &mut publication {
+                    edits.push((path.clone(), prepared.get(&path[1..]).map(Value::from)));
+                }
+                if !changed_paths.contains(&path) {
+                    changed_paths.push(path);
+                }
+            }
+        }
+
+        let next = candidate
+            .snapshot()
+            .map_err(TransactionFailure::Unpublished)?;
+        let context = TransactionContext::new(previous_tree, &next, changed_paths, request);
+        if validate {
+            for (index, participant) in participants.iter().enumerate() {
+                if let Err(error) = participant.pre_commit(&context) {
+                    abort_participants(participants, Some(index), &context);
+                    return Err(TransactionFailure::Rejected(error));
+                }
+            }
+        }
+        for (index, participant) in participants.iter().enumerate() {
+            if let Err(error) = participant.commit(&context) {
+                abort_participants(participants, Some(index), &context);
+                return Err(TransactionFailure::Rejected(error));
+            }
+        }
+
+        if let Err(error) = self
+            .datastores
+            .configuration_mut(target)
+            .and_then(|datastore| match &publication {
+                Publication::Replace { .. } => datastore.replace_snapshot(next.clone()),
+                Publication::Edits(edits) => datastore.publish_edits(edits, previous_tree),
+            })
+        {
+            abort_participants(participants, None, &context);
+            return Err(TransactionFailure::Unpublished(error));
+        }
+
+        for path in changed_paths.iter() {
+            self.mark_edited(target, path);
+        }
+        if target == DatastoreName::Running {
+            for participant in &self.transaction_participants {
+                participant.post_commit(&context);
+            }
+        }
+        Ok(())
     }
 
     /// Register an observer (token → observed resources).
@@ -92,6 +849,10 @@ impl RequestHandler {
         self.observers.remove(token);
     }
 
+    pub fn has_observers(&self) -> bool {
+        !self.observers.is_empty()
+    }
+
     /// Mark a resource path as changed, so registered observers will be
     /// notified on the next poll.  Converts identifier paths to SID strings
     /// so they match the SIDs observers registered via FETCH.
@@ -109,7 +870,7 @@ impl RequestHandler {
         let mut found_any = false;
         for end in 1..=segments.len() {
             let candidate = format!("/{}", segments[..end].join("/"));
-            if let Some(sid) = self.datastore.model().get_sid(&candidate) {
+            if let Some(sid) = self.model().get_sid(&candidate) {
                 self.dirty_resources.insert(sid.to_string());
                 found_any = true;
             }
@@ -120,6 +881,35 @@ impl RequestHandler {
         }
     }
 
+    /// Recompute the expired provided subtrees that observers watch and
+    /// mark those whose value changed, so the next poll notifies them.
+    ///
+    /// Transports call this before collecting notifications.
+    pub fn refresh_provided_state(&mut self) -> Result<()> {
+        let watched: Vec<String> = self
+            .observers
+            .values()
+            .flat_map(|observer| &observer.resources)
+            .filter_map(|resource| resource.parse().ok())
+            .filter_map(|sid| self.model().get_identifier(sid).map(str::to_string))
+            .collect();
+        if watched.is_empty() {
+            return Ok(());
+        }
+        for path in self.datastores.refresh_provided(&watched)? {
+            self.mark_changed(&path);
+        }
+        Ok(())
+    }
+
+    /// Record an edit of `target`. Observers watch the operational view,
+    /// which only running configuration feeds into.
+    fn mark_edited(&mut self, target: DatastoreName, path: &str) {
+        if target == DatastoreName::Running {
+            self.mark_changed(path);
+        }
+    }
+
     /// Collect pending notifications for a given observer token.
     ///
     /// Returns a list of (resource, sequence) pairs that need to be sent.
@@ -160,11 +950,16 @@ impl RequestHandler {
     }
 
     pub fn handle(&mut self, request: &Request) -> Response {
-        if request.raw_content_format.is_some() && request.content_format.is_none() {
-            return Response::error(
-                ResponseCode::UnsupportedContentFormat,
-                "unknown content format",
-            );
+        // A failed rollback stays pending and is retried on the next call.
+        let _ = self.expire_confirmed_commit();
+
+        if let Some(response) = self.handle_read(request) {
+            return response;
+        }
+        if let Some(response) =
+            unknown_content_format(request).or_else(|| unknown_datastore(request))
+        {
+            return response;
         }
 
         // Streaming interface (`/s`) only accepts FETCH+Observe.
@@ -172,27 +967,81 @@ impl RequestHandler {
             return self.handle_streaming(request);
         }
 
+        if let Some(response) = self.check_preconditions(request) {
+            return response;
+        }
+
         match request.method {
-            Method::Get => self.handle_get(request),
-            Method::Fetch => self.handle_fetch(request),
-            Method::IPatch => self.handle_ipatch(request),
+            Method::Get | Method::Fetch => self.serve_read(request),
+            Method::IPatch => {
+                let response = self.handle_ipatch(request);
+                self.tagged(request, &request.path, response)
+            }
             Method::Post => self.handle_post(request),
             Method::Delete => self.handle_delete(request),
         }
     }
 
+    /// Serve `request` if it only reads, returning `None` for anything that
+    /// may change state: edits, operations and Observe registrations.
+    ///
+    /// Unlike [`RequestHandler::handle`] this does not roll back an expired
+    /// confirmed commit first; callers check [`RequestHandler::pending_confirm`].
+    pub fn handle_read(&self, request: &Request) -> Option<Response> {
+        if !request.is_read() {
+            return None;
+        }
+        if let Some(response) =
+            unknown_content_format(request).or_else(|| unknown_datastore(request))
+        {
+            return Some(response);
+        }
+        Some(self.serve_read(request))
+    }
+
+    /// Run `update` on the operational state store, then mark the paths it
+    /// wrote as changed so observers are notified.
+    ///
+    /// Written paths come from the state store's change log; a store
+    /// without one, or one that overflowed, marks every top-level node.
+    pub fn update_state<R>(&mut self, update: impl FnOnce(&mut Datastore) -> R) -> R {
+        let state = self.datastores.state_mut();
+        let since = state.last_change();
+        let result = update(state);
+
+        let state = self.datastores.state();
+        let logged: Option<Vec<String>> = since
+            .and_then(|since| state.changes_since(since))
+            .map(|changes| changes.map(|change| change.path.clone()).collect());
+        let changed = match logged {
+            Some(paths) if !paths.iter().any(|path| path == "/") => paths,
+            _ => match state.snapshot() {
+                Ok(tree) => tree.members().map(|(key, _)| format!("/{key}")).collect(),
+                // A store that can't be read back may have changed anywhere.
+                Err(_) => vec!["/".to_string()],
+            },
+        };
+        for path in &changed {
+            self.mark_changed(path);
+        }
+        result
+    }
+
     /// Handle a request on the streaming interface (`/s`).
     ///
     /// Only FETCH is permitted; observe is optional but typical for `/s`.
     fn handle_streaming(&mut self, request: &Request) -> Response {
         if request.method != Method::Fetch {
-            return Response::error(
+            return Response::data_error(
                 ResponseCode::MethodNotAllowed,
-                &format!("{} not allowed on streaming interface", request.method),
+                &DataError::new(
+                    ErrorTag::OperationNotSupported,
+                    format!("{} not allowed on streaming interface", request.method),
+                ),
             );
         }
 
-        let mut response = self.handle_fetch(request);
+        let mut response = self.serve_read(request);
 
         // Register for observe if the client requested it (Observe=0).
         // Deregister on Observe=1 (client wants to stop).
@@ -227,72 +1076,67 @@ impl RequestHandler {
         response
     }
 
-    fn handle_get(&self, request: &Request) -> Response {
+    fn handle_get(&self, request: &Request, datastore: &Datastore) -> Response {
         if request.path.is_empty() {
-            return match self.datastore.get_all_cbor() {
+            return match datastore.get_all_cbor() {
                 Ok(cbor) => {
                     let filtered = apply_query_filters(&cbor, &request.query);
                     Response::content(filtered, ContentFormat::YangDataCbor)
                 }
-                Err(error) => {
-                    Response::error(ResponseCode::InternalServerError, &error.to_string())
-                }
+                Err(error) => Response::from_error(&error, ResponseCode::InternalServerError),
             };
         }
 
-        match self.datastore.get_path(&request.path) {
+        match datastore.get_path(&request.path) {
             Ok(Some(value)) => {
                 let parsed = match PredicatePath::parse(&request.path) {
                     Ok(p) => p,
                     Err(error) => {
-                        return Response::error(ResponseCode::BadRequest, &error.to_string());
+                        return Response::from_error(&error, ResponseCode::BadRequest);
                     }
                 };
-                let sid_val = match self
-                    .datastore
+                let sid_val = match datastore
                     .model()
                     .identifier_value_to_sid_value_at_path(value, &parsed.canonical_path)
                 {
                     Ok(v) => v,
                     Err(error) => {
-                        return Response::error(
-                            ResponseCode::InternalServerError,
-                            &error.to_string(),
-                        );
+                        return Response::from_error(&error, ResponseCode::InternalServerError);
                     }
                 };
-                match encode_json_value(self.datastore.model(), &sid_val) {
+                match encode_json_value(self.model(), &sid_val) {
                     Ok(payload) => {
                         let filtered = apply_query_filters(&payload, &request.query);
                         Response::content(filtered, ContentFormat::YangDataCbor)
                     }
-                    Err(error) => {
-                        Response::error(ResponseCode::InternalServerError, &error.to_string())
-                    }
+                    Err(error) => Response::from_error(&error, ResponseCode::InternalServerError),
                 }
             }
             Ok(None) => Response::not_found(&request.path),
-            Err(error) => Response::error(ResponseCode::BadRequest, &error.to_string()),
+            Err(error) => Response::from_error(&error, ResponseCode::BadRequest),
         }
     }
 
-    fn handle_fetch(&self, request: &Request) -> Response {
+    fn handle_fetch(&self, request: &Request, datastore: &Datastore) -> Response {
         if !request.path.is_empty() {
-            return self.handle_get(request);
+            return self.handle_get(request, datastore);
         }
 
         if let Some(format) = request.content_format
             && format != ContentFormat::YangIdentifiersCbor
             && format != ContentFormat::YangDataCbor
         {
-            return Response::error(
+            return Response::data_error(
                 ResponseCode::UnsupportedContentFormat,
-                "expected yang-identifiers+cbor",
+                &DataError::new(
+                    ErrorTag::OperationNotSupported,
+                    "expected yang-identifiers+cbor",
+                ),
             );
         }
 
         if request.payload.is_empty() {
-            return self.handle_get(request);
+            return self.handle_get(request, datastore);
         }
 
         match self.parse_fetch_request(&request.payload) {
@@ -300,193 +1144,371 @@ impl RequestHandler {
                 let mut instances = Vec::new();
                 for (sid, key_values) in identifiers {
                     let fetched = if key_values.is_empty() {
-                        self.datastore.fetch_projected_instances(&[sid])
+                        datastore.fetch_projected_instances(&[sid])
                     } else {
-                        self.datastore.fetch_instances_for_sid(sid, &key_values)
+                        datastore.fetch_instances_for_sid(sid, &key_values)
                     };
                     match fetched {
                         Ok(mut fetched) => instances.append(&mut fetched),
                         Err(error) => {
-                            let code = match &error {
+                            let fallback = match &error {
                                 CoreconfError::IdentifierNotFound(_)
                                 | CoreconfError::SidNotFound(_)
                                 | CoreconfError::ValidationError(_) => ResponseCode::BadRequest,
                                 _ => ResponseCode::InternalServerError,
                             };
-                            return Response::error(code, &error.to_string());
+                            return Response::from_error(&error, fallback);
                         }
                     }
                 }
 
-                match self.datastore.encode_instances(&instances) {
+                match datastore.encode_instances(&instances) {
                     Ok(payload) => Response::content(payload, ContentFormat::YangDataCbor),
-                    Err(error) => {
-                        Response::error(ResponseCode::InternalServerError, &error.to_string())
-                    }
+                    Err(error) => Response::from_error(&error, ResponseCode::InternalServerError),
                 }
             }
-            Err(error) => Response::error(ResponseCode::BadRequest, &error.to_string()),
+            Err(error) => Response::from_error(&error, ResponseCode::BadRequest),
         }
     }
 
     fn handle_delete(&mut self, request: &Request) -> Response {
         if request.path.is_empty() {
-            return Response::error(
+            return Response::data_error(
                 ResponseCode::MethodNotAllowed,
-                "DELETE requires a target resource path",
+                &DataError::new(
+                    ErrorTag::OperationNotSupported,
+                    "DELETE requires a target resource path",
+                ),
             );
         }
 
-        match self.datastore.delete_path(&request.path) {
-            Ok(true) => {
-                self.mark_changed(&request.path);
-                Response::changed()
-            }
-            Ok(false) => Response::not_found(&request.path),
-            Err(CoreconfError::ValidationError(message))
-                if message.starts_with("unused predicates in path") =>
-            {
-                Response::not_found(&request.path)
+        let path = request.path.as_str();
+        let deleted = self.transact(request, |candidate, log| {
+            log.apply(candidate, path, |candidate| {
+                if candidate.delete_path(path)? {
+                    return Ok(());
+                }
+                Err(
+                    DataError::new(ErrorTag::DataMissing, format!("Resource not found: {path}"))
+                        .with_path(path)
+                        .into(),
+                )
+            })
+        });
+        match deleted {
+            Ok(()) => Response::changed(),
+            Err(TransactionFailure::Rejected(error)) => {
+                Response::from_error(&error, ResponseCode::BadRequest)
             }
-            Err(error) => Response::error(ResponseCode::BadRequest, &error.to_string()),
+            Err(failure) => failure.into_response(),
         }
     }
 
     fn handle_ipatch(&mut self, request: &Request) -> Response {
+        if request.content_format == Some(ContentFormat::YangPatchCbor) {
+            return self.handle_yang_patch(request);
+        }
         if !request.path.is_empty() {
             if request.content_format != Some(ContentFormat::YangDataCbor) {
-                return Response::error(
+                return Response::data_error(
                     ResponseCode::UnsupportedContentFormat,
-                    "expected yang-data+cbor",
+                    &DataError::new(ErrorTag::OperationNotSupported, "expected yang-data+cbor"),
                 );
             }
 
             let parsed = match PredicatePath::parse(&request.path) {
                 Ok(parsed) => parsed,
-                Err(error) => return Response::error(ResponseCode::BadRequest, &error.to_string()),
+                Err(error) => return Response::from_error(&error, ResponseCode::BadRequest),
             };
 
-            return match decode_json_value(&request.payload)
-                .and_then(|value| {
-                    self.datastore
-                        .model()
-                        .sid_value_to_identifier_value_at_path(value, &parsed.canonical_path)
-                })
-                .and_then(|value| self.datastore.set_path(&request.path, value))
-            {
-                Ok(()) => {
-                    self.mark_changed(&request.path);
-                    Response::changed()
-                }
-                Err(error) => Response::error(ResponseCode::Conflict, &error.to_string()),
+            let position = match request.query.position() {
+                Ok(position) => position,
+                Err(error) => return Response::from_error(&error, ResponseCode::BadRequest),
+            };
+            if let Some(position) = position {
+                return self.handle_positioned_write(request, &parsed, position);
+            }
+
+            let value = match decode_json_value(&request.payload).and_then(|value| {
+                self.model()
+                    .sid_value_to_identifier_value_at_path(value, &parsed.canonical_path)
+            }) {
+                Ok(value) => value,
+                Err(error) => return Response::from_error(&error, ResponseCode::Conflict),
             };
+            let path = request.path.as_str();
+            return self.edit_candidate(request, |candidate, log| {
+                log.apply(candidate, path, |candidate| candidate.set_path(path, value))
+            });
         }
 
         if let Some(format) = request.content_format
             && format != ContentFormat::YangInstancesCborSeq
             && format != ContentFormat::YangDataCbor
         {
-            return Response::error(
+            return Response::data_error(
                 ResponseCode::UnsupportedContentFormat,
-                "expected yang-instances+cbor-seq",
+                &DataError::new(
+                    ErrorTag::OperationNotSupported,
+                    "expected yang-instances+cbor-seq",
+                ),
             );
         }
 
-        let instances = match decode_instances_with_model(self.datastore.model(), &request.payload)
-        {
+        let instances = match decode_instances_with_model(self.model(), &request.payload) {
             Ok(instances) => instances,
-            Err(error) => return Response::error(ResponseCode::BadRequest, &error.to_string()),
+            Err(error) => return Response::from_error(&error, ResponseCode::BadRequest),
         };
         if instances.is_empty() {
-            return Response::error(ResponseCode::BadRequest, "iPATCH contained no operations");
+            return Response::data_error(
+                ResponseCode::BadRequest,
+                &DataError::new(ErrorTag::InvalidValue, "iPATCH contained no operations"),
+            );
         }
 
-        // Root iPATCH is assembled against a detached datastore.  Nothing in
-        // this phase can mutate the live backend or observer state.
-        let previous_tree = self.datastore.get_all();
-        let mut candidate = Datastore::with_backend(
-            self.datastore.model().clone(),
-            MemoryBackend::new(previous_tree.clone()),
-        );
-        let mut changed_paths = Vec::new();
-        let mut changed_path_set = HashSet::new();
-        let mut applied = 0usize;
+        self.edit_candidate(request, |candidate, log| {
+            apply_instances(candidate, log, instances)
+        })
+    }
 
-        for instance in instances {
-            let Some(sid) = instance.path.absolute_sid() else {
-                continue;
-            };
-            let mut keys = Vec::new();
-            for component in &instance.path.components {
-                if let coreconf_model::instance_id::PathComponent::KeyValue(val) = component {
-                    keys.push(val.clone());
-                }
-            }
-            let xpath = match candidate.create_xpath(sid, &keys) {
-                Ok(xp) => xp,
-                Err(error) => {
-                    return Response::error(ResponseCode::Conflict, &error.to_string());
-                }
-            };
-            let parsed_xpath = match PredicatePath::parse(&xpath) {
-                Ok(p) => p,
-                Err(error) => {
-                    return Response::error(ResponseCode::Conflict, &error.to_string());
+    /// Write the list entry at the request path and place it at `position`
+    /// in its `ordered-by user` list, creating the entry or moving an
+    /// existing one.
+    fn handle_positioned_write(
+        &mut self,
+        request: &Request,
+        parsed: &PredicatePath,
+        position: Position,
+    ) -> Response {
+        let Some(list) = entry_list_path(&request.path) else {
+            return Response::data_error(
+                ResponseCode::BadRequest,
+                &DataError::new(ErrorTag::InvalidValue, "insert applies to list entries")
+                    .with_path(request.path.as_str()),
+            );
+        };
+        let value = match decode_json_value(&request.payload).and_then(|value| {
+            self.model()
+                .sid_value_to_identifier_value_at_path(value, &parsed.canonical_path)
+        }) {
+            Ok(value) => value,
+            Err(error) => return Response::from_error(&error, ResponseCode::BadRequest),
+        };
+
+        let path = request.path.as_str();
+        self.edit_candidate(request, |candidate, log| {
+            log.apply(candidate, list, |candidate| {
+                if candidate.get_path(path)?.is_none() {
+                    return candidate.insert_entry(path, value, &position);
                 }
+                candidate.set_path(path, value)?;
+                candidate.move_entry(path, &position)
+            })
+        })
+    }
+
+    /// Apply a YANG Patch to the target datastore as one transaction. Edit
+    /// targets and points are relative to the request path.
+    fn handle_yang_patch(&mut self, request: &Request) -> Response {
+        let mut patch = match YangPatch::from_cbor(&request.payload) {
+            Ok(patch) => patch,
+            Err(error) => return Response::from_error(&error, ResponseCode::BadRequest),
+        };
+        let resource = request.path.trim_end_matches('/');
+        let resolve = |path: &mut String| {
+            *path = match path.as_str() {
+                "/" if !resource.is_empty() => resource.to_string(),
+                relative => format!("{resource}{relative}"),
             };
-            let converted_value = match instance.value {
-                Some(value) => match candidate
-                    .model()
-                    .sid_value_to_identifier_value_at_path(value, &parsed_xpath.canonical_path)
-                {
-                    Ok(v) => Some(v),
-                    Err(error) => {
-                        return Response::error(ResponseCode::Conflict, &error.to_string());
-                    }
-                },
-                None => None,
-            };
-            let result = match converted_value {
-                Some(value) => candidate.set_path(&xpath, value),
-                None => candidate.delete_path(&xpath).map(|_| ()),
-            };
-            if let Err(error) = result {
-                return Response::error(ResponseCode::Conflict, &error.to_string());
-            }
-            if changed_path_set.insert(xpath.clone()) {
-                changed_paths.push(xpath);
+        };
+        for edit in &mut patch.edits {
+            resolve(&mut edit.target);
+            if let Some(Position::Before(point) | Position::After(point)) = &mut edit.position {
+                resolve(point);
             }
-            applied += 1;
         }
 
-        if applied == 0 {
-            return Response::error(ResponseCode::BadRequest, "iPATCH contained no operations");
-        }
-
-        let candidate_tree = candidate.get_all();
-        let context =
-            TransactionContext::new(&previous_tree, &candidate_tree, &changed_paths, request);
-        for participant in &self.transaction_participants {
-            if let Err(error) = participant.pre_commit(&context) {
-                return Response::error(ResponseCode::Conflict, &error.to_string());
+        self.edit_candidate(request, |candidate, log| {
+            for edit in &patch.edits {
+                log.apply(candidate, changed_path(edit), |candidate| {
+                    apply_edit(candidate, edit)
+                })?;
             }
-        }
+            Ok(())
+        })
+    }
 
-        if let Err(error) = self.datastore.replace_tree(candidate_tree.clone()) {
-            return Response::error(ResponseCode::InternalServerError, &error.to_string());
+    /// Apply a yang-instances+cbor-seq payload to running as one
+    /// transaction, as a root iPATCH does, so participants and observers
+    /// see it.
+    pub fn apply_instance_seq(&mut self, cbor: &[u8]) -> Result<()> {
+        let request = Request::new(Method::IPatch)
+            .with_payload(cbor.to_vec(), ContentFormat::YangInstancesCborSeq);
+        let instances = decode_instances_with_model(self.model(), cbor)?;
+        self.transact(&request, |candidate, log| {
+            apply_instances(candidate, log, instances)
+        })
+        .map_err(TransactionFailure::into_error)
+    }
+
+    /// Run `edit` against a detached candidate of the request's target
+    /// datastore and, if it and every transaction participant succeed,
+    /// publish the candidate. Nothing reaches the live backend or observers
+    /// before that.
+    fn edit_candidate(
+        &mut self,
+        request: &Request,
+        edit: impl FnOnce(&mut Datastore, &mut EditLog) -> Result<()>,
+    ) -> Response {
+        match self.transact(request, edit) {
+            Ok(()) => Response::changed(),
+            Err(failure) => failure.into_response(),
         }
+    }
 
-        for path in &changed_paths {
-            self.mark_changed(path);
+    fn transact(
+        &mut self,
+        request: &Request,
+        edit: impl FnOnce(&mut Datastore, &mut EditLog) -> Result<()>,
+    ) -> std::result::Result<(), TransactionFailure> {
+        let target = Self::edit_target(request);
+        self.check_lock(target, request)
+            .map_err(TransactionFailure::Rejected)?;
+        let previous_tree = self
+            .datastores
+            .configuration_mut(target)
+            .map_err(TransactionFailure::Rejected)?
+            .snapshot()
+            .map_err(TransactionFailure::Unpublished)?;
+        let mut candidate = Datastore::from_snapshot(
+            Arc::clone(self.datastores.running().shared_model()),
+            previous_tree.clone(),
+        );
+        let mut log = EditLog::default();
+        edit(&mut candidate, &mut log).map_err(TransactionFailure::Rejected)?;
+        self.check_edits(request, &previous_tree, &candidate, &log.changed_paths)
+            .map_err(TransactionFailure::Rejected)?;
+        let EditLog {
+            mut changed_paths,
+            mut patch,
+            inverse_patch,
+            ..
+        } = log;
+
+        let edited = changed_paths.len();
+        self.run_transaction(
+            target,
+            request,
+            &previous_tree,
+            &mut candidate,
+            &mut changed_paths,
+            Publication::Edits(&mut patch),
+        )?;
+
+        // Nodes changed in preparation are journaled whole, and undone
+        // before the edits.
+        let mut inverse: Vec<_> = changed_paths[edited..]
+            .iter()
+            .map(|path| (path.clone(), previous_tree.get(&path[1..]).map(Value::from)))
+            .collect();
+        inverse.extend(inverse_patch.into_iter().rev());
+        self.record_revision(target, request, changed_paths, patch, inverse);
+        Ok(())
+    }
+
+    /// Check that the peer of `request` may make the edits that turned
+    /// `previous_tree` into `candidate`. Only the nodes at `changed_paths`
+    /// are compared.
+    fn check_edits(
+        &self,
+        request: &Request,
+        previous_tree: &SharedTree,
+        candidate: &Datastore,
+        changed_paths: &[String],
+    ) -> Result<()> {
+        if !self.checks(request.peer.as_deref()) {
+            return Ok(());
         }
-        for participant in &self.transaction_participants {
-            participant.post_commit(&context);
+        let after = candidate.snapshot()?;
+        let Some(decider) = self.decider(request.peer.as_deref(), &[previous_tree, &after])? else {
+            return Ok(());
+        };
+        let before = Datastore::from_snapshot(
+            Arc::clone(self.datastores.running().shared_model()),
+            previous_tree.clone(),
+        );
+        // Rule paths name entries as the data formats them, so changed
+        // paths are formatted the same way.
+        let paths: Vec<String> = changed_paths
+            .iter()
+            .map(|path| {
+                candidate
+                    .resolve_xpath(path)
+                    .and_then(|(sid, keys)| candidate.create_xpath(sid, &keys))
+                    .unwrap_or_else(|_| path.clone())
+            })
+            .collect();
+        let mut diff = Diff::default();
+        for path in &paths {
+            if paths.iter().any(|other| within(path, other)) {
+                continue;
+            }
+            if path.trim_end_matches('/').is_empty() {
+                diff.edits.extend(
+                    diff_trees(
+                        self.model(),
+                        &Value::from(previous_tree),
+                        &candidate.get_all()?,
+                    )?
+                    .edits,
+                );
+                continue;
+            }
+            let schema = PredicatePath::parse(path)?.canonical_path;
+            diff.edits.extend(diff_at(
+                self.model(),
+                &schema,
+                path,
+                before.get_node(path)?.as_ref(),
+                candidate.get_node(path)?.as_ref(),
+            )?);
         }
+        decider.check_edits(&diff)
+    }
 
-        Response::changed()
+    /// Check that the peer of `request` may invoke the operation at `path`.
+    /// Running is only read when a rule path could select it.
+    fn check_exec(&self, request: &Request, path: &str) -> Result<()> {
+        let peer = request.peer.as_deref();
+        if !self.checks(peer) {
+            return Ok(());
+        }
+        let reads_running = self
+            .access_control
+            .as_ref()
+            .zip(peer)
+            .is_some_and(|(access, peer)| access.matches_data(peer, AccessOperation::Exec));
+        let running = if reads_running {
+            self.datastores.running().snapshot()?
+        } else {
+            SharedTree::default()
+        };
+        match self.decider(peer, &[&running])? {
+            Some(decider) => decider.check_exec(path),
+            None => Ok(()),
+        }
     }
 
     fn handle_post(&mut self, request: &Request) -> Response {
+        if !request.path.is_empty()
+            && let Err(error) = self.check_exec(request, &request.path)
+        {
+            return Response::from_error(&error, ResponseCode::BadRequest);
+        }
+        if let Some(response) = self.handle_datastore_operation(request) {
+            return response;
+        }
+
         let invocation = if !request.path.is_empty() {
             self.invoke_operation_path(
                 &request.path,
@@ -498,23 +1520,76 @@ impl RequestHandler {
         };
 
         match invocation {
-            Ok(Some(value)) => match encode_json_value(self.datastore.model(), &value) {
+            Ok(Some(value)) => match encode_json_value(self.model(), &value) {
                 Ok(payload) => Response::content(payload, ContentFormat::YangDataCbor),
-                Err(error) => {
-                    Response::error(ResponseCode::InternalServerError, &error.to_string())
-                }
+                Err(error) => Response::from_error(&error, ResponseCode::InternalServerError),
             },
             Ok(None) => Response::changed(),
             Err(error) => {
-                if matches!(error, CoreconfError::ResourceNotFound(_)) {
-                    Response::not_found(&error.to_string())
+                let fallback = if matches!(error, CoreconfError::ResourceNotFound(_)) {
+                    ResponseCode::NotFound
                 } else {
-                    Response::error(ResponseCode::BadRequest, &error.to_string())
-                }
+                    ResponseCode::BadRequest
+                };
+                Response::from_error(&error, fallback)
             }
         }
     }
 
+    /// Serve the `ietf-netconf` commit and lock operations and the journal
+    /// operations, or `None` for any other POST target.
+    ///
+    /// Inputs and outputs are name-keyed, as these modules have no SIDs in
+    /// the loaded model.
+    fn handle_datastore_operation(&mut self, request: &Request) -> Option<Response> {
+        let input = || decode_operation_input(&request.payload);
+        let result = match request.path.as_str() {
+            COMMIT_PATH => input()
+                .and_then(|input| parse_commit_input(input.as_ref()))
+                .and_then(|timeout| self.commit_from(request, timeout))
+                .map(|()| None),
+            CANCEL_COMMIT_PATH => self.cancel_commit_from(request).map(|()| None),
+            DISCARD_CHANGES_PATH => self.discard_changes_from(request).map(|()| None),
+            LOCK_PATH | UNLOCK_PATH => input()
+                .and_then(|input| self.lock_from(request, input.as_ref()))
+                .map(|()| None),
+            REVISIONS_PATH => self
+                .journal
+                .entries()
+                .rev()
+                .map(|entry| self.readable_entry(entry, false, request.peer.as_deref()))
+                .collect::<Result<_>>()
+                .map(|entries| Some(Value::Array(entries))),
+            REVISION_PATH => input()
+                .and_then(|input| parse_revision_input(input.as_ref()))
+                .and_then(|revision| {
+                    self.journal.entry(revision).ok_or_else(|| {
+                        DataError::new(
+                            ErrorTag::DataMissing,
+                            format!("revision {revision} is not in the journal"),
+                        )
+                        .with_path(REVISION_PATH)
+                        .into()
+                    })
+                })
+                .and_then(|entry| self.readable_entry(entry, true, request.peer.as_deref()))
+                .map(Some),
+            ROLLBACK_PATH => input()
+                .and_then(|input| parse_revision_input(input.as_ref()))
+                .and_then(|revision| self.rollback_to_from(request, revision))
+                .map(|()| None),
+            _ => return None,
+        };
+        Some(match result {
+            Ok(None) => Response::changed(),
+            Ok(Some(output)) => match encode_name_keyed(&output) {
+                Ok(payload) => Response::content(payload, ContentFormat::YangDataCbor),
+                Err(error) => Response::from_error(&error, ResponseCode::InternalServerError),
+            },
+            Err(error) => Response::from_error(&error, ResponseCode::Conflict),
+        })
+    }
+
     fn handle_post_instances(&self, request: &Request) -> Result<Option<Value>> {
         if let Some(format) = request.content_format
             && format != ContentFormat::YangInstancesCborSeq
@@ -523,7 +1598,7 @@ impl RequestHandler {
         }
 
         let mut last = None;
-        for instance in decode_instances_with_model(self.datastore.model(), &request.payload)? {
+        for instance in decode_instances_with_model(self.model(), &request.payload)? {
             let Some(sid) = instance.path.absolute_sid() else {
                 continue;
             };
@@ -533,24 +1608,21 @@ impl RequestHandler {
                     keys.push(val.clone());
                 }
             }
-            let xpath = self.datastore.create_xpath(sid, &keys)?;
+            let xpath = self.datastores.running().create_xpath(sid, &keys)?;
+            self.check_exec(request, &xpath)?;
             let parsed_xpath = PredicatePath::parse(&xpath)?;
-            let converted_value = match instance.value {
-                Some(value) => Some(
-                    self.datastore
-                        .model()
-                        .sid_value_to_identifier_value_at_path(
-                            value,
-                            &parsed_xpath.canonical_path,
-                        )?,
-                ),
-                None => None,
-            };
+            let converted_value =
+                match instance.value {
+                    Some(value) => Some(self.model().sid_value_to_identifier_value_at_path(
+                        value,
+                        &parsed_xpath.canonical_path,
+                    )?),
+                    None => None,
+                };
             let result = self.operations.invoke(&xpath, converted_value.as_ref())?;
             last = match result {
                 Some(val) => Some(
-                    self.datastore
-                        .model()
+                    self.model()
                         .identifier_value_to_sid_value_at_path(val, &parsed_xpath.canonical_path)?,
                 ),
                 None => None,
@@ -570,8 +1642,7 @@ impl RequestHandler {
             Some(ContentFormat::YangDataCbor) if !payload.is_empty() => {
                 let sid_val = decode_json_value(payload)?;
                 Some(
-                    self.datastore
-                        .model()
+                    self.model()
                         .sid_value_to_identifier_value_at_path(sid_val, &parsed.canonical_path)?,
                 )
             }
@@ -581,8 +1652,7 @@ impl RequestHandler {
             None => {
                 let sid_val = decode_json_value(payload)?;
                 Some(
-                    self.datastore
-                        .model()
+                    self.model()
                         .sid_value_to_identifier_value_at_path(sid_val, &parsed.canonical_path)?,
                 )
             }
@@ -594,7 +1664,6 @@ impl RequestHandler {
         match result {
             Some(val) => {
                 let sid_val = self
-                    .datastore
                     .model()
                     .identifier_value_to_sid_value_at_path(val, &parsed.canonical_path)?;
                 Ok(Some(sid_val))
@@ -622,7 +1691,7 @@ impl RequestHandler {
                     && values[1..].iter().all(is_supported_fetch_key_value)
                     && !values[1..].iter().all(|value| {
                         value.as_i64().is_some_and(|candidate| {
-                            self.datastore.model().get_identifier(candidate).is_some()
+                            self.model().get_identifier(candidate).is_some()
                         })
                     })
                 {
@@ -635,7 +1704,7 @@ impl RequestHandler {
     }
 
     fn fetch_key_count(&self, sid: i64) -> usize {
-        let Some(identifier) = self.datastore.model().get_identifier(sid) else {
+        let Some(identifier) = self.model().get_identifier(sid) else {
             return 0;
         };
         let mut current = String::new();
@@ -645,14 +1714,106 @@ impl RequestHandler {
             .filter_map(|segment| {
                 current.push('/');
                 current.push_str(segment);
-                self.datastore.model().get_sid(&current)
+                self.model().get_sid(&current)
             })
-            .filter_map(|list_sid| self.datastore.model().get_keys(list_sid))
-            .map(Vec::len)
+            .filter_map(|list_sid| self.model().get_keys(list_sid))
+            .map(<[i64]>::len)
             .sum()
     }
 }
 
+/// Why a transaction was not published.
+enum TransactionFailure {
+    /// The edit or a participant rejected the candidate.
+    Rejected(CoreconfError),
+    /// The target datastore failed to read or store it.
+    Unpublished(CoreconfError),
+}
+
+impl TransactionFailure {
+    fn into_error(self) -> CoreconfError {
+        match self {
+            Self::Rejected(error) | Self::Unpublished(error) => error,
+        }
+    }
+
+    fn into_response(self) -> Response {
+        match self {
+            Self::Rejected(error) => Response::from_error(&error, ResponseCode::Conflict),
+            Self::Unpublished(error) => {
+                Response::from_error(&error, ResponseCode::InternalServerError)
+            }
+        }
+    }
+}
+
+/// The error for a request that `lock` on `target` holds back.
+fn locked(target: DatastoreName, lock: &DatastoreLock, tag: ErrorTag) -> DataError {
+    let remaining = lock.deadline.saturating_duration_since(Instant::now());
+    DataError::new(
+        tag,
+        format!(
+            "the {target} datastore is locked by {} for another {}s",
+            lock.owner,
+            remaining.as_secs().max(1)
+        ),
+    )
+}
+
+/// Abort `participants` other than the one at `failed`, last registered
+/// first.
+fn abort_participants(
+    participants: &[Box<dyn TransactionParticipant>],
+    failed: Option<usize>,
+    context: &TransactionContext<'_>,
+) {
+    for (index, participant) in participants.iter().enumerate().rev() {
+        if Some(index) != failed {
+            participant.abort(context);
+        }
+    }
+}
+
+/// Write each decoded instance into `candidate`, deleting those without a
+/// value.
+fn apply_instances(
+    candidate: &mut Datastore,
+    log: &mut EditLog,
+    instances: Vec<Instance>,
+) -> Result<()> {
+    for instance in instances {
+        let Some(sid) = instance.path.absolute_sid() else {
+            continue;
+        };
+        let mut keys = Vec::new();
+        for component in &instance.path.components {
+            if let coreconf_model::instance_id::PathComponent::KeyValue(val) = component {
+                keys.push(val.clone());
+            }
+        }
+        let xpath = candidate.create_xpath(sid, &keys)?;
+        let parsed_xpath = PredicatePath::parse(&xpath)?;
+        let converted_value = match instance.value {
+            Some(value) => Some(
+                candidate
+                    .model()
+                    .sid_value_to_identifier_value_at_path(value, &parsed_xpath.canonical_path)?,
+            ),
+            None => None,
+        };
+        log.apply(candidate, &xpath, |candidate| match converted_value {
+            Some(value) => candidate.set_path(&xpath, value),
+            None => candidate.delete_path(&xpath).map(|_| ()),
+        })?;
+    }
+    if log.is_empty() {
+        return Err(
+            DataError::new(ErrorTag::InvalidValue, "iPATCH contained no operations").into(),
+        );
+    }
+    Ok(())
+}
+
 /// Parse a FETCH identifier: `sid` (bare SID) or `[sid, key1, key2, ...]`
 /// (instance ID with list-key values).
 fn parse_fetch_identifier(value: &Value) -> Result<(i64, Vec<Value>)> {
@@ -716,16 +1877,81 @@ fn decode_json_value(payload: &[u8]) -> Result<Value> {
     coreconf_model::codec::cbor_to_json_value(payload)
 }
 
-/// Apply `c=` (content) and `d=` (defaults) query filters to a CBOR payload.
-///
-/// Currently returns the payload unchanged because:
-/// - The datastore has a single schema tree (no candidate/running/startup split),
-///   so all `c=` values behave identically.
-/// - SID files do not carry leaf default values, so `d=t` (trim defaults) cannot
-///   be applied yet.
+fn encode_name_keyed(value: &Value) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes)
+        .map_err(|error| CoreconfError::CborEncode(error.to_string()))?;
+    Ok(bytes)
+}
+
+fn decode_operation_input(payload: &[u8]) -> Result<Option<Value>> {
+    if payload.is_empty() {
+        Ok(None)
+    } else {
+        decode_json_value(payload).map(Some)
+    }
+}
+
+/// A 4.15 response if the request named a content format this server
+/// doesn't know.
+fn unknown_content_format(request: &Request) -> Option<Response> {
+    (request.raw_content_format.is_some() && request.content_format.is_none()).then(|| {
+        Response::data_error(
+            ResponseCode::UnsupportedContentFormat,
+            &DataError::new(ErrorTag::OperationNotSupported, "unknown content format"),
+        )
+    })
+}
+
+fn unknown_datastore(request: &Request) -> Option<Response> {
+    let name = request.query.unknown_datastore.as_deref()?;
+    Some(Response::data_error(
+        ResponseCode::BadRequest,
+        &DataError::new(
+            ErrorTag::InvalidValue,
+            format!("unknown datastore '{name}'"),
+        ),
+    ))
+}
+
+/// The entity-tag of the resource at `path` in `datastore`, the whole
+/// datastore if empty, or `None` if it does not exist.
+fn resource_tag(datastore: &Datastore, path: &str) -> Result<Option<Vec<u8>>> {
+    if path.is_empty() {
+        return Ok(Some(tree_entity_tag(&datastore.snapshot()?)));
+    }
+    Ok(datastore.get_path(path)?.map(|value| entity_tag(&value)))
+}
+
+/// Whether the node at `path` lies under the node at `ancestor`.
+fn within(path: &str, ancestor: &str) -> bool {
+    path.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.starts_with('/') || rest.starts_with('['))
+}
+
+/// Top-level members that differ; members still shared with `previous`
+/// compare equal without being walked.
+fn changed_top_level_paths(previous: &SharedTree, next: &SharedTree) -> Vec<String> {
+    let mut paths: Vec<String> = previous
+        .members()
+        .map(|(key, _)| key)
+        .chain(
+            next.members()
+                .map(|(key, _)| key)
+                .filter(|key| previous.get(key).is_none()),
+        )
+        .filter(|key| previous.get(key) != next.get(key))
+        .map(|key| format!("/{key}"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Apply the `d=` (defaults) query filter to a CBOR payload.
 ///
-/// When multi-datastore support and default-value tracking are added, this
-/// function will perform the filtering as specified in RFC 9595 § 4.1.
+/// `c=` is applied earlier, when choosing the datastore view to read. The
+/// payload is returned unchanged because SID files do not carry leaf
+/// default values, so `d=t` (trim defaults) cannot be applied yet.
 fn apply_query_filters(payload: &[u8], _query: &crate::coap_types::QueryParams) -> Vec<u8> {
     payload.to_vec()
 }
@@ -0,0 +1,86 @@
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Instant;
+
+use coreconf_model::Result;
+
+use crate::coap_types::{Request, Response};
+use crate::datastore::Datastore;
+use crate::request_handler::RequestHandler;
+use crate::subscription::{ChangeEvent, SubscriptionId};
+
+/// A [`RequestHandler`] shared between threads.
+///
+/// Reads (GET, and FETCH other than Observe registrations) are served under
+/// a read lock, so any number of them run at once. Everything else takes
+/// the write lock and is serialized. Clones share the same handler, so one
+/// can serve CoAP while another updates operational state from application
+/// code.
+#[derive(Clone)]
+pub struct SharedHandler {
+    inner: Arc<RwLock<RequestHandler>>,
+}
+
+impl SharedHandler {
+    pub fn new(handler: RequestHandler) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(handler)),
+        }
+    }
+
+    /// Handle `request`, taking the write lock only if it may change state.
+    pub fn handle(&self, request: &Request) -> Response {
+        {
+            let handler = self.read();
+            // An expired confirmed commit must be rolled back before serving,
+            // which needs the write lock.
+            let expired = handler
+                .pending_confirm()
+                .is_some_and(|pending| pending.is_expired(Instant::now()));
+            if !expired && let Some(response) = handler.handle_read(request) {
+                return response;
+            }
+        }
+        self.write().handle(request)
+    }
+
+    /// Update operational state; see [`RequestHandler::update_state`].
+    pub fn update_state<R>(&self, update: impl FnOnce(&mut Datastore) -> R) -> R {
+        self.write().update_state(update)
+    }
+
+    /// Subscribe to changes of running; see [`RequestHandler::subscribe`].
+    ///
+    /// Callbacks run while the write lock is held, so they must not use
+    /// this handler themselves.
+    pub fn subscribe(
+        &self,
+        pattern: &str,
+        callback: impl FnMut(&ChangeEvent) + Send + Sync + 'static,
+    ) -> Result<SubscriptionId> {
+        self.write().subscribe(pattern, callback)
+    }
+
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        self.write().unsubscribe(id)
+    }
+
+    /// Lock the handler for reading.
+    ///
+    /// A panic while the lock was held does not leave the handler
+    /// unusable: each request either completes its edit or leaves the
+    /// datastores as they were.
+    pub fn read(&self) -> RwLockReadGuard<'_, RequestHandler> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lock the handler for writing.
+    pub fn write(&self) -> RwLockWriteGuard<'_, RequestHandler> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl From<RequestHandler> for SharedHandler {
+    fn from(handler: RequestHandler) -> Self {
+        Self::new(handler)
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use coreconf_model::Result;
+use serde_json::Value;
+
+use crate::datastore::{TreeNode, expected_array, expected_object};
+
+/// A datastore tree whose containers and lists are reference-counted.
+///
+/// Cloning a `SharedTree` is a reference-count bump, and clones share every
+/// subtree. Writing through one clone copies only the containers and lists
+/// on the path to the change, so snapshots, candidates and the datastore
+/// they came from keep sharing everything else.
+#[derive(Clone)]
+pub struct SharedTree(Node);
+
+#[derive(Clone)]
+enum Node {
+    /// Anything but an object or array.
+    Leaf(Value),
+    Object(Arc<BTreeMap<String, SharedTree>>),
+    Array(Arc<Vec<SharedTree>>),
+}
+
+impl SharedTree {
+    /// Copy the tree out into a plain JSON value.
+    pub fn to_value(&self) -> Value {
+        match &self.0 {
+            Node::Leaf(value) => value.clone(),
+            Node::Object(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|(key, member)| (key.clone(), member.to_value()))
+                    .collect(),
+            ),
+            Node::Array(entries) => Value::Array(entries.iter().map(Self::to_value).collect()),
+        }
+    }
+
+    /// Member `key` of an object node.
+    pub fn get(&self, key: &str) -> Option<&SharedTree> {
+        match &self.0 {
+            Node::Object(members) => members.get(key),
+            _ => None,
+        }
+    }
+
+    /// The value of a node that is neither an object nor an array.
+    pub(crate) fn as_leaf(&self) -> Option<&Value> {
+        match &self.0 {
+            Node::Leaf(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Members of an object node, in key order; empty for other nodes.
+    pub fn members(&self) -> impl Iterator<Item = (&str, &SharedTree)> {
+        let members = match &self.0 {
+            Node::Object(members) => Some(members.iter()),
+            _ => None,
+        };
+        members
+            .into_iter()
+            .flatten()
+            .map(|(key, member)| (key.as_str(), member))
+    }
+
+    /// Whether the tree is null or an object without members.
+    pub fn is_empty(&self) -> bool {
+        match &self.0 {
+            Node::Leaf(value) => value.is_null(),
+            Node::Object(members) => members.is_empty(),
+            Node::Array(_) => false,
+        }
+    }
+
+    /// Whether `a` and `b` are the same shared container, which implies they
+    /// are equal without looking inside.
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        match (&a.0, &b.0) {
+            (Node::Object(a), Node::Object(b)) => Arc::ptr_eq(a, b),
+            (Node::Array(a), Node::Array(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    fn object_mut(&mut self) -> Result<&mut BTreeMap<String, SharedTree>> {
+        if matches!(self.0, Node::Leaf(Value::Null)) {
+            self.0 = Node::Object(Arc::default());
+        }
+        match &mut self.0 {
+            Node::Object(members) => Ok(Arc::make_mut(members)),
+            _ => Err(expected_object()),
+        }
+    }
+}
+
+impl Default for SharedTree {
+    fn default() -> Self {
+        Self(Node::Object(Arc::default()))
+    }
+}
+
+impl From<Value> for SharedTree {
+    fn from(value: Value) -> Self {
+        Self(match value {
+            Value::Object(members) => Node::Object(Arc::new(
+                members
+                    .into_iter()
+                    .map(|(key, member)| (key, Self::from(member)))
+                    .collect(),
+            )),
+            Value::Array(entries) => {
+                Node::Array(Arc::new(entries.into_iter().map(Self::from).collect()))
+            }
+            leaf => Node::Leaf(leaf),
+        })
+    }
+}
+
+impl From<&SharedTree> for Value {
+    fn from(tree: &SharedTree) -> Self {
+        tree.to_value()
+    }
+}
+
+impl PartialEq for SharedTree {
+    fn eq(&self, other: &Self) -> bool {
+        if Self::ptr_eq(self, other) {
+            return true;
+        }
+        match (&self.0, &other.0) {
+            (Node::Leaf(a), Node::Leaf(b)) => a == b,
+            (Node::Object(a), Node::Object(b)) => a == b,
+            (Node::Array(a), Node::Array(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for SharedTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_value(), f)
+    }
+}
+
+impl TreeNode for SharedTree {
+    fn from_value(value: Value) -> Self {
+        Self::from(value)
+    }
+
+    fn to_value(&self) -> Value {
+        SharedTree::to_value(self)
+    }
+
+    fn is_container(&self) -> bool {
+        !matches!(self.0, Node::Leaf(_))
+    }
+
+    fn member(&self, key: &str) -> Option<&Self> {
+        self.get(key)
+    }
+
+    fn member_mut(&mut self, key: &str) -> Option<&mut Self> {
+        match &mut self.0 {
+            Node::Object(members) if members.contains_key(key) => {
+                Arc::make_mut(members).get_mut(key)
+            }
+            _ => None,
+        }
+    }
+
+    fn leaf(&self, key: &str) -> Option<&Value> {
+        match &self.get(key)?.0 {
+            Node::Leaf(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn entries(&self) -> Option<&[Self]> {
+        match &self.0 {
+            Node::Array(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    fn entries_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match &mut self.0 {
+            Node::Array(entries) => Some(Arc::make_mut(entries)),
+            _ => None,
+        }
+    }
+
+    fn ensure_entries(&mut self) -> Result<&mut Vec<Self>> {
+        if matches!(self.0, Node::Leaf(Value::Null)) {
+            self.0 = Node::Array(Arc::default());
+        }
+        self.entries_mut().ok_or_else(expected_array)
+    }
+
+    fn insert_member(&mut self, key: String, value: Self) -> Result<Option<Self>> {
+        Ok(self.object_mut()?.insert(key, value))
+    }
+
+    fn member_or_insert(
+        &mut self,
+        key: String,
+        default: impl FnOnce() -> Self,
+    ) -> Result<&mut Self> {
+        Ok(self.object_mut()?.entry(key).or_insert_with(default))
+    }
+
+    fn remove_member(&mut self, key: &str) -> Option<Self> {
+        match &mut self.0 {
+            Node::Object(members) if members.contains_key(key) => {
+                Arc::make_mut(members).remove(key)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! In-process change subscriptions.
+//!
+//! [`Datastore::subscribe`] registers a callback for the nodes matching a
+//! [`Query`] pattern. Every mutation of the datastore, whatever its source
+//! (direct calls, path or root iPATCH, DELETE, YANG Patch, commits and
+//! rollbacks), re-evaluates the pattern against the top-level node it
+//! starts at, before and after, and reports each matching node whose value
+//! changed as a
+//! [`ChangeEvent`]. Events therefore come at the granularity of the pattern:
+//! subscribing to `/m:sensors/sensor[*]/precision` yields one event per
+//! entry whose `precision` changed, even when the whole entry was replaced.
+//!
+//! Nodes are only compared while at least one subscription exists, only
+//! the top-level nodes that patterns start at are read, and the comparison
+//! walks both [`SharedTree`]s together, skipping the subtrees they still
+//! share.
+//!
+//! [`Datastore::subscribe`]: crate::Datastore::subscribe
+
+use std::collections::BTreeMap;
+
+use coreconf_model::{CompositeModel, Result};
+use serde_json::Value;
+
+use crate::datastore::storage_key;
+use crate::diff::DiffOperation;
+use crate::query::Query;
+use crate::shared_tree::SharedTree;
+
+/// Identifies a subscription for [`crate::Datastore::unsubscribe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// A node matching a subscription whose value changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeEvent {
+    /// The concrete predicate path of the node.
+    pub path: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl ChangeEvent {
+    pub fn operation(&self) -> DiffOperation {
+        match (&self.old, &self.new) {
+            (None, _) => DiffOperation::Add,
+            (Some(_), Some(_)) => DiffOperation::Modify,
+            (Some(_), None) => DiffOperation::Delete,
+        }
+    }
+}
+
+pub type ChangeCallback = Box<dyn FnMut(&ChangeEvent) + Send + Sync>;
+
+/// The watched top-level nodes present in a datastore, by storage key.
+pub(crate) type Watched = BTreeMap<String, SharedTree>;
+
+struct Subscription {
+    id: SubscriptionId,
+    query: Query,
+    callback: ChangeCallback,
+}
+
+#[derive(Default)]
+pub(crate) struct Subscriptions {
+    next_id: u64,
+    entries: Vec<Subscription>,
+}
+
+impl Subscriptions {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn add(&mut self, query: Query, callback: ChangeCallback) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.entries.push(Subscription {
+            id,
+            query,
+            callback,
+        });
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: SubscriptionId) -> bool {
+        let count = self.entries.len();
+        self.entries.retain(|subscription| subscription.id != id);
+        self.entries.len() != count
+    }
+
+    /// Storage keys of the top-level nodes the patterns start at.
+    pub(crate) fn roots(&self) -> Vec<String> {
+        let mut roots: Vec<String> = self
+            .entries
+            .iter()
+            .filter_map(|subscription| subscription.query.steps.first())
+            .map(|step| storage_key(&step.name, 0))
+            .collect();
+        roots.sort();
+        roots.dedup();
+        roots
+    }
+
+    /// Deliver the events between the watched nodes `before` and `after`
+    /// to every subscription, in registration order.
+    pub(crate) fn notify(&mut self, model: &CompositeModel, before: &Watched, after: &Watched) {
+        let unchanged = before.len() == after.len()
+            && before
+                .iter()
+                .zip(after)
+                .all(|((name, old), (other, new))| name == other && SharedTree::ptr_eq(old, new));
+        if unchanged {
+            return;
+        }
+        for subscription in &mut self.entries {
+            // Patterns are checked against the model when subscribing; a
+            // comparison that only fails on some data skips the event.
+            let Ok(events) = changes(&subscription.query, model, before, after) else {
+                continue;
+            };
+            for event in &events {
+                (subscription.callback)(event);
+            }
+        }
+    }
+}
+
+/// The nodes matching `query` that differ between the watched nodes
+/// `before` and `after`, removals first. Only the changed matches are copied out of the trees.
+fn changes(
+    query: &Query,
+    model: &CompositeModel,
+    before: &Watched,
+    after: &Watched,
+) -> Result<Vec<ChangeEvent>> {
+    let root = |watched: &Watched| {
+        query
+            .steps
+            .first()
+            .and_then(|step| watched.get(&storage_key(&step.name, 0)))
+            .cloned()
+    };
+    let (before, after) = (root(before), root(after));
+    let (removed, others): (Vec<_>, Vec<_>) = query
+        .changes(model, before.as_ref(), after.as_ref())?
+        .into_iter()
+        .partition(|(_, _, new)| new.is_none());
+    Ok(removed
+        .into_iter()
+        .chain(others)
+        .map(|(path, old, new)| ChangeEvent {
+            path,
+            old: old.map(SharedTree::to_value),
+            new: new.map(SharedTree::to_value),
+        })
+        .collect())
+}
@@ -1,31 +1,41 @@
+use std::cell::OnceCell;
+
 use coreconf_model::Result;
 use serde_json::Value;
 
 use crate::coap_types::Request;
+use crate::datastore::Datastore;
+use crate::shared_tree::SharedTree;
 
 /// Read-only state made available to a transaction participant.
 ///
 /// The context is valid only while the participant callback is running.
-/// `candidate_tree` is the complete tree that is published after all
-/// pre-commit callbacks succeed, and is the committed tree in post-commit
-/// callbacks.
+/// `candidate_tree` is the complete tree that is published once every
+/// participant has validated and committed it, and is the published tree in
+/// post-commit callbacks. During preparation it is the candidate as left by
+/// the participants prepared so far. Both trees are held as snapshots and
+/// copied out into plain values only when a participant asks for them.
 pub struct TransactionContext<'a> {
-    previous_tree: &'a Value,
-    candidate_tree: &'a Value,
+    previous: &'a SharedTree,
+    candidate: &'a SharedTree,
+    previous_tree: OnceCell<Value>,
+    candidate_tree: OnceCell<Value>,
     changed_paths: &'a [String],
     request: &'a Request,
 }
 
 impl<'a> TransactionContext<'a> {
     pub(crate) fn new(
-        previous_tree: &'a Value,
-        candidate_tree: &'a Value,
+        previous: &'a SharedTree,
+        candidate: &'a SharedTree,
         changed_paths: &'a [String],
         request: &'a Request,
     ) -> Self {
         Self {
-            previous_tree,
-            candidate_tree,
+            previous,
+            candidate,
+            previous_tree: OnceCell::new(),
+            candidate_tree: OnceCell::new(),
             changed_paths,
             request,
         }
@@ -33,12 +43,23 @@ impl<'a> TransactionContext<'a> {
 
     /// Return the complete tree that was present before this transaction.
     pub fn previous_tree(&self) -> &Value {
-        self.previous_tree
+        self.previous_tree.get_or_init(|| self.previous.to_value())
     }
 
     /// Return the complete candidate tree.
     pub fn candidate_tree(&self) -> &Value {
         self.candidate_tree
+            .get_or_init(|| self.candidate.to_value())
+    }
+
+    /// The tree before this transaction, without copying it.
+    pub fn previous_snapshot(&self) -> &SharedTree {
+        self.previous
+    }
+
+    /// The candidate tree, without copying it.
+    pub fn candidate_snapshot(&self) -> &SharedTree {
+        self.candidate
     }
 
     /// Return changed canonical paths, including predicates for keyed list
@@ -53,17 +74,43 @@ impl<'a> TransactionContext<'a> {
     }
 }
 
-/// A generic participant in root iPATCH transactions.
+/// A participant in every transaction on running: path and root iPATCH,
+/// DELETE, YANG Patch, commits and journal rollbacks.
+///
+/// Each phase calls participants in registration order:
+///
+/// 1. `prepare` may adjust the candidate, for example to fill in derived
+///    leaves. Its changes are part of the published transaction.
+/// 2. `pre_commit` validates the prepared candidate and may veto it.
+/// 3. `commit` applies side effects, such as programming hardware, and may
+///    still fail.
+/// 4. The candidate is published, then `post_commit` observes it.
 ///
-/// Participants are called in registration order.  A participant may reject
-/// a candidate during `pre_commit`; `post_commit` is notification-only and
-/// cannot veto or roll back a successful publication.
-pub trait TransactionParticipant: Send {
+/// When a participant fails in any phase, or publication fails, `abort` is
+/// called on every other participant the transaction has reached, last
+/// registered first, so that side effects already applied can be undone.
+/// Rolling back an expired confirmed commit skips `prepare` and
+/// `pre_commit`, as it cannot be vetoed.
+pub trait TransactionParticipant: Send + Sync {
+    /// Adjust the candidate before it is validated.
+    fn prepare(&self, _context: &TransactionContext<'_>, _candidate: &mut Datastore) -> Result<()> {
+        Ok(())
+    }
+
     /// Validate the candidate before it is published.
     fn pre_commit(&self, _context: &TransactionContext<'_>) -> Result<()> {
         Ok(())
     }
 
+    /// Apply the side effects of the validated candidate.
+    fn commit(&self, _context: &TransactionContext<'_>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Undo whatever this participant did for a transaction that will not
+    /// be published.
+    fn abort(&self, _context: &TransactionContext<'_>) {}
+
     /// Observe a successfully committed transaction.
     fn post_commit(&self, _context: &TransactionContext<'_>) {}
 }
@@ -1 +1,3 @@
 pub mod coap_lite;
+#[cfg(feature = "tokio")]
+pub mod coap_tokio;
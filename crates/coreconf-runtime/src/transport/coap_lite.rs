@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, RwLockReadGuard, RwLockWriteGuard};
 use std::time::{Duration, Instant};
 
 use coap_lite::block_handler::BlockValue;
+use coap_lite::option_value::{OptionValueU16, OptionValueU32};
 use coap_lite::{
     CoapOption, ContentFormat as CoapContentFormat, MessageClass, MessageType, Packet, RequestType,
     ResponseType,
@@ -13,11 +15,16 @@ use serde_json::Value;
 use crate::coap_types::{
     ContentFormat, Interface, Method, QueryParams, Request, Response, ResponseCode,
 };
+use crate::commit::{CANCEL_COMMIT_PATH, COMMIT_PATH, DISCARD_CHANGES_PATH, commit_input};
+use crate::journal::{JournalEntry, REVISION_PATH, REVISIONS_PATH, ROLLBACK_PATH, revision_input};
+use crate::lock::{LOCK_PATH, UNLOCK_PATH, lock_input};
+use crate::nmda::DatastoreName;
 use crate::request_handler::RequestHandler;
+use crate::shared_handler::SharedHandler;
 
 /// Maximum payload bytes per CoAP block to stay safely under the
 /// 1152-byte default message size after adding headers and options.
-const MAX_BLOCK_PAYLOAD: usize = 1024;
+pub(crate) const MAX_BLOCK_PAYLOAD: usize = 1024;
 const BLOCK1_TRANSFER_TTL: Duration = Duration::from_secs(30);
 
 pub trait CoreconfClient {
@@ -28,24 +35,92 @@ pub trait CoreconfClient {
         ))
     }
     fn apply_patch(&mut self, patch: &[(String, Option<Value>)]) -> Result<()>;
+    /// Send a yang-instances+cbor-seq payload, such as an encoded
+    /// [`crate::Diff`], as one root iPATCH.
+    fn apply_instances(&mut self, _payload: &[u8]) -> Result<()> {
+        Err(CoreconfError::ValidationError(
+            "instance iPATCH is not supported by this client".into(),
+        ))
+    }
+    /// [`CoreconfClient::fetch_snapshot`] of the datastore edits go to,
+    /// running unless one is set, with its entity-tag if the server sent one.
+    fn fetch_tagged_snapshot(&mut self) -> Result<(Value, Option<Vec<u8>>)> {
+        Ok((self.fetch_snapshot()?, None))
+    }
+    /// [`CoreconfClient::apply_instances`] only if the datastore's
+    /// entity-tag is still `etag`, returning the entity-tag after the edit.
+    fn apply_instances_if_match(
+        &mut self,
+        _payload: &[u8],
+        _etag: &[u8],
+    ) -> Result<Option<Vec<u8>>> {
+        Err(CoreconfError::ValidationError(
+            "conditional iPATCH is not supported by this client".into(),
+        ))
+    }
     fn discover(&mut self, _query: Option<&str>) -> Result<String> {
         Err(CoreconfError::ValidationError(
             "discovery is not supported by this client".into(),
         ))
     }
+    /// Commit candidate into running; with a timeout, as a confirmed commit.
+    fn commit(&mut self, _confirm_timeout: Option<Duration>) -> Result<()> {
+        Err(CoreconfError::ValidationError(
+            "commit is not supported by this client".into(),
+        ))
+    }
+    fn cancel_commit(&mut self) -> Result<()> {
+        Err(CoreconfError::ValidationError(
+            "cancel-commit is not supported by this client".into(),
+        ))
+    }
+    fn discard_changes(&mut self) -> Result<()> {
+        Err(CoreconfError::ValidationError(
+            "discard-changes is not supported by this client".into(),
+        ))
+    }
+    /// Journal entries of the server, newest first and without patches.
+    fn journal_revisions(&mut self) -> Result<Vec<JournalEntry>> {
+        Err(CoreconfError::ValidationError(
+            "the change journal is not supported by this client".into(),
+        ))
+    }
+    fn journal_revision(&mut self, _revision: u64) -> Result<JournalEntry> {
+        Err(CoreconfError::ValidationError(
+            "the change journal is not supported by this client".into(),
+        ))
+    }
+    fn rollback_to(&mut self, _revision: u64) -> Result<()> {
+        Err(CoreconfError::ValidationError(
+            "rollback is not supported by this client".into(),
+        ))
+    }
+    /// Lock `target` for this client until `timeout`, or the server's
+    /// default, elapses.
+    fn lock(&mut self, _target: DatastoreName, _timeout: Option<Duration>) -> Result<()> {
+        Err(CoreconfError::ValidationError(
+            "locking is not supported by this client".into(),
+        ))
+    }
+    fn unlock(&mut self, _target: DatastoreName) -> Result<()> {
+        Err(CoreconfError::ValidationError(
+            "locking is not supported by this client".into(),
+        ))
+    }
 }
 
 pub struct CoapLiteClient {
     socket: UdpSocket,
     endpoint: String,
     resource_path: String,
-    model: CompositeModel,
+    model: Arc<CompositeModel>,
+    datastore: Option<DatastoreName>,
     next_message_id: u16,
 }
 
 impl CoapLiteClient {
     pub fn connect(
-        model: CompositeModel,
+        model: impl Into<Arc<CompositeModel>>,
         endpoint: impl ToSocketAddrs,
         resource_path: impl Into<String>,
     ) -> Result<Self> {
@@ -58,7 +133,8 @@ impl CoapLiteClient {
             socket,
             endpoint,
             resource_path: resource_path.into(),
-            model,
+            model: model.into(),
+            datastore: None,
             next_message_id: 1,
         })
     }
@@ -67,8 +143,25 @@ impl CoapLiteClient {
         &self.endpoint
     }
 
-    /// Send an iPATCH request split across multiple Block1 transfers (RFC 7959).
-    fn send_blockwise_ipatch(&mut self, path: &str, payload: &[u8]) -> Result<()> {
+    /// Address requests to one NMDA datastore (`ds=`) instead of the
+    /// server's defaults.
+    pub fn set_datastore(&mut self, datastore: Option<DatastoreName>) {
+        self.datastore = datastore;
+    }
+
+    pub fn datastore(&self) -> Option<DatastoreName> {
+        self.datastore
+    }
+
+    /// Send an iPATCH request split across multiple Block1 transfers (RFC 7959),
+    /// returning the response to the last block.
+    fn send_blockwise_ipatch(
+        &mut self,
+        path: Option<&str>,
+        payload: &[u8],
+        content_format: ContentFormat,
+        if_match: Option<&[u8]>,
+    ) -> Result<Packet> {
         let blocks: Vec<&[u8]> = payload.chunks(MAX_BLOCK_PAYLOAD).collect();
         let total = blocks.len();
 
@@ -77,13 +170,15 @@ impl CoapLiteClient {
             let block = BlockValue::new(i, more, MAX_BLOCK_PAYLOAD)
                 .map_err(|e| invalid_data(e.to_string()))?;
 
-            let response = self.send_coreconf_request_with_block(
+            let mut packet = self.build_packet(
                 RequestType::IPatch,
-                Some(path),
+                path,
                 chunk.to_vec(),
-                Some(ContentFormat::YangDataCbor),
-                block,
-            )?;
+                Some(content_format),
+            );
+            add_if_match(&mut packet, if_match);
+            packet.add_option_as(CoapOption::Block1, block);
+            let response = self.send_packet(packet)?;
 
             if more {
                 if !matches!(
@@ -99,22 +194,33 @@ impl CoapLiteClient {
                 }
             } else {
                 ensure_success(&response)?;
+                return Ok(response);
             }
         }
-        Ok(())
+        Err(invalid_data("iPATCH had no payload".into()))
     }
 
-    fn send_coreconf_request_with_block(
-        &mut self,
-        method: RequestType,
-        path: Option<&str>,
-        payload: Vec<u8>,
-        content_format: Option<ContentFormat>,
-        block1: BlockValue,
-    ) -> Result<Packet> {
-        let mut packet = self.build_packet(method, path, payload, content_format);
-        packet.add_option_as(CoapOption::Block1, block1);
-        self.send_packet(packet)
+    /// Send a yang-instances+cbor-seq payload as one root iPATCH, in blocks
+    /// if it is large, and return the final response.
+    fn send_instances(&mut self, payload: &[u8], if_match: Option<&[u8]>) -> Result<Packet> {
+        if payload.len() > MAX_BLOCK_PAYLOAD {
+            return self.send_blockwise_ipatch(
+                None,
+                payload,
+                ContentFormat::YangInstancesCborSeq,
+                if_match,
+            );
+        }
+        let mut packet = self.build_packet(
+            RequestType::IPatch,
+            None,
+            payload.to_vec(),
+            Some(ContentFormat::YangInstancesCborSeq),
+        );
+        add_if_match(&mut packet, if_match);
+        let response = self.send_packet(packet)?;
+        ensure_success(&response)?;
+        Ok(response)
     }
 
     fn send_coreconf_request(
@@ -135,23 +241,17 @@ impl CoapLiteClient {
         payload: Vec<u8>,
         content_format: Option<ContentFormat>,
     ) -> Packet {
-        let mut packet = Packet::new();
+        let mut packet = coreconf_request_packet(
+            &self.resource_path,
+            self.datastore,
+            method,
+            path,
+            payload,
+            content_format,
+        );
         packet.header.message_id = self.next_message_id;
         self.next_message_id = self.next_message_id.wrapping_add(1);
-        packet.header.code = MessageClass::Request(method);
-        packet.header.set_type(MessageType::Confirmable);
         packet.set_token(vec![0xC0]);
-        add_uri_path(&mut packet, &self.resource_path);
-        if let Some(path) = path {
-            add_uri_path(&mut packet, path);
-        }
-
-        if !payload.is_empty() {
-            packet.payload = payload;
-            if let Some(format) = content_format {
-                packet.set_content_format(content_format_to_coap(format));
-            }
-        }
         packet
     }
 
@@ -166,17 +266,22 @@ impl CoapLiteClient {
         Packet::from_bytes(&buffer[..len]).map_err(|error| invalid_data(error.to_string()))
     }
 
+    /// POST a name-keyed operation and decode its name-keyed output.
+    fn send_operation(&mut self, path: &str, input: Option<Value>) -> Result<Option<Value>> {
+        let response = self.send_coreconf_request(
+            RequestType::Post,
+            Some(path),
+            encode_value(input.as_ref())?,
+            Some(ContentFormat::YangDataCbor),
+        )?;
+        operation_output(&response)
+    }
+
     fn send_discovery_request(&mut self, query: Option<&str>) -> Result<Packet> {
-        let mut packet = Packet::new();
+        let mut packet = discovery_packet(query);
         packet.header.message_id = self.next_message_id;
         self.next_message_id = self.next_message_id.wrapping_add(1);
-        packet.header.code = MessageClass::Request(RequestType::Get);
-        packet.header.set_type(MessageType::Confirmable);
         packet.set_token(vec![0xC0]);
-        add_uri_path(&mut packet, "/.well-known/core");
-        if let Some(query) = query.filter(|q| !q.is_empty()) {
-            packet.add_option(CoapOption::UriQuery, query.as_bytes().to_vec());
-        }
         self.send_packet(packet)
     }
 }
@@ -184,32 +289,28 @@ impl CoapLiteClient {
 impl CoreconfClient for CoapLiteClient {
     fn discover(&mut self, query: Option<&str>) -> Result<String> {
         let response = self.send_discovery_request(query)?;
-        ensure_success(&response)?;
-        String::from_utf8(response.payload).map_err(|error| invalid_data(error.to_string()))
+        discovery_output(response)
     }
 
     fn fetch_snapshot(&mut self) -> Result<Value> {
         let response = self.send_coreconf_request(RequestType::Get, None, Vec::new(), None)?;
-        ensure_success(&response)?;
-        let json = coreconf_model::decode_cbor_to_json(&self.model, &response.payload)?;
-        serde_json::from_str(&json).map_err(CoreconfError::from)
+        snapshot_output(&self.model, &response)
+    }
+
+    fn fetch_tagged_snapshot(&mut self) -> Result<(Value, Option<Vec<u8>>)> {
+        let mut packet = self.build_packet(RequestType::Get, None, Vec::new(), None);
+        read_edited_datastore(&mut packet, self.datastore);
+        let response = self.send_packet(packet)?;
+        Ok((
+            snapshot_output(&self.model, &response)?,
+            response_etag(&response),
+        ))
     }
 
     fn fetch_path(&mut self, path: &str) -> Result<Option<Value>> {
         let response =
             self.send_coreconf_request(RequestType::Get, Some(path), Vec::new(), None)?;
-        if matches!(
-            response.header.code,
-            MessageClass::Response(ResponseType::NotFound)
-        ) {
-            return Ok(None);
-        }
-        ensure_success(&response)?;
-        let sid_value = coreconf_model::codec::cbor_to_json_value(&response.payload)?;
-        let parsed = crate::PredicatePath::parse(path)?;
-        self.model
-            .sid_value_to_identifier_value_at_path(sid_value, &parsed.canonical_path)
-            .map(Some)
+        path_output(&self.model, path, &response)
     }
 
     fn apply_patch(&mut self, patch: &[(String, Option<Value>)]) -> Result<()> {
@@ -221,10 +322,7 @@ impl CoreconfClient for CoapLiteClient {
                 continue;
             };
 
-            let mut payload = Vec::new();
-            ciborium::into_writer(value, &mut payload)
-                .map_err(|error| CoreconfError::CborEncode(error.to_string()))?;
-
+            let payload = encode_value(Some(value))?;
             if payload.len() <= MAX_BLOCK_PAYLOAD {
                 let response = self.send_coreconf_request(
                     RequestType::IPatch,
@@ -234,22 +332,92 @@ impl CoreconfClient for CoapLiteClient {
                 )?;
                 ensure_success(&response)?;
             } else {
-                self.send_blockwise_ipatch(path, &payload)?;
+                self.send_blockwise_ipatch(
+                    Some(path),
+                    &payload,
+                    ContentFormat::YangDataCbor,
+                    None,
+                )?;
             }
         }
         Ok(())
     }
+
+    fn apply_instances(&mut self, payload: &[u8]) -> Result<()> {
+        self.send_instances(payload, None).map(drop)
+    }
+
+    fn apply_instances_if_match(&mut self, payload: &[u8], etag: &[u8]) -> Result<Option<Vec<u8>>> {
+        let response = self.send_instances(payload, Some(etag))?;
+        Ok(response_etag(&response))
+    }
+
+    fn commit(&mut self, confirm_timeout: Option<Duration>) -> Result<()> {
+        self.send_operation(COMMIT_PATH, commit_input(confirm_timeout))
+            .map(drop)
+    }
+
+    fn cancel_commit(&mut self) -> Result<()> {
+        self.send_operation(CANCEL_COMMIT_PATH, None).map(drop)
+    }
+
+    fn discard_changes(&mut self) -> Result<()> {
+        self.send_operation(DISCARD_CHANGES_PATH, None).map(drop)
+    }
+
+    fn journal_revisions(&mut self) -> Result<Vec<JournalEntry>> {
+        let output = self.send_operation(REVISIONS_PATH, None)?;
+        revisions_output(output)
+    }
+
+    fn journal_revision(&mut self, revision: u64) -> Result<JournalEntry> {
+        let output = self.send_operation(REVISION_PATH, Some(revision_input(revision)))?;
+        revision_output(output)
+    }
+
+    fn rollback_to(&mut self, revision: u64) -> Result<()> {
+        self.send_operation(ROLLBACK_PATH, Some(revision_input(revision)))
+            .map(drop)
+    }
+
+    fn lock(&mut self, target: DatastoreName, timeout: Option<Duration>) -> Result<()> {
+        self.send_operation(LOCK_PATH, Some(lock_input(target, timeout)))
+            .map(drop)
+    }
+
+    fn unlock(&mut self, target: DatastoreName) -> Result<()> {
+        self.send_operation(UNLOCK_PATH, Some(lock_input(target, None)))
+            .map(drop)
+    }
 }
 
 pub struct CoapLiteServer {
     socket: UdpSocket,
+    handler: SharedHandler,
+    session: ServerSession,
+}
+
+/// Per-server protocol state that lives outside the request handler:
+/// observer addresses and Block1 transfers in progress. Shared by the
+/// blocking and async servers.
+pub(crate) struct ServerSession {
     resource_path: String,
-    handler: RequestHandler,
     /// Maps observer token → peer address for unsolicited notifications.
     observer_peers: HashMap<Vec<u8>, SocketAddr>,
     block1_transfers: HashMap<Block1Key, PendingBlock1>,
 }
 
+/// What to do with a received packet.
+pub(crate) enum Inbound {
+    /// Hand the request to the handler and answer `packet` with its response.
+    Request {
+        packet: Packet,
+        request: Box<Request>,
+    },
+    /// Answer at once, without involving the handler.
+    Respond(Packet),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Block1Key {
     peer: SocketAddr,
@@ -269,13 +437,21 @@ impl CoapLiteServer {
         bind_addr: impl ToSocketAddrs,
         resource_path: impl Into<String>,
         handler: RequestHandler,
+    ) -> Result<Self> {
+        Self::bind_shared(bind_addr, resource_path, SharedHandler::new(handler))
+    }
+
+    /// Bind a server around a handler that other threads also hold, e.g.
+    /// to update operational state while it serves.
+    pub fn bind_shared(
+        bind_addr: impl ToSocketAddrs,
+        resource_path: impl Into<String>,
+        handler: SharedHandler,
     ) -> Result<Self> {
         Ok(Self {
             socket: UdpSocket::bind(bind_addr)?,
-            resource_path: resource_path.into(),
             handler,
-            observer_peers: HashMap::new(),
-            block1_transfers: HashMap::new(),
+            session: ServerSession::new(resource_path),
         })
     }
 
@@ -287,44 +463,155 @@ impl CoapLiteServer {
         &self.socket
     }
 
-    pub fn handler(&self) -> &RequestHandler {
-        &self.handler
+    pub fn handler(&self) -> RwLockReadGuard<'_, RequestHandler> {
+        self.handler.read()
+    }
+
+    pub fn handler_mut(&mut self) -> RwLockWriteGuard<'_, RequestHandler> {
+        self.handler.write()
     }
 
-    pub fn handler_mut(&mut self) -> &mut RequestHandler {
-        &mut self.handler
+    pub fn shared_handler(&self) -> &SharedHandler {
+        &self.handler
     }
 
     /// Handle a packet from a known peer.  Associates the peer with any
     /// observer registration so unsolicited notifications can be sent back.
     pub fn handle_packet(&mut self, packet: &Packet, peer: SocketAddr) -> Packet {
+        match self.session.accept(packet, peer) {
+            Inbound::Request { packet, request } => {
+                response_to_packet(&packet, self.handler.handle(&request))
+            }
+            Inbound::Respond(response) => response,
+        }
+    }
+
+    /// Send pending notifications to all registered observers.
+    /// Call after each request so observers receive push updates promptly.
+    pub fn flush_pending_notifications(&mut self) {
+        if !self.session.has_observers() {
+            return;
+        }
+        let mut handler = self.handler.write();
+        // A failing provider only holds back its own notification.
+        let _ = handler.refresh_provided_state();
+        let notifications = self.session.notifications(&mut handler);
+        drop(handler);
+        for (packet, peer) in notifications {
+            if let Ok(bytes) = packet.to_bytes() {
+                let _ = self.socket.send_to(&bytes, peer);
+            }
+        }
+    }
+
+    /// Roll back a confirmed commit whose timeout has elapsed and notify
+    /// observers of the restored tree. Returns whether a rollback happened.
+    pub fn expire_confirmed_commit(&mut self) -> Result<bool> {
+        let rolled_back = self.handler.write().expire_confirmed_commit()?;
+        if rolled_back {
+            self.flush_pending_notifications();
+        }
+        Ok(rolled_back)
+    }
+
+    pub fn serve_once(&mut self) -> Result<()> {
+        self.expire_confirmed_commit()?;
+        let mut buffer = [0u8; 1500];
+        let (len, peer) = self.socket.recv_from(&mut buffer)?;
+        let packet =
+            Packet::from_bytes(&buffer[..len]).map_err(|error| invalid_data(error.to_string()))?;
+        let response = self.handle_packet(&packet, peer);
+        let bytes = response
+            .to_bytes()
+            .map_err(|error| invalid_data(error.to_string()))?;
+        self.socket.send_to(&bytes, peer)?;
+        self.flush_pending_notifications();
+        Ok(())
+    }
+}
+
+impl ServerSession {
+    pub(crate) fn new(resource_path: impl Into<String>) -> Self {
+        Self {
+            resource_path: resource_path.into(),
+            observer_peers: HashMap::new(),
+            block1_transfers: HashMap::new(),
+        }
+    }
+
+    /// Answer discovery and Block1 continuations, and turn anything else
+    /// into a handler request, tracking the peer of Observe registrations.
+    pub(crate) fn accept(&mut self, packet: &Packet, peer: SocketAddr) -> Inbound {
         // ── /.well-known/core resource discovery ──────────────────────
         let uri = uri_path(packet);
         if uri.trim_start_matches('/') == ".well-known/core" {
-            return self.well_known_core_response(packet);
+            return Inbound::Respond(self.well_known_core_response(packet));
         }
 
         let packet = match self.reassemble_block1(packet, peer) {
             Block1Outcome::Complete(packet) => packet,
-            Block1Outcome::Respond(response) => return response,
+            Block1Outcome::Respond(response) => return Inbound::Respond(response),
         };
 
-        let request = packet_to_request(&packet, &self.resource_path);
-        let response = match request {
-            Ok(ref req) => {
+        match packet_to_request(&packet, &self.resource_path) {
+            Ok(request) => {
+                let request = request.with_peer(peer.to_string());
                 // Track peer for observer registration (Observe=0 on /s).
-                if req.observe == Some(0) && !req.token.is_empty() {
-                    self.observer_peers.insert(req.token.clone(), peer);
+                if request.observe == Some(0) && !request.token.is_empty() {
+                    self.observer_peers.insert(request.token.clone(), peer);
                 }
                 // Remove on Observe=1 (client deregisters).
-                if req.observe == Some(1) && !req.token.is_empty() {
-                    self.observer_peers.remove(&req.token);
+                if request.observe == Some(1) && !request.token.is_empty() {
+                    self.observer_peers.remove(&request.token);
+                }
+                Inbound::Request {
+                    packet,
+                    request: Box::new(request),
                 }
-                self.handler.handle(req)
             }
-            Err(response) => response,
-        };
-        response_to_packet(&packet, response)
+            Err(response) => Inbound::Respond(response_to_packet(&packet, response)),
+        }
+    }
+
+    /// Whether any peer observes a resource, so notifications can be due.
+    pub(crate) fn has_observers(&self) -> bool {
+        !self.observer_peers.is_empty()
+    }
+
+    /// Notification packets due to observers, with the peers to send them to.
+    ///
+    /// Provided state is not recomputed here; callers that want changes of
+    /// it noticed call [`RequestHandler::refresh_provided_state`] first.
+    pub(crate) fn notifications(&self, handler: &mut RequestHandler) -> Vec<(Packet, SocketAddr)> {
+        let mut packets = Vec::new();
+        for (token, peer) in &self.observer_peers {
+            let pending = handler.pending_notifications(token);
+            for (_resource, sequence) in pending {
+                // Re-fetch the full operational datastore as CORECONF/SID CBOR,
+                // leaving out what the observer may not read.
+                let Ok(payload) = handler.notification_payload(Some(&peer.to_string())) else {
+                    continue;
+                };
+                let response = Response::observe(payload, ContentFormat::YangDataCbor, sequence);
+                // Build a non-confirmable notification packet.
+                let mut packet = Packet::new();
+                packet.header.message_id = 0;
+                packet.header.set_type(MessageType::NonConfirmable);
+                packet.set_token(token.clone());
+                packet.header.code = response_code_to_coap(response.code);
+                if !response.payload.is_empty() {
+                    packet.payload = response.payload;
+                    if let Some(format) = response.content_format {
+                        set_content_format(&mut packet, format);
+                    }
+                }
+                if let Some(seq) = response.observe {
+                    packet.set_observe_value(seq);
+                }
+                packets.push((packet, *peer));
+            }
+        }
+        packets
     }
 
     fn reassemble_block1(&mut self, packet: &Packet, peer: SocketAddr) -> Block1Outcome {
@@ -422,62 +709,6 @@ impl CoapLiteServer {
         packet.set_content_format(CoapContentFormat::TextPlain);
         packet
     }
-
-    /// Send pending notifications to all registered observers.
-    /// Call after each request so observers receive push updates promptly.
-    pub fn flush_pending_notifications(&mut self) {
-        // Snapshot peer list — pending_notifications takes &mut self.
-        let peers: Vec<(Vec<u8>, SocketAddr)> = self
-            .observer_peers
-            .iter()
-            .map(|(t, p)| (t.clone(), *p))
-            .collect();
-
-        for (token, peer) in &peers {
-            let pending = self.handler.pending_notifications(token);
-            for (_resource, sequence) in pending {
-                // Re-fetch the full datastore as CORECONF/SID CBOR.
-                let notification_payload = self.handler.datastore().get_all_cbor();
-
-                if let Ok(payload) = notification_payload {
-                    let response =
-                        Response::observe(payload, ContentFormat::YangDataCbor, sequence);
-                    // Build a non-confirmable notification packet.
-                    let mut packet = Packet::new();
-                    packet.header.message_id = 0;
-                    packet.header.set_type(MessageType::NonConfirmable);
-                    packet.set_token(token.clone());
-                    packet.header.code = response_code_to_coap(response.code);
-                    if !response.payload.is_empty() {
-                        packet.payload = response.payload;
-                        if let Some(format) = response.content_format {
-                            packet.set_content_format(content_format_to_coap(format));
-                        }
-                    }
-                    if let Some(seq) = response.observe {
-                        packet.set_observe_value(seq);
-                    }
-                    if let Ok(bytes) = packet.to_bytes() {
-                        let _ = self.socket.send_to(&bytes, *peer);
-                    }
-                }
-            }
-        }
-    }
-
-    pub fn serve_once(&mut self) -> Result<()> {
-        let mut buffer = [0u8; 1500];
-        let (len, peer) = self.socket.recv_from(&mut buffer)?;
-        let packet =
-            Packet::from_bytes(&buffer[..len]).map_err(|error| invalid_data(error.to_string()))?;
-        let response = self.handle_packet(&packet, peer);
-        let bytes = response
-            .to_bytes()
-            .map_err(|error| invalid_data(error.to_string()))?;
-        self.socket.send_to(&bytes, peer)?;
-        self.flush_pending_notifications();
-        Ok(())
-    }
 }
 
 enum Block1Outcome {
@@ -552,6 +783,11 @@ pub fn packet_to_request(
     // Extract CoAP token for observer tracking.
     request.token = packet.get_token().to_vec();
 
+    if let Some(tags) = packet.get_option(CoapOption::IfMatch) {
+        request.if_match = tags.iter().cloned().collect();
+    }
+    request.if_none_match = packet.get_option(CoapOption::IfNoneMatch).is_some();
+
     Ok(request)
 }
 
@@ -600,7 +836,7 @@ fn route_coreconf_path(segments: &[&str], resource: &[&str]) -> Option<(Interfac
     Some((interface, consumed))
 }
 
-fn advertised_paths(resource_path: &str) -> (String, String) {
+pub(crate) fn advertised_paths(resource_path: &str) -> (String, String) {
     let management_path = resource_segments(resource_path).join("/");
     let streaming_path = if management_path == "c" {
         "s".to_string()
@@ -620,7 +856,7 @@ pub fn response_to_packet(request: &Packet, response: Response) -> Packet {
     if !response.payload.is_empty() {
         packet.payload = response.payload;
         if let Some(format) = response.content_format {
-            packet.set_content_format(content_format_to_coap(format));
+            set_content_format(&mut packet, format);
         }
     }
 
@@ -628,6 +864,12 @@ pub fn response_to_packet(request: &Packet, response: Response) -> Packet {
     if let Some(sequence) = response.observe {
         packet.set_observe_value(sequence);
     }
+    if let Some(max_age) = response.max_age {
+        packet.add_option_as(CoapOption::MaxAge, OptionValueU32(max_age));
+    }
+    if let Some(etag) = response.etag {
+        packet.add_option(CoapOption::ETag, etag);
+    }
 
     packet
 }
@@ -717,18 +959,25 @@ fn content_format_from_raw(method: Method, raw: u16) -> Option<ContentFormat> {
         (_, 141) => Some(ContentFormat::YangDataCbor),
         (_, 142) | (_, 140) => Some(ContentFormat::YangDataCbor),
         (_, 143) | (_, 63) | (_, 271) => Some(ContentFormat::YangInstancesCborSeq),
+        (Method::IPatch, 65000) => Some(ContentFormat::YangPatchCbor),
         _ => None,
     }
 }
 
-fn content_format_to_coap(format: ContentFormat) -> CoapContentFormat {
+fn set_content_format(packet: &mut Packet, format: ContentFormat) {
     // Use RFC 9595 format numbers when available; fall back to coap-lite
     // generics for broader compatibility.
-    match format {
+    let format = match format {
         ContentFormat::YangDataCbor => CoapContentFormat::ApplicationYangDataCborSid, // 140
         ContentFormat::YangIdentifiersCbor => CoapContentFormat::ApplicationCBOR,     // 60
         ContentFormat::YangInstancesCborSeq => CoapContentFormat::ApplicationCborSeq, // 63
-    }
+        // coap-lite has no variant for unregistered numbers.
+        ContentFormat::YangPatchCbor => {
+            packet.add_option_as(CoapOption::ContentFormat, OptionValueU16(format.as_u16()));
+            return;
+        }
+    };
+    packet.set_content_format(format);
 }
 
 fn content_format_from_coap(method: Method, format: CoapContentFormat) -> Option<ContentFormat> {
@@ -753,12 +1002,16 @@ fn response_code_to_coap(code: ResponseCode) -> MessageClass {
         ResponseCode::BadRequest => MessageClass::Response(ResponseType::BadRequest),
         ResponseCode::Unauthorized => MessageClass::Response(ResponseType::Unauthorized),
         ResponseCode::BadOption => MessageClass::Response(ResponseType::BadOption),
+        ResponseCode::Forbidden => MessageClass::Response(ResponseType::Forbidden),
         ResponseCode::NotFound => MessageClass::Response(ResponseType::NotFound),
         ResponseCode::MethodNotAllowed => MessageClass::Response(ResponseType::MethodNotAllowed),
         ResponseCode::RequestEntityIncomplete => {
             MessageClass::Response(ResponseType::RequestEntityIncomplete)
         }
         ResponseCode::Conflict => MessageClass::Response(ResponseType::Conflict),
+        ResponseCode::PreconditionFailed => {
+            MessageClass::Response(ResponseType::PreconditionFailed)
+        }
         ResponseCode::RequestEntityTooLarge => {
             MessageClass::Response(ResponseType::RequestEntityTooLarge)
         }
@@ -771,7 +1024,137 @@ fn response_code_to_coap(code: ResponseCode) -> MessageClass {
     }
 }
 
-fn ensure_success(packet: &Packet) -> Result<()> {
+/// A confirmable request on the CORECONF resource, without message id or
+/// token; the client assigns those.
+pub(crate) fn coreconf_request_packet(
+    resource_path: &str,
+    datastore: Option<DatastoreName>,
+    method: RequestType,
+    path: Option<&str>,
+    payload: Vec<u8>,
+    content_format: Option<ContentFormat>,
+) -> Packet {
+    let mut packet = Packet::new();
+    packet.header.code = MessageClass::Request(method);
+    packet.header.set_type(MessageType::Confirmable);
+    add_uri_path(&mut packet, resource_path);
+    if let Some(path) = path {
+        add_uri_path(&mut packet, path);
+    }
+    if let Some(datastore) = datastore {
+        packet.add_option(CoapOption::UriQuery, format!("ds={datastore}").into_bytes());
+    }
+
+    if !payload.is_empty() {
+        packet.payload = payload;
+        if let Some(format) = content_format {
+            set_content_format(&mut packet, format);
+        }
+    }
+    packet
+}
+
+/// Make `packet` conditional on the target's entity-tag being `etag`.
+pub(crate) fn add_if_match(packet: &mut Packet, etag: Option<&[u8]>) {
+    if let Some(etag) = etag {
+        packet.add_option(CoapOption::IfMatch, etag.to_vec());
+    }
+}
+
+/// Address the read in `packet` to running when no datastore is set, as
+/// edits are, so its entity-tag is the one they are checked against.
+pub(crate) fn read_edited_datastore(packet: &mut Packet, datastore: Option<DatastoreName>) {
+    if datastore.is_none() {
+        packet.add_option(
+            CoapOption::UriQuery,
+            format!("ds={}", DatastoreName::Running).into_bytes(),
+        );
+    }
+}
+
+/// The entity-tag a response carries, if any.
+pub(crate) fn response_etag(packet: &Packet) -> Option<Vec<u8>> {
+    packet.get_first_option(CoapOption::ETag).cloned()
+}
+
+pub(crate) fn discovery_packet(query: Option<&str>) -> Packet {
+    let mut packet = Packet::new();
+    packet.header.code = MessageClass::Request(RequestType::Get);
+    packet.header.set_type(MessageType::Confirmable);
+    add_uri_path(&mut packet, "/.well-known/core");
+    if let Some(query) = query.filter(|q| !q.is_empty()) {
+        packet.add_option(CoapOption::UriQuery, query.as_bytes().to_vec());
+    }
+    packet
+}
+
+/// CBOR for a request payload; `None` is an empty payload.
+pub(crate) fn encode_value(value: Option<&Value>) -> Result<Vec<u8>> {
+    let mut payload = Vec::new();
+    if let Some(value) = value {
+        ciborium::into_writer(value, &mut payload)
+            .map_err(|error| CoreconfError::CborEncode(error.to_string()))?;
+    }
+    Ok(payload)
+}
+
+pub(crate) fn discovery_output(response: Packet) -> Result<String> {
+    ensure_success(&response)?;
+    String::from_utf8(response.payload).map_err(|error| invalid_data(error.to_string()))
+}
+
+/// The identifier-keyed tree in a whole-datastore response.
+pub(crate) fn snapshot_output(model: &CompositeModel, response: &Packet) -> Result<Value> {
+    ensure_success(response)?;
+    let json = coreconf_model::decode_cbor_to_json(model, &response.payload)?;
+    serde_json::from_str(&json).map_err(CoreconfError::from)
+}
+
+/// The identifier-keyed value in a GET response for `path`, `None` on 4.04.
+pub(crate) fn path_output(
+    model: &CompositeModel,
+    path: &str,
+    response: &Packet,
+) -> Result<Option<Value>> {
+    if matches!(
+        response.header.code,
+        MessageClass::Response(ResponseType::NotFound)
+    ) {
+        return Ok(None);
+    }
+    ensure_success(response)?;
+    let sid_value = coreconf_model::codec::cbor_to_json_value(&response.payload)?;
+    let parsed = crate::PredicatePath::parse(path)?;
+    model
+        .sid_value_to_identifier_value_at_path(sid_value, &parsed.canonical_path)
+        .map(Some)
+}
+
+/// The name-keyed output of an operation, if it returned any.
+pub(crate) fn operation_output(response: &Packet) -> Result<Option<Value>> {
+    ensure_success(response)?;
+    if response.payload.is_empty() {
+        return Ok(None);
+    }
+    coreconf_model::codec::cbor_to_json_value(&response.payload).map(Some)
+}
+
+pub(crate) fn revisions_output(output: Option<Value>) -> Result<Vec<JournalEntry>> {
+    output
+        .as_ref()
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(JournalEntry::from_value)
+        .collect()
+}
+
+pub(crate) fn revision_output(output: Option<Value>) -> Result<JournalEntry> {
+    let output = output.ok_or_else(|| invalid_data("journal revision had no payload".into()))?;
+    JournalEntry::from_value(&output)
+}
+
+pub(crate) fn ensure_success(packet: &Packet) -> Result<()> {
     if matches!(
         packet.header.code,
         MessageClass::Response(ResponseType::Created)
@@ -781,13 +1164,19 @@ fn ensure_success(packet: &Packet) -> Result<()> {
         return Ok(());
     }
 
+    if !packet.payload.is_empty()
+        && let Ok(error) = crate::error_payload::decode_error_payload(&packet.payload)
+    {
+        return Err(error.into());
+    }
+
     Err(CoreconfError::ValidationError(format!(
         "CoAP request failed with {:?}",
         packet.header.code
     )))
 }
 
-fn invalid_data(message: String) -> CoreconfError {
+pub(crate) fn invalid_data(message: String) -> CoreconfError {
     CoreconfError::Io(std::io::Error::new(
         std::io::ErrorKind::InvalidData,
         message,
@@ -809,7 +1198,7 @@ mod tests {
         let mut packet = request_packet(RequestType::IPatch, path);
         packet.set_token(vec![0x44]);
         packet.payload = payload;
-        packet.set_content_format(content_format_to_coap(ContentFormat::YangDataCbor));
+        set_content_format(&mut packet, ContentFormat::YangDataCbor);
         packet.add_option_as(CoapOption::Block1, BlockValue::new(num, more, 16).unwrap());
         packet
     }
@@ -896,7 +1285,7 @@ mod tests {
 
     #[test]
     fn unknown_raw_root_ipatch_cannot_reach_handler() {
-        let handler = RequestHandler::new(crate::Datastore::new_in_memory(small_model()));
+        let handler = RequestHandler::new(crate::Datastore::new_in_memory(small_model())).unwrap();
         let mut server = CoapLiteServer::bind("127.0.0.1:0", "c", handler).unwrap();
         let peer: SocketAddr = "127.0.0.1:56831".parse().unwrap();
         let mut packet = request_packet(RequestType::IPatch, "/c");
@@ -923,6 +1312,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ensure_success_decodes_coreconf_error_payload() {
+        let handler = RequestHandler::new(crate::Datastore::new_in_memory(small_model())).unwrap();
+        let mut server = CoapLiteServer::bind("127.0.0.1:0", "c", handler).unwrap();
+        let peer: SocketAddr = "127.0.0.1:56832".parse().unwrap();
+        let packet = request_packet(RequestType::Delete, "/c/example:settings/text");
+
+        let response = server.handle_packet(&packet, peer);
+        let error = ensure_success(&response).unwrap_err();
+
+        assert_eq!(error.error_tag(), coreconf_model::ErrorTag::DataMissing);
+        assert_eq!(
+            error.data().and_then(|data| data.path.as_deref()),
+            Some("/example:settings/text")
+        );
+    }
+
+    #[test]
+    fn packet_to_request_reads_conditions() {
+        let mut packet = request_packet(RequestType::Delete, "/c/example:settings/text");
+        packet.add_option(CoapOption::IfMatch, vec![1, 2]);
+        packet.add_option(CoapOption::IfMatch, Vec::new());
+        packet.add_option(CoapOption::IfNoneMatch, Vec::new());
+
+        let request = packet_to_request(&packet, "c").unwrap();
+
+        assert_eq!(request.if_match, [vec![1, 2], Vec::new()]);
+        assert!(request.if_none_match);
+        let plain = request_packet(RequestType::Delete, "/c/example:settings/text");
+        let request = packet_to_request(&plain, "c").unwrap();
+        assert!(request.if_match.is_empty() && !request.if_none_match);
+    }
+
     #[test]
     fn packet_to_request_rejects_unknown_coreconf_root() {
         let packet = request_packet(RequestType::Get, "/foo");
@@ -964,11 +1386,11 @@ mod tests {
 
     #[test]
     fn well_known_core_advertises_custom_resource_path() {
-        let handler = RequestHandler::new(crate::Datastore::new_in_memory(small_model()));
+        let handler = RequestHandler::new(crate::Datastore::new_in_memory(small_model())).unwrap();
         let server = CoapLiteServer::bind("127.0.0.1:0", "mgmt", handler).unwrap();
         let request = request_packet(RequestType::Get, "/.well-known/core");
 
-        let response = server.well_known_core_response(&request);
+        let response = server.session.well_known_core_response(&request);
         let payload = String::from_utf8(response.payload).unwrap();
 
         assert!(payload.contains("</mgmt>;rt=\"core.c.ds\""));
@@ -977,7 +1399,7 @@ mod tests {
 
     #[test]
     fn handle_packet_reassembles_block1_ipatch_before_dispatch() {
-        let handler = RequestHandler::new(crate::Datastore::new_in_memory(small_model()));
+        let handler = RequestHandler::new(crate::Datastore::new_in_memory(small_model())).unwrap();
         let mut server = CoapLiteServer::bind("127.0.0.1:0", "c", handler).unwrap();
         let peer: SocketAddr = "127.0.0.1:56830".parse().unwrap();
         let value = serde_json::json!("abcdefghijklmnopqrstuvwxyz");
@@ -1019,7 +1441,7 @@ mod tests {
         token: Vec<u8>,
         expected_wire_header: &[u8],
     ) -> std::result::Result<(), Box<dyn std::error::Error>> {
-        let handler = RequestHandler::new(crate::Datastore::new_in_memory(small_model()));
+        let handler = RequestHandler::new(crate::Datastore::new_in_memory(small_model())).unwrap();
         let mut server = CoapLiteServer::bind("127.0.0.1:0", "c", handler)?;
         let peer: SocketAddr = "127.0.0.1:56832".parse()?;
         let mut request = request_packet(RequestType::Get, "/c");
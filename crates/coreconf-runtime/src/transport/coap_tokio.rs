@@ -0,0 +1,695 @@
+//! Async CoAP client and server on tokio.
+//!
+//! These speak the same protocol as [`super::coap_lite`] and share its
+//! packet handling. The client matches responses to requests by token, so
+//! any number of requests and observations can be in flight on one socket;
+//! the server handles each packet on its own task, with reads running
+//! concurrently through a [`SharedHandler`].
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use coap_lite::block_handler::BlockValue;
+use coap_lite::{CoapOption, MessageClass, Packet, RequestType, ResponseType};
+use coreconf_model::{CompositeModel, CoreconfError, Result};
+use serde_json::Value;
+use tokio::net::{ToSocketAddrs, UdpSocket};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use super::coap_lite::{
+    Inbound, MAX_BLOCK_PAYLOAD, ServerSession, add_if_match, advertised_paths,
+    coreconf_request_packet, discovery_output, discovery_packet, encode_value, ensure_success,
+    invalid_data, operation_output, path_output, read_edited_datastore, response_etag,
+    response_to_packet, revision_output, revisions_output, snapshot_output,
+};
+use crate::coap_types::ContentFormat;
+use crate::commit::{CANCEL_COMMIT_PATH, COMMIT_PATH, DISCARD_CHANGES_PATH, commit_input};
+use crate::journal::{JournalEntry, REVISION_PATH, REVISIONS_PATH, ROLLBACK_PATH, revision_input};
+use crate::lock::{LOCK_PATH, UNLOCK_PATH, lock_input};
+use crate::nmda::DatastoreName;
+use crate::shared_handler::SharedHandler;
+
+/// How long a request waits for its response.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the server checks for an expired confirmed commit and, while
+/// anyone observes, for changed provided state.
+const CONFIRM_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Async counterpart of [`super::coap_lite::CoreconfClient`].
+///
+/// Methods take `&self` so one client can have several requests in flight.
+pub trait AsyncCoreconfClient: Sync {
+    fn fetch_snapshot(&self) -> impl Future<Output = Result<Value>> + Send;
+    fn fetch_path(&self, _path: &str) -> impl Future<Output = Result<Option<Value>>> + Send {
+        unsupported("path GET is not supported by this client")
+    }
+    fn apply_patch(
+        &self,
+        patch: &[(String, Option<Value>)],
+    ) -> impl Future<Output = Result<()>> + Send;
+    /// Send a yang-instances+cbor-seq payload as one root iPATCH.
+    fn apply_instances(&self, _payload: &[u8]) -> impl Future<Output = Result<()>> + Send {
+        unsupported("instance iPATCH is not supported by this client")
+    }
+    /// [`AsyncCoreconfClient::fetch_snapshot`] of the datastore edits go
+    /// to, running unless one is set, with its entity-tag if the server
+    /// sent one.
+    fn fetch_tagged_snapshot(
+        &self,
+    ) -> impl Future<Output = Result<(Value, Option<Vec<u8>>)>> + Send {
+        async { Ok((self.fetch_snapshot().await?, None)) }
+    }
+    /// [`AsyncCoreconfClient::apply_instances`] only if the datastore's
+    /// entity-tag is still `etag`, returning the entity-tag after the edit.
+    fn apply_instances_if_match(
+        &self,
+        _payload: &[u8],
+        _etag: &[u8],
+    ) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send {
+        unsupported("conditional iPATCH is not supported by this client")
+    }
+    fn discover(&self, _query: Option<&str>) -> impl Future<Output = Result<String>> + Send {
+        unsupported("discovery is not supported by this client")
+    }
+    /// Commit candidate into running; with a timeout, as a confirmed commit.
+    fn commit(
+        &self,
+        _confirm_timeout: Option<Duration>,
+    ) -> impl Future<Output = Result<()>> + Send {
+        unsupported("commit is not supported by this client")
+    }
+    fn cancel_commit(&self) -> impl Future<Output = Result<()>> + Send {
+        unsupported("cancel-commit is not supported by this client")
+    }
+    fn discard_changes(&self) -> impl Future<Output = Result<()>> + Send {
+        unsupported("discard-changes is not supported by this client")
+    }
+    /// Journal entries of the server, newest first and without patches.
+    fn journal_revisions(&self) -> impl Future<Output = Result<Vec<JournalEntry>>> + Send {
+        unsupported("the change journal is not supported by this client")
+    }
+    fn journal_revision(
+        &self,
+        _revision: u64,
+    ) -> impl Future<Output = Result<JournalEntry>> + Send {
+        unsupported("the change journal is not supported by this client")
+    }
+    fn rollback_to(&self, _revision: u64) -> impl Future<Output = Result<()>> + Send {
+        unsupported("rollback is not supported by this client")
+    }
+    /// Lock `target` for this client until `timeout`, or the server's
+    /// default, elapses.
+    fn lock(
+        &self,
+        _target: DatastoreName,
+        _timeout: Option<Duration>,
+    ) -> impl Future<Output = Result<()>> + Send {
+        unsupported("locking is not supported by this client")
+    }
+    fn unlock(&self, _target: DatastoreName) -> impl Future<Output = Result<()>> + Send {
+        unsupported("locking is not supported by this client")
+    }
+}
+
+async fn unsupported<T>(message: &'static str) -> Result<T> {
+    Err(CoreconfError::ValidationError(message.into()))
+}
+
+pub struct AsyncCoapLiteClient {
+    socket: Arc<UdpSocket>,
+    endpoint: String,
+    resource_path: String,
+    model: Arc<CompositeModel>,
+    datastore: Option<DatastoreName>,
+    exchanges: Arc<Exchanges>,
+    receiver: JoinHandle<()>,
+}
+
+/// Requests and observations waiting for packets, keyed by token.
+#[derive(Default)]
+struct Exchanges {
+    next_message_id: AtomicU16,
+    next_token: AtomicU64,
+    responses: Mutex<HashMap<Vec<u8>, oneshot::Sender<Packet>>>,
+    observations: Mutex<HashMap<Vec<u8>, mpsc::UnboundedSender<Packet>>>,
+}
+
+/// Notifications for one observation started with
+/// [`AsyncCoapLiteClient::observe`].
+///
+/// Dropping it cancels the observation on the server as well.
+pub struct Observation {
+    token: Vec<u8>,
+    model: Arc<CompositeModel>,
+    notifications: mpsc::UnboundedReceiver<Packet>,
+    exchanges: Arc<Exchanges>,
+    socket: Arc<UdpSocket>,
+    /// The registration request with Observe=1, sent on drop.
+    deregistration: Packet,
+}
+
+impl AsyncCoapLiteClient {
+    pub async fn connect(
+        model: impl Into<Arc<CompositeModel>>,
+        endpoint: impl ToSocketAddrs,
+        resource_path: impl Into<String>,
+    ) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.connect(endpoint).await?;
+        let endpoint = socket.peer_addr()?.to_string();
+        let socket = Arc::new(socket);
+        let exchanges = Arc::new(Exchanges {
+            next_message_id: AtomicU16::new(1),
+            ..Exchanges::default()
+        });
+        let receiver = tokio::spawn(receive(Arc::clone(&socket), Arc::clone(&exchanges)));
+
+        Ok(Self {
+            socket,
+            endpoint,
+            resource_path: resource_path.into(),
+            model: model.into(),
+            datastore: None,
+            exchanges,
+            receiver,
+        })
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Address requests to one NMDA datastore (`ds=`) instead of the
+    /// server's defaults.
+    pub fn set_datastore(&mut self, datastore: Option<DatastoreName>) {
+        self.datastore = datastore;
+    }
+
+    pub fn datastore(&self) -> Option<DatastoreName> {
+        self.datastore
+    }
+
+    /// Observe the subtrees at the schema node identifiers `paths` on the
+    /// streaming resource. Each notification carries the whole datastore.
+    pub async fn observe(&self, paths: &[&str]) -> Result<Observation> {
+        let mut payload = Vec::new();
+        for path in paths {
+            let sid = self.model.get_sid(path).ok_or_else(|| {
+                CoreconfError::ValidationError(format!("unknown schema node {path}"))
+            })?;
+            ciborium::into_writer(&sid, &mut payload)
+                .map_err(|error| CoreconfError::CborEncode(error.to_string()))?;
+        }
+        let (_, streaming_path) = advertised_paths(&self.resource_path);
+        let mut packet = coreconf_request_packet(
+            &streaming_path,
+            None,
+            RequestType::Fetch,
+            None,
+            payload,
+            Some(ContentFormat::YangIdentifiersCbor),
+        );
+        let mut deregistration = packet.clone();
+        deregistration.set_observe_value(1);
+        packet.set_observe_value(0);
+
+        // Register before sending so no notification can slip past.
+        let token = self.exchanges.token();
+        let (sender, notifications) = mpsc::unbounded_channel();
+        lock(&self.exchanges.observations).insert(token.clone(), sender);
+        let observation = Observation {
+            token: token.clone(),
+            model: Arc::clone(&self.model),
+            notifications,
+            exchanges: Arc::clone(&self.exchanges),
+            socket: Arc::clone(&self.socket),
+            deregistration,
+        };
+        let response = self.exchange(packet, token).await?;
+        ensure_success(&response)?;
+        Ok(observation)
+    }
+
+    /// Send an iPATCH request split across multiple Block1 transfers (RFC 7959),
+    /// returning the response to the last block.
+    async fn send_blockwise_ipatch(
+        &self,
+        path: Option<&str>,
+        payload: &[u8],
+        content_format: ContentFormat,
+        if_match: Option<&[u8]>,
+    ) -> Result<Packet> {
+        // All blocks of one transfer share a token.
+        let token = self.exchanges.token();
+        let blocks: Vec<&[u8]> = payload.chunks(MAX_BLOCK_PAYLOAD).collect();
+        let total = blocks.len();
+
+        for (i, chunk) in blocks.iter().enumerate() {
+            let more = i + 1 < total;
+            let block = BlockValue::new(i, more, MAX_BLOCK_PAYLOAD)
+                .map_err(|e| invalid_data(e.to_string()))?;
+            let mut packet = self.build_packet(
+                RequestType::IPatch,
+                path,
+                chunk.to_vec(),
+                Some(content_format),
+            );
+            add_if_match(&mut packet, if_match);
+            packet.add_option_as(CoapOption::Block1, block);
+            let response = self.exchange(packet, token.clone()).await?;
+
+            if more {
+                if !matches!(
+                    response.header.code,
+                    MessageClass::Response(ResponseType::Continue)
+                ) {
+                    return Err(CoreconfError::ValidationError(format!(
+                        "Block1: expected Continue (2.31) for block {}/{}, got {:?}",
+                        i + 1,
+                        total,
+                        response.header.code
+                    )));
+                }
+            } else {
+                ensure_success(&response)?;
+                return Ok(response);
+            }
+        }
+        Err(invalid_data("iPATCH had no payload".into()))
+    }
+
+    /// Send a yang-instances+cbor-seq payload as one root iPATCH, in blocks
+    /// if it is large, and return the final response.
+    async fn send_instances(&self, payload: &[u8], if_match: Option<&[u8]>) -> Result<Packet> {
+        if payload.len() > MAX_BLOCK_PAYLOAD {
+            return self
+                .send_blockwise_ipatch(None, payload, ContentFormat::YangInstancesCborSeq, if_match)
+                .await;
+        }
+        let mut packet = self.build_packet(
+            RequestType::IPatch,
+            None,
+            payload.to_vec(),
+            Some(ContentFormat::YangInstancesCborSeq),
+        );
+        add_if_match(&mut packet, if_match);
+        let response = self.exchange(packet, self.exchanges.token()).await?;
+        ensure_success(&response)?;
+        Ok(response)
+    }
+
+    async fn send_coreconf_request(
+        &self,
+        method: RequestType,
+        path: Option<&str>,
+        payload: Vec<u8>,
+        content_format: Option<ContentFormat>,
+    ) -> Result<Packet> {
+        let packet = self.build_packet(method, path, payload, content_format);
+        self.exchange(packet, self.exchanges.token()).await
+    }
+
+    fn build_packet(
+        &self,
+        method: RequestType,
+        path: Option<&str>,
+        payload: Vec<u8>,
+        content_format: Option<ContentFormat>,
+    ) -> Packet {
+        coreconf_request_packet(
+            &self.resource_path,
+            self.datastore,
+            method,
+            path,
+            payload,
+            content_format,
+        )
+    }
+
+    /// Send `packet` under `token` and wait for the response carrying it.
+    async fn exchange(&self, mut packet: Packet, token: Vec<u8>) -> Result<Packet> {
+        packet.header.message_id = self
+            .exchanges
+            .next_message_id
+            .fetch_add(1, Ordering::Relaxed);
+        packet.set_token(token.clone());
+        let bytes = packet
+            .to_bytes()
+            .map_err(|error| invalid_data(error.to_string()))?;
+
+        let (sender, response) = oneshot::channel();
+        lock(&self.exchanges.responses).insert(token.clone(), sender);
+        if let Err(error) = self.socket.send(&bytes).await {
+            lock(&self.exchanges.responses).remove(&token);
+            return Err(error.into());
+        }
+        match tokio::time::timeout(RESPONSE_TIMEOUT, response).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(invalid_data("client receiver stopped".into())),
+            Err(_) => {
+                lock(&self.exchanges.responses).remove(&token);
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "no response from CoAP server",
+                )
+                .into())
+            }
+        }
+    }
+
+    /// POST a name-keyed operation and decode its name-keyed output.
+    async fn send_operation(&self, path: &str, input: Option<Value>) -> Result<Option<Value>> {
+        let response = self
+            .send_coreconf_request(
+                RequestType::Post,
+                Some(path),
+                encode_value(input.as_ref())?,
+                Some(ContentFormat::YangDataCbor),
+            )
+            .await?;
+        operation_output(&response)
+    }
+}
+
+impl Drop for AsyncCoapLiteClient {
+    fn drop(&mut self) {
+        self.receiver.abort();
+    }
+}
+
+impl AsyncCoreconfClient for AsyncCoapLiteClient {
+    async fn discover(&self, query: Option<&str>) -> Result<String> {
+        let packet = discovery_packet(query);
+        let response = self.exchange(packet, self.exchanges.token()).await?;
+        discovery_output(response)
+    }
+
+    async fn fetch_snapshot(&self) -> Result<Value> {
+        let response = self
+            .send_coreconf_request(RequestType::Get, None, Vec::new(), None)
+            .await?;
+        snapshot_output(&self.model, &response)
+    }
+
+    async fn fetch_tagged_snapshot(&self) -> Result<(Value, Option<Vec<u8>>)> {
+        let mut packet = self.build_packet(RequestType::Get, None, Vec::new(), None);
+        read_edited_datastore(&mut packet, self.datastore);
+        let response = self.exchange(packet, self.exchanges.token()).await?;
+        Ok((
+            snapshot_output(&self.model, &response)?,
+            response_etag(&response),
+        ))
+    }
+
+    async fn fetch_path(&self, path: &str) -> Result<Option<Value>> {
+        let response = self
+            .send_coreconf_request(RequestType::Get, Some(path), Vec::new(), None)
+            .await?;
+        path_output(&self.model, path, &response)
+    }
+
+    async fn apply_patch(&self, patch: &[(String, Option<Value>)]) -> Result<()> {
+        for (path, value) in patch {
+            let Some(value) = value else {
+                let response = self
+                    .send_coreconf_request(RequestType::Delete, Some(path), Vec::new(), None)
+                    .await?;
+                ensure_success(&response)?;
+                continue;
+            };
+
+            let payload = encode_value(Some(value))?;
+            if payload.len() <= MAX_BLOCK_PAYLOAD {
+                let response = self
+                    .send_coreconf_request(
+                        RequestType::IPatch,
+                        Some(path),
+                        payload,
+                        Some(ContentFormat::YangDataCbor),
+                    )
+                    .await?;
+                ensure_success(&response)?;
+            } else {
+                self.send_blockwise_ipatch(Some(path), &payload, ContentFormat::YangDataCbor, None)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply_instances(&self, payload: &[u8]) -> Result<()> {
+        self.send_instances(payload, None).await.map(drop)
+    }
+
+    async fn apply_instances_if_match(
+        &self,
+        payload: &[u8],
+        etag: &[u8],
+    ) -> Result<Option<Vec<u8>>> {
+        let response = self.send_instances(payload, Some(etag)).await?;
+        Ok(response_etag(&response))
+    }
+
+    async fn commit(&self, confirm_timeout: Option<Duration>) -> Result<()> {
+        self.send_operation(COMMIT_PATH, commit_input(confirm_timeout))
+            .await
+            .map(drop)
+    }
+
+    async fn cancel_commit(&self) -> Result<()> {
+        self.send_operation(CANCEL_COMMIT_PATH, None)
+            .await
+            .map(drop)
+    }
+
+    async fn discard_changes(&self) -> Result<()> {
+        self.send_operation(DISCARD_CHANGES_PATH, None)
+            .await
+            .map(drop)
+    }
+
+    async fn journal_revisions(&self) -> Result<Vec<JournalEntry>> {
+        let output = self.send_operation(REVISIONS_PATH, None).await?;
+        revisions_output(output)
+    }
+
+    async fn journal_revision(&self, revision: u64) -> Result<JournalEntry> {
+        let output = self
+            .send_operation(REVISION_PATH, Some(revision_input(revision)))
+            .await?;
+        revision_output(output)
+    }
+
+    async fn rollback_to(&self, revision: u64) -> Result<()> {
+        self.send_operation(ROLLBACK_PATH, Some(revision_input(revision)))
+            .await
+            .map(drop)
+    }
+
+    async fn lock(&self, target: DatastoreName, timeout: Option<Duration>) -> Result<()> {
+        self.send_operation(LOCK_PATH, Some(lock_input(target, timeout)))
+            .await
+            .map(drop)
+    }
+
+    async fn unlock(&self, target: DatastoreName) -> Result<()> {
+        self.send_operation(UNLOCK_PATH, Some(lock_input(target, None)))
+            .await
+            .map(drop)
+    }
+}
+
+impl Exchanges {
+    fn token(&self) -> Vec<u8> {
+        let token = self.next_token.fetch_add(1, Ordering::Relaxed);
+        token.to_be_bytes().to_vec()
+    }
+}
+
+/// Route every packet from the server to the request or observation
+/// waiting on its token; packets nobody waits for are dropped.
+async fn receive(socket: Arc<UdpSocket>, exchanges: Arc<Exchanges>) {
+    let mut buffer = [0u8; 1500];
+    loop {
+        let Ok(len) = socket.recv(&mut buffer).await else {
+            continue;
+        };
+        let Ok(packet) = Packet::from_bytes(&buffer[..len]) else {
+            continue;
+        };
+        let token = packet.get_token().to_vec();
+        if let Some(waiting) = lock(&exchanges.responses).remove(&token) {
+            let _ = waiting.send(packet);
+            continue;
+        }
+        let mut observations = lock(&exchanges.observations);
+        if let Some(observation) = observations.get(&token)
+            && observation.send(packet).is_err()
+        {
+            observations.remove(&token);
+        }
+    }
+}
+
+impl Observation {
+    /// The next notification's tree, or `None` once the client is gone.
+    pub async fn next(&mut self) -> Option<Result<Value>> {
+        let packet = self.notifications.recv().await?;
+        Some(snapshot_output(&self.model, &packet))
+    }
+}
+
+impl Drop for Observation {
+    /// Stop routing notifications here and deregister from the server. The
+    /// deregistration is sent without waiting; if it is lost, the server
+    /// keeps notifying and the client drops the packets.
+    fn drop(&mut self) {
+        lock(&self.exchanges.observations).remove(&self.token);
+        let mut packet = std::mem::take(&mut self.deregistration);
+        packet.header.message_id = self
+            .exchanges
+            .next_message_id
+            .fetch_add(1, Ordering::Relaxed);
+        packet.set_token(std::mem::take(&mut self.token));
+        if let Ok(bytes) = packet.to_bytes() {
+            let _ = self.socket.try_send(&bytes);
+        }
+    }
+}
+
+/// Async counterpart of [`super::coap_lite::CoapLiteServer`].
+///
+/// Each packet is handled on its own task, with the handler itself run on
+/// the blocking pool, so slow edits don't hold up reads.
+pub struct AsyncCoapLiteServer {
+    socket: Arc<UdpSocket>,
+    handler: SharedHandler,
+    session: Arc<Mutex<ServerSession>>,
+}
+
+impl AsyncCoapLiteServer {
+    pub async fn bind(
+        bind_addr: impl ToSocketAddrs,
+        resource_path: impl Into<String>,
+        handler: impl Into<SharedHandler>,
+    ) -> Result<Self> {
+        Ok(Self {
+            socket: Arc::new(UdpSocket::bind(bind_addr).await?),
+            handler: handler.into(),
+            session: Arc::new(Mutex::new(ServerSession::new(resource_path))),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.socket.local_addr().map_err(CoreconfError::from)
+    }
+
+    pub fn shared_handler(&self) -> &SharedHandler {
+        &self.handler
+    }
+
+    /// Serve until the socket fails, rolling back expired confirmed
+    /// commits and notifying observers of changed provided state on a
+    /// timer.
+    pub async fn run(self) -> Result<()> {
+        let mut confirm_check = tokio::time::interval(CONFIRM_CHECK_INTERVAL);
+        let mut buffer = [0u8; 1500];
+        loop {
+            tokio::select! {
+                received = self.socket.recv_from(&mut buffer) => {
+                    let (len, peer) = received?;
+                    let Ok(packet) = Packet::from_bytes(&buffer[..len]) else {
+                        continue;
+                    };
+                    tokio::spawn(self.respond(packet, peer));
+                }
+                _ = confirm_check.tick() => {
+                    let handler = self.handler.clone();
+                    let observed = lock(&self.session).has_observers();
+                    blocking(move || {
+                        let mut handler = handler.write();
+                        handler.expire_confirmed_commit()?;
+                        if observed {
+                            // A failing provider only holds back its own
+                            // notification.
+                            let _ = handler.refresh_provided_state();
+                        }
+                        Ok::<_, CoreconfError>(())
+                    })
+                    .await??;
+                    self.notify().await;
+                }
+            }
+        }
+    }
+
+    /// Answer one packet and, if it may have changed anything, push the
+    /// notifications it caused.
+    fn respond(&self, packet: Packet, peer: SocketAddr) -> impl Future<Output = ()> + 'static {
+        let inbound = lock(&self.session).accept(&packet, peer);
+        let socket = Arc::clone(&self.socket);
+        let handler = self.handler.clone();
+        let session = Arc::clone(&self.session);
+        async move {
+            let (response, mutates) = match inbound {
+                Inbound::Respond(response) => (response, false),
+                Inbound::Request { packet, request } => {
+                    let mutates = !request.is_read();
+                    let handler = handler.clone();
+                    let Ok(response) = blocking(move || handler.handle(&request)).await else {
+                        return;
+                    };
+                    (response_to_packet(&packet, response), mutates)
+                }
+            };
+            if let Ok(bytes) = response.to_bytes() {
+                let _ = socket.send_to(&bytes, peer).await;
+            }
+            if mutates {
+                send_notifications(&socket, &handler, &session).await;
+            }
+        }
+    }
+
+    async fn notify(&self) {
+        send_notifications(&self.socket, &self.handler, &self.session).await;
+    }
+}
+
+async fn send_notifications(
+    socket: &UdpSocket,
+    handler: &SharedHandler,
+    session: &Arc<Mutex<ServerSession>>,
+) {
+    if !lock(session).has_observers() {
+        return;
+    }
+    let handler = handler.clone();
+    let session = Arc::clone(session);
+    let Ok(notifications) =
+        blocking(move || lock(&session).notifications(&mut handler.write())).await
+    else {
+        return;
+    };
+    for (packet, peer) in notifications {
+        if let Ok(bytes) = packet.to_bytes() {
+            let _ = socket.send_to(&bytes, peer).await;
+        }
+    }
+}
+
+/// Run `work` on the blocking pool, as the handler takes std locks.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|error| invalid_data(error.to_string()))
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
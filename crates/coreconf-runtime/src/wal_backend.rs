@@ -0,0 +1,456 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use coreconf_model::instance_id::{
+    Instance, InstancePath, PathComponent, decode_instances_with_model,
+};
+use coreconf_model::{CompositeModel, CoreconfError, Result};
+use serde_json::Value;
+
+use crate::backend::Backend;
+use crate::datastore::{Datastore, tree_delete, tree_get, tree_set};
+use crate::file_backend::EditableFormat;
+use crate::list_index::ListIndex;
+use crate::path::PredicatePath;
+use crate::shared_tree::SharedTree;
+
+const SNAPSHOT_FILE: &str = "snapshot.cbor";
+const LOG_FILE: &str = "wal.log";
+
+/// Records appended between snapshots unless configured otherwise.
+pub const DEFAULT_COMPACTION_INTERVAL: usize = 1024;
+
+// Record frame: payload length and CRC-32 of the payload, both u32 LE.
+const FRAME_HEADER_LEN: usize = 8;
+// Payload: sequence number (u64 LE) followed by the change set.
+const SEQUENCE_LEN: usize = 8;
+
+/// Backend that makes every published tree durable before returning.
+///
+/// Each write appends a `yang-instances+cbor-seq` change set (deletions as
+/// null) to `wal.log` and fsyncs it: [`Backend::write_path`] and
+/// [`Backend::delete_path`] log the one instance they edit,
+/// [`Backend::apply_edits`] all of its edits in one record, and
+/// [`Backend::replace_tree`] the top-level nodes that changed. Every
+/// `compaction_interval` records the whole tree is written to
+/// `snapshot.cbor` and the log is truncated.
+///
+/// The tree is held as a [`SharedTree`], so snapshots share it and a
+/// replacement built from one is compared node by node only where it was
+/// written.
+///
+/// On open the snapshot is loaded and the log replayed up to the last
+/// complete record; a torn or corrupt tail left by a crash is discarded.
+pub struct WalBackend {
+    directory: PathBuf,
+    model: Arc<CompositeModel>,
+    tree: SharedTree,
+    index: ListIndex,
+    log: File,
+    log_len: u64,
+    sequence: u64,
+    records_since_snapshot: usize,
+    compaction_interval: usize,
+}
+
+impl WalBackend {
+    /// Open the log in `directory`, creating the directory if needed, and
+    /// recover the last durable tree.
+    pub fn open(
+        model: impl Into<Arc<CompositeModel>>,
+        directory: impl Into<PathBuf>,
+    ) -> Result<Self> {
+        let model = model.into();
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+
+        let (snapshot_sequence, snapshot_tree) = read_snapshot(&model, &directory)?;
+        let mut log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(directory.join(LOG_FILE))?;
+        let mut bytes = Vec::new();
+        log.read_to_end(&mut bytes)?;
+
+        let codec = Datastore::new_in_memory(Arc::clone(&model));
+        let mut tree = SharedTree::from(snapshot_tree);
+        let mut sequence = snapshot_sequence;
+        let mut records = 0usize;
+        let mut offset = 0usize;
+        while let Some((record_sequence, change_set, next)) = next_record(&bytes, offset) {
+            if record_sequence > sequence {
+                apply_change_set(&codec, &mut tree, change_set)?;
+                sequence = record_sequence;
+            }
+            records += 1;
+            offset = next;
+        }
+
+        // Drop a torn tail so new records follow the last complete one.
+        let log_len = offset as u64;
+        if log_len < bytes.len() as u64 {
+            log.set_len(log_len)?;
+            log.sync_all()?;
+        }
+        log.seek(SeekFrom::End(0))?;
+
+        Ok(Self {
+            directory,
+            model,
+            tree,
+            index: ListIndex::default(),
+            log,
+            log_len,
+            sequence,
+            records_since_snapshot: records,
+            compaction_interval: DEFAULT_COMPACTION_INTERVAL,
+        })
+    }
+
+    /// Snapshot after this many records; 0 never compacts automatically.
+    pub fn with_compaction_interval(mut self, records: usize) -> Self {
+        self.compaction_interval = records;
+        self
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Sequence number of the last durable change set, or 0 for a new log.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Write the current tree to the snapshot and truncate the log.
+    ///
+    /// The snapshot carries the sequence number it covers, so a crash
+    /// between the two steps only leaves records that replay skips.
+    pub fn compact(&mut self) -> Result<()> {
+        let mut bytes = self.sequence.to_le_bytes().to_vec();
+        bytes.extend(crate::file_backend::encode_editable_value(
+            &self.model,
+            &self.tree.to_value(),
+            EditableFormat::Cbor,
+        )?);
+        durable_write(&self.directory.join(SNAPSHOT_FILE), &bytes)?;
+        sync_directory(&self.directory)?;
+
+        self.log.set_len(0)?;
+        self.log.sync_all()?;
+        self.log.seek(SeekFrom::Start(0))?;
+        self.log_len = 0;
+        self.records_since_snapshot = 0;
+        Ok(())
+    }
+
+    fn append(&mut self, change_set: &[u8]) -> Result<()> {
+        let sequence = self.sequence + 1;
+        let mut payload = Vec::with_capacity(SEQUENCE_LEN + change_set.len());
+        payload.extend_from_slice(&sequence.to_le_bytes());
+        payload.extend_from_slice(change_set);
+        let length = u32::try_from(payload.len()).map_err(|_| {
+            CoreconfError::ValidationError("change set too large for the log".into())
+        })?;
+
+        let mut record = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+        record.extend_from_slice(&length.to_le_bytes());
+        record.extend_from_slice(&crc32(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+
+        if let Err(error) = self
+            .log
+            .write_all(&record)
+            .and_then(|()| self.log.sync_data())
+        {
+            // Best effort: don't leave a partial record ahead of later ones.
+            let _ = self.log.set_len(self.log_len);
+            return Err(error.into());
+        }
+        self.log_len += record.len() as u64;
+        self.sequence = sequence;
+        self.records_since_snapshot += 1;
+        Ok(())
+    }
+
+    /// Make `next` the tree once `change_set` is durable, compacting when
+    /// the interval is reached.
+    fn publish(&mut self, next: SharedTree, change_set: &[u8]) -> Result<()> {
+        if !change_set.is_empty() {
+            self.append(change_set)?;
+        }
+        self.tree = next;
+
+        if self.compaction_interval > 0 && self.records_since_snapshot >= self.compaction_interval {
+            // The change is already durable in the log; a failed snapshot
+            // is retried at the next interval.
+            let _ = self.compact();
+        }
+        Ok(())
+    }
+
+    /// Encode the top-level nodes that differ between the stored tree and
+    /// `next` as SID-keyed instances. Nodes `next` still shares with the
+    /// stored tree are skipped without being compared.
+    fn change_set(&self, next: &SharedTree) -> Result<Vec<u8>> {
+        let removed = self
+            .tree
+            .members()
+            .filter(|(key, _)| next.get(key).is_none());
+        let mut instances = Vec::new();
+        for (key, node) in next.members().chain(removed) {
+            let value = next.get(key);
+            if self.tree.get(key) == value {
+                continue;
+            }
+            let identifier = format!("/{key}");
+            let sid = self
+                .model
+                .get_sid(&identifier)
+                .ok_or_else(|| CoreconfError::SidNotFound(identifier.clone()))?;
+            let mut path = InstancePath::new();
+            path.push_delta(sid);
+            instances.push(match value {
+                Some(_) => Instance::new(path, node.to_value()),
+                None => Instance::delete(path),
+            });
+        }
+
+        self.codec().encode_instances(&instances)
+    }
+
+    /// Throwaway datastore for converting between paths and instances.
+    fn codec(&self) -> Datastore {
+        Datastore::new_in_memory(Arc::clone(&self.model))
+    }
+}
+
+impl Backend for WalBackend {
+    fn read_tree(&self) -> Result<Value> {
+        Ok(self.tree.to_value())
+    }
+
+    fn replace_tree(&mut self, next: Value) -> Result<()> {
+        self.replace_snapshot(SharedTree::from(next))
+    }
+
+    fn snapshot(&self) -> Result<SharedTree> {
+        Ok(self.tree.clone())
+    }
+
+    fn replace_snapshot(&mut self, next: SharedTree) -> Result<()> {
+        let change_set = self.change_set(&next)?;
+        self.publish(next, &change_set)?;
+        self.index.clear();
+        Ok(())
+    }
+
+    fn read_path(&self, model: &CompositeModel, path: &str) -> Result<Option<Value>> {
+        tree_get(&self.tree, model, path, Some(&self.index))
+    }
+
+    fn read_top_level(&self, _model: &CompositeModel, name: &str) -> Result<Option<SharedTree>> {
+        Ok(self.tree.get(name).cloned())
+    }
+
+    fn write_path(&mut self, model: &CompositeModel, path: &str, value: Value) -> Result<()> {
+        let mut next = self.tree.clone();
+        if PredicatePath::parse(path)?.canonical_path == "/" {
+            tree_set(&mut next, model, path, value, None)?;
+            return self.replace_snapshot(next);
+        }
+        let change_set = self.codec().encode_edits([(path, Some(&value))])?;
+        tree_set(&mut next, model, path, value, Some(&mut self.index))?;
+        self.publish(next, &change_set)
+            .inspect_err(|_| self.index.clear())
+    }
+
+    fn delete_path(&mut self, model: &CompositeModel, path: &str) -> Result<bool> {
+        let mut next = self.tree.clone();
+        if PredicatePath::parse(path)?.canonical_path == "/" {
+            let deleted = tree_delete(&mut next, model, path, None)?;
+            self.replace_snapshot(next)?;
+            return Ok(deleted);
+        }
+        if !tree_delete(&mut next, model, path, Some(&mut self.index))? {
+            return Ok(false);
+        }
+        let change_set = self.codec().encode_edits([(path, None)])?;
+        self.publish(next, &change_set)
+            .inspect_err(|_| self.index.clear())?;
+        Ok(true)
+    }
+
+    fn apply_edits(
+        &mut self,
+        model: &CompositeModel,
+        edits: &[(String, Option<Value>)],
+    ) -> Result<()> {
+        let mut next = self.tree.clone();
+        // After a whole-tree edit the index no longer matches, and the
+        // record holds the top-level nodes that changed.
+        let mut whole_tree = false;
+        for (path, value) in edits {
+            whole_tree |= PredicatePath::parse(path)?.canonical_path == "/";
+            let index = (!whole_tree).then_some(&mut self.index);
+            match value {
+                Some(value) => tree_set(&mut next, model, path, value.clone(), index),
+                None => tree_delete(&mut next, model, path, index).map(drop),
+            }
+            .inspect_err(|_| self.index.clear())?;
+        }
+        let change_set = if whole_tree {
+            self.change_set(&next)?
+        } else {
+            self.codec().encode_edits(
+                edits
+                    .iter()
+                    .map(|(path, value)| (path.as_str(), value.as_ref())),
+            )?
+        };
+        let published = self.publish(next, &change_set);
+        if whole_tree || published.is_err() {
+            self.index.clear();
+        }
+        published
+    }
+}
+
+impl std::fmt::Debug for WalBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalBackend")
+            .field("directory", &self.directory)
+            .field("sequence", &self.sequence)
+            .field("records_since_snapshot", &self.records_since_snapshot)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Apply a change set written by [`WalBackend`] to `tree`, converting
+/// instance paths through `codec`.
+fn apply_change_set(codec: &Datastore, tree: &mut SharedTree, change_set: &[u8]) -> Result<()> {
+    let model = codec.model();
+    for instance in decode_instances_with_model(model, change_set)? {
+        let sid = instance
+            .path
+            .absolute_sid()
+            .filter(|sid| model.get_identifier(*sid).is_some())
+            .ok_or_else(|| CoreconfError::CborDecode("change set names an unknown SID".into()))?;
+        let keys: Vec<Value> = instance
+            .path
+            .components
+            .iter()
+            .filter_map(|component| match component {
+                PathComponent::KeyValue(value) => Some(value.clone()),
+                PathComponent::SidDelta(_) => None,
+            })
+            .collect();
+        let path = codec.create_xpath(sid, &keys)?;
+        match instance.value {
+            Some(value) => {
+                let canonical = PredicatePath::parse(&path)?.canonical_path;
+                let value = model.sid_value_to_identifier_value_at_path(value, &canonical)?;
+                tree_set(tree, model, &path, value, None)?;
+            }
+            None => {
+                tree_delete(tree, model, &path, None)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_snapshot(model: &CompositeModel, directory: &Path) -> Result<(u64, Value)> {
+    let bytes = match std::fs::read(directory.join(SNAPSHOT_FILE)) {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((0, Value::Object(serde_json::Map::new())));
+        }
+        Err(error) => return Err(error.into()),
+    };
+    let (sequence, tree) = bytes
+        .split_first_chunk::<SEQUENCE_LEN>()
+        .ok_or_else(|| CoreconfError::CborDecode("snapshot is missing its sequence".into()))?;
+    let json = coreconf_model::decode_cbor_to_json(model, tree)?;
+    Ok((u64::from_le_bytes(*sequence), serde_json::from_str(&json)?))
+}
+
+/// The record starting at `offset`: its sequence number, change set and the
+/// offset of the next record. `None` at the end of the log or at a torn or
+/// corrupt record.
+fn next_record(bytes: &[u8], offset: usize) -> Option<(u64, &[u8], usize)> {
+    let header = bytes.get(offset..offset + FRAME_HEADER_LEN)?;
+    let length = u32::from_le_bytes(header[..4].try_into().ok()?) as usize;
+    let checksum = u32::from_le_bytes(header[4..].try_into().ok()?);
+    let start = offset + FRAME_HEADER_LEN;
+    let payload = bytes.get(start..start.checked_add(length)?)?;
+    if length < SEQUENCE_LEN || crc32(payload) != checksum {
+        return None;
+    }
+    let (sequence, change_set) = payload.split_first_chunk::<SEQUENCE_LEN>()?;
+    Some((u64::from_le_bytes(*sequence), change_set, start + length))
+}
+
+fn durable_write(path: &Path, bytes: &[u8]) -> Result<()> {
+    let directory = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp_file = tempfile::NamedTempFile::new_in(directory)?;
+    temp_file.write_all(bytes)?;
+    temp_file.as_file().sync_all()?;
+    temp_file
+        .persist(path)
+        .map_err(|error| CoreconfError::Io(error.error))?;
+    Ok(())
+}
+
+/// Make a rename inside `directory` durable. Only Unix can fsync a directory.
+fn sync_directory(directory: &Path) -> Result<()> {
+    #[cfg(unix)]
+    File::open(directory)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = directory;
+    Ok(())
+}
+
+/// CRC-32 (IEEE 802.3), as used by zlib and PNG.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_reference_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn next_record_stops_at_torn_and_corrupt_records() {
+        let payload = [7u64.to_le_bytes().as_slice(), b"change"].concat();
+        let mut record = (payload.len() as u32).to_le_bytes().to_vec();
+        record.extend_from_slice(&crc32(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+
+        let (sequence, change_set, next) = next_record(&record, 0).unwrap();
+        assert_eq!(
+            (sequence, change_set, next),
+            (7, b"change".as_slice(), record.len())
+        );
+
+        assert!(next_record(&record[..record.len() - 1], 0).is_none());
+        let mut corrupt = record.clone();
+        *corrupt.last_mut().unwrap() ^= 0xFF;
+        assert!(next_record(&corrupt, 0).is_none());
+    }
+}
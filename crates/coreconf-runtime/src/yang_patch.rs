@@ -0,0 +1,356 @@
+//! YANG Patch edits (RFC 8072).
+//!
+//! A patch is an ordered list of edits, each naming an operation and a
+//! target predicate path. On the wire it is the name-keyed
+//! `ietf-yang-patch:yang-patch` container encoded as CBOR, with edit values
+//! keyed the way [`Datastore::get_path`] returns them.
+
+use coreconf_model::{CoreconfError, DataError, ErrorTag, Result};
+use serde_json::{Map, Value, json};
+
+use crate::datastore::{Datastore, Position};
+use crate::path::entry_list_path;
+
+/// Member holding the patch in a `yang-patch+cbor` payload.
+pub const YANG_PATCH_MEMBER: &str = "ietf-yang-patch:yang-patch";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditOperation {
+    /// Create the target; fails with `data-exists` if it is present.
+    Create,
+    /// Delete the target; fails with `data-missing` if it is absent.
+    Delete,
+    /// Create a list entry at a position in its list.
+    Insert,
+    /// Merge the value into the target, creating it if needed.
+    Merge,
+    /// Move an existing list entry to a position in its list.
+    Move,
+    /// Replace the target with the value, creating it if needed.
+    Replace,
+    /// Delete the target if it is present.
+    Remove,
+}
+
+impl EditOperation {
+    pub const ALL: [Self; 7] = [
+        Self::Create,
+        Self::Delete,
+        Self::Insert,
+        Self::Merge,
+        Self::Move,
+        Self::Replace,
+        Self::Remove,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Delete => "delete",
+            Self::Insert => "insert",
+            Self::Merge => "merge",
+            Self::Move => "move",
+            Self::Replace => "replace",
+            Self::Remove => "remove",
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|operation| operation.as_str() == input)
+    }
+
+    fn takes_value(self) -> bool {
+        matches!(
+            self,
+            Self::Create | Self::Insert | Self::Merge | Self::Replace
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    pub edit_id: String,
+    pub operation: EditOperation,
+    pub target: String,
+    pub value: Option<Value>,
+    /// Only used by `insert` and `move`; `None` means last.
+    pub position: Option<Position>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct YangPatch {
+    pub patch_id: String,
+    pub comment: Option<String>,
+    pub edits: Vec<Edit>,
+}
+
+impl Edit {
+    pub fn new(
+        edit_id: impl Into<String>,
+        operation: EditOperation,
+        target: impl Into<String>,
+    ) -> Self {
+        Self {
+            edit_id: edit_id.into(),
+            operation,
+            target: target.into(),
+            value: None,
+            position: None,
+        }
+    }
+
+    pub fn with_value(mut self, value: Value) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    pub fn with_position(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    fn from_value(value: &Value) -> Result<Self> {
+        let field = |name: &str| value.get(name).and_then(Value::as_str);
+        let edit_id = field("edit-id").ok_or_else(|| invalid_patch("edit has no edit-id"))?;
+        let operation = field("operation")
+            .ok_or_else(|| invalid_patch(&format!("edit '{edit_id}' has no operation")))?;
+        let operation = EditOperation::parse(operation).ok_or_else(|| {
+            invalid_patch(&format!(
+                "edit '{edit_id}' has unknown operation '{operation}'"
+            ))
+        })?;
+        let target = field("target")
+            .ok_or_else(|| invalid_patch(&format!("edit '{edit_id}' has no target")))?;
+        let position = match (field("where"), field("point")) {
+            (None, None) => None,
+            (Some("first"), _) => Some(Position::First),
+            (Some("last"), _) => Some(Position::Last),
+            (Some("before"), Some(point)) => Some(Position::Before(point.into())),
+            (Some("after"), Some(point)) => Some(Position::After(point.into())),
+            (Some("before" | "after"), None) => {
+                return Err(invalid_patch(&format!(
+                    "edit '{edit_id}' positions before or after without a point"
+                )));
+            }
+            (other, _) => {
+                return Err(invalid_patch(&format!(
+                    "edit '{edit_id}' has unknown where '{}'",
+                    other.unwrap_or_default()
+                )));
+            }
+        };
+        Ok(Self {
+            edit_id: edit_id.into(),
+            operation,
+            target: target.into(),
+            value: value.get("value").cloned(),
+            position,
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let mut edit = Map::new();
+        edit.insert("edit-id".into(), json!(self.edit_id));
+        edit.insert("operation".into(), json!(self.operation.as_str()));
+        edit.insert("target".into(), json!(self.target));
+        match &self.position {
+            None => {}
+            Some(Position::First) => {
+                edit.insert("where".into(), json!("first"));
+            }
+            Some(Position::Last) => {
+                edit.insert("where".into(), json!("last"));
+            }
+            Some(Position::Before(point)) => {
+                edit.insert("where".into(), json!("before"));
+                edit.insert("point".into(), json!(point));
+            }
+            Some(Position::After(point)) => {
+                edit.insert("where".into(), json!("after"));
+                edit.insert("point".into(), json!(point));
+            }
+        }
+        if let Some(value) = &self.value {
+            edit.insert("value".into(), value.clone());
+        }
+        Value::Object(edit)
+    }
+}
+
+impl YangPatch {
+    pub fn new(patch_id: impl Into<String>) -> Self {
+        Self {
+            patch_id: patch_id.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_edit(mut self, edit: Edit) -> Self {
+        self.edits.push(edit);
+        self
+    }
+
+    /// Parse the name-keyed form, with or without the
+    /// `ietf-yang-patch:yang-patch` wrapper.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let patch = value.get(YANG_PATCH_MEMBER).unwrap_or(value);
+        let patch_id = patch
+            .get("patch-id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid_patch("patch has no patch-id"))?;
+        let edits = patch
+            .get("edit")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(Edit::from_value)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            patch_id: patch_id.into(),
+            comment: patch
+                .get("comment")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            edits,
+        })
+    }
+
+    /// The name-keyed form, wrapped in `ietf-yang-patch:yang-patch`.
+    pub fn to_value(&self) -> Value {
+        let mut patch = Map::new();
+        patch.insert("patch-id".into(), json!(self.patch_id));
+        if let Some(comment) = &self.comment {
+            patch.insert("comment".into(), json!(comment));
+        }
+        patch.insert(
+            "edit".into(),
+            Value::Array(self.edits.iter().map(Edit::to_value).collect()),
+        );
+        json!({ YANG_PATCH_MEMBER: patch })
+    }
+
+    pub fn from_cbor(payload: &[u8]) -> Result<Self> {
+        let value = coreconf_model::codec::cbor_to_json_value(payload)?;
+        Self::from_value(&value)
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        ciborium::into_writer(&self.to_value(), &mut payload)
+            .map_err(|error| CoreconfError::CborEncode(error.to_string()))?;
+        Ok(payload)
+    }
+}
+
+/// Apply one edit to `datastore`. Errors name the edit and its target.
+pub(crate) fn apply_edit(datastore: &mut Datastore, edit: &Edit) -> Result<()> {
+    apply_operation(datastore, edit).map_err(|error| edit_failed(edit, error))
+}
+
+/// The path whose value `edit` changes: its target, or for `insert` and
+/// `move` the list holding it, since those reorder the whole list.
+pub(crate) fn changed_path(edit: &Edit) -> &str {
+    match edit.operation {
+        EditOperation::Insert | EditOperation::Move => {
+            entry_list_path(&edit.target).unwrap_or(&edit.target)
+        }
+        _ => &edit.target,
+    }
+}
+
+fn apply_operation(datastore: &mut Datastore, edit: &Edit) -> Result<()> {
+    let target = edit.target.as_str();
+    let value = || {
+        edit.value
+            .clone()
+            .ok_or_else(|| invalid_edit(target, "edit has no value"))
+    };
+    if !edit.operation.takes_value() && edit.value.is_some() {
+        return Err(invalid_edit(target, "edit must not carry a value"));
+    }
+    match edit.operation {
+        EditOperation::Create => {
+            if datastore.get_path(target)?.is_some() {
+                return Err(data_exists(target));
+            }
+            datastore.set_path(target, value()?)
+        }
+        EditOperation::Delete => match datastore.delete_path(target) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(data_missing(target)),
+            Err(error) => Err(error),
+        },
+        EditOperation::Remove => match datastore.delete_path(target) {
+            Err(error) if error.error_tag() != ErrorTag::DataMissing => Err(error),
+            _ => Ok(()),
+        },
+        EditOperation::Merge => datastore.merge_path(target, value()?),
+        EditOperation::Replace => datastore.set_path(target, value()?),
+        EditOperation::Insert => {
+            datastore.insert_entry(target, value()?, &edit.position.clone().unwrap_or_default())
+        }
+        EditOperation::Move => {
+            datastore.move_entry(target, &edit.position.clone().unwrap_or_default())
+        }
+    }
+}
+
+fn edit_failed(edit: &Edit, error: CoreconfError) -> CoreconfError {
+    let mut data = error
+        .data()
+        .cloned()
+        .unwrap_or_else(|| DataError::new(error.error_tag(), error.to_string()));
+    data.message = format!("edit '{}' failed: {}", edit.edit_id, data.message);
+    if data.path.is_none() {
+        data.path = Some(edit.target.clone());
+    }
+    data.into()
+}
+
+fn data_exists(path: &str) -> CoreconfError {
+    DataError::new(ErrorTag::DataExists, "data already exists")
+        .with_path(path)
+        .into()
+}
+
+fn data_missing(path: &str) -> CoreconfError {
+    DataError::new(ErrorTag::DataMissing, "data does not exist")
+        .with_path(path)
+        .into()
+}
+
+fn invalid_edit(path: &str, message: &str) -> CoreconfError {
+    DataError::new(ErrorTag::InvalidValue, message)
+        .with_path(path)
+        .into()
+}
+
+fn invalid_patch(message: &str) -> CoreconfError {
+    DataError::new(ErrorTag::InvalidValue, message)
+        .with_app_tag("invalid-yang-patch")
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patches_round_trip_through_their_name_keyed_form() {
+        let patch = YangPatch::new("p1")
+            .with_edit(
+                Edit::new("1", EditOperation::Insert, "/m:a/list[k='x']")
+                    .with_value(json!({"k": "x"}))
+                    .with_position(Position::Before("/m:a/list[k='y']".into())),
+            )
+            .with_edit(Edit::new("2", EditOperation::Remove, "/m:a/leaf"));
+
+        assert_eq!(YangPatch::from_value(&patch.to_value()).unwrap(), patch);
+        assert_eq!(
+            YangPatch::from_cbor(&patch.to_cbor().unwrap()).unwrap(),
+            patch
+        );
+    }
+}
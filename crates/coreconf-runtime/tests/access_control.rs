@@ -0,0 +1,382 @@
+use coreconf_model::{CompositeModel, ErrorTag};
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request};
+use coreconf_runtime::error_payload::decode_error_payload;
+use coreconf_runtime::journal::{REVISION_PATH, REVISIONS_PATH, revision_input};
+use coreconf_runtime::{
+    AccessControl, AccessOperation, AccessRule, Datastore, JournalEntry, OperationBinding,
+    RequestHandler, ResponseCode, RuleAction, RuleList, RuleTarget,
+};
+use serde_json::{Value, json};
+
+const ADMIN: &str = "10.0.0.1:5683";
+const GUEST: &str = "10.0.0.2:40000";
+const SENSOR_A: &str = "/example:sensors/sensor[id='a']";
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:settings","sid":60001},
+            {"identifier":"/example:settings/level","sid":60002,"type":"int32"},
+            {"identifier":"/example:sensors","sid":60003},
+            {"identifier":"/example:sensors/sensor","sid":60004},
+            {"identifier":"/example:sensors/sensor/id","sid":60005,"type":"string"},
+            {"identifier":"/example:sensors/sensor/label","sid":60006,"type":"string"},
+            {"identifier":"/example:sensors/sensor/secret","sid":60007,"type":"string"},
+            {"identifier":"/example:reboot","sid":60008}
+        ],
+        "key-mapping":{"60004":[60005]}
+    }"#])
+    .unwrap()
+}
+
+struct Reboot;
+
+impl OperationBinding for Reboot {
+    fn canonical_path(&self) -> &str {
+        "/example:reboot"
+    }
+
+    fn invoke(&self, _input: Option<&Value>) -> coreconf_model::Result<Option<Value>> {
+        Ok(None)
+    }
+}
+
+/// Admins may do anything; guests may change the level and nothing else,
+/// may not read secrets and may not reboot.
+fn access() -> AccessControl {
+    AccessControl::new()
+        .with_group("admin", ["10.0.0.1"])
+        .with_group("guest", ["10.0.0.2"])
+        .with_rule_list(
+            RuleList::new("admin")
+                .with_group("admin")
+                .with_rule(AccessRule::permit("all", RuleTarget::Any)),
+        )
+        .with_rule_list(
+            RuleList::new("guest")
+                .with_group("guest")
+                .with_rule(
+                    AccessRule::deny(
+                        "secrets",
+                        RuleTarget::path("/example:sensors/sensor/secret").unwrap(),
+                    )
+                    .with_operations([AccessOperation::Read]),
+                )
+                .with_rule(
+                    AccessRule::permit("level", RuleTarget::path("/example:settings").unwrap())
+                        .with_operations([AccessOperation::Update, AccessOperation::Create]),
+                )
+                .with_rule(
+                    AccessRule::deny("reboot", RuleTarget::operation("reboot"))
+                        .with_operations([AccessOperation::Exec]),
+                ),
+        )
+}
+
+fn handler() -> RequestHandler {
+    let mut running = Datastore::new_in_memory(model());
+    running
+        .set_path("/example:settings/level", json!(3))
+        .unwrap();
+    running
+        .set_path(
+            SENSOR_A,
+            json!({"id": "a", "label": "roof", "secret": "hunter2"}),
+        )
+        .unwrap();
+    let mut handler = RequestHandler::new(running).unwrap();
+    handler.register_operation(Box::new(Reboot));
+    handler.set_access_control(access()).unwrap();
+    handler
+}
+
+fn encode(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Value {
+    coreconf_model::codec::cbor_to_json_value(bytes).unwrap()
+}
+
+fn write(path: &str, value: Value) -> Request {
+    Request::new(Method::IPatch)
+        .with_path(path)
+        .with_payload(encode(&value), ContentFormat::YangDataCbor)
+}
+
+#[test]
+fn reads_leave_out_what_the_peer_may_not_read() {
+    let mut handler = handler();
+    let get = Request::new(Method::Get).with_path(SENSOR_A);
+
+    let guest = handler.handle(&get.clone().with_peer(GUEST));
+    assert_eq!(decode(&guest.payload), json!({"1": "a", "2": "roof"}));
+    let secret = Request::new(Method::Get)
+        .with_path(format!("{SENSOR_A}/secret"))
+        .with_peer(GUEST);
+    assert_eq!(handler.handle(&secret).code, ResponseCode::NotFound);
+
+    let admin = handler.handle(&get.clone().with_peer(ADMIN));
+    assert_eq!(
+        decode(&admin.payload),
+        json!({"1": "a", "2": "roof", "3": "hunter2"})
+    );
+    // Requests without a peer come from the server itself.
+    let local = handler.handle(&get);
+    assert_eq!(decode(&local.payload), decode(&admin.payload));
+
+    let fetch = Request::new(Method::Fetch)
+        .with_payload(encode(&json!(60004)), ContentFormat::YangIdentifiersCbor)
+        .with_peer(GUEST);
+    let response = handler.handle(&fetch);
+    assert_eq!(response.code, ResponseCode::Content);
+    assert!(!String::from_utf8_lossy(&response.payload).contains("hunter2"));
+    assert!(
+        !String::from_utf8_lossy(&handler.notification_payload(Some(GUEST)).unwrap())
+            .contains("hunter2")
+    );
+}
+
+#[test]
+fn journal_entries_leave_out_what_the_peer_may_not_read() {
+    let mut handler = handler();
+    let sensor_b = "/example:sensors/sensor[id='b']";
+    for request in [
+        write(&format!("{SENSOR_A}/secret"), json!("swordfish")),
+        write(sensor_b, json!({"1": "b", "2": "door", "3": "letmein"})),
+        Request::new(Method::Delete).with_path(SENSOR_A),
+    ] {
+        let response = handler.handle(&request.with_peer(ADMIN));
+        assert_eq!(response.code, ResponseCode::Changed);
+    }
+    let revision = |handler: &mut RequestHandler, peer: &str, revision: u64| {
+        let request = Request::new(Method::Post)
+            .with_path(REVISION_PATH)
+            .with_payload(
+                encode(&revision_input(revision)),
+                ContentFormat::YangDataCbor,
+            )
+            .with_peer(peer);
+        let response = handler.handle(&request);
+        assert_eq!(response.code, ResponseCode::Content);
+        JournalEntry::from_value(&decode(&response.payload)).unwrap()
+    };
+
+    let changed_secret = revision(&mut handler, GUEST, 1);
+    assert!(changed_secret.changed_paths.is_empty());
+    assert!(changed_secret.patch.is_empty());
+    assert!(changed_secret.inverse_patch.is_empty());
+
+    let created = revision(&mut handler, GUEST, 2);
+    assert_eq!(
+        created.patch,
+        vec![(
+            sensor_b.to_string(),
+            Some(json!({"id": "b", "label": "door"}))
+        )]
+    );
+    // Sensor a is gone from running, but its secret is still denied.
+    let deleted = revision(&mut handler, GUEST, 3);
+    assert_eq!(deleted.patch, vec![(SENSOR_A.to_string(), None)]);
+    assert_eq!(
+        deleted.inverse_patch,
+        vec![(
+            SENSOR_A.to_string(),
+            Some(json!({"id": "a", "label": "roof"}))
+        )]
+    );
+
+    let revisions = Request::new(Method::Post)
+        .with_path(REVISIONS_PATH)
+        .with_peer(GUEST);
+    let response = handler.handle(&revisions);
+    assert_eq!(response.code, ResponseCode::Content);
+    assert!(!String::from_utf8_lossy(&response.payload).contains("secret"));
+
+    let admin = revision(&mut handler, ADMIN, 1);
+    assert_eq!(
+        admin.inverse_patch,
+        vec![(format!("{SENSOR_A}/secret"), Some(json!("hunter2")))]
+    );
+}
+
+#[test]
+fn writes_need_create_update_or_delete_access() {
+    let mut handler = handler();
+
+    let level = write("/example:settings/level", json!(5));
+    assert_eq!(
+        handler.handle(&level.with_peer(GUEST)).code,
+        ResponseCode::Changed
+    );
+
+    let add = write(
+        "/example:sensors/sensor[id='b']",
+        json!({"1": "b", "2": "cellar"}),
+    )
+    .with_peer(GUEST);
+    let response = handler.handle(&add);
+    assert_eq!(response.code, ResponseCode::Forbidden);
+    let error = decode_error_payload(&response.payload).unwrap();
+    assert_eq!(error.tag, ErrorTag::AccessDenied);
+    assert_eq!(
+        error.path.as_deref(),
+        Some("/example:sensors/sensor[id='b']")
+    );
+
+    let delete = Request::new(Method::Delete).with_path(SENSOR_A);
+    assert_eq!(
+        handler.handle(&delete.clone().with_peer(GUEST)).code,
+        ResponseCode::Forbidden
+    );
+    assert!(handler.datastore().get_path(SENSOR_A).unwrap().is_some());
+    assert_eq!(handler.journal().entries().count(), 1);
+
+    assert_eq!(
+        handler.handle(&add.with_peer(ADMIN)).code,
+        ResponseCode::Changed
+    );
+    assert_eq!(
+        handler.handle(&delete.with_peer(ADMIN)).code,
+        ResponseCode::Changed
+    );
+}
+
+#[test]
+fn operations_need_exec_access() {
+    let mut handler = handler();
+    let reboot = Request::new(Method::Post).with_path("/example:reboot");
+
+    assert_eq!(
+        handler.handle(&reboot.clone().with_peer(GUEST)).code,
+        ResponseCode::Forbidden
+    );
+    assert_eq!(
+        handler.handle(&reboot.with_peer(ADMIN)).code,
+        ResponseCode::Changed
+    );
+
+    // Other operations fall back to the exec default, which permits.
+    let discard = Request::new(Method::Post)
+        .with_path("/ietf-netconf:discard-changes")
+        .with_peer(GUEST);
+    assert_eq!(handler.handle(&discard).code, ResponseCode::Changed);
+}
+
+#[test]
+fn the_first_matching_rule_decides() {
+    let mut handler = handler();
+    handler
+        .datastore_mut()
+        .set_path(
+            "/example:sensors/sensor[id='b']",
+            json!({"id": "b", "label": "cellar"}),
+        )
+        .unwrap();
+    let access = AccessControl::new()
+        .with_read_default(RuleAction::Deny)
+        .with_group("guest", [GUEST])
+        .with_rule_list(
+            RuleList::new("guest")
+                .with_group("guest")
+                .with_rule(
+                    AccessRule::deny(
+                        "roof",
+                        RuleTarget::path("/example:sensors/sensor[label='roof']").unwrap(),
+                    )
+                    .with_operations([AccessOperation::Read]),
+                )
+                .with_rule(AccessRule::permit(
+                    "sensors",
+                    RuleTarget::path("/example:sensors").unwrap(),
+                )),
+        );
+    handler.set_access_control(access).unwrap();
+
+    let read = |handler: &mut RequestHandler, path: &str| {
+        handler
+            .handle(&Request::new(Method::Get).with_path(path).with_peer(GUEST))
+            .code
+    };
+    assert_eq!(read(&mut handler, SENSOR_A), ResponseCode::NotFound);
+    assert_eq!(
+        read(&mut handler, "/example:sensors/sensor[id='b']"),
+        ResponseCode::Content
+    );
+    assert_eq!(
+        read(&mut handler, "/example:settings/level"),
+        ResponseCode::NotFound
+    );
+
+    // A peer in no group only gets the defaults.
+    let stranger = Request::new(Method::Get)
+        .with_path("/example:sensors/sensor[id='b']")
+        .with_peer("10.0.0.9:5683");
+    assert_eq!(handler.handle(&stranger).code, ResponseCode::NotFound);
+}
+
+#[test]
+fn edits_are_checked_where_they_are_made() {
+    let mut handler = handler();
+    let access = AccessControl::new()
+        .with_group("guest", [GUEST])
+        .with_rule_list(
+            RuleList::new("guest")
+                .with_group("guest")
+                .with_rule(AccessRule::deny("a", RuleTarget::path(SENSOR_A).unwrap()))
+                .with_rule(AccessRule::permit(
+                    "sensors",
+                    RuleTarget::path("/example:sensors").unwrap(),
+                )),
+        );
+    handler.set_access_control(access).unwrap();
+
+    // However the request quotes its keys, the rule's entry is found.
+    let label = write("/example:sensors/sensor[id=\"a\"]/label", json!("attic"));
+    let response = handler.handle(&label.with_peer(GUEST));
+    assert_eq!(response.code, ResponseCode::Forbidden);
+    assert_eq!(
+        decode_error_payload(&response.payload)
+            .unwrap()
+            .path
+            .as_deref(),
+        Some("/example:sensors/sensor[id='a']/label")
+    );
+
+    let add = write(
+        "/example:sensors/sensor[id='b']",
+        json!({"1": "b", "2": "cellar"}),
+    );
+    assert_eq!(
+        handler.handle(&add.with_peer(GUEST)).code,
+        ResponseCode::Changed
+    );
+    let label = write("/example:sensors/sensor[id='b']/label", json!("attic"));
+    assert_eq!(
+        handler.handle(&label.with_peer(GUEST)).code,
+        ResponseCode::Changed
+    );
+    assert_eq!(
+        handler
+            .datastore()
+            .get_path(&format!("{SENSOR_A}/label"))
+            .unwrap(),
+        Some(json!("roof"))
+    );
+}
+
+#[test]
+fn rules_must_name_model_nodes() {
+    let mut handler = handler();
+    let access =
+        AccessControl::new().with_rule_list(RuleList::new("all").with_group("*").with_rule(
+            AccessRule::deny("typo", RuleTarget::path("/example:sensor").unwrap()),
+        ));
+    assert!(handler.set_access_control(access).is_err());
+    assert_eq!(handler.access_control(), Some(&self::access()));
+}
@@ -0,0 +1,189 @@
+use ciborium::Value as CborValue;
+use coreconf_model::{CompositeModel, ErrorTag};
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request};
+use coreconf_runtime::journal::{REVISION_PATH, REVISIONS_PATH, ROLLBACK_PATH, revision_input};
+use coreconf_runtime::{Datastore, JournalEntry, RequestHandler, ResponseCode};
+use serde_json::json;
+
+const ENABLED: &str = "/example:settings/enabled";
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:devices","sid":60001},
+            {"identifier":"/example:devices/device","sid":60002},
+            {"identifier":"/example:devices/device/id","sid":60003,"type":"string"},
+            {"identifier":"/example:devices/device/enabled","sid":60004,"type":"boolean"},
+            {"identifier":"/example:settings","sid":60006},
+            {"identifier":"/example:settings/enabled","sid":60007,"type":"boolean"}
+        ],
+        "key-mapping":{"60002":[60003]}
+    }"#])
+    .unwrap()
+}
+
+fn encode_value(value: &serde_json::Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+fn decode_value(bytes: &[u8]) -> serde_json::Value {
+    coreconf_model::codec::cbor_to_json_value(bytes).unwrap()
+}
+
+fn set_enabled(handler: &mut RequestHandler, enabled: bool) {
+    let request = Request::new(Method::IPatch)
+        .with_path(ENABLED)
+        .with_payload(encode_value(&json!(enabled)), ContentFormat::YangDataCbor)
+        .with_peer("192.0.2.7:5683");
+    assert_eq!(handler.handle(&request).code, ResponseCode::Changed);
+}
+
+fn root_ipatch(instances: Vec<(CborValue, CborValue)>) -> Request {
+    let mut payload = Vec::new();
+    for instance in instances {
+        ciborium::into_writer(&CborValue::Map(vec![instance]), &mut payload).unwrap();
+    }
+    Request::new(Method::IPatch).with_payload(payload, ContentFormat::YangInstancesCborSeq)
+}
+
+fn operation(path: &str, input: Option<serde_json::Value>) -> Request {
+    let request = Request::new(Method::Post).with_path(path);
+    match input {
+        Some(input) => request.with_payload(encode_value(&input), ContentFormat::YangDataCbor),
+        None => request,
+    }
+}
+
+#[test]
+fn edits_to_running_are_journaled_with_inverse_patches() {
+    let mut handler = RequestHandler::new(Datastore::new_in_memory(model())).unwrap();
+
+    set_enabled(&mut handler, true);
+    set_enabled(&mut handler, false);
+    let delete = Request::new(Method::Delete).with_path(ENABLED);
+    assert_eq!(handler.handle(&delete).code, ResponseCode::Changed);
+
+    let journal = handler.journal();
+    assert_eq!(journal.latest_revision(), 3);
+    let first = journal.entry(1).unwrap();
+    assert_eq!(first.peer.as_deref(), Some("192.0.2.7:5683"));
+    assert_eq!(first.changed_paths, vec![ENABLED.to_string()]);
+    assert_eq!(first.patch, vec![(ENABLED.to_string(), Some(json!(true)))]);
+    // Setting the leaf created its container, so undoing it removes both.
+    assert_eq!(
+        first.inverse_patch,
+        vec![("/example:settings".to_string(), None)]
+    );
+    assert_eq!(
+        journal.entry(3).unwrap().inverse_patch,
+        vec![(ENABLED.to_string(), Some(json!(false)))]
+    );
+}
+
+#[test]
+fn candidate_edits_are_not_journaled_until_committed() {
+    let mut handler = RequestHandler::new(Datastore::new_in_memory(model())).unwrap();
+    let request = Request::new(Method::IPatch)
+        .with_path(ENABLED)
+        .with_payload(encode_value(&json!(true)), ContentFormat::YangDataCbor)
+        .with_query(coreconf_runtime::coap_types::QueryParams::parse(
+            "ds=candidate",
+        ));
+    assert_eq!(handler.handle(&request).code, ResponseCode::Changed);
+    assert_eq!(handler.journal().latest_revision(), 0);
+
+    handler.commit(None).unwrap();
+
+    let entry = handler.journal().entry(1).unwrap();
+    assert_eq!(entry.changed_paths, vec!["/example:settings".to_string()]);
+    assert_eq!(
+        entry.inverse_patch,
+        vec![("/example:settings".to_string(), None)]
+    );
+}
+
+#[test]
+fn rollback_restores_an_earlier_revision_and_is_journaled() {
+    let mut handler = RequestHandler::new(Datastore::new_in_memory(model())).unwrap();
+    set_enabled(&mut handler, true);
+    let response = handler.handle(&root_ipatch(vec![
+        (CborValue::from(60007), CborValue::Bool(false)),
+        (
+            // [list SID, key, delta to the leaf]
+            CborValue::Array(vec![
+                CborValue::from(60002),
+                CborValue::Text("rdc-1".into()),
+                CborValue::from(2),
+            ]),
+            CborValue::Bool(true),
+        ),
+    ]));
+    assert_eq!(response.code, ResponseCode::Changed);
+    let entries_after_patch = handler.journal().latest_revision();
+    assert_eq!(entries_after_patch, 2);
+
+    handler.rollback_to(1).unwrap();
+
+    assert_eq!(
+        handler.datastore().get_all().unwrap(),
+        json!({"example:settings": {"enabled": true}})
+    );
+    assert_eq!(handler.journal().latest_revision(), 3);
+
+    handler.rollback_to(2).unwrap();
+    assert_eq!(
+        handler
+            .datastore()
+            .get_path("/example:devices/device[id='rdc-1']/enabled")
+            .unwrap(),
+        Some(json!(true))
+    );
+
+    handler.rollback_to(0).unwrap();
+    assert_eq!(handler.datastore().get_all().unwrap(), json!({}));
+
+    let error = handler.rollback_to(99).unwrap_err();
+    assert_eq!(error.error_tag(), ErrorTag::InvalidValue);
+}
+
+#[test]
+fn journal_operations_are_served_over_post() {
+    let mut handler = RequestHandler::new(Datastore::new_in_memory(model())).unwrap();
+    set_enabled(&mut handler, true);
+    set_enabled(&mut handler, false);
+
+    let response = handler.handle(&operation(REVISIONS_PATH, None));
+    assert_eq!(response.code, ResponseCode::Content);
+    let revisions = decode_value(&response.payload);
+    let revisions: Vec<_> = revisions
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| JournalEntry::from_value(entry).unwrap().revision)
+        .collect();
+    assert_eq!(revisions, vec![2, 1]);
+
+    let response = handler.handle(&operation(REVISION_PATH, Some(revision_input(2))));
+    assert_eq!(response.code, ResponseCode::Content);
+    let entry = JournalEntry::from_value(&decode_value(&response.payload)).unwrap();
+    assert_eq!(entry.patch, vec![(ENABLED.to_string(), Some(json!(false)))]);
+    assert_eq!(
+        entry.inverse_patch,
+        vec![(ENABLED.to_string(), Some(json!(true)))]
+    );
+
+    let response = handler.handle(&operation(ROLLBACK_PATH, Some(revision_input(1))));
+    assert_eq!(response.code, ResponseCode::Changed);
+    assert_eq!(
+        handler.datastore().get_path(ENABLED).unwrap(),
+        Some(json!(true))
+    );
+
+    let response = handler.handle(&operation(REVISION_PATH, Some(revision_input(42))));
+    assert_eq!(response.code, ResponseCode::NotFound);
+}
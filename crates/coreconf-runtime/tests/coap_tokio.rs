@@ -0,0 +1,259 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use coreconf_model::CompositeModel;
+use coreconf_runtime::transport::coap_tokio::{
+    AsyncCoapLiteClient, AsyncCoapLiteServer, AsyncCoreconfClient,
+};
+use coreconf_runtime::{Datastore, MemoryBackend, RequestHandler};
+use serde_json::json;
+use tokio::task::JoinSet;
+
+fn model() -> Arc<CompositeModel> {
+    Arc::new(
+        CompositeModel::from_sid_strings(&[r#"{
+            "module-name":"example",
+            "module-revision":"2026-01-01",
+            "item":[
+                {"identifier":"example","sid":60000},
+                {"identifier":"/example:devices","sid":60001},
+                {"identifier":"/example:devices/device","sid":60002},
+                {"identifier":"/example:devices/device/id","sid":60003,"type":"string"},
+                {"identifier":"/example:devices/device/enabled","sid":60004,"type":"boolean"},
+                {"identifier":"/example:settings","sid":60006},
+                {"identifier":"/example:settings/level","sid":60007,"type":"int32"},
+                {"identifier":"/example:status","sid":60008},
+                {"identifier":"/example:status/uptime","sid":60009,"type":"uint32"}
+            ],
+            "key-mapping":{"60002":[60003]}
+        }"#])
+        .unwrap(),
+    )
+}
+
+async fn start_server(model: &Arc<CompositeModel>) -> SocketAddr {
+    let mut running = Datastore::new_in_memory(Arc::clone(model));
+    running
+        .set_path("/example:settings/level", json!(1))
+        .unwrap();
+    let mut handler = RequestHandler::new(running).unwrap();
+    handler.update_state(|state| state.set_path("/example:status/uptime", json!(7)).unwrap());
+    let server = AsyncCoapLiteServer::bind("127.0.0.1:0", "c", handler)
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    tokio::spawn(server.run());
+    addr
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn many_clients_edit_and_read_concurrently() {
+    let model = model();
+    let addr = start_server(&model).await;
+
+    let mut clients = JoinSet::new();
+    for client in 0..8 {
+        let model = Arc::clone(&model);
+        clients.spawn(async move {
+            let client_id = format!("dev-{client}");
+            let client_conn = AsyncCoapLiteClient::connect(model, addr, "c")
+                .await
+                .unwrap();
+            let path = format!("/example:devices/device[id='{client_id}']/enabled");
+            client_conn
+                .apply_patch(&[(path.clone(), Some(json!(true)))])
+                .await
+                .unwrap();
+            assert_eq!(
+                client_conn.fetch_path(&path).await.unwrap(),
+                Some(json!(true))
+            );
+        });
+    }
+    while let Some(done) = clients.join_next().await {
+        done.unwrap();
+    }
+
+    let client = AsyncCoapLiteClient::connect(model, addr, "c")
+        .await
+        .unwrap();
+    let snapshot = client.fetch_snapshot().await.unwrap();
+    assert_eq!(
+        snapshot["example:devices"]["device"]
+            .as_array()
+            .unwrap()
+            .len(),
+        8
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn one_client_keeps_many_requests_in_flight() {
+    let model = model();
+    let addr = start_server(&model).await;
+    let client = Arc::new(
+        AsyncCoapLiteClient::connect(model, addr, "c")
+            .await
+            .unwrap(),
+    );
+
+    let mut requests = JoinSet::new();
+    for _ in 0..32 {
+        let client = Arc::clone(&client);
+        requests.spawn(async move { client.fetch_path("/example:settings/level").await });
+    }
+    while let Some(response) = requests.join_next().await {
+        assert_eq!(response.unwrap().unwrap(), Some(json!(1)));
+    }
+    assert!(client.discover(None).await.unwrap().contains("core.c.ev"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn observers_are_notified_of_edits_from_other_clients() {
+    let model = model();
+    let addr = start_server(&model).await;
+    let observer = AsyncCoapLiteClient::connect(Arc::clone(&model), addr, "c")
+        .await
+        .unwrap();
+    let editor = AsyncCoapLiteClient::connect(model, addr, "c")
+        .await
+        .unwrap();
+
+    let mut settings = observer.observe(&["/example:settings"]).await.unwrap();
+    let mut devices = observer.observe(&["/example:devices"]).await.unwrap();
+    editor
+        .apply_patch(&[("/example:settings/level".into(), Some(json!(5)))])
+        .await
+        .unwrap();
+
+    let tree = tokio::time::timeout(Duration::from_secs(5), settings.next())
+        .await
+        .expect("no notification")
+        .unwrap()
+        .unwrap();
+    assert_eq!(tree["example:settings"]["level"], json!(5));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), devices.next())
+            .await
+            .is_err(),
+        "only observers of the edited subtree are notified"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn dropped_observations_are_cancelled_on_the_server() {
+    let model = model();
+    let running = Datastore::new_in_memory(Arc::clone(&model));
+    let server =
+        AsyncCoapLiteServer::bind("127.0.0.1:0", "c", RequestHandler::new(running).unwrap())
+            .await
+            .unwrap();
+    let addr = server.local_addr().unwrap();
+    let handler = server.shared_handler().clone();
+    tokio::spawn(server.run());
+    let client = AsyncCoapLiteClient::connect(model, addr, "c")
+        .await
+        .unwrap();
+
+    let observation = client.observe(&["/example:settings"]).await.unwrap();
+    assert!(handler.read().has_observers());
+    drop(observation);
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while handler.read().has_observers() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the observation was not cancelled");
+}
+
+#[tokio::test]
+async fn diffs_are_pushed_as_one_root_ipatch() {
+    let model = model();
+    let addr = start_server(&model).await;
+    let client = AsyncCoapLiteClient::connect(Arc::clone(&model), addr, "c")
+        .await
+        .unwrap();
+
+    let base = Datastore::new_in_memory(Arc::clone(&model));
+    let mut staged = Datastore::new_in_memory(Arc::clone(&model));
+    for id in 0..128 {
+        staged
+            .set_path(
+                &format!("/example:devices/device[id='device-{id:03}']"),
+                json!({"id": format!("device-{id:03}"), "enabled": id % 2 == 0}),
+            )
+            .unwrap();
+    }
+    let payload = base.encode_diff(&base.diff(&staged).unwrap()).unwrap();
+    assert!(payload.len() > 1024, "large enough to need Block1");
+
+    client.apply_instances(&payload).await.unwrap();
+    for id in ["device-000", "device-127"] {
+        let path = format!("/example:devices/device[id='{id}']");
+        assert_eq!(
+            client.fetch_path(&path).await.unwrap(),
+            staged.get_path(&path).unwrap()
+        );
+    }
+    assert_eq!(
+        client.fetch_path("/example:settings/level").await.unwrap(),
+        Some(json!(1))
+    );
+}
+
+#[tokio::test]
+async fn conditional_pushes_refuse_to_overwrite_other_edits() {
+    let model = model();
+    let addr = start_server(&model).await;
+    let client = AsyncCoapLiteClient::connect(Arc::clone(&model), addr, "c")
+        .await
+        .unwrap();
+    let other = AsyncCoapLiteClient::connect(Arc::clone(&model), addr, "c")
+        .await
+        .unwrap();
+
+    // Edits go to running, so that is the snapshot the tag comes with.
+    let (snapshot, etag) = client.fetch_tagged_snapshot().await.unwrap();
+    let etag = etag.expect("the datastore's entity-tag");
+    assert_eq!(snapshot.get("example:status"), None);
+    assert!(
+        client
+            .fetch_snapshot()
+            .await
+            .unwrap()
+            .get("example:status")
+            .is_some()
+    );
+    let base = Datastore::with_backend(Arc::clone(&model), MemoryBackend::new(snapshot.clone()));
+    let mut staged = Datastore::with_backend(Arc::clone(&model), MemoryBackend::new(snapshot));
+    staged
+        .set_path("/example:settings/level", json!(2))
+        .unwrap();
+    let payload = base.encode_diff(&base.diff(&staged).unwrap()).unwrap();
+
+    let next = client
+        .apply_instances_if_match(&payload, &etag)
+        .await
+        .unwrap()
+        .expect("the entity-tag after the edit");
+    assert_ne!(next, etag);
+    assert_eq!(client.fetch_tagged_snapshot().await.unwrap().1, Some(next));
+
+    other
+        .apply_patch(&[("/example:settings/level".into(), Some(json!(3)))])
+        .await
+        .unwrap();
+    assert!(
+        client
+            .apply_instances_if_match(&payload, &etag)
+            .await
+            .is_err()
+    );
+    assert_eq!(
+        client.fetch_path("/example:settings/level").await.unwrap(),
+        Some(json!(3))
+    );
+}
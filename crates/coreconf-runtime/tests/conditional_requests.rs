@@ -0,0 +1,178 @@
+use coreconf_model::{CompositeModel, ErrorTag};
+use coreconf_runtime::coap_types::{ContentFormat, Method, QueryParams, Request};
+use coreconf_runtime::commit::COMMIT_PATH;
+use coreconf_runtime::{Datastore, RequestHandler, Response, ResponseCode, decode_error_payload};
+use serde_json::{Value, json};
+
+const LEVEL: &str = "/example:settings/level";
+const NAME: &str = "/example:settings/name";
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:settings","sid":60001},
+            {"identifier":"/example:settings/level","sid":60002,"type":"int32"},
+            {"identifier":"/example:settings/name","sid":60003,"type":"string"},
+            {"identifier":"/example:status","sid":60004},
+            {"identifier":"/example:status/uptime","sid":60005,"type":"uint32"}
+        ]
+    }"#])
+    .unwrap()
+}
+
+fn handler() -> RequestHandler {
+    let mut running = Datastore::new_in_memory(model());
+    running.set_path(LEVEL, json!(1)).unwrap();
+    RequestHandler::new(running).unwrap()
+}
+
+fn encode(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+fn get(handler: &mut RequestHandler, path: &str) -> Response {
+    handler.handle(&Request::new(Method::Get).with_path(path))
+}
+
+fn etag(handler: &mut RequestHandler, path: &str) -> Vec<u8> {
+    get(handler, path).etag.expect("an entity-tag")
+}
+
+fn write(path: &str, value: Value) -> Request {
+    Request::new(Method::IPatch)
+        .with_path(path)
+        .with_payload(encode(&value), ContentFormat::YangDataCbor)
+}
+
+#[test]
+fn entity_tags_change_with_the_data_of_their_resource() {
+    let mut handler = handler();
+    let level = etag(&mut handler, LEVEL);
+    let settings = etag(&mut handler, "/example:settings");
+    let root = etag(&mut handler, "");
+    assert_eq!(etag(&mut handler, LEVEL), level);
+
+    handler
+        .datastore_mut()
+        .set_path(NAME, json!("edge"))
+        .unwrap();
+    assert_eq!(etag(&mut handler, LEVEL), level);
+    assert_ne!(etag(&mut handler, "/example:settings"), settings);
+    assert_ne!(etag(&mut handler, ""), root);
+
+    // Operational tags follow state as well; running's only follow
+    // configuration.
+    let root = etag(&mut handler, "");
+    let running = Request::new(Method::Get).with_query(QueryParams::parse("ds=running"));
+    let running_root = handler.handle(&running).etag;
+    handler.update_state(|state| state.set_path("/example:status/uptime", json!(42)).unwrap());
+    assert_ne!(etag(&mut handler, ""), root);
+    assert_eq!(handler.handle(&running).etag, running_root);
+    assert_eq!(etag(&mut handler, LEVEL), level);
+    let uptime = etag(&mut handler, "/example:status/uptime");
+    handler.update_state(|state| state.set_path("/example:status/uptime", json!(43)).unwrap());
+    assert_ne!(etag(&mut handler, "/example:status/uptime"), uptime);
+
+    // FETCH is tagged with the datastore as served to it, which leaves
+    // out state it did not select.
+    let fetch = Request::new(Method::Fetch)
+        .with_payload(encode(&json!(60002)), ContentFormat::YangIdentifiersCbor);
+    let fetched = handler.handle(&fetch).etag;
+    assert!(fetched.is_some());
+    handler.update_state(|state| state.set_path("/example:status/uptime", json!(44)).unwrap());
+    assert_eq!(handler.handle(&fetch).etag, fetched);
+
+    let candidate = Request::new(Method::Get)
+        .with_path(LEVEL)
+        .with_query(QueryParams::parse("ds=candidate"));
+    assert_eq!(handler.handle(&candidate).etag, Some(level));
+}
+
+#[test]
+fn writes_with_a_stale_if_match_fail_with_precondition_failed() {
+    let mut handler = handler();
+    let level = etag(&mut handler, LEVEL);
+
+    let response = handler.handle(&write(LEVEL, json!(2)).with_if_match(level.clone()));
+    assert_eq!(response.code, ResponseCode::Changed);
+    let fresh = response.etag.expect("the new entity-tag");
+    assert_eq!(fresh, etag(&mut handler, LEVEL));
+
+    let response = handler.handle(&write(LEVEL, json!(3)).with_if_match(level.clone()));
+    assert_eq!(response.code, ResponseCode::PreconditionFailed);
+    let error = decode_error_payload(&response.payload).unwrap();
+    assert_eq!(error.path.as_deref(), Some(LEVEL));
+    let delete = Request::new(Method::Delete)
+        .with_path(LEVEL)
+        .with_if_match(level);
+    assert_eq!(
+        handler.handle(&delete).code,
+        ResponseCode::PreconditionFailed
+    );
+    assert_eq!(handler.datastore().get_path(LEVEL).unwrap(), Some(json!(2)));
+
+    // Any of several tags will do.
+    let delete = Request::new(Method::Delete)
+        .with_path(LEVEL)
+        .with_if_match(vec![0; 8])
+        .with_if_match(fresh);
+    assert_eq!(handler.handle(&delete).code, ResponseCode::Changed);
+    assert_eq!(handler.datastore().get_path(LEVEL).unwrap(), None);
+}
+
+#[test]
+fn if_none_match_only_creates() {
+    let mut handler = handler();
+
+    let create = write(NAME, json!("edge")).with_if_none_match();
+    assert_eq!(handler.handle(&create).code, ResponseCode::Changed);
+    let response = handler.handle(&create);
+    assert_eq!(response.code, ResponseCode::PreconditionFailed);
+    assert_eq!(
+        decode_error_payload(&response.payload).unwrap().tag,
+        ErrorTag::DataExists
+    );
+
+    // An empty If-Match asks for the resource to exist.
+    let missing = Request::new(Method::Delete)
+        .with_path("/example:status")
+        .with_if_match(Vec::new());
+    assert_eq!(
+        handler.handle(&missing).code,
+        ResponseCode::PreconditionFailed
+    );
+    let present = write(NAME, json!("core")).with_if_match(Vec::new());
+    assert_eq!(handler.handle(&present).code, ResponseCode::Changed);
+}
+
+#[test]
+fn operations_are_conditional_on_the_whole_datastore() {
+    let mut handler = handler();
+    let candidate = write(LEVEL, json!(5)).with_query(QueryParams::parse("ds=candidate"));
+    assert_eq!(handler.handle(&candidate).code, ResponseCode::Changed);
+    let running = etag(&mut handler, "");
+
+    handler
+        .datastore_mut()
+        .set_path(NAME, json!("edge"))
+        .unwrap();
+    let commit = Request::new(Method::Post)
+        .with_path(COMMIT_PATH)
+        .with_if_match(running);
+    assert_eq!(
+        handler.handle(&commit).code,
+        ResponseCode::PreconditionFailed
+    );
+    assert_eq!(handler.datastore().get_path(LEVEL).unwrap(), Some(json!(1)));
+
+    let commit = Request::new(Method::Post)
+        .with_path(COMMIT_PATH)
+        .with_if_match(etag(&mut handler, ""));
+    assert_eq!(handler.handle(&commit).code, ResponseCode::Changed);
+    assert_eq!(handler.datastore().get_path(LEVEL).unwrap(), Some(json!(5)));
+}
@@ -0,0 +1,173 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use coreconf_model::{CompositeModel, ErrorTag};
+use coreconf_runtime::coap_types::{ContentFormat, Method, QueryParams, Request};
+use coreconf_runtime::commit::{COMMIT_PATH, commit_input};
+use coreconf_runtime::{
+    Datastore, RequestHandler, ResponseCode, TransactionContext, TransactionParticipant,
+    decode_error_payload,
+};
+use serde_json::json;
+
+const ENABLED: &str = "/example:settings/enabled";
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:settings","sid":60006},
+            {"identifier":"/example:settings/enabled","sid":60007,"type":"boolean"}
+        ]
+    }"#])
+    .unwrap()
+}
+
+fn encode_value(value: &serde_json::Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+/// A handler whose running has `enabled: false` and whose candidate has
+/// been edited to `enabled: true`.
+fn handler_with_candidate_edit() -> RequestHandler {
+    let mut running = Datastore::new_in_memory(model());
+    running.set_path(ENABLED, json!(false)).unwrap();
+    let mut handler = RequestHandler::new(running).unwrap();
+    handler
+        .datastores_mut()
+        .candidate_mut()
+        .set_path(ENABLED, json!(true))
+        .unwrap();
+    handler
+}
+
+fn running_enabled(handler: &RequestHandler) -> Option<serde_json::Value> {
+    handler.datastore().get_path(ENABLED).unwrap()
+}
+
+struct PathRecorder {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl TransactionParticipant for PathRecorder {
+    fn post_commit(&self, context: &TransactionContext<'_>) {
+        self.events.lock().unwrap().push(format!(
+            "{}:{:?}",
+            context.request().path,
+            context.changed_paths()
+        ));
+    }
+}
+
+#[test]
+fn commit_copies_candidate_into_running_and_notifies_participants() {
+    let mut handler = handler_with_candidate_edit();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    handler.register_transaction_participant(Box::new(PathRecorder {
+        events: Arc::clone(&events),
+    }));
+
+    handler.commit(None).unwrap();
+
+    assert_eq!(running_enabled(&handler), Some(json!(true)));
+    assert!(handler.pending_confirm().is_none());
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![r#"/ietf-netconf:commit:["/example:settings"]"#.to_string()]
+    );
+}
+
+#[test]
+fn unconfirmed_commit_rolls_back_when_timeout_elapses() {
+    let mut handler = handler_with_candidate_edit();
+
+    handler.commit(Some(Duration::ZERO)).unwrap();
+    assert_eq!(running_enabled(&handler), Some(json!(true)));
+
+    assert!(handler.expire_confirmed_commit().unwrap());
+    assert_eq!(running_enabled(&handler), Some(json!(false)));
+    assert!(handler.pending_confirm().is_none());
+    assert!(!handler.expire_confirmed_commit().unwrap());
+}
+
+#[test]
+fn confirming_commit_keeps_running_and_clears_pending() {
+    let mut handler = handler_with_candidate_edit();
+
+    handler.commit(Some(Duration::from_secs(60))).unwrap();
+    assert!(handler.pending_confirm().is_some());
+    handler.commit(None).unwrap();
+
+    assert!(handler.pending_confirm().is_none());
+    assert_eq!(running_enabled(&handler), Some(json!(true)));
+}
+
+#[test]
+fn repeated_confirmed_commits_roll_back_to_the_first_baseline() {
+    let mut handler = handler_with_candidate_edit();
+
+    handler.commit(Some(Duration::from_secs(60))).unwrap();
+    handler
+        .datastores_mut()
+        .candidate_mut()
+        .delete_path(ENABLED)
+        .unwrap();
+    handler.commit(Some(Duration::from_secs(60))).unwrap();
+    assert_eq!(running_enabled(&handler), None);
+
+    handler.cancel_commit().unwrap();
+
+    assert_eq!(running_enabled(&handler), Some(json!(false)));
+    let error = handler.cancel_commit().unwrap_err();
+    assert_eq!(error.error_tag(), ErrorTag::InvalidValue);
+}
+
+#[test]
+fn discard_changes_resets_candidate_to_running() {
+    let mut handler = handler_with_candidate_edit();
+
+    handler.discard_changes().unwrap();
+
+    assert_eq!(
+        handler.datastores().candidate().get_path(ENABLED).unwrap(),
+        Some(json!(false))
+    );
+}
+
+#[test]
+fn commit_operations_are_served_over_post() {
+    let mut handler = handler_with_candidate_edit();
+
+    let confirmed = Request::new(Method::Post)
+        .with_path(COMMIT_PATH)
+        .with_payload(
+            encode_value(&commit_input(Some(Duration::from_secs(60))).unwrap()),
+            ContentFormat::YangDataCbor,
+        );
+    assert_eq!(handler.handle(&confirmed).code, ResponseCode::Changed);
+    assert!(handler.pending_confirm().is_some());
+
+    let cancel = Request::new(Method::Post).with_path("/ietf-netconf:cancel-commit");
+    assert_eq!(handler.handle(&cancel).code, ResponseCode::Changed);
+    assert_eq!(running_enabled(&handler), Some(json!(false)));
+
+    let read = Request::new(Method::Get)
+        .with_path(ENABLED)
+        .with_query(QueryParams::parse("ds=candidate"));
+    assert_eq!(handler.handle(&read).code, ResponseCode::Content);
+
+    let bad_input = Request::new(Method::Post)
+        .with_path(COMMIT_PATH)
+        .with_payload(
+            encode_value(&json!({"confirmed": true, "confirm-timeout": 0})),
+            ContentFormat::YangDataCbor,
+        );
+    let response = handler.handle(&bad_input);
+    assert_eq!(response.code, ResponseCode::BadRequest);
+    let error = decode_error_payload(&response.payload).unwrap();
+    assert_eq!(error.tag, ErrorTag::InvalidValue);
+}
@@ -0,0 +1,183 @@
+use coreconf_model::CompositeModel;
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request};
+use coreconf_runtime::{Datastore, DiffOperation, MemoryBackend, RequestHandler, ResponseCode};
+use serde_json::{Value, json};
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:sensors","sid":60001},
+            {"identifier":"/example:sensors/sensor","sid":60002},
+            {"identifier":"/example:sensors/sensor/id","sid":60003,"type":"uint16"},
+            {"identifier":"/example:sensors/sensor/label","sid":60004,"type":"string"},
+            {"identifier":"/example:sensors/sensor/limits","sid":60005},
+            {"identifier":"/example:sensors/sensor/limits/high","sid":60006,"type":"int32"},
+            {"identifier":"/example:firewall","sid":60007},
+            {"identifier":"/example:firewall/rule","sid":60008,"ordered-by":"user"},
+            {"identifier":"/example:firewall/rule/name","sid":60009,"type":"string"},
+            {"identifier":"/example:firewall/rule/action","sid":60010,"type":"string"},
+            {"identifier":"/example:settings","sid":60011},
+            {"identifier":"/example:settings/level","sid":60012,"type":"int32"},
+            {"identifier":"/example:settings/tags","sid":60013,"type":"string"}
+        ],
+        "key-mapping":{"60002":[60003],"60008":[60009]}
+    }"#])
+    .unwrap()
+}
+
+fn datastore(data: Value) -> Datastore {
+    Datastore::with_backend(model(), MemoryBackend::new(data))
+}
+
+fn sensors(entries: Value) -> Value {
+    json!({"example:sensors": {"sensor": entries}})
+}
+
+fn rules(names: &[&str]) -> Value {
+    let entries = names
+        .iter()
+        .map(|name| json!({"name": name, "action": "accept"}))
+        .collect::<Vec<_>>();
+    json!({"example:firewall": {"rule": entries}})
+}
+
+/// Apply the diff's edits in order, as a client replaying it would.
+fn replay(mut datastore: Datastore, target: &Datastore) -> Datastore {
+    for (path, value) in datastore.diff(target).unwrap().patch() {
+        match value {
+            Some(value) => datastore.set_path(&path, value).unwrap(),
+            None => {
+                datastore.delete_path(&path).unwrap();
+            }
+        }
+    }
+    datastore
+}
+
+#[test]
+fn list_entries_are_matched_by_key() {
+    let before = datastore(sensors(json!([
+        {"id": 1, "label": "a", "limits": {"high": 10}},
+        {"id": 2, "label": "b"},
+        {"id": 3, "label": "c"}
+    ])));
+    let after = datastore(sensors(json!([
+        {"id": 1, "label": "a", "limits": {"high": 12}},
+        {"id": 4, "label": "d"},
+        {"id": 3, "label": "c"}
+    ])));
+
+    let edits = before
+        .diff(&after)
+        .unwrap()
+        .edits
+        .into_iter()
+        .map(|edit| (edit.operation, edit.path, edit.before, edit.after))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        edits,
+        vec![
+            (
+                DiffOperation::Delete,
+                "/example:sensors/sensor[id='2']".to_string(),
+                Some(json!({"id": 2, "label": "b"})),
+                None
+            ),
+            (
+                DiffOperation::Modify,
+                "/example:sensors/sensor[id='1']/limits/high".to_string(),
+                Some(json!(10)),
+                Some(json!(12))
+            ),
+            (
+                DiffOperation::Add,
+                "/example:sensors/sensor[id='4']".to_string(),
+                None,
+                Some(json!({"id": 4, "label": "d"}))
+            ),
+        ]
+    );
+    assert!(after.diff(&after).unwrap().is_empty());
+}
+
+#[test]
+fn leaves_and_leaf_lists_change_as_a_whole() {
+    let before = datastore(json!({"example:settings": {"level": 1, "tags": ["x", "y"]}}));
+    let after = datastore(json!({"example:settings": {"tags": ["y", "x"]}}));
+
+    assert_eq!(
+        before.diff(&after).unwrap().patch(),
+        vec![
+            ("/example:settings/level".to_string(), None),
+            (
+                "/example:settings/tags".to_string(),
+                Some(json!(["y", "x"]))
+            ),
+        ]
+    );
+}
+
+#[test]
+fn user_ordered_lists_keep_their_new_order() {
+    for (from, to) in [
+        (&["a", "b", "c"][..], &["a", "b", "c", "d"][..]),
+        (&["a", "b", "c"], &["a", "d", "b", "c"]),
+        (&["a", "b", "c"], &["c", "a", "b"]),
+        (&["a", "b", "c", "d"], &["a", "d", "c"]),
+    ] {
+        let target = datastore(rules(to));
+        let replayed = replay(datastore(rules(from)), &target);
+        assert_eq!(
+            replayed.get_all().unwrap(),
+            target.get_all().unwrap(),
+            "{from:?} -> {to:?}"
+        );
+    }
+
+    // Entries before the first one out of place are left alone.
+    let diff = datastore(rules(&["a", "b", "c"]))
+        .diff(&datastore(rules(&["a", "c", "b"])))
+        .unwrap();
+    assert_eq!(
+        diff.patch()
+            .into_iter()
+            .map(|(path, value)| (path, value.is_some()))
+            .collect::<Vec<_>>(),
+        [
+            ("/example:firewall/rule[name='b']".to_string(), false),
+            ("/example:firewall/rule[name='c']".to_string(), false),
+            ("/example:firewall/rule[name='c']".to_string(), true),
+            ("/example:firewall/rule[name='b']".to_string(), true),
+        ]
+    );
+}
+
+#[test]
+fn encoded_diffs_apply_as_a_root_ipatch() {
+    let running = datastore(json!({
+        "example:sensors": {"sensor": [{"id": 1, "label": "a"}, {"id": 2, "label": "b"}]},
+        "example:settings": {"level": 1}
+    }));
+    let staged = datastore(json!({
+        "example:sensors": {"sensor": [
+            {"id": 1, "label": "renamed", "limits": {"high": 5}},
+            {"id": 3, "label": "c"}
+        ]},
+        "example:firewall": {"rule": [{"name": "ssh", "action": "drop"}]}
+    }));
+    let diff = running.diff(&staged).unwrap();
+    let payload = running.encode_diff(&diff).unwrap();
+
+    let mut handler = RequestHandler::new(running).unwrap();
+    let request =
+        Request::new(Method::IPatch).with_payload(payload, ContentFormat::YangInstancesCborSeq);
+    assert_eq!(handler.handle(&request).code, ResponseCode::Changed);
+    assert_eq!(
+        handler.datastore().get_all().unwrap(),
+        staged.get_all().unwrap()
+    );
+    assert_eq!(handler.journal().entry(1).unwrap().patch, diff.patch());
+}
@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use coreconf_model::{CompositeModel, ErrorTag};
+use coreconf_runtime::coap_types::{ContentFormat, Method, QueryParams, Request};
+use coreconf_runtime::commit::{COMMIT_PATH, DISCARD_CHANGES_PATH};
+use coreconf_runtime::lock::{LOCK_PATH, UNLOCK_PATH, lock_input};
+use coreconf_runtime::{
+    Datastore, DatastoreName, RequestHandler, ResponseCode, decode_error_payload,
+};
+use serde_json::{Value, json};
+
+const LEVEL: &str = "/example:settings/level";
+const ALICE: &str = "192.0.2.1:5683";
+const BOB: &str = "192.0.2.2:5683";
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:settings","sid":60001},
+            {"identifier":"/example:settings/level","sid":60002,"type":"int32"}
+        ]
+    }"#])
+    .unwrap()
+}
+
+fn handler() -> RequestHandler {
+    let mut running = Datastore::new_in_memory(model());
+    running.set_path(LEVEL, json!(1)).unwrap();
+    RequestHandler::new(running).unwrap()
+}
+
+fn encode(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+fn operation(path: &str, input: Option<Value>, peer: &str) -> Request {
+    let request = Request::new(Method::Post).with_path(path).with_peer(peer);
+    match input {
+        Some(input) => request.with_payload(encode(&input), ContentFormat::YangDataCbor),
+        None => request,
+    }
+}
+
+fn lock(target: DatastoreName, timeout: Option<Duration>, peer: &str) -> Request {
+    operation(LOCK_PATH, Some(lock_input(target, timeout)), peer)
+}
+
+fn unlock(target: DatastoreName, peer: &str) -> Request {
+    operation(UNLOCK_PATH, Some(lock_input(target, None)), peer)
+}
+
+fn set_level(level: i64, peer: &str) -> Request {
+    Request::new(Method::IPatch)
+        .with_path(LEVEL)
+        .with_payload(encode(&json!(level)), ContentFormat::YangDataCbor)
+        .with_peer(peer)
+}
+
+#[test]
+fn only_the_lock_owner_may_edit_the_locked_datastore() {
+    let mut handler = handler();
+    assert_eq!(
+        handler
+            .handle(&lock(DatastoreName::Running, None, ALICE))
+            .code,
+        ResponseCode::Changed
+    );
+    assert_eq!(
+        handler.lock_holder(DatastoreName::Running).unwrap().owner,
+        ALICE
+    );
+
+    let response = handler.handle(&set_level(2, BOB));
+    assert_eq!(response.code, ResponseCode::Conflict);
+    let error = decode_error_payload(&response.payload).unwrap();
+    assert_eq!(error.tag, ErrorTag::InUse);
+    assert!(error.message.contains(ALICE), "{}", error.message);
+    let delete = Request::new(Method::Delete).with_path(LEVEL).with_peer(BOB);
+    assert_eq!(handler.handle(&delete).code, ResponseCode::Conflict);
+    assert_eq!(handler.datastore().get_path(LEVEL).unwrap(), Some(json!(1)));
+
+    assert_eq!(
+        handler.handle(&set_level(3, ALICE)).code,
+        ResponseCode::Changed
+    );
+    // The server's own edits, which carry no peer, are not held back.
+    handler.datastore_mut().set_path(LEVEL, json!(4)).unwrap();
+
+    // Other datastores stay open.
+    let candidate = set_level(5, BOB).with_query(QueryParams::parse("ds=candidate"));
+    assert_eq!(handler.handle(&candidate).code, ResponseCode::Changed);
+
+    assert_eq!(
+        handler.handle(&unlock(DatastoreName::Running, ALICE)).code,
+        ResponseCode::Changed
+    );
+    assert_eq!(
+        handler.handle(&set_level(6, BOB)).code,
+        ResponseCode::Changed
+    );
+}
+
+#[test]
+fn a_held_lock_is_denied_to_others_and_renewed_by_its_owner() {
+    let mut handler = handler();
+    handler
+        .lock(
+            DatastoreName::Candidate,
+            ALICE,
+            Some(Duration::from_secs(5)),
+        )
+        .unwrap();
+
+    let response = handler.handle(&lock(DatastoreName::Candidate, None, BOB));
+    assert_eq!(response.code, ResponseCode::Conflict);
+    let error = decode_error_payload(&response.payload).unwrap();
+    assert_eq!(error.tag, ErrorTag::LockDenied);
+    assert_eq!(
+        handler.handle(&unlock(DatastoreName::Candidate, BOB)).code,
+        ResponseCode::Conflict
+    );
+
+    let before = handler
+        .lock_holder(DatastoreName::Candidate)
+        .unwrap()
+        .deadline;
+    handler
+        .lock(
+            DatastoreName::Candidate,
+            ALICE,
+            Some(Duration::from_secs(60)),
+        )
+        .unwrap();
+    assert!(
+        handler
+            .lock_holder(DatastoreName::Candidate)
+            .unwrap()
+            .deadline
+            > before
+    );
+
+    handler.unlock(DatastoreName::Candidate, ALICE).unwrap();
+    let error = handler.unlock(DatastoreName::Candidate, ALICE).unwrap_err();
+    assert_eq!(error.error_tag(), ErrorTag::InvalidValue);
+    assert!(
+        handler
+            .lock(DatastoreName::Operational, ALICE, None)
+            .is_err()
+    );
+}
+
+#[test]
+fn locks_expire_after_their_timeout() {
+    let mut handler = handler();
+    handler
+        .lock(
+            DatastoreName::Running,
+            ALICE,
+            Some(Duration::from_millis(20)),
+        )
+        .unwrap();
+    assert_eq!(
+        handler.handle(&set_level(2, BOB)).code,
+        ResponseCode::Conflict
+    );
+
+    std::thread::sleep(Duration::from_millis(30));
+    assert!(handler.lock_holder(DatastoreName::Running).is_none());
+    assert_eq!(
+        handler.handle(&set_level(2, BOB)).code,
+        ResponseCode::Changed
+    );
+    assert_eq!(
+        handler
+            .handle(&lock(DatastoreName::Running, None, BOB))
+            .code,
+        ResponseCode::Changed
+    );
+}
+
+#[test]
+fn commit_and_discard_respect_locks() {
+    let mut handler = handler();
+    handler
+        .datastores_mut()
+        .candidate_mut()
+        .set_path(LEVEL, json!(9))
+        .unwrap();
+    handler.lock(DatastoreName::Running, ALICE, None).unwrap();
+    handler.lock(DatastoreName::Candidate, ALICE, None).unwrap();
+
+    assert_eq!(
+        handler.handle(&operation(COMMIT_PATH, None, BOB)).code,
+        ResponseCode::Conflict
+    );
+    assert_eq!(
+        handler
+            .handle(&operation(DISCARD_CHANGES_PATH, None, BOB))
+            .code,
+        ResponseCode::Conflict
+    );
+    assert_eq!(handler.datastore().get_path(LEVEL).unwrap(), Some(json!(1)));
+
+    assert_eq!(
+        handler.handle(&operation(COMMIT_PATH, None, ALICE)).code,
+        ResponseCode::Changed
+    );
+    assert_eq!(handler.datastore().get_path(LEVEL).unwrap(), Some(json!(9)));
+}
+
+#[test]
+fn only_the_candidate_lock_owner_may_commit_it() {
+    let mut handler = handler();
+    handler
+        .datastores_mut()
+        .candidate_mut()
+        .set_path(LEVEL, json!(9))
+        .unwrap();
+    handler.lock(DatastoreName::Candidate, ALICE, None).unwrap();
+
+    let response = handler.handle(&operation(COMMIT_PATH, None, BOB));
+    assert_eq!(response.code, ResponseCode::Conflict);
+    assert_eq!(
+        decode_error_payload(&response.payload).unwrap().tag,
+        ErrorTag::InUse
+    );
+    assert_eq!(handler.datastore().get_path(LEVEL).unwrap(), Some(json!(1)));
+
+    assert_eq!(
+        handler.handle(&operation(COMMIT_PATH, None, ALICE)).code,
+        ResponseCode::Changed
+    );
+    assert_eq!(handler.datastore().get_path(LEVEL).unwrap(), Some(json!(9)));
+}
+
+#[test]
+fn lock_requests_need_a_target_and_a_peer() {
+    let mut handler = handler();
+    let response = handler.handle(&operation(LOCK_PATH, Some(json!({})), ALICE));
+    assert_eq!(response.code, ResponseCode::BadRequest);
+
+    let anonymous = Request::new(Method::Post)
+        .with_path(LOCK_PATH)
+        .with_payload(
+            encode(&lock_input(DatastoreName::Running, None)),
+            ContentFormat::YangDataCbor,
+        );
+    assert_eq!(
+        handler.handle(&anonymous).code,
+        ResponseCode::MethodNotAllowed
+    );
+    assert!(handler.lock_holder(DatastoreName::Running).is_none());
+}
@@ -0,0 +1,166 @@
+use coreconf_model::{CompositeModel, ErrorTag};
+use coreconf_runtime::Datastore;
+use serde_json::json;
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:sensors","sid":60001},
+            {"identifier":"/example:sensors/sensor","sid":60002},
+            {"identifier":"/example:sensors/sensor/id","sid":60003,"type":"uint16"},
+            {"identifier":"/example:sensors/sensor/alert","sid":60004,"type":"boolean"},
+            {"identifier":"/example:sensors/sensor/transducer","sid":60005},
+            {"identifier":"/example:sensors/sensor/transducer/name","sid":60006,"type":"string"},
+            {"identifier":"/example:sensors/sensor/transducer/unit","sid":60007,"type":"string"},
+            {"identifier":"/example:sensors/sensor/transducer/reading","sid":60008,"type":"int32"},
+            {"identifier":"/example:sensors/sensor/transducer/scale","sid":60009,"type":"decimal64"}
+        ],
+        "key-mapping":{"60002":[60003],"60005":[60006]}
+    }"#])
+    .unwrap()
+}
+
+fn datastore() -> Datastore {
+    let mut datastore = Datastore::new_in_memory(model());
+    datastore
+        .set_path(
+            "/example:sensors",
+            json!({"sensor": [
+                {"id": 1, "alert": true, "transducer": [
+                    {"name": "depth", "unit": "mm", "reading": 12, "scale": 0.5},
+                    {"name": "temp", "unit": "C", "reading": -4, "scale": 1.5}
+                ]},
+                {"id": 2, "alert": false, "transducer": [
+                    {"name": "depth", "unit": "mm", "reading": 40, "scale": 2.5}
+                ]},
+                {"id": 3, "alert": true}
+            ]}),
+        )
+        .unwrap();
+    datastore
+}
+
+fn paths(matches: Vec<(String, serde_json::Value)>) -> Vec<String> {
+    matches.into_iter().map(|(path, _)| path).collect()
+}
+
+#[test]
+fn wildcards_expand_to_concrete_paths() {
+    let matches = datastore()
+        .query("/example:sensors/sensor[*]/transducer/unit")
+        .unwrap();
+
+    assert_eq!(
+        matches,
+        vec![
+            (
+                "/example:sensors/sensor[id='1']/transducer[name='depth']/unit".to_string(),
+                json!("mm")
+            ),
+            (
+                "/example:sensors/sensor[id='1']/transducer[name='temp']/unit".to_string(),
+                json!("C")
+            ),
+            (
+                "/example:sensors/sensor[id='2']/transducer[name='depth']/unit".to_string(),
+                json!("mm")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn predicates_filter_on_non_key_leaves() {
+    let datastore = datastore();
+
+    assert_eq!(
+        paths(
+            datastore
+                .query("/example:sensors/sensor[alert='true']")
+                .unwrap()
+        ),
+        [
+            "/example:sensors/sensor[id='1']",
+            "/example:sensors/sensor[id='3']"
+        ]
+    );
+    assert_eq!(
+        paths(
+            datastore
+                .query("/example:sensors/sensor[alert='true']/transducer[unit!='mm']/name")
+                .unwrap()
+        ),
+        ["/example:sensors/sensor[id='1']/transducer[name='temp']/name"]
+    );
+    // Keys filter like any other leaf.
+    assert_eq!(
+        paths(
+            datastore
+                .query("/example:sensors/sensor[id='2']/alert")
+                .unwrap()
+        ),
+        ["/example:sensors/sensor[id='2']/alert"]
+    );
+}
+
+#[test]
+fn numeric_leaves_compare_by_value() {
+    let datastore = datastore();
+
+    assert_eq!(
+        paths(
+            datastore
+                .query("/example:sensors/sensor/transducer[reading>=12][unit='mm']")
+                .unwrap()
+        ),
+        [
+            "/example:sensors/sensor[id='1']/transducer[name='depth']",
+            "/example:sensors/sensor[id='2']/transducer[name='depth']"
+        ]
+    );
+    assert_eq!(
+        paths(
+            datastore
+                .query("/example:sensors/sensor/transducer[reading<0]")
+                .unwrap()
+        ),
+        ["/example:sensors/sensor[id='1']/transducer[name='temp']"]
+    );
+    assert_eq!(
+        paths(
+            datastore
+                .query("/example:sensors/sensor[id>1]/transducer[scale>'2']")
+                .unwrap()
+        ),
+        ["/example:sensors/sensor[id='2']/transducer[name='depth']"]
+    );
+}
+
+#[test]
+fn invalid_queries_are_rejected() {
+    let datastore = datastore();
+
+    for query in [
+        "/example:sensors/sensor/transducer[unit>3]",
+        "/example:sensors/sensor/transducer[reading>'high']",
+        "/example:sensors[id='1']",
+        "/example:sensors/sensor[alert]",
+    ] {
+        let error = datastore.query(query).unwrap_err();
+        assert_eq!(
+            error.error_tag(),
+            ErrorTag::InvalidValue,
+            "{query}: {error}"
+        );
+    }
+    assert!(datastore.query("/example:sensors/missing").is_err());
+    assert!(
+        Datastore::new_in_memory(model())
+            .query("/example:sensors/sensor[*]")
+            .unwrap()
+            .is_empty()
+    );
+}
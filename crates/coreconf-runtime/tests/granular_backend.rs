@@ -0,0 +1,254 @@
+use coreconf_model::{CompositeModel, ErrorTag};
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request};
+use coreconf_runtime::{
+    Backend, Change, Datastore, MemoryBackend, RequestHandler, ResponseCode, SharedTree,
+};
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:devices","sid":60001},
+            {"identifier":"/example:devices/device","sid":60002},
+            {"identifier":"/example:devices/device/id","sid":60003,"type":"string"},
+            {"identifier":"/example:devices/device/enabled","sid":60004,"type":"boolean"},
+            {"identifier":"/example:devices/device/history","sid":60005},
+            {"identifier":"/example:devices/device/history/sample","sid":60006},
+            {"identifier":"/example:devices/device/history/sample/time","sid":60007,"type":"uint32"},
+            {"identifier":"/example:settings","sid":60009},
+            {"identifier":"/example:settings/level","sid":60010,"type":"int32"}
+        ],
+        "key-mapping":{"60002":[60003],"60006":[60007]}
+    }"#])
+    .unwrap()
+}
+
+/// A backend that only serves path-level access, so any whole-tree read or
+/// write by `Datastore` fails the test.
+struct PathOnly(MemoryBackend);
+
+impl Backend for PathOnly {
+    fn read_tree(&self) -> coreconf_model::Result<Value> {
+        panic!("whole tree read");
+    }
+
+    fn replace_tree(&mut self, _next: Value) -> coreconf_model::Result<()> {
+        panic!("whole tree replaced");
+    }
+
+    fn read_path(
+        &self,
+        model: &CompositeModel,
+        path: &str,
+    ) -> coreconf_model::Result<Option<Value>> {
+        self.0.read_path(model, path)
+    }
+
+    fn write_path(
+        &mut self,
+        model: &CompositeModel,
+        path: &str,
+        value: Value,
+    ) -> coreconf_model::Result<()> {
+        self.0.write_path(model, path, value)
+    }
+
+    fn delete_path(&mut self, model: &CompositeModel, path: &str) -> coreconf_model::Result<bool> {
+        self.0.delete_path(model, path)
+    }
+}
+
+/// A backend that may be read whole but only written path by path.
+struct PathWrites(MemoryBackend);
+
+impl Backend for PathWrites {
+    fn read_tree(&self) -> coreconf_model::Result<Value> {
+        self.0.read_tree()
+    }
+
+    fn snapshot(&self) -> coreconf_model::Result<SharedTree> {
+        self.0.snapshot()
+    }
+
+    fn replace_tree(&mut self, _next: Value) -> coreconf_model::Result<()> {
+        panic!("whole tree replaced");
+    }
+
+    fn write_path(
+        &mut self,
+        model: &CompositeModel,
+        path: &str,
+        value: Value,
+    ) -> coreconf_model::Result<()> {
+        self.0.write_path(model, path, value)
+    }
+
+    fn delete_path(&mut self, model: &CompositeModel, path: &str) -> coreconf_model::Result<bool> {
+        self.0.delete_path(model, path)
+    }
+}
+
+#[test]
+fn path_operations_never_touch_the_whole_tree() {
+    let mut datastore = Datastore::with_backend(model(), PathOnly(MemoryBackend::default()));
+
+    datastore
+        .set_path("/example:devices/device[id='a']/enabled", json!(true))
+        .unwrap();
+    datastore
+        .set_path(
+            "/example:devices/device[id='b']/history/sample[time='5']",
+            json!({}),
+        )
+        .unwrap();
+    datastore.set_by_sid(60010, json!(2)).unwrap();
+
+    assert_eq!(
+        datastore
+            .get_path("/example:devices/device[id='a']/enabled")
+            .unwrap(),
+        Some(json!(true))
+    );
+    assert_eq!(datastore.get_by_sid(60010).unwrap(), Some(json!(2)));
+    assert_eq!(
+        datastore.predicates("/example:devices/device").unwrap(),
+        vec!["[id='a']".to_string(), "[id='b']".to_string()]
+    );
+    assert_eq!(
+        datastore.fetch_instances_for_sid(60004, &[]).unwrap().len(),
+        1
+    );
+    assert_eq!(
+        datastore.fetch_instances_for_sid(60007, &[]).unwrap().len(),
+        1
+    );
+    assert!(
+        datastore
+            .delete_path("/example:devices/device[id='a']")
+            .unwrap()
+    );
+}
+
+#[test]
+fn subscriptions_read_only_the_nodes_they_watch() {
+    let mut datastore = Datastore::with_backend(model(), PathOnly(MemoryBackend::default()));
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&events);
+    datastore
+        .subscribe("/example:devices/device[*]/enabled", move |event| {
+            seen.lock().unwrap().push(event.clone());
+        })
+        .unwrap();
+
+    datastore
+        .set_path("/example:devices/device[id='a']/enabled", json!(true))
+        .unwrap();
+    datastore
+        .set_path("/example:settings/level", json!(2))
+        .unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].path, "/example:devices/device[id='a']/enabled");
+    assert_eq!(events[0].new, Some(json!(true)));
+}
+
+#[test]
+fn failed_path_writes_leave_the_tree_unchanged() {
+    let mut datastore = Datastore::new_in_memory(model());
+    datastore
+        .set_path("/example:devices/device[id='a']/enabled", json!(true))
+        .unwrap();
+    let before = datastore.get_all().unwrap();
+
+    // The device entry would be created before the sample key is rejected.
+    let error = datastore
+        .set_path(
+            "/example:devices/device[id='b']/history/sample[time='soon']",
+            json!({}),
+        )
+        .unwrap_err();
+    assert_eq!(error.error_tag(), ErrorTag::InvalidValue);
+    // The setting would be written before the stray predicate is noticed.
+    assert!(
+        datastore
+            .set_path("/example:settings[level='1']/level", json!(1))
+            .is_err()
+    );
+    // Likewise the device entry would already be removed.
+    assert!(
+        datastore
+            .delete_path("/example:devices/device[id='a'][id='b']")
+            .is_err()
+    );
+
+    assert_eq!(datastore.get_all().unwrap(), before);
+}
+
+#[test]
+fn memory_backend_logs_changed_paths() {
+    let mut datastore = Datastore::new_in_memory(model());
+    let start = datastore.last_change().unwrap();
+
+    datastore
+        .set_path("/example:settings/level", json!(1))
+        .unwrap();
+    datastore
+        .set_path("/example:devices/device[id='a']/enabled", json!(true))
+        .unwrap();
+    // Deleting nothing isn't a change.
+    assert!(
+        !datastore
+            .delete_path("/example:devices/device[id='a']/history")
+            .unwrap()
+    );
+    datastore.delete_path("/example:settings").unwrap();
+
+    let changes: Vec<Change> = datastore.changes_since(start).unwrap().cloned().collect();
+    let paths: Vec<&str> = changes.iter().map(|change| change.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "/example:settings/level",
+            "/example:devices/device[id='a']/enabled",
+            "/example:settings",
+        ]
+    );
+    assert_eq!(
+        datastore
+            .changes_since(changes[1].sequence)
+            .unwrap()
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn handler_edits_are_published_path_by_path() {
+    let running = Datastore::with_backend(model(), PathWrites(MemoryBackend::default()));
+    let mut handler = RequestHandler::new(running).unwrap();
+    let write = |path: &str, value: Value| {
+        let mut payload = Vec::new();
+        ciborium::into_writer(&value, &mut payload).unwrap();
+        Request::new(Method::IPatch)
+            .with_path(path)
+            .with_payload(payload, ContentFormat::YangDataCbor)
+    };
+
+    let device = "/example:devices/device[id='a']";
+    let response = handler.handle(&write(&format!("{device}/enabled"), json!(true)));
+    assert_eq!(response.code, ResponseCode::Changed);
+    let response = handler.handle(&write("/example:settings/level", json!(3)));
+    assert_eq!(response.code, ResponseCode::Changed);
+    let response = handler.handle(&Request::new(Method::Delete).with_path(device));
+    assert_eq!(response.code, ResponseCode::Changed);
+
+    assert_eq!(
+        handler.datastore().get_all().unwrap(),
+        json!({"example:devices": {"device": []}, "example:settings": {"level": 3}})
+    );
+}
@@ -0,0 +1,190 @@
+use coreconf_model::CompositeModel;
+use coreconf_runtime::{Backend, Datastore, KvBackend};
+use serde_json::{Value, json};
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:devices","sid":60001},
+            {"identifier":"/example:devices/device","sid":60002},
+            {"identifier":"/example:devices/device/id","sid":60003,"type":"string"},
+            {"identifier":"/example:devices/device/enabled","sid":60004,"type":"boolean"},
+            {"identifier":"/example:devices/device/history","sid":60005},
+            {"identifier":"/example:devices/device/history/sample","sid":60006},
+            {"identifier":"/example:devices/device/history/sample/time","sid":60007,"type":"uint32"},
+            {"identifier":"/example:devices/device/history/sample/value","sid":60008,"type":"int32"},
+            {"identifier":"/example:settings","sid":60009},
+            {"identifier":"/example:settings/level","sid":60010,"type":"int32"},
+            {"identifier":"/example:zone","sid":60011},
+            {"identifier":"/example:zone/name","sid":60012,"type":"string"},
+            {"identifier":"/example:zone/size","sid":60013,"type":"uint32"}
+        ],
+        "key-mapping":{"60002":[60003],"60006":[60007],"60011":[60012]}
+    }"#])
+    .unwrap()
+}
+
+enum Edit {
+    Set(&'static str, Value),
+    Delete(&'static str),
+}
+
+fn edits() -> Vec<Edit> {
+    use Edit::{Delete, Set};
+    vec![
+        Set("/example:settings/level", json!(3)),
+        Set("/example:devices/device[id='b']/enabled", json!(true)),
+        Set("/example:devices/device[id='a']/enabled", json!(false)),
+        Set(
+            "/example:devices/device[id='a']/history/sample[time='10']/value",
+            json!(7),
+        ),
+        Set(
+            "/example:devices/device[id='a']/history/sample[time='5']",
+            json!({"value": 1}),
+        ),
+        Set("/example:zone[name='lab']/size", json!(40)),
+        Set("/example:zone[name='hall']", json!({"size": 90})),
+        Set("/example:devices/device[id='b']/enabled", json!(false)),
+        Delete("/example:devices/device[id='a']/history/sample[time='10']"),
+        Set(
+            "/example:devices/device[id='a']/history",
+            json!({"sample": [{"time": 1, "value": 2}, {"time": 0, "value": 3}]}),
+        ),
+        Delete("/example:zone[name='lab']"),
+        Delete("/example:devices/device[id='b']"),
+        Delete("/example:devices/device[id='a']"),
+        Delete("/example:settings"),
+    ]
+}
+
+#[test]
+fn kv_backend_matches_memory_backend_edit_for_edit() {
+    let directory = tempfile::tempdir().unwrap();
+    let backend = KvBackend::open(model(), directory.path().join("data.redb")).unwrap();
+    let mut stored = Datastore::with_backend(model(), backend);
+    let mut memory = Datastore::new_in_memory(model());
+
+    for edit in edits() {
+        let (stored_result, memory_result) = match edit {
+            Edit::Set(path, value) => (
+                stored.set_path(path, value.clone()).map(|()| true),
+                memory.set_path(path, value).map(|()| true),
+            ),
+            Edit::Delete(path) => (stored.delete_path(path), memory.delete_path(path)),
+        };
+        assert_eq!(stored_result.unwrap(), memory_result.unwrap());
+        assert_eq!(stored.get_all().unwrap(), memory.get_all().unwrap());
+
+        for path in [
+            "/example:devices/device[id='a']",
+            "/example:devices/device[id='a']/history/sample[time='1']/value",
+            "/example:devices/device",
+            "/example:zone[name='hall']/size",
+            "/example:settings",
+        ] {
+            // Missing list entries are errors; compare those too.
+            assert_eq!(
+                format!("{:?}", stored.get_path(path)),
+                format!("{:?}", memory.get_path(path)),
+                "{path}"
+            );
+        }
+    }
+}
+
+#[test]
+fn kv_backend_persists_tree_and_list_order() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("data.redb");
+    let tree = json!({
+        "example:devices": {"device": [
+            {"id": "z", "enabled": true, "history": {"sample": [
+                {"time": 9, "value": 1},
+                {"time": 3, "value": 2}
+            ]}},
+            {"id": "a"}
+        ]},
+        "example:zone": [{"name": "lab", "size": 4}]
+    });
+    {
+        let mut backend = KvBackend::open(model(), &path).unwrap();
+        backend.replace_tree(tree.clone()).unwrap();
+    }
+
+    let backend = KvBackend::open(model(), &path).unwrap();
+    assert_eq!(backend.read_tree().unwrap(), tree);
+    assert_eq!(
+        backend
+            .read_path(
+                &model(),
+                "/example:devices/device[id='z']/history/sample[time='3']/value"
+            )
+            .unwrap(),
+        Some(json!(2))
+    );
+}
+
+#[test]
+fn kv_backend_rejects_invalid_edits_without_changes() {
+    let directory = tempfile::tempdir().unwrap();
+    let backend = KvBackend::open(model(), directory.path().join("data.redb")).unwrap();
+    let mut datastore = Datastore::with_backend(model(), backend);
+    datastore
+        .set_path("/example:devices/device[id='a']/enabled", json!(true))
+        .unwrap();
+    let before = datastore.get_all().unwrap();
+
+    assert!(
+        datastore
+            .set_path("/example:devices/device/enabled", json!(false))
+            .is_err()
+    );
+    assert!(
+        datastore
+            .set_path(
+                "/example:devices/device[id='a']/history/sample[time='x']",
+                json!({})
+            )
+            .is_err()
+    );
+    assert_eq!(datastore.get_all().unwrap(), before);
+}
+
+#[test]
+fn kv_backend_applies_a_batch_of_edits_whole_or_not_at_all() {
+    let directory = tempfile::tempdir().unwrap();
+    let model = model();
+    let mut backend = KvBackend::open(model.clone(), directory.path().join("data.redb")).unwrap();
+    let batch = [
+        ("/example:settings/level".to_string(), Some(json!(3))),
+        (
+            "/example:devices/device[id='a']/enabled".to_string(),
+            Some(json!(true)),
+        ),
+        ("/example:zone[name='lab']/size".to_string(), Some(json!(4))),
+    ];
+    backend.apply_edits(&model, &batch).unwrap();
+    let applied = json!({
+        "example:devices": {"device": [{"id": "a", "enabled": true}]},
+        "example:settings": {"level": 3},
+        "example:zone": [{"name": "lab", "size": 4}]
+    });
+    assert_eq!(backend.read_tree().unwrap(), applied);
+
+    // The last edit fails, so the transaction holding the others is
+    // never committed.
+    let bad = [
+        ("/example:settings/level".to_string(), Some(json!(4))),
+        ("/example:devices/device[id='a']".to_string(), None),
+        (
+            "/example:devices/device/enabled".to_string(),
+            Some(json!(false)),
+        ),
+    ];
+    assert!(backend.apply_edits(&model, &bad).is_err());
+    assert_eq!(backend.read_tree().unwrap(), applied);
+}
@@ -0,0 +1,167 @@
+use coreconf_model::{CompositeModel, SidFile};
+use coreconf_runtime::{Backend, Datastore};
+use serde_json::{Value, json};
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:devices","sid":60001},
+            {"identifier":"/example:devices/device","sid":60002},
+            {"identifier":"/example:devices/device/id","sid":60003,"type":"string"},
+            {"identifier":"/example:devices/device/enabled","sid":60004,"type":"boolean"},
+            {"identifier":"/example:devices/device/history","sid":60005},
+            {"identifier":"/example:devices/device/history/sample","sid":60006},
+            {"identifier":"/example:devices/device/history/sample/time","sid":60007,"type":"uint32"},
+            {"identifier":"/example:devices/device/history/sample/value","sid":60008,"type":"int32"}
+        ],
+        "key-mapping":{"60002":[60003],"60006":[60007]}
+    }"#])
+    .unwrap()
+}
+
+fn m2m_model() -> CompositeModel {
+    let sid_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/coreconf-m2m@2026-03-29.sid");
+    CompositeModel::from_sid_files(vec![SidFile::from_file(&sid_path).unwrap()]).unwrap()
+}
+
+/// Only implements whole-tree access, so path operations take the
+/// unindexed `Backend` defaults.
+#[derive(Default)]
+struct Unindexed(Value);
+
+impl Backend for Unindexed {
+    fn read_tree(&self) -> coreconf_model::Result<Value> {
+        Ok(if self.0.is_null() {
+            json!({})
+        } else {
+            self.0.clone()
+        })
+    }
+
+    fn replace_tree(&mut self, next: Value) -> coreconf_model::Result<()> {
+        self.0 = next;
+        Ok(())
+    }
+}
+
+enum Edit {
+    Set(&'static str, Value),
+    Delete(&'static str),
+}
+
+#[test]
+fn indexed_lookups_agree_with_linear_scans() {
+    use Edit::{Delete, Set};
+    let edits = vec![
+        Set("/example:devices/device[id='c']/enabled", json!(true)),
+        Set("/example:devices/device[id='a']/enabled", json!(false)),
+        Set("/example:devices/device[id='b']/enabled", json!(true)),
+        Set(
+            "/example:devices/device[id='a']/history/sample[time='1']/value",
+            json!(1),
+        ),
+        Set(
+            "/example:devices/device[id='a']/history/sample[time='2']/value",
+            json!(2),
+        ),
+        Set(
+            "/example:devices/device[id='b']/history/sample[time='1']/value",
+            json!(3),
+        ),
+        // Removing an entry shifts the ones after it.
+        Delete("/example:devices/device[id='c']"),
+        Set("/example:devices/device[id='b']/enabled", json!(false)),
+        Delete("/example:devices/device[id='a']/history/sample[time='1']"),
+        Set(
+            "/example:devices/device[id='a']/history/sample[time='2']/value",
+            json!(4),
+        ),
+        // Replacing a container or a whole list drops the lists inside it.
+        Set(
+            "/example:devices/device[id='a']/history",
+            json!({"sample": [{"time": 7, "value": 7}, {"time": 2, "value": 8}]}),
+        ),
+        Set(
+            "/example:devices/device[id='a']/history/sample[time='2']/value",
+            json!(9),
+        ),
+        Set(
+            "/example:devices/device",
+            json!([{"id": "z"}, {"id": "b", "enabled": true}]),
+        ),
+        Set("/example:devices/device[id='b']/enabled", json!(false)),
+        // An entry whose key leaf is rewritten moves to the new key.
+        Set("/example:devices/device[id='z']/id", json!("y")),
+        Set("/example:devices/device[id='y']/enabled", json!(true)),
+        Set("/example:devices/device[id='z']/enabled", json!(true)),
+        Set("/example:devices/device[id='b']", json!({"id": "x"})),
+        Set("/example:devices/device[id='x']/enabled", json!(false)),
+        Set("/example:devices/device[id='b']/enabled", json!(true)),
+        Delete("/example:devices/device[id='y']/id"),
+        Set("/example:devices/device[id='y']/enabled", json!(false)),
+        Delete("/example:devices"),
+        Set("/example:devices/device[id='a']/enabled", json!(true)),
+    ];
+
+    let mut indexed = Datastore::new_in_memory(model());
+    let mut linear = Datastore::with_backend(model(), Unindexed::default());
+    for edit in edits {
+        match edit {
+            Edit::Set(path, value) => {
+                indexed.set_path(path, value.clone()).unwrap();
+                linear.set_path(path, value).unwrap();
+            }
+            Edit::Delete(path) => {
+                assert_eq!(
+                    indexed.delete_path(path).unwrap(),
+                    linear.delete_path(path).unwrap(),
+                    "{path}"
+                );
+            }
+        }
+        assert_eq!(indexed.get_all().unwrap(), linear.get_all().unwrap());
+
+        for path in [
+            "/example:devices/device[id='a']",
+            "/example:devices/device[id='b']/enabled",
+            "/example:devices/device[id='y']",
+            "/example:devices/device[id='a']/history/sample[time='2']/value",
+        ] {
+            assert_eq!(
+                format!("{:?}", indexed.get_path(path)),
+                format!("{:?}", linear.get_path(path)),
+                "{path}"
+            );
+        }
+    }
+}
+
+#[test]
+fn identityref_keys_match_names_and_sids() {
+    let mut datastore = Datastore::new_in_memory(m2m_model());
+    datastore
+        .replace_tree(json!({
+            "coreconf-m2m:transducers": {"transducer": [
+                {"type": "coreconf-m2m:air-temperature", "id": 0, "precision": 1},
+                {"type": "coreconf-m2m:solar-radiation", "id": 0, "precision": 2}
+            ]}
+        }))
+        .unwrap();
+
+    let path = "/coreconf-m2m:transducers/transducer[type='coreconf-m2m:solar-radiation'][id='0']/precision";
+    datastore.set_path(path, json!(3)).unwrap();
+    datastore.set_path(path, json!(4)).unwrap();
+
+    assert_eq!(datastore.get_path(path).unwrap(), Some(json!(4)));
+    assert_eq!(
+        datastore
+            .predicates("/coreconf-m2m:transducers/transducer")
+            .unwrap()
+            .len(),
+        2
+    );
+}
@@ -1,7 +1,7 @@
 //! Integration tests using the coreconf-m2m weather station SID file,
 //! mirroring the pycoreconf samples/datastore/main.py workflow.
 
-use coreconf_model::{CompositeModel, SidFile};
+use coreconf_model::{CompositeModel, ErrorTag, SidFile};
 use coreconf_runtime::Datastore;
 use serde_json::json;
 
@@ -307,3 +307,43 @@ fn simple_leaf_set_inside_list() {
     ).unwrap();
     assert_eq!(value, Some(json!(2)));
 }
+
+#[test]
+fn m2m_bad_key_value_reports_leaf_sid_and_value() {
+    let model = load_m2m_model();
+    let id_sid = model
+        .get_sid("/coreconf-m2m:transducers/transducer/id")
+        .unwrap();
+    let mut ds = Datastore::new_in_memory(model);
+
+    let error = ds
+        .set_path(
+            "/coreconf-m2m:transducers/transducer[type='coreconf-m2m:solar-radiation'][id='abc']/precision",
+            json!(2),
+        )
+        .unwrap_err();
+
+    assert_eq!(error.error_tag(), ErrorTag::InvalidValue);
+    let data = error.data().expect("structured error");
+    assert_eq!(data.app_tag.as_deref(), Some("data-type-mismatch"));
+    assert_eq!(
+        data.path.as_deref(),
+        Some("/coreconf-m2m:transducers/transducer/id")
+    );
+    assert_eq!(data.sid, Some(id_sid));
+    assert_eq!(data.value, Some(json!("abc")));
+}
+
+#[test]
+fn m2m_missing_list_entry_is_data_missing() {
+    let model = load_m2m_model();
+    let datastore = Datastore::new_in_memory(model);
+
+    let error = datastore
+        .get_path(
+            "/coreconf-m2m:transducers/transducer[type='coreconf-m2m:solar-radiation'][id='7']/unit",
+        )
+        .unwrap_err();
+
+    assert_eq!(error.error_tag(), ErrorTag::DataMissing);
+}
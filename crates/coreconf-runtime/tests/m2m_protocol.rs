@@ -173,7 +173,7 @@ fn from_cbor_handles_empty_response() {
 #[test]
 fn streaming_interface_rejects_non_fetch_methods() {
     let datastore = Datastore::new_in_memory(m2m_model().clone());
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let req = Request::new(Method::Get).with_interface(Interface::Streaming);
     let resp = handler.handle(&req);
@@ -183,7 +183,7 @@ fn streaming_interface_rejects_non_fetch_methods() {
 #[test]
 fn streaming_fetch_without_observe_still_succeeds() {
     let datastore = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let precision_sid = sid("/coreconf-m2m:transducers/transducer/precision");
     let payload = encode_fetch_sids(&[precision_sid]);
@@ -199,7 +199,7 @@ fn streaming_fetch_without_observe_still_succeeds() {
 #[test]
 fn streaming_fetch_with_observe_stamps_sequence() {
     let datastore = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let precision_sid = sid("/coreconf-m2m:transducers/transducer/precision");
     let payload = encode_fetch_sids(&[precision_sid]);
@@ -227,7 +227,7 @@ fn streaming_fetch_with_observe_stamps_sequence() {
 #[test]
 fn management_interface_passes_through_unchanged() {
     let datastore = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let precision_sid = sid("/coreconf-m2m:transducers/transducer/precision");
     let payload = encode_fetch_sids(&[precision_sid]);
@@ -326,7 +326,7 @@ fn create_xpath_roundtrip_all_paths() {
 #[test]
 fn fetch_measurement_value_via_instance_id() {
     let ds = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(ds);
+    let mut handler = RequestHandler::new(ds).unwrap();
 
     let (target_sid, key_values) = handler
         .datastore()
@@ -346,7 +346,7 @@ fn fetch_measurement_value_via_instance_id() {
 #[test]
 fn fetch_statistics_via_instance_id() {
     let ds = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(ds);
+    let mut handler = RequestHandler::new(ds).unwrap();
 
     let (target_sid, key_values) = handler
         .datastore()
@@ -370,7 +370,7 @@ fn fetch_statistics_via_instance_id() {
 #[test]
 fn ipatch_sensor_alert_threshold() {
     let ds = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(ds);
+    let mut handler = RequestHandler::new(ds).unwrap();
 
     let req = Request::new(Method::IPatch)
         .with_path(
@@ -401,7 +401,7 @@ fn ipatch_sensor_alert_threshold() {
 #[test]
 fn ipatch_history_notification() {
     let ds = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(ds);
+    let mut handler = RequestHandler::new(ds).unwrap();
 
     let req = Request::new(Method::IPatch)
         .with_path(
@@ -512,7 +512,7 @@ fn replace_from_cbor_notification_response() {
 #[test]
 fn full_bootstrap_fetch_cycle() {
     let datastore = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     // Step 1: bootstrap — GET the entire datastore as CORECONF CBOR.
     let req = Request::new(Method::Get);
@@ -570,7 +570,7 @@ fn full_bootstrap_fetch_cycle() {
 #[test]
 fn fetch_with_instance_id_navigates_list_entry() {
     let ds = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(ds);
+    let mut handler = RequestHandler::new(ds).unwrap();
 
     let xpath = "/coreconf-m2m:transducers/transducer[type='coreconf-m2m:solar-radiation'][id='0']/quantity/value";
     let (target_sid, key_values) = handler.datastore().resolve_xpath(xpath).unwrap();
@@ -635,7 +635,7 @@ fn from_cbor_instance_seq_reconstructs_datastore() {
 #[test]
 fn observe_register_and_notify() {
     let ds = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(ds);
+    let mut handler = RequestHandler::new(ds).unwrap();
 
     let token = b"\x01\x02\x03".to_vec();
     let mut resources = std::collections::HashSet::new();
@@ -658,7 +658,7 @@ fn observe_register_and_notify() {
 #[test]
 fn observe_deregister_stops_notifications() {
     let ds = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(ds);
+    let mut handler = RequestHandler::new(ds).unwrap();
 
     let token = b"\xaa".to_vec();
     let mut resources = std::collections::HashSet::new();
@@ -677,7 +677,7 @@ fn observe_deregister_stops_notifications() {
 #[test]
 fn observe_multiple_resources() {
     let ds = bootstrapped_datastore();
-    let mut handler = RequestHandler::new(ds);
+    let mut handler = RequestHandler::new(ds).unwrap();
 
     let token = b"\xbb".to_vec();
     let mut resources = std::collections::HashSet::new();
@@ -0,0 +1,238 @@
+use coreconf_model::CompositeModel;
+use coreconf_runtime::coap_types::{ContentFormat, ContentParam, Method, QueryParams, Request};
+use coreconf_runtime::{
+    Backend, Datastore, DatastoreName, Datastores, RequestHandler, ResponseCode,
+};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:devices","sid":60001},
+            {"identifier":"/example:devices/device","sid":60002},
+            {"identifier":"/example:devices/device/id","sid":60003,"type":"string"},
+            {"identifier":"/example:devices/device/enabled","sid":60004,"type":"boolean"},
+            {"identifier":"/example:devices/device/temperature","sid":60005,"type":"int32"},
+            {"identifier":"/example:settings","sid":60006},
+            {"identifier":"/example:settings/enabled","sid":60007,"type":"boolean"}
+        ],
+        "key-mapping":{"60002":[60003]}
+    }"#])
+    .unwrap()
+}
+
+fn encode_value(value: &serde_json::Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+fn decode_value(bytes: &[u8]) -> serde_json::Value {
+    coreconf_model::codec::cbor_to_json_value(bytes).unwrap()
+}
+
+fn handler_with_settings(enabled: bool) -> RequestHandler {
+    let mut running = Datastore::new_in_memory(model());
+    running
+        .set_path("/example:settings/enabled", json!(enabled))
+        .unwrap();
+    RequestHandler::new(running).unwrap()
+}
+
+/// A running datastore whose backend can't be read.
+struct Unreadable;
+
+impl Backend for Unreadable {
+    fn read_tree(&self) -> coreconf_model::Result<Value> {
+        Err(coreconf_model::CoreconfError::ResourceNotFound(
+            "running".into(),
+        ))
+    }
+
+    fn replace_tree(&mut self, _next: Value) -> coreconf_model::Result<()> {
+        Ok(())
+    }
+}
+
+fn query(raw: &str) -> QueryParams {
+    QueryParams::parse(raw)
+}
+
+const TEMPERATURE: &str = "/example:devices/device[id='rdc-1']/temperature";
+
+fn provide_temperature(datastores: &mut Datastores) {
+    datastores
+        .register_subtree_provider(TEMPERATURE, Duration::ZERO, Box::new(|| Ok(json!(21))))
+        .unwrap();
+}
+
+#[test]
+fn query_params_parse_datastore_names_and_identities() {
+    assert_eq!(
+        query("ds=candidate").datastore,
+        Some(DatastoreName::Candidate)
+    );
+    assert_eq!(
+        query("c=c&ds=ietf-datastores:startup").datastore,
+        Some(DatastoreName::Startup)
+    );
+    assert_eq!(query("ds=bogus").datastore, None);
+    assert_eq!(
+        query("ds=bogus").unknown_datastore.as_deref(),
+        Some("bogus")
+    );
+}
+
+#[test]
+fn unknown_datastore_names_are_rejected() {
+    let mut handler = handler_with_settings(false);
+
+    let edit = Request::new(Method::IPatch)
+        .with_path("/example:settings/enabled")
+        .with_payload(encode_value(&json!(true)), ContentFormat::YangDataCbor)
+        .with_query(query("ds=candidte"));
+    let response = handler.handle(&edit);
+    assert_eq!(response.code, ResponseCode::BadRequest);
+    let error = coreconf_runtime::decode_error_payload(&response.payload).unwrap();
+    assert_eq!(error.tag, coreconf_model::ErrorTag::InvalidValue);
+    assert!(error.message.contains("candidte"), "{}", error.message);
+    assert_eq!(
+        handler
+            .datastore()
+            .get_path("/example:settings/enabled")
+            .unwrap(),
+        Some(json!(false))
+    );
+
+    let read = Request::new(Method::Get)
+        .with_path("/example:settings")
+        .with_query(query("ds=bogus"));
+    assert_eq!(handler.handle(&read).code, ResponseCode::BadRequest);
+}
+
+#[test]
+fn candidate_edits_stay_out_of_running_until_copied() {
+    let mut handler = handler_with_settings(false);
+
+    let edit = Request::new(Method::IPatch)
+        .with_path("/example:settings/enabled")
+        .with_payload(encode_value(&json!(true)), ContentFormat::YangDataCbor)
+        .with_query(query("ds=candidate"));
+    assert_eq!(handler.handle(&edit).code, ResponseCode::Changed);
+
+    let read_candidate = Request::new(Method::Get)
+        .with_path("/example:settings/enabled")
+        .with_query(query("ds=candidate"));
+    let response = handler.handle(&read_candidate);
+    assert_eq!(decode_value(&response.payload), json!(true));
+
+    let read_default = Request::new(Method::Get).with_path("/example:settings/enabled");
+    let response = handler.handle(&read_default);
+    assert_eq!(decode_value(&response.payload), json!(false));
+
+    handler
+        .datastores_mut()
+        .copy(DatastoreName::Candidate, DatastoreName::Running)
+        .unwrap();
+    let response = handler.handle(&read_default);
+    assert_eq!(decode_value(&response.payload), json!(true));
+}
+
+#[test]
+fn operational_datastore_rejects_edits() {
+    let mut handler = handler_with_settings(false);
+
+    let edit = Request::new(Method::IPatch)
+        .with_path("/example:settings/enabled")
+        .with_payload(encode_value(&json!(true)), ContentFormat::YangDataCbor)
+        .with_query(query("ds=operational"));
+    let delete = Request::new(Method::Delete)
+        .with_path("/example:settings/enabled")
+        .with_query(query("ds=operational"));
+
+    assert_eq!(handler.handle(&edit).code, ResponseCode::MethodNotAllowed);
+    assert_eq!(handler.handle(&delete).code, ResponseCode::MethodNotAllowed);
+    assert_eq!(
+        handler
+            .datastore()
+            .get_path("/example:settings/enabled")
+            .unwrap(),
+        Some(json!(false))
+    );
+}
+
+#[test]
+fn operational_merges_state_into_keyed_list_entries() {
+    let mut running = Datastore::new_in_memory(model());
+    running
+        .set_path("/example:devices/device[id='rdc-1']/enabled", json!(true))
+        .unwrap();
+    let mut datastores = Datastores::new(running).unwrap();
+    provide_temperature(&mut datastores);
+    datastores
+        .state_mut()
+        .set_path("/example:devices/device[id='rdc-2']/temperature", json!(30))
+        .unwrap();
+
+    let operational = datastores
+        .tree(DatastoreName::Operational, ContentParam::All)
+        .unwrap();
+    assert_eq!(
+        operational,
+        json!({"example:devices": {"device": [
+            {"id": "rdc-1", "enabled": true, "temperature": 21},
+            {"id": "rdc-2", "temperature": 30}
+        ]}})
+    );
+
+    let config = datastores
+        .tree(DatastoreName::Operational, ContentParam::Config)
+        .unwrap();
+    assert_eq!(
+        config,
+        json!({"example:devices": {"device": [{"id": "rdc-1", "enabled": true}]}})
+    );
+
+    let nonconfig = datastores
+        .tree(DatastoreName::Operational, ContentParam::Nonconfig)
+        .unwrap();
+    assert_eq!(
+        nonconfig,
+        json!({"example:devices": {"device": [
+            {"id": "rdc-2", "temperature": 30},
+            {"id": "rdc-1", "temperature": 21}
+        ]}})
+    );
+}
+
+#[test]
+fn content_filter_selects_config_or_state_over_coap() {
+    let mut handler = handler_with_settings(true);
+    provide_temperature(handler.datastores_mut());
+
+    let all = Request::new(Method::Get).with_path(TEMPERATURE);
+    let response = handler.handle(&all);
+    assert_eq!(response.code, ResponseCode::Content);
+    assert_eq!(decode_value(&response.payload), json!(21));
+
+    let config_only = Request::new(Method::Get)
+        .with_path(TEMPERATURE)
+        .with_query(query("c=c"));
+    assert_eq!(handler.handle(&config_only).code, ResponseCode::NotFound);
+
+    let running_state = Request::new(Method::Get)
+        .with_path("/example:settings/enabled")
+        .with_query(query("ds=running&c=n"));
+    assert_eq!(handler.handle(&running_state).code, ResponseCode::NotFound);
+}
+
+#[test]
+fn unreadable_running_fails_to_build_the_datastores() {
+    let running = || Datastore::with_backend(model(), Unreadable);
+    assert!(Datastores::new(running()).is_err());
+    assert!(RequestHandler::new(running()).is_err());
+}
@@ -0,0 +1,207 @@
+use coreconf_model::{CompositeModel, ErrorTag};
+#[cfg(feature = "kv")]
+use coreconf_runtime::KvBackend;
+use coreconf_runtime::coap_types::{ContentFormat, Method, QueryParams, Request};
+use coreconf_runtime::{Datastore, Position, RequestHandler, ResponseCode};
+use serde_json::{Value, json};
+
+const RULES: &str = "/example:firewall/rule";
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:firewall","sid":60001},
+            {"identifier":"/example:firewall/rule","sid":60002,"ordered-by":"user"},
+            {"identifier":"/example:firewall/rule/name","sid":60003,"type":"string"},
+            {"identifier":"/example:firewall/rule/action","sid":60004,"type":"string"},
+            {"identifier":"/example:host","sid":60005},
+            {"identifier":"/example:host/name","sid":60006,"type":"string"}
+        ],
+        "key-mapping":{"60002":[60003],"60005":[60006]}
+    }"#])
+    .unwrap()
+}
+
+fn rule(name: &str) -> String {
+    format!("{RULES}[name='{name}']")
+}
+
+fn with_rules(mut datastore: Datastore, names: &[&str]) -> Datastore {
+    for name in names {
+        datastore
+            .set_path(&rule(name), json!({"name": name, "action": "accept"}))
+            .unwrap();
+    }
+    datastore
+}
+
+fn rule_names(datastore: &Datastore) -> Vec<String> {
+    let Some(Value::Array(rules)) = datastore.get_node(RULES).unwrap() else {
+        return Vec::new();
+    };
+    rules
+        .iter()
+        .map(|entry| entry["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn encode_value(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+fn positioned_ipatch(path: &str, value: Value, query: &str) -> Request {
+    Request::new(Method::IPatch)
+        .with_path(path)
+        .with_payload(encode_value(&value), ContentFormat::YangDataCbor)
+        .with_query(QueryParams::parse(query))
+}
+
+#[test]
+fn entries_are_inserted_and_moved_where_asked() {
+    let mut datastore = with_rules(Datastore::new_in_memory(model()), &["a", "b"]);
+
+    datastore
+        .insert_entry(&rule("first"), json!({"name": "first"}), &Position::First)
+        .unwrap();
+    datastore
+        .insert_entry(
+            &rule("mid"),
+            json!({"name": "mid"}),
+            &Position::After(rule("a")),
+        )
+        .unwrap();
+    datastore
+        .insert_entry(&rule("end"), json!({"name": "end"}), &Position::Last)
+        .unwrap();
+    assert_eq!(rule_names(&datastore), ["first", "a", "mid", "b", "end"]);
+
+    datastore
+        .move_entry(&rule("end"), &Position::Before(rule("a")))
+        .unwrap();
+    datastore
+        .move_entry(&rule("first"), &Position::Last)
+        .unwrap();
+    assert_eq!(rule_names(&datastore), ["end", "a", "mid", "b", "first"]);
+
+    assert_eq!(
+        datastore.predicates(RULES).unwrap(),
+        [
+            "[name='end']",
+            "[name='a']",
+            "[name='mid']",
+            "[name='b']",
+            "[name='first']"
+        ]
+    );
+}
+
+#[test]
+fn positioning_errors_leave_the_list_alone() {
+    let mut datastore = with_rules(Datastore::new_in_memory(model()), &["a", "b"]);
+
+    let exists = datastore
+        .insert_entry(&rule("a"), json!({"name": "a"}), &Position::First)
+        .unwrap_err();
+    assert_eq!(exists.error_tag(), ErrorTag::DataExists);
+
+    let missing = datastore
+        .move_entry(&rule("z"), &Position::First)
+        .unwrap_err();
+    assert_eq!(missing.error_tag(), ErrorTag::DataMissing);
+
+    let missing_point = datastore
+        .insert_entry(
+            &rule("c"),
+            json!({"name": "c"}),
+            &Position::Before(rule("z")),
+        )
+        .unwrap_err();
+    assert_eq!(missing_point.error_tag(), ErrorTag::DataMissing);
+
+    let foreign_point = datastore
+        .move_entry(
+            &rule("a"),
+            &Position::After("/example:host[name='h']".into()),
+        )
+        .unwrap_err();
+    assert_eq!(foreign_point.error_tag(), ErrorTag::InvalidValue);
+
+    assert_eq!(rule_names(&datastore), ["a", "b"]);
+}
+
+#[test]
+fn lists_ordered_by_system_cannot_be_positioned() {
+    let mut datastore = Datastore::new_in_memory(model());
+    let error = datastore
+        .insert_entry(
+            "/example:host[name='h']",
+            json!({"name": "h"}),
+            &Position::First,
+        )
+        .unwrap_err();
+
+    assert_eq!(error.error_tag(), ErrorTag::InvalidValue);
+    assert!(error.to_string().contains("not ordered-by user"), "{error}");
+    assert_eq!(datastore.get_all().unwrap(), json!({}));
+}
+
+#[cfg(feature = "kv")]
+#[test]
+fn user_order_survives_reopening_a_kv_store() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("data.redb");
+    {
+        let backend = KvBackend::open(model(), &path).unwrap();
+        let mut datastore = with_rules(Datastore::with_backend(model(), backend), &["a", "b"]);
+        datastore.move_entry(&rule("b"), &Position::First).unwrap();
+        datastore
+            .insert_entry(
+                &rule("c"),
+                json!({"name": "c"}),
+                &Position::Before(rule("a")),
+            )
+            .unwrap();
+    }
+
+    let backend = KvBackend::open(model(), &path).unwrap();
+    let datastore = Datastore::with_backend(model(), backend);
+    assert_eq!(rule_names(&datastore), ["b", "c", "a"]);
+}
+
+#[test]
+fn ipatch_insert_and_point_position_entries() {
+    let mut handler =
+        RequestHandler::new(with_rules(Datastore::new_in_memory(model()), &["a", "b"])).unwrap();
+
+    let insert = positioned_ipatch(&rule("c"), json!({"name": "c"}), "insert=first");
+    assert_eq!(handler.handle(&insert).code, ResponseCode::Changed);
+    let point = format!("insert=after&point={}", rule("b"));
+    let moved = positioned_ipatch(&rule("c"), json!({"name": "c", "action": "drop"}), &point);
+    assert_eq!(handler.handle(&moved).code, ResponseCode::Changed);
+    assert_eq!(rule_names(handler.datastore()), ["a", "b", "c"]);
+    assert_eq!(
+        handler.datastore().get_path(&rule("c")).unwrap(),
+        Some(json!({"name": "c", "action": "drop"}))
+    );
+
+    let no_point = positioned_ipatch(&rule("d"), json!({"name": "d"}), "insert=before");
+    assert_eq!(handler.handle(&no_point).code, ResponseCode::BadRequest);
+    let system = positioned_ipatch(
+        "/example:host[name='h']",
+        json!({"name": "h"}),
+        "insert=last",
+    );
+    assert_eq!(handler.handle(&system).code, ResponseCode::BadRequest);
+
+    assert_eq!(
+        handler.journal().entry(2).unwrap().changed_paths,
+        vec![RULES.to_string()]
+    );
+    handler.rollback_to(1).unwrap();
+    assert_eq!(rule_names(handler.datastore()), ["c", "a", "b"]);
+}
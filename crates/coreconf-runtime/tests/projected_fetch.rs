@@ -36,7 +36,7 @@ fn projected_fetch_returns_requested_leaf_for_each_keyed_instance() {
             }
         }),
     );
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
     let mut request_payload = Vec::new();
     ciborium::into_writer(&60004_i64, &mut request_payload).expect("identifier");
     let request = Request::new(Method::Fetch)
@@ -151,7 +151,7 @@ fn encode_identifiers_explicit_key_fetch_keeps_normal_wire_shape() {
             }
         }),
     );
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
     let mut path = InstancePath::new();
     path.push_delta(62002);
     path.push_key(serde_json::json!(20));
@@ -1,10 +1,10 @@
 use std::sync::{Arc, Mutex};
 
-use coreconf_model::CompositeModel;
+use coreconf_model::{CompositeModel, ErrorTag};
 use coreconf_runtime::coap_types::{ContentFormat, Method, Request, ResponseCode};
 use coreconf_runtime::{
     Backend, Datastore, OperationBinding, OperationRegistry, RequestHandler, TransactionContext,
-    TransactionParticipant,
+    TransactionParticipant, decode_error_payload,
 };
 use serde_json::json;
 
@@ -86,7 +86,7 @@ impl OperationBinding for RecordingOperation {
 #[test]
 fn request_handler_applies_ipatch_to_predicate_path() {
     let datastore = Datastore::new_in_memory(runtime_model());
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let request = Request::new(Method::IPatch)
         .with_path("/example:devices/device[id='rdc-1']/enabled")
@@ -108,7 +108,7 @@ fn request_handler_applies_ipatch_to_predicate_path() {
 fn request_handler_decodes_path_ipatch_sid_object_before_storing() {
     let model = runtime_model();
     let datastore = Datastore::new_in_memory(model.clone());
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let request = Request::new(Method::IPatch)
         .with_path("/example:devices/device[id='rdc-1']")
@@ -132,7 +132,7 @@ fn request_handler_decodes_path_ipatch_sid_object_before_storing() {
 #[test]
 fn request_handler_rejects_scalar_root_ipatch_payload() {
     let datastore = Datastore::new_in_memory(runtime_model());
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let request = Request::new(Method::IPatch).with_payload(
         encode_value(&json!(true)),
@@ -147,7 +147,7 @@ fn request_handler_rejects_scalar_root_ipatch_payload() {
 #[test]
 fn request_handler_rejects_empty_root_ipatch_payload() {
     let datastore = Datastore::new_in_memory(runtime_model());
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let request = Request::new(Method::IPatch).with_payload(
         encode_value(&json!({})),
@@ -162,7 +162,7 @@ fn request_handler_rejects_empty_root_ipatch_payload() {
 #[test]
 fn request_handler_applies_valid_root_ipatch_instance() {
     let datastore = Datastore::new_in_memory(runtime_model());
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let request = Request::new(Method::IPatch).with_payload(
         encode_value(&json!({"60007": true})),
@@ -183,7 +183,8 @@ fn request_handler_applies_valid_root_ipatch_instance() {
 
 #[test]
 fn request_handler_root_ipatch_stores_binary_leaf_in_identifier_form() {
-    let mut handler = RequestHandler::new(Datastore::new_in_memory(binary_runtime_model()));
+    let mut handler =
+        RequestHandler::new(Datastore::new_in_memory(binary_runtime_model())).unwrap();
     let request = Request::new(Method::IPatch).with_payload(
         encode_value(&json!({
             "61001": {
@@ -217,7 +218,7 @@ fn request_handler_dispatches_post_using_canonical_predicate_path() {
     operations.register(Box::new(RecordingOperation {
         calls: Arc::clone(&calls),
     }));
-    let mut handler = RequestHandler::with_operations(datastore, operations);
+    let mut handler = RequestHandler::with_operations(datastore, operations).unwrap();
 
     let request = Request::new(Method::Post)
         .with_path("/example:devices/device[id='rdc-1']/reset")
@@ -240,7 +241,7 @@ fn request_handler_dispatches_post_using_canonical_predicate_path() {
 #[test]
 fn request_handler_rejects_malformed_fetch_payloads() {
     let datastore = Datastore::new_in_memory(runtime_model());
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let payload = encode_value(&json!([60004, {"unexpected": true}]));
 
@@ -255,7 +256,7 @@ fn request_handler_rejects_malformed_fetch_payloads() {
 #[test]
 fn request_handler_deletes_existing_path() {
     let datastore = Datastore::new_in_memory(runtime_model());
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
     handler
         .datastore_mut()
         .set_path("/example:devices/device[id='rdc-1']/enabled", json!(true))
@@ -279,7 +280,7 @@ fn request_handler_deletes_existing_path() {
 #[test]
 fn request_handler_delete_missing_path_returns_not_found() {
     let datastore = Datastore::new_in_memory(runtime_model());
-    let mut handler = RequestHandler::new(datastore);
+    let mut handler = RequestHandler::new(datastore).unwrap();
 
     let request =
         Request::new(Method::Delete).with_path("/example:devices/device[id='rdc-1']/enabled");
@@ -289,6 +290,40 @@ fn request_handler_delete_missing_path_returns_not_found() {
     assert_eq!(response.code, ResponseCode::NotFound);
 }
 
+#[test]
+fn request_handler_get_missing_list_entry_returns_not_found() {
+    let datastore = Datastore::new_in_memory(runtime_model());
+    let mut handler = RequestHandler::new(datastore).unwrap();
+
+    let request =
+        Request::new(Method::Get).with_path("/example:devices/device[id='rdc-9']/enabled");
+
+    let response = handler.handle(&request);
+
+    assert_eq!(response.code, ResponseCode::NotFound);
+}
+
+#[test]
+fn request_handler_reports_malformed_path_with_error_tag() {
+    let datastore = Datastore::new_in_memory(runtime_model());
+    let mut handler = RequestHandler::new(datastore).unwrap();
+
+    let request = Request::new(Method::IPatch)
+        .with_path("/example:devices/device[id='rdc-1'/enabled")
+        .with_payload(encode_value(&json!(true)), ContentFormat::YangDataCbor);
+
+    let response = handler.handle(&request);
+
+    assert_eq!(response.code, ResponseCode::BadRequest);
+    let error = decode_error_payload(&response.payload).unwrap();
+    assert_eq!(error.tag, ErrorTag::InvalidValue);
+    assert_eq!(error.app_tag.as_deref(), Some("invalid-path"));
+    assert_eq!(
+        error.path.as_deref(),
+        Some("/example:devices/device[id='rdc-1'/enabled")
+    );
+}
+
 #[derive(Clone)]
 struct RecordingBackend {
     tree: serde_json::Value,
@@ -297,8 +332,8 @@ struct RecordingBackend {
 }
 
 impl Backend for RecordingBackend {
-    fn read_tree(&self) -> serde_json::Value {
-        self.tree.clone()
+    fn read_tree(&self) -> coreconf_model::Result<serde_json::Value> {
+        Ok(self.tree.clone())
     }
 
     fn replace_tree(&mut self, next: serde_json::Value) -> coreconf_model::Result<()> {
@@ -368,7 +403,7 @@ fn root_ipatch_payload(values: &[serde_json::Value]) -> Vec<u8> {
 
 #[test]
 fn unknown_raw_root_ipatch_is_rejected_before_commit() {
-    let mut handler = RequestHandler::new(Datastore::new_in_memory(runtime_model()));
+    let mut handler = RequestHandler::new(Datastore::new_in_memory(runtime_model())).unwrap();
     let request = unknown_raw_request(
         Method::IPatch,
         root_ipatch_payload(&[json!({"60007": true})]),
@@ -377,12 +412,12 @@ fn unknown_raw_root_ipatch_is_rejected_before_commit() {
     let response = handler.handle(&request);
 
     assert_eq!(response.code, ResponseCode::UnsupportedContentFormat);
-    assert_eq!(handler.datastore().get_all(), json!({}));
+    assert_eq!(handler.datastore().get_all().unwrap(), json!({}));
 }
 
 #[test]
 fn unknown_raw_fetch_and_post_are_rejected_before_dispatch() {
-    let mut handler = RequestHandler::new(Datastore::new_in_memory(runtime_model()));
+    let mut handler = RequestHandler::new(Datastore::new_in_memory(runtime_model())).unwrap();
 
     let fetch = handler.handle(&unknown_raw_request(
         Method::Fetch,
@@ -395,7 +430,7 @@ fn unknown_raw_fetch_and_post_are_rejected_before_dispatch() {
         root_ipatch_payload(&[json!({"60005": null})]),
     ));
     assert_eq!(post.code, ResponseCode::UnsupportedContentFormat);
-    assert_eq!(handler.datastore().get_all(), json!({}));
+    assert_eq!(handler.datastore().get_all().unwrap(), json!({}));
 }
 
 #[test]
@@ -407,7 +442,8 @@ fn root_ipatch_success_publishes_complete_candidate_and_notifies_in_order() {
         replacements: Arc::clone(&replacements),
         fail: false,
     };
-    let mut handler = RequestHandler::new(Datastore::with_backend(runtime_model(), backend));
+    let mut handler =
+        RequestHandler::new(Datastore::with_backend(runtime_model(), backend)).unwrap();
     handler.register_transaction_participant(Box::new(RecordingParticipant {
         events: Arc::clone(&events),
         reject: false,
@@ -441,9 +477,27 @@ fn root_ipatch_success_publishes_complete_candidate_and_notifies_in_order() {
     assert_eq!(events[2].matches("/example:settings/enabled").count(), 1);
 }
 
+#[test]
+fn root_ipatch_candidates_share_the_model_instead_of_copying_it() {
+    let model = Arc::new(runtime_model());
+    let mut handler = RequestHandler::new(Datastore::new_in_memory(Arc::clone(&model))).unwrap();
+    // One handle per NMDA datastore plus ours; requests must not add any.
+    let holders = Arc::strong_count(&model);
+
+    for enabled in [true, false, true] {
+        let response = handler.handle(&root_ipatch_request(root_ipatch_payload(&[
+            json!({"60007": enabled}),
+        ])));
+        assert_eq!(response.code, ResponseCode::Changed);
+    }
+
+    assert!(Arc::ptr_eq(handler.datastore().shared_model(), &model));
+    assert_eq!(Arc::strong_count(&model), holders);
+}
+
 #[test]
 fn root_ipatch_late_edit_is_atomic_and_does_not_dirty_observers() {
-    let mut handler = RequestHandler::new(Datastore::new_in_memory(runtime_model()));
+    let mut handler = RequestHandler::new(Datastore::new_in_memory(runtime_model())).unwrap();
     handler.register_observer(vec![1], ["60007".to_string()].into_iter().collect());
 
     let response = handler.handle(&root_ipatch_request(root_ipatch_payload(&[
@@ -452,7 +506,7 @@ fn root_ipatch_late_edit_is_atomic_and_does_not_dirty_observers() {
     ])));
 
     assert_eq!(response.code, ResponseCode::Conflict);
-    assert_eq!(handler.datastore().get_all(), json!({}));
+    assert_eq!(handler.datastore().get_all().unwrap(), json!({}));
     assert!(handler.pending_notifications(&[1]).is_empty());
 }
 
@@ -465,7 +519,8 @@ fn root_ipatch_validator_rejection_does_not_publish_or_notify() {
         replacements: Arc::clone(&replacements),
         fail: false,
     };
-    let mut handler = RequestHandler::new(Datastore::with_backend(runtime_model(), backend));
+    let mut handler =
+        RequestHandler::new(Datastore::with_backend(runtime_model(), backend)).unwrap();
     handler.register_observer(vec![2], ["60007".to_string()].into_iter().collect());
     handler.register_transaction_participant(Box::new(RecordingParticipant {
         events: Arc::clone(&events),
@@ -478,7 +533,7 @@ fn root_ipatch_validator_rejection_does_not_publish_or_notify() {
 
     assert_eq!(response.code, ResponseCode::Conflict);
     assert!(replacements.lock().unwrap().is_empty());
-    assert_eq!(handler.datastore().get_all(), json!({}));
+    assert_eq!(handler.datastore().get_all().unwrap(), json!({}));
     assert!(handler.pending_notifications(&[2]).is_empty());
     assert_eq!(events.lock().unwrap().len(), 1);
 }
@@ -492,7 +547,8 @@ fn root_ipatch_publication_failure_has_no_post_commit_or_dirty_resources() {
         replacements: Arc::clone(&replacements),
         fail: true,
     };
-    let mut handler = RequestHandler::new(Datastore::with_backend(runtime_model(), backend));
+    let mut handler =
+        RequestHandler::new(Datastore::with_backend(runtime_model(), backend)).unwrap();
     handler.register_observer(vec![3], ["60007".to_string()].into_iter().collect());
     handler.register_transaction_participant(Box::new(RecordingParticipant {
         events: Arc::clone(&events),
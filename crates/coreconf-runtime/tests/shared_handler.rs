@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use coreconf_model::CompositeModel;
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request};
+use coreconf_runtime::{Datastore, RequestHandler, ResponseCode, SharedHandler};
+use serde_json::{Value, json};
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:sensor","sid":60001},
+            {"identifier":"/example:sensor/reading","sid":60002,"type":"int32"},
+            {"identifier":"/example:sensor/samples","sid":60003,"type":"uint32"},
+            {"identifier":"/example:settings","sid":60006},
+            {"identifier":"/example:settings/enabled","sid":60007,"type":"boolean"}
+        ]
+    }"#])
+    .unwrap()
+}
+
+fn handler() -> SharedHandler {
+    let mut running = Datastore::new_in_memory(model());
+    running
+        .set_path("/example:settings/enabled", json!(true))
+        .unwrap();
+    SharedHandler::new(RequestHandler::new(running).unwrap())
+}
+
+fn encode_value(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+fn decode_value(bytes: &[u8]) -> Value {
+    coreconf_model::codec::cbor_to_json_value(bytes).unwrap()
+}
+
+#[test]
+fn reads_proceed_while_another_reader_holds_the_handler() {
+    let shared = handler();
+    let guard = shared.read();
+
+    let (sender, receiver) = mpsc::channel();
+    let reader = shared.clone();
+    thread::spawn(move || {
+        let request = Request::new(Method::Get).with_path("/example:settings/enabled");
+        sender.send(reader.handle(&request)).unwrap();
+    });
+
+    let response = receiver
+        .recv_timeout(Duration::from_secs(5))
+        .expect("GET blocked behind a reader");
+    assert_eq!(response.code, ResponseCode::Content);
+    assert_eq!(decode_value(&response.payload), json!(true));
+    drop(guard);
+}
+
+#[test]
+fn edits_wait_for_readers_and_then_apply() {
+    let shared = handler();
+    let guard = shared.read();
+
+    let (sender, receiver) = mpsc::channel();
+    let writer = shared.clone();
+    thread::spawn(move || {
+        let request = Request::new(Method::IPatch)
+            .with_path("/example:settings/enabled")
+            .with_payload(encode_value(&json!(false)), ContentFormat::YangDataCbor);
+        sender.send(writer.handle(&request)).unwrap();
+    });
+
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+    drop(guard);
+    let response = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(response.code, ResponseCode::Changed);
+    assert_eq!(
+        shared
+            .read()
+            .datastore()
+            .get_path("/example:settings/enabled")
+            .unwrap(),
+        Some(json!(false))
+    );
+}
+
+#[test]
+fn sampler_updates_state_while_readers_serve() {
+    let shared = handler();
+    shared.write().register_observer(
+        b"obs".to_vec(),
+        HashSet::from(["60002".to_string(), "60003".to_string()]),
+    );
+
+    let sampler = {
+        let shared = shared.clone();
+        thread::spawn(move || {
+            for sample in 1..=200 {
+                shared.update_state(|state| {
+                    state
+                        .set_path("/example:sensor/reading", json!(sample * 10))
+                        .unwrap();
+                    state
+                        .set_path("/example:sensor/samples", json!(sample))
+                        .unwrap();
+                });
+            }
+        })
+    };
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for _ in 0..200 {
+                    let response =
+                        shared.handle(&Request::new(Method::Get).with_path("/example:sensor"));
+                    if response.code == ResponseCode::NotFound {
+                        continue;
+                    }
+                    assert_eq!(response.code, ResponseCode::Content);
+                    // Both leaves come from one update, never half of one;
+                    // members are keyed by SID delta from the container.
+                    let sensor = decode_value(&response.payload);
+                    assert_eq!(sensor["1"], json!(sensor["2"].as_i64().unwrap() * 10));
+                }
+            })
+        })
+        .collect();
+
+    sampler.join().unwrap();
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    let response = shared.handle(&Request::new(Method::Get).with_path("/example:sensor/samples"));
+    assert_eq!(decode_value(&response.payload), json!(200));
+    assert!(!shared.write().pending_notifications(b"obs").is_empty());
+}
@@ -0,0 +1,107 @@
+use coreconf_model::CompositeModel;
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request, ResponseCode};
+use coreconf_runtime::{Datastore, RequestHandler, SharedTree};
+use serde_json::{Value, json};
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:devices","sid":60001},
+            {"identifier":"/example:devices/device","sid":60002},
+            {"identifier":"/example:devices/device/id","sid":60003,"type":"string"},
+            {"identifier":"/example:devices/device/enabled","sid":60004,"type":"boolean"},
+            {"identifier":"/example:settings","sid":60009},
+            {"identifier":"/example:settings/level","sid":60010,"type":"int32"}
+        ],
+        "key-mapping":{"60002":[60003]}
+    }"#])
+    .unwrap()
+}
+
+fn tree() -> Value {
+    json!({
+        "example:devices": {"device": [
+            {"id": "a", "enabled": true},
+            {"id": "b", "enabled": false}
+        ]},
+        "example:settings": {"level": 1}
+    })
+}
+
+#[test]
+fn snapshots_share_everything_a_write_does_not_touch() {
+    let mut datastore = Datastore::from_snapshot(model(), SharedTree::from(tree()));
+    let before = datastore.snapshot().unwrap();
+    assert!(SharedTree::ptr_eq(&before, &datastore.snapshot().unwrap()));
+
+    datastore
+        .set_path("/example:devices/device[id='b']/enabled", json!(true))
+        .unwrap();
+    let after = datastore.snapshot().unwrap();
+
+    assert_eq!(before.to_value(), tree());
+    assert!(SharedTree::ptr_eq(
+        before.get("example:settings").unwrap(),
+        after.get("example:settings").unwrap()
+    ));
+    assert!(!SharedTree::ptr_eq(
+        before.get("example:devices").unwrap(),
+        after.get("example:devices").unwrap()
+    ));
+    let device = |tree: &SharedTree, position: usize| {
+        let list = tree.get("example:devices").unwrap().get("device").unwrap();
+        list.to_value()[position].clone()
+    };
+    assert_eq!(device(&after, 0), device(&before, 0));
+    assert_eq!(device(&after, 1), json!({"id": "b", "enabled": true}));
+}
+
+#[test]
+fn candidates_leave_their_source_untouched() {
+    let source = Datastore::from_snapshot(model(), SharedTree::from(tree()));
+    let mut candidate = Datastore::from_snapshot(model(), source.snapshot().unwrap());
+
+    candidate
+        .delete_path("/example:devices/device[id='a']")
+        .unwrap();
+    candidate
+        .set_path("/example:settings/level", json!(5))
+        .unwrap();
+
+    assert_eq!(source.get_all().unwrap(), tree());
+    assert_ne!(candidate.snapshot().unwrap(), source.snapshot().unwrap());
+    assert_eq!(
+        SharedTree::from(candidate.get_all().unwrap()),
+        candidate.snapshot().unwrap(),
+        "a snapshot equals its materialised tree"
+    );
+}
+
+#[test]
+fn root_ipatch_publishes_the_candidate_without_copying_unchanged_modules() {
+    let datastore = Datastore::from_snapshot(model(), SharedTree::from(tree()));
+    let mut handler = RequestHandler::new(datastore).unwrap();
+    let before = handler.datastore().snapshot().unwrap();
+
+    let mut payload = Vec::new();
+    ciborium::into_writer(&json!({"60010": 7}), &mut payload).unwrap();
+    let request =
+        Request::new(Method::IPatch).with_payload(payload, ContentFormat::YangInstancesCborSeq);
+    assert_eq!(handler.handle(&request).code, ResponseCode::Changed);
+
+    let after = handler.datastore().snapshot().unwrap();
+    assert_eq!(
+        handler
+            .datastore()
+            .get_path("/example:settings/level")
+            .unwrap(),
+        Some(json!(7))
+    );
+    assert!(SharedTree::ptr_eq(
+        before.get("example:devices").unwrap(),
+        after.get("example:devices").unwrap()
+    ));
+}
@@ -0,0 +1,241 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use coreconf_model::CompositeModel;
+use coreconf_model::instance_id::decode_instances_with_model;
+use coreconf_runtime::coap_types::{ContentFormat, Interface, Method, QueryParams, Request};
+use coreconf_runtime::{Datastore, RequestHandler, ResponseCode, SubtreeProvider};
+use serde_json::{Value, json};
+
+const UPTIME: &str = "/example:state/uptime";
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:state","sid":60001},
+            {"identifier":"/example:state/uptime","sid":60002,"type":"uint32"},
+            {"identifier":"/example:sensors","sid":60003},
+            {"identifier":"/example:sensors/sensor","sid":60004},
+            {"identifier":"/example:sensors/sensor/id","sid":60005,"type":"string"},
+            {"identifier":"/example:sensors/sensor/label","sid":60006,"type":"string"},
+            {"identifier":"/example:sensors/sensor/reading","sid":60007,"type":"int32"},
+            {"identifier":"/example:settings","sid":60008},
+            {"identifier":"/example:settings/level","sid":60009,"type":"int32"}
+        ],
+        "key-mapping":{"60004":[60005]}
+    }"#])
+    .unwrap()
+}
+
+fn handler() -> RequestHandler {
+    let mut running = Datastore::new_in_memory(model());
+    running
+        .set_path("/example:settings/level", json!(3))
+        .unwrap();
+    running
+        .set_path(
+            "/example:sensors/sensor[id='a']",
+            json!({"id": "a", "label": "roof"}),
+        )
+        .unwrap();
+    RequestHandler::new(running).unwrap()
+}
+
+/// A provider returning `value`, counting its calls in `calls`.
+fn counting(value: Value, calls: &Arc<AtomicUsize>) -> Box<dyn SubtreeProvider> {
+    let calls = Arc::clone(calls);
+    Box::new(move || {
+        calls.fetch_add(1, Ordering::SeqCst);
+        Ok(value.clone())
+    })
+}
+
+fn encode(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Value {
+    coreconf_model::codec::cbor_to_json_value(bytes).unwrap()
+}
+
+fn get(path: &str) -> Request {
+    Request::new(Method::Get).with_path(path)
+}
+
+fn fetch(sid: i64) -> Request {
+    Request::new(Method::Fetch)
+        .with_payload(encode(&json!(sid)), ContentFormat::YangIdentifiersCbor)
+}
+
+#[test]
+fn providers_only_run_for_reads_that_touch_them() {
+    let mut handler = handler();
+    let calls = Arc::new(AtomicUsize::new(0));
+    handler
+        .datastores_mut()
+        .register_subtree_provider(UPTIME, Duration::ZERO, counting(json!(42), &calls))
+        .unwrap();
+
+    let response = handler.handle(&get("/example:settings/level"));
+    assert_eq!(decode(&response.payload), json!(3));
+    assert_eq!(handler.handle(&fetch(60009)).code, ResponseCode::Content);
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    let response = handler.handle(&get(UPTIME));
+    assert_eq!(response.code, ResponseCode::Content);
+    assert_eq!(decode(&response.payload), json!(42));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // Ancestors and the whole datastore include the provided node.
+    assert_eq!(
+        handler.handle(&get("/example:state")).code,
+        ResponseCode::Content
+    );
+    assert_eq!(handler.handle(&fetch(60001)).code, ResponseCode::Content);
+    assert_eq!(
+        handler.handle(&Request::new(Method::Get)).code,
+        ResponseCode::Content
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+    // Configuration reads never call providers.
+    let config = get(UPTIME).with_query(QueryParams::parse("c=c"));
+    assert_eq!(handler.handle(&config).code, ResponseCode::NotFound);
+    let running = get(UPTIME).with_query(QueryParams::parse("ds=running"));
+    assert_eq!(handler.handle(&running).code, ResponseCode::NotFound);
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn values_are_cached_for_their_max_age() {
+    let mut handler = handler();
+    let cached = Arc::new(AtomicUsize::new(0));
+    let live = Arc::new(AtomicUsize::new(0));
+    handler
+        .datastores_mut()
+        .register_subtree_provider(
+            UPTIME,
+            Duration::from_secs(60),
+            counting(json!(42), &cached),
+        )
+        .unwrap();
+    handler
+        .datastores_mut()
+        .register_subtree_provider(
+            "/example:sensors/sensor[id='a']/reading",
+            Duration::ZERO,
+            counting(json!(-7), &live),
+        )
+        .unwrap();
+
+    for _ in 0..3 {
+        let response = handler.handle(&get(UPTIME));
+        let max_age = response.max_age.unwrap();
+        assert!((59..=60).contains(&max_age), "{max_age}");
+    }
+    assert_eq!(cached.load(Ordering::SeqCst), 1);
+
+    for _ in 0..3 {
+        let response = handler.handle(&get("/example:sensors/sensor[id='a']/reading"));
+        assert_eq!(response.max_age, Some(0));
+    }
+    assert_eq!(live.load(Ordering::SeqCst), 3);
+
+    // A read covering both is only as fresh as its least fresh part.
+    let response = handler.handle(&Request::new(Method::Get));
+    assert_eq!(response.max_age, Some(0));
+    assert_eq!(cached.load(Ordering::SeqCst), 1);
+
+    // Content no provider computed carries no Max-Age.
+    let response = handler.handle(&get("/example:settings/level"));
+    assert_eq!(response.max_age, None);
+}
+
+#[test]
+fn provided_values_merge_into_list_entries_and_projections() {
+    let mut handler = handler();
+    let calls = Arc::new(AtomicUsize::new(0));
+    handler
+        .datastores_mut()
+        .register_subtree_provider(
+            "/example:sensors/sensor[id='a']/reading",
+            Duration::from_secs(5),
+            counting(json!(-7), &calls),
+        )
+        .unwrap();
+
+    let response = handler.handle(&get("/example:sensors/sensor[id='a']"));
+    assert_eq!(
+        decode(&response.payload),
+        json!({"1": "a", "2": "roof", "3": -7})
+    );
+
+    let response = handler.handle(&fetch(60007));
+    assert_eq!(response.code, ResponseCode::Content);
+    let instances = decode_instances_with_model(&model(), &response.payload).unwrap();
+    assert_eq!(
+        instances
+            .into_iter()
+            .map(|instance| instance.value)
+            .collect::<Vec<_>>(),
+        [Some(json!(-7))]
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert!(response.max_age.is_some());
+}
+
+#[test]
+fn observers_are_notified_when_provided_values_change() {
+    let mut handler = handler();
+    let uptime = Arc::new(AtomicI64::new(100));
+    let source = Arc::clone(&uptime);
+    handler
+        .datastores_mut()
+        .register_subtree_provider(
+            UPTIME,
+            Duration::ZERO,
+            Box::new(move || Ok(json!(source.load(Ordering::SeqCst)))),
+        )
+        .unwrap();
+
+    let token = b"uptime".to_vec();
+    let observe = fetch(60002)
+        .with_interface(Interface::Streaming)
+        .with_observe(0)
+        .with_token(token.clone());
+    let response = handler.handle(&observe);
+    assert_eq!(response.code, ResponseCode::Content);
+    assert!(response.max_age.is_some());
+
+    handler.refresh_provided_state().unwrap();
+    assert!(handler.pending_notifications(&token).is_empty());
+
+    uptime.store(160, Ordering::SeqCst);
+    handler.refresh_provided_state().unwrap();
+    assert_eq!(handler.pending_notifications(&token).len(), 1);
+
+    handler.refresh_provided_state().unwrap();
+    assert!(handler.pending_notifications(&token).is_empty());
+}
+
+#[test]
+fn providers_must_name_a_model_node() {
+    let mut handler = handler();
+    let calls = Arc::new(AtomicUsize::new(0));
+    assert!(
+        handler
+            .datastores_mut()
+            .register_subtree_provider(
+                "/example:state/missing",
+                Duration::ZERO,
+                counting(json!(1), &calls)
+            )
+            .is_err()
+    );
+}
@@ -0,0 +1,249 @@
+use std::sync::{Arc, Mutex};
+
+use coreconf_model::{CompositeModel, ErrorTag};
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request};
+use coreconf_runtime::{
+    ChangeEvent, Datastore, DiffOperation, Edit, EditOperation, Position, RequestHandler,
+    ResponseCode, SharedHandler, YangPatch,
+};
+use serde_json::{Value, json};
+
+const PRECISION: &str = "/example:sensors/sensor[*]/precision";
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:sensors","sid":60001},
+            {"identifier":"/example:sensors/sensor","sid":60002,"ordered-by":"user"},
+            {"identifier":"/example:sensors/sensor/id","sid":60003,"type":"string"},
+            {"identifier":"/example:sensors/sensor/precision","sid":60004,"type":"uint8"},
+            {"identifier":"/example:sensors/sensor/alert","sid":60005,"type":"boolean"},
+            {"identifier":"/example:settings","sid":60006},
+            {"identifier":"/example:settings/level","sid":60007,"type":"int32"}
+        ],
+        "key-mapping":{"60002":[60003]}
+    }"#])
+    .unwrap()
+}
+
+fn sensor(id: &str) -> String {
+    format!("/example:sensors/sensor[id='{id}']")
+}
+
+fn datastore() -> Datastore {
+    let mut datastore = Datastore::new_in_memory(model());
+    for (id, precision) in [("a", 1), ("b", 2)] {
+        datastore
+            .set_path(
+                &sensor(id),
+                json!({"id": id, "precision": precision, "alert": false}),
+            )
+            .unwrap();
+    }
+    datastore
+}
+
+type Events = Arc<Mutex<Vec<ChangeEvent>>>;
+
+fn recorder() -> (Events, impl FnMut(&ChangeEvent) + Send + Sync + 'static) {
+    let events = Events::default();
+    let sink = Arc::clone(&events);
+    (events, move |event: &ChangeEvent| {
+        sink.lock().unwrap().push(event.clone())
+    })
+}
+
+fn take(events: &Events) -> Vec<(String, Option<Value>, Option<Value>)> {
+    events
+        .lock()
+        .unwrap()
+        .drain(..)
+        .map(|event| (event.path, event.old, event.new))
+        .collect()
+}
+
+fn encode(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn events_come_at_the_granularity_of_the_pattern() {
+    let mut datastore = datastore();
+    let (events, callback) = recorder();
+    datastore.subscribe(PRECISION, callback).unwrap();
+
+    datastore
+        .set_path(&sensor("a"), json!({"id": "a", "precision": 3}))
+        .unwrap();
+    datastore
+        .set_path(&format!("{}/alert", sensor("b")), json!(true))
+        .unwrap();
+    datastore
+        .set_path(&sensor("c"), json!({"id": "c", "precision": 0}))
+        .unwrap();
+    datastore.delete_path(&sensor("b")).unwrap();
+
+    assert_eq!(
+        take(&events),
+        vec![
+            (
+                format!("{}/precision", sensor("a")),
+                Some(json!(1)),
+                Some(json!(3))
+            ),
+            (format!("{}/precision", sensor("c")), None, Some(json!(0))),
+            (format!("{}/precision", sensor("b")), Some(json!(2)), None),
+        ]
+    );
+}
+
+#[test]
+fn predicates_on_other_leaves_filter_events() {
+    let mut datastore = datastore();
+    let (events, callback) = recorder();
+    datastore
+        .subscribe("/example:sensors/sensor[alert='true']", callback)
+        .unwrap();
+
+    datastore
+        .set_path(&format!("{}/alert", sensor("a")), json!(true))
+        .unwrap();
+    datastore
+        .set_path(&format!("{}/precision", sensor("b")), json!(9))
+        .unwrap();
+    datastore
+        .set_path(&format!("{}/alert", sensor("a")), json!(false))
+        .unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(
+        events
+            .iter()
+            .map(|event| (event.path.as_str(), event.operation()))
+            .collect::<Vec<_>>(),
+        [
+            (sensor("a").as_str(), DiffOperation::Add),
+            (sensor("a").as_str(), DiffOperation::Delete),
+        ]
+    );
+}
+
+#[test]
+fn composite_mutations_report_their_net_effect_once() {
+    let mut datastore = datastore();
+    let (events, callback) = recorder();
+    let id = datastore
+        .subscribe("/example:sensors/sensor/id", callback)
+        .unwrap();
+
+    datastore
+        .insert_entry(&sensor("z"), json!({"id": "z"}), &Position::First)
+        .unwrap();
+    datastore
+        .move_entry(&sensor("z"), &Position::After(sensor("b")))
+        .unwrap();
+    let failed = YangPatch::new("p")
+        .with_edit(Edit::new("1", EditOperation::Delete, sensor("a")))
+        .with_edit(Edit::new("2", EditOperation::Delete, sensor("missing")));
+    assert!(datastore.apply_yang_patch(&failed).is_err());
+
+    assert_eq!(
+        take(&events),
+        vec![(format!("{}/id", sensor("z")), None, Some(json!("z")))]
+    );
+
+    assert!(datastore.unsubscribe(id));
+    datastore.delete_path(&sensor("z")).unwrap();
+    assert!(take(&events).is_empty());
+    assert!(!datastore.unsubscribe(id));
+}
+
+#[test]
+fn patterns_are_checked_when_subscribing() {
+    let mut datastore = datastore();
+    for pattern in [
+        "/example:sensors/sensor/nothing",
+        "/example:settings[level='1']",
+        "/example:sensors/sensor[id>'a']",
+    ] {
+        let (_, callback) = recorder();
+        assert!(datastore.subscribe(pattern, callback).is_err(), "{pattern}");
+    }
+
+    let (_, callback) = recorder();
+    let error = datastore
+        .subscribe("/example:sensors/sensor[alert]", callback)
+        .unwrap_err();
+    assert_eq!(error.error_tag(), ErrorTag::InvalidValue);
+}
+
+#[test]
+fn every_request_kind_reaches_handler_subscriptions() {
+    let mut handler = RequestHandler::new(datastore()).unwrap();
+    let (events, callback) = recorder();
+    handler.subscribe(PRECISION, callback).unwrap();
+    let precision = format!("{}/precision", sensor("a"));
+
+    let ipatch = Request::new(Method::IPatch)
+        .with_path(precision.as_str())
+        .with_payload(encode(&json!(4)), ContentFormat::YangDataCbor);
+    assert_eq!(handler.handle(&ipatch).code, ResponseCode::Changed);
+
+    let patch = YangPatch::new("p").with_edit(
+        Edit::new("1", EditOperation::Merge, sensor("b")).with_value(json!({"precision": 5})),
+    );
+    let yang_patch = Request::new(Method::IPatch)
+        .with_payload(patch.to_cbor().unwrap(), ContentFormat::YangPatchCbor);
+    assert_eq!(handler.handle(&yang_patch).code, ResponseCode::Changed);
+
+    let delete = Request::new(Method::Delete).with_path(sensor("b"));
+    assert_eq!(handler.handle(&delete).code, ResponseCode::Changed);
+
+    handler.rollback_to(1).unwrap();
+
+    assert_eq!(
+        take(&events),
+        vec![
+            (precision.clone(), Some(json!(1)), Some(json!(4))),
+            (
+                format!("{}/precision", sensor("b")),
+                Some(json!(2)),
+                Some(json!(5))
+            ),
+            (format!("{}/precision", sensor("b")), Some(json!(5)), None),
+            (format!("{}/precision", sensor("b")), None, Some(json!(2))),
+        ]
+    );
+}
+
+#[test]
+fn shared_handlers_notify_application_code() {
+    let shared = SharedHandler::new(RequestHandler::new(datastore()).unwrap());
+    let (events, callback) = recorder();
+    shared.subscribe(PRECISION, callback).unwrap();
+
+    let writer = shared.clone();
+    std::thread::spawn(move || {
+        writer
+            .write()
+            .datastore_mut()
+            .set_path(&format!("{}/precision", sensor("a")), json!(7))
+            .unwrap();
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(
+        take(&events),
+        vec![(
+            format!("{}/precision", sensor("a")),
+            Some(json!(1)),
+            Some(json!(7))
+        )]
+    );
+}
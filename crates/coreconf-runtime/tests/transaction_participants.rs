@@ -0,0 +1,309 @@
+use std::sync::{Arc, Mutex};
+
+use coreconf_model::{CompositeModel, CoreconfError, DataError, ErrorTag};
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request};
+use coreconf_runtime::{
+    Datastore, RequestHandler, ResponseCode, TransactionContext, TransactionParticipant,
+};
+use serde_json::{Value, json};
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:ports","sid":60001},
+            {"identifier":"/example:ports/port","sid":60002},
+            {"identifier":"/example:ports/port/name","sid":60003,"type":"string"},
+            {"identifier":"/example:ports/port/speed","sid":60004,"type":"uint32"},
+            {"identifier":"/example:summary","sid":60005},
+            {"identifier":"/example:summary/ports","sid":60006,"type":"uint32"}
+        ],
+        "key-mapping":{"60002":[60003]}
+    }"#])
+    .unwrap()
+}
+
+const PORT: &str = "/example:ports/port[name='eth0']";
+
+fn handler() -> RequestHandler {
+    let mut running = Datastore::new_in_memory(model());
+    running
+        .set_path(PORT, json!({"name": "eth0", "speed": 100}))
+        .unwrap();
+    RequestHandler::new(running).unwrap()
+}
+
+fn encode(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Prepare,
+    Validate,
+    Commit,
+}
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// Records each phase as `name:phase` and fails in `fails`, if set.
+struct Recorder {
+    name: &'static str,
+    log: Log,
+    fails: Option<Phase>,
+}
+
+impl Recorder {
+    fn boxed(name: &'static str, log: &Log, fails: Option<Phase>) -> Box<Self> {
+        Box::new(Self {
+            name,
+            log: Arc::clone(log),
+            fails,
+        })
+    }
+
+    fn step(&self, phase: Phase, label: &str) -> coreconf_model::Result<()> {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{}:{label}", self.name));
+        if self.fails == Some(phase) {
+            return Err(DataError::new(ErrorTag::OperationFailed, "hardware refused").into());
+        }
+        Ok(())
+    }
+}
+
+impl TransactionParticipant for Recorder {
+    fn prepare(
+        &self,
+        _context: &TransactionContext<'_>,
+        _candidate: &mut Datastore,
+    ) -> coreconf_model::Result<()> {
+        self.step(Phase::Prepare, "prepare")
+    }
+
+    fn pre_commit(&self, _context: &TransactionContext<'_>) -> coreconf_model::Result<()> {
+        self.step(Phase::Validate, "validate")
+    }
+
+    fn commit(&self, _context: &TransactionContext<'_>) -> coreconf_model::Result<()> {
+        self.step(Phase::Commit, "commit")
+    }
+
+    fn abort(&self, _context: &TransactionContext<'_>) {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{}:abort", self.name));
+    }
+
+    fn post_commit(&self, _context: &TransactionContext<'_>) {
+        self.log.lock().unwrap().push(format!("{}:post", self.name));
+    }
+}
+
+/// Rejects any port slower than 10.
+struct SpeedCheck;
+
+impl TransactionParticipant for SpeedCheck {
+    fn pre_commit(&self, context: &TransactionContext<'_>) -> coreconf_model::Result<()> {
+        let ports = context.candidate_tree()["example:ports"]["port"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        if ports.iter().any(|port| port["speed"].as_u64() < Some(10)) {
+            return Err(CoreconfError::ValidationError("port too slow".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Keeps `/example:summary/ports` equal to the number of ports.
+struct Summary;
+
+impl TransactionParticipant for Summary {
+    fn prepare(
+        &self,
+        context: &TransactionContext<'_>,
+        candidate: &mut Datastore,
+    ) -> coreconf_model::Result<()> {
+        let count = context.candidate_tree()["example:ports"]["port"]
+            .as_array()
+            .map_or(0, Vec::len);
+        candidate.set_path("/example:summary/ports", json!(count))
+    }
+}
+
+fn take(log: &Log) -> Vec<String> {
+    log.lock().unwrap().drain(..).collect()
+}
+
+#[test]
+fn every_mutation_path_is_validated() {
+    let mut handler = handler();
+    handler.register_transaction_participant(Box::new(SpeedCheck));
+    let speed = format!("{PORT}/speed");
+
+    let slow = Request::new(Method::IPatch)
+        .with_path(speed.as_str())
+        .with_payload(encode(&json!(5)), ContentFormat::YangDataCbor);
+    assert_eq!(handler.handle(&slow).code, ResponseCode::Conflict);
+
+    let slow_entry = encode(&json!({"60002": [{"1": "eth1", "2": 1}]}));
+    assert!(handler.apply_instance_seq(&slow_entry).is_err());
+
+    let fast = encode(&json!({"60002": [{"1": "eth0", "2": 100}, {"1": "eth1", "2": 1000}]}));
+    handler.apply_instance_seq(&fast).unwrap();
+    assert_eq!(
+        handler
+            .datastore()
+            .get_path("/example:ports/port[name='eth1']/speed")
+            .unwrap(),
+        Some(json!(1000))
+    );
+    assert_eq!(
+        handler.datastore().get_path(&speed).unwrap(),
+        Some(json!(100))
+    );
+    assert_eq!(handler.journal().entries().count(), 1);
+}
+
+#[test]
+fn deletes_run_through_participants_and_the_journal() {
+    let mut handler = handler();
+    let log = Log::default();
+    handler.register_transaction_participant(Recorder::boxed("a", &log, None));
+
+    let delete = Request::new(Method::Delete).with_path(PORT);
+    assert_eq!(handler.handle(&delete).code, ResponseCode::Changed);
+    assert_eq!(
+        take(&log),
+        ["a:prepare", "a:validate", "a:commit", "a:post"]
+    );
+    assert_eq!(handler.datastore().get_path(PORT).unwrap(), None);
+
+    assert_eq!(handler.handle(&delete).code, ResponseCode::NotFound);
+    assert!(take(&log).is_empty());
+
+    handler.rollback_to(0).unwrap();
+    assert!(handler.datastore().get_path(PORT).unwrap().is_some());
+}
+
+#[test]
+fn prepared_changes_are_published_and_journaled() {
+    let mut handler = handler();
+    handler.register_transaction_participant(Box::new(Summary));
+
+    let add = Request::new(Method::IPatch)
+        .with_path("/example:ports/port[name='eth1']")
+        .with_payload(
+            encode(&json!({"1": "eth1", "2": 10})),
+            ContentFormat::YangDataCbor,
+        );
+    assert_eq!(handler.handle(&add).code, ResponseCode::Changed);
+    assert_eq!(
+        handler
+            .datastore()
+            .get_path("/example:summary/ports")
+            .unwrap(),
+        Some(json!(2))
+    );
+
+    let entry = handler.journal().entry(1).unwrap();
+    assert_eq!(
+        entry.changed_paths,
+        ["/example:ports/port[name='eth1']", "/example:summary"]
+    );
+
+    // Undoing the edit is a transaction too, which derives the summary
+    // again.
+    handler.rollback_to(0).unwrap();
+    assert_eq!(
+        handler.datastore().get_path("/example:summary").unwrap(),
+        Some(json!({"ports": 1}))
+    );
+    assert_eq!(
+        handler.journal().entry(2).unwrap().changed_paths,
+        ["/example:ports", "/example:summary"]
+    );
+}
+
+#[test]
+fn a_veto_aborts_the_participants_already_reached() {
+    let mut handler = handler();
+    let log = Log::default();
+    handler.register_transaction_participant(Recorder::boxed("a", &log, None));
+    handler.register_transaction_participant(Recorder::boxed("b", &log, Some(Phase::Validate)));
+    handler.register_transaction_participant(Recorder::boxed("c", &log, None));
+
+    let write = Request::new(Method::IPatch)
+        .with_path(format!("{PORT}/speed"))
+        .with_payload(encode(&json!(10)), ContentFormat::YangDataCbor);
+    assert_eq!(
+        handler.handle(&write).code,
+        ResponseCode::InternalServerError
+    );
+    assert_eq!(
+        take(&log),
+        [
+            "a:prepare",
+            "b:prepare",
+            "c:prepare",
+            "a:validate",
+            "b:validate",
+            "c:abort",
+            "a:abort"
+        ]
+    );
+    assert_eq!(
+        handler
+            .datastore()
+            .get_path(&format!("{PORT}/speed"))
+            .unwrap(),
+        Some(json!(100))
+    );
+}
+
+#[test]
+fn a_failed_commit_lets_earlier_participants_undo_their_side_effects() {
+    let mut handler = handler();
+    let log = Log::default();
+    handler.register_transaction_participant(Recorder::boxed("a", &log, None));
+    handler.register_transaction_participant(Recorder::boxed("b", &log, Some(Phase::Commit)));
+
+    let error = handler
+        .apply_instance_seq(&encode(&json!({"60002": [{"1": "eth1", "2": 10}]})))
+        .unwrap_err();
+    assert_eq!(error.error_tag(), ErrorTag::OperationFailed);
+    assert_eq!(
+        take(&log),
+        [
+            "a:prepare",
+            "b:prepare",
+            "a:validate",
+            "b:validate",
+            "a:commit",
+            "b:commit",
+            "a:abort"
+        ]
+    );
+    assert!(handler.journal().entries().next().is_none());
+
+    // A participant failing to prepare only aborts those before it.
+    let log = Log::default();
+    let mut handler = self::handler();
+    handler.register_transaction_participant(Recorder::boxed("a", &log, None));
+    handler.register_transaction_participant(Recorder::boxed("b", &log, Some(Phase::Prepare)));
+    handler.register_transaction_participant(Recorder::boxed("c", &log, None));
+    let delete = Request::new(Method::Delete).with_path(PORT);
+    assert_eq!(
+        handler.handle(&delete).code,
+        ResponseCode::InternalServerError
+    );
+    assert_eq!(take(&log), ["a:prepare", "b:prepare", "a:abort"]);
+}
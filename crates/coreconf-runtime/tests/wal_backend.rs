@@ -0,0 +1,304 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+use coreconf_model::CompositeModel;
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request};
+use coreconf_runtime::{
+    Backend, Datastore, Edit, EditOperation, RequestHandler, ResponseCode, WalBackend, YangPatch,
+};
+use serde_json::{Value, json};
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:devices","sid":60001},
+            {"identifier":"/example:devices/device","sid":60002},
+            {"identifier":"/example:devices/device/id","sid":60003,"type":"string"},
+            {"identifier":"/example:devices/device/enabled","sid":60004,"type":"boolean"},
+            {"identifier":"/example:devices/device/temperature","sid":60005,"type":"int32"},
+            {"identifier":"/example:settings","sid":60006},
+            {"identifier":"/example:settings/enabled","sid":60007,"type":"boolean"},
+            {"identifier":"/example:settings/level","sid":60008,"type":"int32"}
+        ],
+        "key-mapping":{"60002":[60003]}
+    }"#])
+    .unwrap()
+}
+
+/// One transaction touching two top-level nodes, so a recovered tree is
+/// complete only if both carry the same `step`.
+fn step_tree(step: i64) -> Value {
+    json!({
+        "example:devices": {"device": [{"id": "rdc-1", "temperature": step}]},
+        "example:settings": {"level": step}
+    })
+}
+
+fn recovered_step(tree: &Value) -> (i64, i64) {
+    (
+        tree["example:devices"]["device"][0]["temperature"]
+            .as_i64()
+            .unwrap(),
+        tree["example:settings"]["level"].as_i64().unwrap(),
+    )
+}
+
+#[test]
+fn wal_replays_committed_edits_after_reopen() {
+    let directory = tempfile::tempdir().unwrap();
+    {
+        let backend = WalBackend::open(model(), directory.path()).unwrap();
+        let mut datastore = Datastore::with_backend(model(), backend);
+        datastore
+            .set_path("/example:devices/device[id='rdc-1']/enabled", json!(true))
+            .unwrap();
+        datastore
+            .set_path("/example:devices/device[id='rdc-2']/temperature", json!(-4))
+            .unwrap();
+        datastore
+            .set_path("/example:settings/enabled", json!(false))
+            .unwrap();
+        datastore.delete_path("/example:settings").unwrap();
+    }
+
+    let backend = WalBackend::open(model(), directory.path()).unwrap();
+
+    assert_eq!(backend.sequence(), 4);
+    assert_eq!(
+        backend.read_tree().unwrap(),
+        json!({"example:devices": {"device": [
+            {"id": "rdc-1", "enabled": true},
+            {"id": "rdc-2", "temperature": -4}
+        ]}})
+    );
+}
+
+#[test]
+fn wal_path_writes_log_only_the_edited_instance() {
+    let directory = tempfile::tempdir().unwrap();
+    let log = directory.path().join("wal.log");
+    let devices: Vec<Value> = (0..200)
+        .map(|id| json!({"id": format!("rdc-{id}"), "temperature": id}))
+        .collect();
+    {
+        let mut backend = WalBackend::open(model(), directory.path()).unwrap();
+        backend
+            .replace_tree(json!({"example:devices": {"device": devices}}))
+            .unwrap();
+        let before = std::fs::metadata(&log).unwrap().len();
+
+        let model = model();
+        backend
+            .write_path(
+                &model,
+                "/example:devices/device[id='rdc-7']/temperature",
+                json!(-7),
+            )
+            .unwrap();
+        assert!(
+            backend
+                .delete_path(&model, "/example:devices/device[id='rdc-9']")
+                .unwrap()
+        );
+        assert!(
+            !backend
+                .delete_path(&model, "/example:devices/device[id='rdc-9']")
+                .unwrap()
+        );
+        assert_eq!(backend.sequence(), 3);
+        assert!(std::fs::metadata(&log).unwrap().len() - before < 128);
+    }
+
+    let backend = WalBackend::open(model(), directory.path()).unwrap();
+    let tree = backend.read_tree().unwrap();
+    let devices = tree["example:devices"]["device"].as_array().unwrap();
+    assert_eq!(devices.len(), 199);
+    assert_eq!(devices[7], json!({"id": "rdc-7", "temperature": -7}));
+    assert_eq!(devices[9]["id"], "rdc-10");
+}
+
+#[test]
+fn wal_logs_a_batch_of_edits_as_one_record() {
+    let directory = tempfile::tempdir().unwrap();
+    let model = model();
+    let edits = [
+        (
+            "/example:devices/device[id='rdc-1']/temperature".to_string(),
+            Some(json!(20)),
+        ),
+        ("/example:settings/level".to_string(), Some(json!(3))),
+        ("/example:devices/device[id='rdc-2']".to_string(), None),
+    ];
+    {
+        let mut backend = WalBackend::open(model.clone(), directory.path()).unwrap();
+        backend
+            .replace_tree(json!({"example:devices": {"device": [{"id": "rdc-2"}]}}))
+            .unwrap();
+        backend.apply_edits(&model, &edits).unwrap();
+        assert_eq!(backend.sequence(), 2);
+
+        // A batch with a bad edit logs nothing and changes nothing.
+        let bad = [
+            ("/example:settings/level".to_string(), Some(json!(4))),
+            (
+                "/example:devices/device/enabled".to_string(),
+                Some(json!(true)),
+            ),
+        ];
+        assert!(backend.apply_edits(&model, &bad).is_err());
+        assert_eq!(backend.sequence(), 2);
+    }
+
+    let backend = WalBackend::open(model, directory.path()).unwrap();
+    assert_eq!(backend.sequence(), 2);
+    assert_eq!(
+        backend.read_tree().unwrap(),
+        json!({
+            "example:devices": {"device": [{"id": "rdc-1", "temperature": 20}]},
+            "example:settings": {"level": 3}
+        })
+    );
+}
+
+#[test]
+fn wal_discards_a_torn_record_and_keeps_appending() {
+    let directory = tempfile::tempdir().unwrap();
+    let log = directory.path().join("wal.log");
+    {
+        let mut backend = WalBackend::open(model(), directory.path()).unwrap();
+        backend.replace_tree(step_tree(1)).unwrap();
+        backend.replace_tree(step_tree(2)).unwrap();
+    }
+    // Simulate a crash halfway through writing the third record.
+    let complete = std::fs::metadata(&log).unwrap().len();
+    {
+        let mut backend = WalBackend::open(model(), directory.path()).unwrap();
+        backend.replace_tree(step_tree(3)).unwrap();
+    }
+    let with_third = std::fs::metadata(&log).unwrap().len();
+    let file = std::fs::OpenOptions::new().write(true).open(&log).unwrap();
+    file.set_len(complete + (with_third - complete) / 2)
+        .unwrap();
+
+    let mut backend = WalBackend::open(model(), directory.path()).unwrap();
+    assert_eq!(recovered_step(&backend.read_tree().unwrap()), (2, 2));
+    assert_eq!(std::fs::metadata(&log).unwrap().len(), complete);
+
+    backend.replace_tree(step_tree(4)).unwrap();
+    drop(backend);
+    let backend = WalBackend::open(model(), directory.path()).unwrap();
+    assert_eq!(recovered_step(&backend.read_tree().unwrap()), (4, 4));
+}
+
+#[test]
+fn wal_compacts_into_a_snapshot() {
+    let directory = tempfile::tempdir().unwrap();
+    {
+        let mut backend = WalBackend::open(model(), directory.path())
+            .unwrap()
+            .with_compaction_interval(3);
+        for step in 1..=7 {
+            backend.replace_tree(step_tree(step)).unwrap();
+        }
+    }
+
+    assert!(directory.path().join("snapshot.cbor").exists());
+    let backend = WalBackend::open(model(), directory.path()).unwrap();
+    assert_eq!(backend.sequence(), 7);
+    assert_eq!(recovered_step(&backend.read_tree().unwrap()), (7, 7));
+}
+
+#[test]
+fn wal_recovers_none_or_all_of_a_handler_transaction() {
+    let directory = tempfile::tempdir().unwrap();
+    let log = directory.path().join("wal.log");
+    let mut backend = WalBackend::open(model(), directory.path()).unwrap();
+    backend.replace_tree(step_tree(1)).unwrap();
+    let before = std::fs::metadata(&log).unwrap().len();
+
+    let mut handler = RequestHandler::new(Datastore::with_backend(model(), backend)).unwrap();
+    let patch = YangPatch::new("step")
+        .with_edit(
+            Edit::new(
+                "1",
+                EditOperation::Replace,
+                "/example:devices/device[id='rdc-1']/temperature",
+            )
+            .with_value(json!(2)),
+        )
+        .with_edit(
+            Edit::new("2", EditOperation::Replace, "/example:settings/level").with_value(json!(2)),
+        );
+    let request = Request::new(Method::IPatch)
+        .with_payload(patch.to_cbor().unwrap(), ContentFormat::YangPatchCbor);
+    assert_eq!(handler.handle(&request).code, ResponseCode::Changed);
+    drop(handler);
+    let after = std::fs::metadata(&log).unwrap().len();
+
+    // Cut the log anywhere after the first record, as a crash would.
+    for cut in before..=after {
+        let copy = tempfile::tempdir().unwrap();
+        std::fs::copy(&log, copy.path().join("wal.log")).unwrap();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(copy.path().join("wal.log"))
+            .unwrap()
+            .set_len(cut)
+            .unwrap();
+
+        let backend = WalBackend::open(model(), copy.path()).unwrap();
+        let expected = if cut == after { (2, 2) } else { (1, 1) };
+        assert_eq!(
+            recovered_step(&backend.read_tree().unwrap()),
+            expected,
+            "log cut at {cut} of {after} bytes"
+        );
+    }
+}
+
+/// Child half of `wal_recovers_after_process_is_killed_mid_commit`: commits
+/// forever and reports each acknowledged step. A no-op in normal test runs.
+#[test]
+fn wal_child_writer() {
+    let Some(directory) = std::env::var_os("WAL_CHILD_DIR") else {
+        return;
+    };
+    let mut backend = WalBackend::open(model(), directory).unwrap();
+    for step in 1.. {
+        backend.replace_tree(step_tree(step)).unwrap();
+        println!("committed {step}");
+    }
+}
+
+#[test]
+fn wal_recovers_after_process_is_killed_mid_commit() {
+    let directory = tempfile::tempdir().unwrap();
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "wal_child_writer", "--nocapture"])
+        .env("WAL_CHILD_DIR", directory.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut acknowledged = 0;
+    for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+        if let Some(step) = line.unwrap().strip_prefix("committed ") {
+            acknowledged = step.parse().unwrap();
+            if acknowledged >= 50 {
+                break;
+            }
+        }
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    let backend = WalBackend::open(model(), directory.path()).unwrap();
+    let (temperature, level) = recovered_step(&backend.read_tree().unwrap());
+    assert_eq!(temperature, level, "recovered a partial transaction");
+    assert!(temperature >= acknowledged);
+    assert_eq!(backend.sequence(), temperature as u64);
+}
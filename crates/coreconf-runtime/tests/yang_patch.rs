@@ -0,0 +1,275 @@
+use coreconf_model::{CompositeModel, ErrorTag};
+use coreconf_runtime::coap_types::{ContentFormat, Method, Request};
+use coreconf_runtime::{
+    Datastore, Edit, EditOperation, Position, RequestHandler, ResponseCode, YangPatch,
+};
+use serde_json::{Value, json};
+
+const DEVICES: &str = "/example:devices/device";
+
+fn model() -> CompositeModel {
+    CompositeModel::from_sid_strings(&[r#"{
+        "module-name":"example",
+        "module-revision":"2026-01-01",
+        "item":[
+            {"identifier":"example","sid":60000},
+            {"identifier":"/example:devices","sid":60001},
+            {"identifier":"/example:devices/device","sid":60002,"ordered-by":"user"},
+            {"identifier":"/example:devices/device/id","sid":60003,"type":"string"},
+            {"identifier":"/example:devices/device/enabled","sid":60004,"type":"boolean"},
+            {"identifier":"/example:settings","sid":60006},
+            {"identifier":"/example:settings/enabled","sid":60007,"type":"boolean"},
+            {"identifier":"/example:settings/level","sid":60008,"type":"int32"}
+        ],
+        "key-mapping":{"60002":[60003]}
+    }"#])
+    .unwrap()
+}
+
+fn device(id: &str) -> String {
+    format!("{DEVICES}[id='{id}']")
+}
+
+fn handler() -> RequestHandler {
+    let mut running = Datastore::new_in_memory(model());
+    for id in ["a", "b", "c"] {
+        running
+            .set_path(&device(id), json!({"id": id, "enabled": true}))
+            .unwrap();
+    }
+    running
+        .set_path("/example:settings", json!({"enabled": true}))
+        .unwrap();
+    RequestHandler::new(running).unwrap()
+}
+
+fn yang_patch(path: &str, patch: &YangPatch) -> Request {
+    Request::new(Method::IPatch)
+        .with_path(path)
+        .with_payload(patch.to_cbor().unwrap(), ContentFormat::YangPatchCbor)
+}
+
+fn device_ids(handler: &RequestHandler) -> Vec<String> {
+    let Some(Value::Array(devices)) = handler.datastore().get_node(DEVICES).unwrap() else {
+        return Vec::new();
+    };
+    devices
+        .iter()
+        .map(|entry| entry["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn decode_value(bytes: &[u8]) -> Value {
+    coreconf_model::codec::cbor_to_json_value(bytes).unwrap()
+}
+
+#[test]
+fn create_and_delete_check_whether_the_target_exists() {
+    let mut handler = handler();
+
+    let create = YangPatch::new("create-a").with_edit(
+        Edit::new("1", EditOperation::Create, device("a"))
+            .with_value(json!({"id": "a", "enabled": false})),
+    );
+    let response = handler.handle(&yang_patch("", &create));
+    assert_eq!(response.code, ResponseCode::Conflict);
+    let error = decode_value(&response.payload);
+    assert!(error.to_string().contains("edit '1' failed"), "{error}");
+
+    let delete =
+        YangPatch::new("delete-z").with_edit(Edit::new("1", EditOperation::Delete, device("z")));
+    assert_eq!(
+        handler.handle(&yang_patch("", &delete)).code,
+        ResponseCode::NotFound
+    );
+
+    // remove is delete that tolerates an absent target.
+    let remove = YangPatch::new("remove-z")
+        .with_edit(Edit::new("1", EditOperation::Remove, device("z")))
+        .with_edit(Edit::new("2", EditOperation::Remove, device("a")));
+    assert_eq!(
+        handler.handle(&yang_patch("", &remove)).code,
+        ResponseCode::Changed
+    );
+    assert_eq!(device_ids(&handler), ["b", "c"]);
+}
+
+#[test]
+fn merge_keeps_siblings_and_replace_drops_them() {
+    let mut handler = handler();
+    let patch = YangPatch::new("settings")
+        .with_edit(
+            Edit::new("merge", EditOperation::Merge, "/example:settings")
+                .with_value(json!({"level": 3})),
+        )
+        .with_edit(
+            Edit::new("replace", EditOperation::Replace, device("b"))
+                .with_value(json!({"id": "b"})),
+        );
+
+    assert_eq!(
+        handler.handle(&yang_patch("", &patch)).code,
+        ResponseCode::Changed
+    );
+    assert_eq!(
+        handler.datastore().get_path("/example:settings").unwrap(),
+        Some(json!({"enabled": true, "level": 3}))
+    );
+    assert_eq!(
+        handler.datastore().get_path(&device("b")).unwrap(),
+        Some(json!({"id": "b"}))
+    );
+}
+
+#[test]
+fn insert_and_move_position_entries_and_roll_back() {
+    let mut handler = handler();
+    let patch = YangPatch::new("order")
+        .with_edit(
+            Edit::new("first", EditOperation::Insert, device("x"))
+                .with_value(json!({"id": "x"}))
+                .with_position(Position::First),
+        )
+        .with_edit(
+            Edit::new("after-b", EditOperation::Insert, device("y"))
+                .with_value(json!({"id": "y"}))
+                .with_position(Position::After(device("b"))),
+        )
+        .with_edit(
+            Edit::new("c-before-a", EditOperation::Move, device("c"))
+                .with_position(Position::Before(device("a"))),
+        );
+
+    assert_eq!(
+        handler.handle(&yang_patch("", &patch)).code,
+        ResponseCode::Changed
+    );
+    assert_eq!(device_ids(&handler), ["x", "c", "a", "b", "y"]);
+
+    let entry = handler.journal().entry(1).unwrap();
+    assert_eq!(entry.changed_paths, vec![DEVICES.to_string()]);
+    handler.rollback_to(0).unwrap();
+    assert_eq!(device_ids(&handler), ["a", "b", "c"]);
+}
+
+#[test]
+fn positioned_edits_reject_points_outside_the_list() {
+    let mut handler = handler();
+    let insert_existing = YangPatch::new("dup").with_edit(
+        Edit::new("1", EditOperation::Insert, device("a")).with_value(json!({"id": "a"})),
+    );
+    assert_eq!(
+        handler.handle(&yang_patch("", &insert_existing)).code,
+        ResponseCode::Conflict
+    );
+
+    let stray_point = YangPatch::new("stray").with_edit(
+        Edit::new("1", EditOperation::Move, device("a"))
+            .with_position(Position::After("/example:settings".into())),
+    );
+    assert_eq!(
+        handler.handle(&yang_patch("", &stray_point)).code,
+        ResponseCode::BadRequest
+    );
+
+    let move_missing =
+        YangPatch::new("missing").with_edit(Edit::new("1", EditOperation::Move, device("z")));
+    assert_eq!(
+        handler.handle(&yang_patch("", &move_missing)).code,
+        ResponseCode::NotFound
+    );
+    assert_eq!(device_ids(&handler), ["a", "b", "c"]);
+}
+
+#[test]
+fn a_failing_edit_leaves_the_datastore_and_journal_untouched() {
+    let mut handler = handler();
+    let before = handler.datastore().get_all().unwrap();
+    let patch = YangPatch::new("atomic")
+        .with_edit(Edit::new("1", EditOperation::Delete, device("a")))
+        .with_edit(
+            Edit::new("2", EditOperation::Merge, "/example:settings")
+                .with_value(json!({"level": 9})),
+        )
+        .with_edit(
+            Edit::new("3", EditOperation::Create, "/example:settings/enabled")
+                .with_value(json!(false)),
+        );
+
+    assert_eq!(
+        handler.handle(&yang_patch("", &patch)).code,
+        ResponseCode::Conflict
+    );
+    assert_eq!(handler.datastore().get_all().unwrap(), before);
+    assert_eq!(handler.journal().latest_revision(), 0);
+}
+
+#[test]
+fn targets_are_relative_to_the_request_path() {
+    let mut handler = handler();
+    let patch = YangPatch::new("scoped")
+        .with_edit(Edit::new("level", EditOperation::Create, "/level").with_value(json!(2)))
+        .with_edit(Edit::new("drop", EditOperation::Remove, "/enabled"));
+
+    assert_eq!(
+        handler
+            .handle(&yang_patch("/example:settings", &patch))
+            .code,
+        ResponseCode::Changed
+    );
+    assert_eq!(
+        handler.datastore().get_path("/example:settings").unwrap(),
+        Some(json!({"level": 2}))
+    );
+
+    let whole = YangPatch::new("whole").with_edit(Edit::new("1", EditOperation::Delete, "/"));
+    assert_eq!(
+        handler
+            .handle(&yang_patch("/example:settings", &whole))
+            .code,
+        ResponseCode::Changed
+    );
+    assert_eq!(
+        handler.datastore().get_path("/example:settings").unwrap(),
+        None
+    );
+}
+
+#[test]
+fn malformed_patches_are_rejected() {
+    let mut handler = handler();
+    let mut payload = Vec::new();
+    ciborium::into_writer(
+        &json!({"ietf-yang-patch:yang-patch": {
+            "patch-id": "p",
+            "edit": [{"edit-id": "1", "operation": "upsert", "target": "/example:settings"}]
+        }}),
+        &mut payload,
+    )
+    .unwrap();
+    let request = Request::new(Method::IPatch).with_payload(payload, ContentFormat::YangPatchCbor);
+
+    assert_eq!(handler.handle(&request).code, ResponseCode::BadRequest);
+}
+
+#[test]
+fn datastores_apply_patches_atomically() {
+    let mut datastore = Datastore::new_in_memory(model());
+    let patch = YangPatch::new("p")
+        .with_edit(
+            Edit::new("1", EditOperation::Create, "/example:settings/level").with_value(json!(1)),
+        )
+        .with_edit(Edit::new("2", EditOperation::Delete, device("a")));
+
+    let error = datastore.apply_yang_patch(&patch).unwrap_err();
+    assert_eq!(error.error_tag(), ErrorTag::DataMissing);
+    assert_eq!(datastore.get_all().unwrap(), json!({}));
+
+    datastore
+        .apply_yang_patch(&YangPatch::new("p").with_edit(patch.edits[0].clone()))
+        .unwrap();
+    assert_eq!(
+        datastore.get_path("/example:settings/level").unwrap(),
+        Some(json!(1))
+    );
+}